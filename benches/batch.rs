@@ -97,6 +97,35 @@ fn benchmark_batch(c: &mut Criterion) {
             },
         );
     }
+
+    // Many small requests completing together, which stresses the acking.
+    group.bench_function("unpartitioned/small_requests", |b| {
+        b.iter_batched(
+            || {
+                let rt = runtime();
+                let (acker, _) = Acker::basic();
+                let mut batch = BatchSettings::default();
+                batch.size.bytes = 2_000_000;
+                batch.size.events = 10;
+
+                let batch_sink = BatchSink::new(
+                    tower::service_fn(|_| future::ok::<_, Infallible>(())),
+                    Buffer::new(batch.size, Compression::None),
+                    Duration::from_secs(1),
+                    acker,
+                )
+                .sink_map_err(|error| panic!("{}", error));
+
+                (
+                    rt,
+                    stream::iter(input.clone()).map(|item| Ok(EncodedEvent::new(item, 0))),
+                    batch_sink,
+                )
+            },
+            |(rt, input, batch_sink)| rt.block_on(input.forward(batch_sink)).unwrap(),
+            criterion::BatchSize::LargeInput,
+        )
+    });
 }
 
 criterion_group!(
@@ -102,6 +102,10 @@ fn main() {
         println!("cargo:rerun-if-changed=proto/vector.proto");
         println!("cargo:rerun-if-changed=proto/dnstap.proto");
         println!("cargo:rerun-if-changed=proto/ddsketch.proto");
+        println!("cargo:rerun-if-changed=proto/dd_process.proto");
+        println!("cargo:rerun-if-changed=proto/dd_dns.proto");
+        println!("cargo:rerun-if-changed=proto/otlp_trace.proto");
+        println!("cargo:rerun-if-changed=proto/otlp_metrics.proto");
 
         let mut prost_build = prost_build::Config::new();
         prost_build.btree_map(&["."]);
@@ -114,6 +118,10 @@ fn main() {
                     "proto/vector.proto",
                     "proto/dnstap.proto",
                     "proto/ddsketch.proto",
+                    "proto/dd_process.proto",
+                    "proto/dd_dns.proto",
+                    "proto/otlp_trace.proto",
+                    "proto/otlp_metrics.proto",
                 ],
                 &["proto/", "lib/vector-core/proto/"],
             )
@@ -1,6 +1,6 @@
 #![deny(missing_docs)]
 
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 
 use getset::{Getters, Setters};
 use serde::{Deserialize, Serialize};
@@ -23,6 +23,32 @@ pub struct EventMetadata {
     #[getset(get = "pub", set = "pub")]
     #[serde(default, skip)]
     splunk_hec_token: Option<Arc<str>>,
+    /// Trace id (`vector.trace_id`) of the request the event was received in
+    #[getset(get = "pub", set = "pub")]
+    #[serde(default, skip)]
+    trace_id: Option<String>,
+    /// Span id (`vector.span_id`) of the request the event was received in
+    #[getset(get = "pub", set = "pub")]
+    #[serde(default, skip)]
+    span_id: Option<String>,
+    /// Parent span id (`vector.parent_span_id`) of the request the event was received in
+    #[getset(get = "pub", set = "pub")]
+    #[serde(default, skip)]
+    parent_span_id: Option<String>,
+    /// Address of the client which sent the event, when known
+    #[getset(get = "pub", set = "pub")]
+    #[serde(default, skip)]
+    client_ip: Option<IpAddr>,
+    /// Id of the source component which received the event, only set when a
+    /// sink tracks provenance
+    #[getset(get = "pub", set = "pub")]
+    #[serde(default, skip)]
+    source_id: Option<Arc<str>>,
+    /// Location the event was stored at by the service it was delivered to,
+    /// when the service reports one
+    #[getset(get = "pub", set = "pub")]
+    #[serde(default, skip)]
+    delivery_location: Option<String>,
     #[serde(default, skip)]
     finalizers: EventFinalizers,
 }
@@ -33,6 +59,10 @@ impl ByteSizeOf for EventMetadata {
         // else. We're just moving around the pointer, which is already captured
         // by `ByteSizeOf::size_of`.
         self.finalizers.allocated_bytes()
+            + self.trace_id.allocated_bytes()
+            + self.span_id.allocated_bytes()
+            + self.parent_span_id.allocated_bytes()
+            + self.delivery_location.allocated_bytes()
     }
 }
 
@@ -59,6 +89,10 @@ impl EventMetadata {
     /// Merge the other `EventMetadata` into this.
     /// If a Datadog API key is not set in `self`, the one from `other` will be used.
     /// If a Splunk HEC token is not set in `self`, the one from `other` will be used.
+    /// If a trace context is not set in `self`, the one from `other` will be used.
+    /// If a client IP is not set in `self`, the one from `other` will be used.
+    /// If a source id is not set in `self`, the one from `other` will be used.
+    /// If a delivery location is not set in `self`, the one from `other` will be used.
     pub fn merge(&mut self, other: Self) {
         self.finalizers.merge(other.finalizers);
         if self.datadog_api_key.is_none() {
@@ -67,6 +101,20 @@ impl EventMetadata {
         if self.splunk_hec_token.is_none() {
             self.splunk_hec_token = other.splunk_hec_token;
         }
+        if self.trace_id.is_none() {
+            self.trace_id = other.trace_id;
+            self.span_id = other.span_id;
+            self.parent_span_id = other.parent_span_id;
+        }
+        if self.client_ip.is_none() {
+            self.client_ip = other.client_ip;
+        }
+        if self.source_id.is_none() {
+            self.source_id = other.source_id;
+        }
+        if self.delivery_location.is_none() {
+            self.delivery_location = other.delivery_location;
+        }
     }
 
     /// Update the finalizer(s) status.
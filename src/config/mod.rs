@@ -370,6 +370,12 @@ pub trait SinkConfig: core::fmt::Debug + Send + Sync {
     fn resources(&self) -> Vec<Resource> {
         Vec::new()
     }
+
+    /// Whether the sink needs to know which source received each event,
+    /// see `EventMetadata::source_id`.
+    fn tracks_provenance(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,399 @@
+// ## skip check-events ##
+
+use std::{collections::HashMap, net::IpAddr};
+
+use metrics::{counter, histogram};
+use vector_core::internal_event::InternalEvent;
+
+#[derive(Debug)]
+pub struct DatadogAgentSecuritySignalReceived {
+    pub count: usize,
+}
+
+impl InternalEvent for DatadogAgentSecuritySignalReceived {
+    fn emit_logs(&self) {
+        trace!(message = "Received security signals.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "datadog_agent_security_signals_received_total",
+            self.count as u64
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentSchemaValidationError {
+    pub endpoint: &'static str,
+    pub errors: Vec<String>,
+}
+
+impl InternalEvent for DatadogAgentSchemaValidationError {
+    fn emit_logs(&self) {
+        error!(
+            message = "Payload failed schema validation.",
+            endpoint = %self.endpoint,
+            errors = ?self.errors,
+            error_type = "validation_failed",
+            stage = "receiving",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => "validation_failed",
+            "endpoint" => self.endpoint,
+            "stage" => "receiving",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentParseError<'a> {
+    pub endpoint: &'static str,
+    pub error: &'a dyn std::error::Error,
+}
+
+impl<'a> InternalEvent for DatadogAgentParseError<'a> {
+    fn emit_logs(&self) {
+        error!(
+            message = "Failed to parse payload.",
+            endpoint = %self.endpoint,
+            error = %self.error,
+            error_type = "parser_failed",
+            stage = "processing",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => "parser_failed",
+            "endpoint" => self.endpoint,
+            "stage" => "processing",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentEnrichmentError {
+    pub error: String,
+}
+
+impl InternalEvent for DatadogAgentEnrichmentError {
+    fn emit_logs(&self) {
+        error!(
+            message = "Failed to enrich event; passing it through unchanged.",
+            error = %self.error,
+            error_type = "script_failed",
+            stage = "processing",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => "script_failed",
+            "stage" => "processing",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentTraceContext {
+    pub trace_id: String,
+    pub endpoint: &'static str,
+}
+
+impl InternalEvent for DatadogAgentTraceContext {
+    fn emit_logs(&self) {
+        trace!(
+            message = "Received request with trace context.",
+            trace_id = %self.trace_id,
+            endpoint = %self.endpoint,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentRequestReceived {
+    pub endpoint: &'static str,
+    pub body_size_bytes: usize,
+    pub content_type: String,
+}
+
+impl InternalEvent for DatadogAgentRequestReceived {
+    fn emit_logs(&self) {
+        trace!(
+            message = "Received request.",
+            endpoint = %self.endpoint,
+            body_size_bytes = %self.body_size_bytes,
+            content_type = %self.content_type,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "component_received_event_bytes_total", self.body_size_bytes as u64,
+            "endpoint" => self.endpoint,
+        );
+        histogram!(
+            "http_request_body_bytes", self.body_size_bytes as f64,
+            "endpoint" => self.endpoint,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentRequestDenied {
+    pub ip: IpAddr,
+}
+
+impl InternalEvent for DatadogAgentRequestDenied {
+    fn emit_logs(&self) {
+        debug!(
+            message = "Denied request from client.",
+            ip = %self.ip,
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("datadog_agent_requests_denied_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentDbmMetricsReceived {
+    pub count: usize,
+}
+
+impl InternalEvent for DatadogAgentDbmMetricsReceived {
+    fn emit_logs(&self) {
+        trace!(message = "Received database monitoring metrics.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "datadog_agent_dbm_metrics_received_total",
+            self.count as u64
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentIoTMetricsReceived {
+    pub count: usize,
+}
+
+impl InternalEvent for DatadogAgentIoTMetricsReceived {
+    fn emit_logs(&self) {
+        trace!(message = "Received IoT metrics.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "datadog_agent_iot_metrics_received_total",
+            self.count as u64
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentErrorTrackingReceived {
+    pub count: usize,
+}
+
+impl InternalEvent for DatadogAgentErrorTrackingReceived {
+    fn emit_logs(&self) {
+        trace!(message = "Received error tracking events.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "datadog_agent_error_tracking_events_received_total",
+            self.count as u64
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentProcessesReceived {
+    pub count: usize,
+}
+
+impl InternalEvent for DatadogAgentProcessesReceived {
+    fn emit_logs(&self) {
+        trace!(message = "Received processes.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("datadog_agent_processes_received_total", self.count as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentRuntimeSecurityEventReceived {
+    pub count: usize,
+}
+
+impl InternalEvent for DatadogAgentRuntimeSecurityEventReceived {
+    fn emit_logs(&self) {
+        trace!(message = "Received runtime security events.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "datadog_agent_runtime_security_events_received_total",
+            self.count as u64
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentKubernetesMetadataReceived {
+    pub count: usize,
+    pub resource_type: String,
+}
+
+impl InternalEvent for DatadogAgentKubernetesMetadataReceived {
+    fn emit_logs(&self) {
+        trace!(
+            message = "Received Kubernetes metadata.",
+            count = %self.count,
+            resource_type = %self.resource_type,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "datadog_agent_kubernetes_metadata_received_total", self.count as u64,
+            "resource_type" => self.resource_type.clone(),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentSignatureVerificationFailed {
+    pub reason: &'static str,
+}
+
+impl InternalEvent for DatadogAgentSignatureVerificationFailed {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Rejected webhook with an unverified signature.",
+            reason = %self.reason,
+            error_type = "signature_verification_failed",
+            stage = "receiving",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => "signature_verification_failed",
+            "stage" => "receiving",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentDiagnoseReceived {
+    pub pass: usize,
+    pub warn: usize,
+    pub fail: usize,
+}
+
+impl InternalEvent for DatadogAgentDiagnoseReceived {
+    fn emit_logs(&self) {
+        trace!(
+            message = "Received agent diagnoses.",
+            pass = %self.pass,
+            warn = %self.warn,
+            fail = %self.fail,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "datadog_agent_diagnoses_received_total", self.pass as u64,
+            "result" => "pass",
+        );
+        counter!(
+            "datadog_agent_diagnoses_received_total", self.warn as u64,
+            "result" => "warning",
+        );
+        counter!(
+            "datadog_agent_diagnoses_received_total", self.fail as u64,
+            "result" => "fail",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentFleetReportReceived {
+    pub hostname: String,
+}
+
+impl InternalEvent for DatadogAgentFleetReportReceived {
+    fn emit_logs(&self) {
+        trace!(
+            message = "Received fleet report.",
+            hostname = %self.hostname,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("datadog_agent_fleet_reports_received_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentConfigUpdateReceived {
+    pub check_name: String,
+}
+
+impl InternalEvent for DatadogAgentConfigUpdateReceived {
+    fn emit_logs(&self) {
+        trace!(
+            message = "Received configuration update.",
+            check_name = %self.check_name,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("datadog_agent_config_updates_received_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct DatadogAgentRumEventsReceived {
+    pub count: usize,
+    /// Number of events of each RUM event type.
+    pub event_types: HashMap<String, usize>,
+}
+
+impl InternalEvent for DatadogAgentRumEventsReceived {
+    fn emit_logs(&self) {
+        trace!(
+            message = "Received RUM events.",
+            count = %self.count,
+            event_types = ?self.event_types,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        for (event_type, count) in &self.event_types {
+            counter!(
+                "datadog_agent_rum_events_received_total", *count as u64,
+                "event_type" => event_type.clone(),
+            );
+        }
+    }
+}
@@ -37,6 +37,8 @@ mod concat;
 mod conditions;
 #[cfg(feature = "sinks-console")]
 mod console;
+#[cfg(feature = "sources-datadog_agent")]
+mod datadog_agent;
 #[cfg(feature = "sinks-datadog_events")]
 mod datadog_events;
 #[cfg(feature = "sinks-datadog_logs")]
@@ -122,6 +124,7 @@ mod route;
 mod sample;
 #[cfg(feature = "sinks-sematext")]
 mod sematext_metrics;
+mod sink;
 mod socket;
 mod split;
 #[cfg(any(feature = "sources-splunk_hec", feature = "sinks-splunk_hec"))]
@@ -181,6 +184,8 @@ pub(crate) use self::coercer::*;
 pub use self::concat::*;
 #[cfg(feature = "sinks-console")]
 pub use self::console::*;
+#[cfg(feature = "sources-datadog_agent")]
+pub(crate) use self::datadog_agent::*;
 #[cfg(feature = "sinks-datadog_events")]
 pub use self::datadog_events::*;
 #[cfg(feature = "sinks-datadog_logs")]
@@ -285,8 +290,8 @@ pub use self::windows::*;
 pub use self::{
     adaptive_concurrency::*, add_fields::*, add_tags::*, aggregate::*, ansi_stripper::*, batch::*,
     blackhole::*, common::*, conditions::*, elasticsearch::*, encoding_transcode::*, heartbeat::*,
-    logplex::*, open::*, process::*, pulsar::*, remap::*, sample::*, split::*, stdin::*, syslog::*,
-    tcp::*, template::*, udp::*, unix::*, vector::*,
+    logplex::*, open::*, process::*, pulsar::*, remap::*, sample::*, sink::*, split::*, stdin::*,
+    syslog::*, tcp::*, template::*, udp::*, unix::*, vector::*,
 };
 
 // this version won't be needed once all `InternalEvent`s implement `name()`
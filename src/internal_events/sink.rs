@@ -0,0 +1,406 @@
+// ## skip check-events ##
+
+use metrics::{counter, histogram};
+use vector_core::internal_event::InternalEvent;
+
+use crate::sinks::util::PartitionBatchConfig;
+
+#[derive(Debug)]
+pub struct ServicePollReadyStalled {
+    pub duration_ms: u64,
+}
+
+impl InternalEvent for ServicePollReadyStalled {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Service has not been ready for an extended duration.",
+            duration_ms = %self.duration_ms,
+            error_type = "poll_ready_stalled",
+            stage = "sending",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => "poll_ready_stalled",
+            "stage" => "sending",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct PartitionBatchSinkMemoryPressure {
+    pub buffered_bytes: usize,
+    pub limit_bytes: usize,
+}
+
+impl InternalEvent for PartitionBatchSinkMemoryPressure {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Buffered batches exceed the memory limit, pausing intake.",
+            buffered_bytes = %self.buffered_bytes,
+            limit_bytes = %self.limit_bytes,
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("partition_batch_sink_memory_pressure_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchBytesSent {
+    pub batch_size: usize,
+    pub wire_size: usize,
+}
+
+impl InternalEvent for BatchBytesSent {
+    fn emit_logs(&self) {
+        trace!(
+            message = "Batch sent.",
+            batch_size = %self.batch_size,
+            wire_size = %self.wire_size,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("batch_wire_bytes_sent_total", self.wire_size as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchSinkNearlyFull {
+    pub fill_ratio: f64,
+    pub partition: Option<String>,
+    /// Partition label value of the metric, if metrics are labelled with
+    /// their partition.
+    pub metric_label: Option<String>,
+}
+
+impl InternalEvent for BatchSinkNearlyFull {
+    fn emit_logs(&self) {
+        debug!(
+            message = "Batch is nearly full.",
+            fill_ratio = %self.fill_ratio,
+            partition = ?self.partition,
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        match &self.metric_label {
+            Some(label) => counter!("batch_nearly_full_total", 1, "partition" => label.clone()),
+            None => counter!("batch_nearly_full_total", 1),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PartitionBatchConfigUpdated {
+    pub old: PartitionBatchConfig,
+    pub new: PartitionBatchConfig,
+}
+
+impl InternalEvent for PartitionBatchConfigUpdated {
+    fn emit_logs(&self) {
+        info!(
+            message = "Batch configuration updated.",
+            old = ?self.old,
+            new = ?self.new,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct SinkBackpressureActive;
+
+impl InternalEvent for SinkBackpressureActive {
+    fn emit_logs(&self) {
+        debug!(
+            message = "Sink is applying backpressure.",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("sink_backpressure_events_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct SinkBackpressureCleared;
+
+impl InternalEvent for SinkBackpressureCleared {
+    fn emit_logs(&self) {
+        debug!(
+            message = "Sink stopped applying backpressure.",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("sink_backpressure_cleared_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct PartitionBatchSinkGcCycle {
+    pub partitions_removed: usize,
+}
+
+impl InternalEvent for PartitionBatchSinkGcCycle {
+    fn emit_logs(&self) {
+        debug!(
+            message = "Removed idle partitions.",
+            partitions_removed = %self.partitions_removed,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("partition_batch_sink_gc_cycles_total", 1);
+        counter!(
+            "partition_batch_sink_gc_partitions_removed_total",
+            self.partitions_removed as u64
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ServiceKeepaliveFailed<'a> {
+    pub error: Option<&'a crate::Error>,
+}
+
+impl<'a> InternalEvent for ServiceKeepaliveFailed<'a> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Keepalive heartbeat failed.",
+            error = ?self.error,
+            error_type = "keepalive_failed",
+            stage = "sending",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => "keepalive_failed",
+            "stage" => "sending",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ServiceAuditLogFailed {
+    pub error: String,
+}
+
+impl InternalEvent for ServiceAuditLogFailed {
+    fn emit_logs(&self) {
+        error!(
+            message = "Failed writing to the audit sink, no more audit records will be written.",
+            error = %self.error,
+            error_type = "audit_log_failed",
+            stage = "sending",
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => "audit_log_failed",
+            "stage" => "sending",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ServiceAuditRecordDropped {
+    pub request_id: usize,
+}
+
+impl InternalEvent for ServiceAuditRecordDropped {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Audit sink is falling behind, dropping audit record.",
+            request_id = %self.request_id,
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("audit_records_dropped_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct ShadowServiceMismatch {
+    pub partition: String,
+    pub batch_size: usize,
+}
+
+impl InternalEvent for ShadowServiceMismatch {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Responses of the service and of its shadow differ.",
+            partition = %self.partition,
+            batch_size = %self.batch_size,
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("shadow_service_mismatches_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct RegionalFailoverActivated {
+    pub from_region: String,
+    pub to_region: String,
+}
+
+impl InternalEvent for RegionalFailoverActivated {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Service is unhealthy, failing over to another region.",
+            from_region = %self.from_region,
+            to_region = %self.to_region,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "regional_failovers_total", 1,
+            "from_region" => self.from_region.clone(),
+            "to_region" => self.to_region.clone(),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct RegionalFailoverRecovered;
+
+impl InternalEvent for RegionalFailoverRecovered {
+    fn emit_logs(&self) {
+        info!(message = "Service recovered, routing requests back to the primary region.");
+    }
+
+    fn emit_metrics(&self) {
+        counter!("regional_failover_recoveries_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct LeaseCoordinatorFailed {
+    pub error: String,
+}
+
+impl InternalEvent for LeaseCoordinatorFailed {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Failed coordinating partition leases, holding batches until their lease is taken.",
+            error = %self.error,
+            error_type = "lease_failed",
+            stage = "sending",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => "lease_failed",
+            "stage" => "sending",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ServiceRetryBudgetExhausted;
+
+impl InternalEvent for ServiceRetryBudgetExhausted {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Retry budget exhausted, not retrying the request.",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("retry_budget_exhausted_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct SinkColdStartComplete {
+    pub elapsed_ms: u64,
+}
+
+impl InternalEvent for SinkColdStartComplete {
+    fn emit_logs(&self) {
+        debug!(
+            message = "Sink dispatched its first request.",
+            elapsed_ms = %self.elapsed_ms,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        histogram!("sink_cold_start_ms", self.elapsed_ms as f64);
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchLatencySlaViolated {
+    pub exceeded_by_ms: u64,
+    pub batch_size: usize,
+}
+
+impl InternalEvent for BatchLatencySlaViolated {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Batch was sent later than its latency SLA allows.",
+            exceeded_by_ms = %self.exceeded_by_ms,
+            batch_size = %self.batch_size,
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("sink_latency_sla_violations_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchSinkSchemaViolation<'a> {
+    pub errors: &'a [String],
+    /// Whether the event is dropped rather than sent anyway.
+    pub dropped: bool,
+}
+
+impl<'a> InternalEvent for BatchSinkSchemaViolation<'a> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Event does not match the schema.",
+            errors = ?self.errors,
+            dropped = %self.dropped,
+            error_type = "validation_failed",
+            stage = "processing",
+            internal_log_rate_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => "validation_failed",
+            "stage" => "processing",
+        );
+        if self.dropped {
+            counter!("component_discarded_events_total", 1);
+        }
+    }
+}
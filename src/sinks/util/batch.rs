@@ -331,6 +331,51 @@ pub trait Batch: Sized {
     fn fresh(&self) -> Self;
     fn finish(self) -> Self::Output;
     fn num_items(&self) -> usize;
+
+    /// How full the batch is, from 0 to 1, for batches with a bounded size.
+    fn fill_ratio(&self) -> Option<f64> {
+        None
+    }
+
+    /// Changes the maximum size of the batch, for batches with a bounded size.
+    fn set_size(&mut self, _max_events: usize, _max_bytes: usize) {}
+
+    /// Returns what `finish` would, without consuming the batch.
+    fn peek_output(&self) -> Self::Output
+    where
+        Self: Clone,
+    {
+        self.clone().finish()
+    }
+
+    /// Returns the item at `idx`, for batches which store their items as is.
+    fn item_at(&self, _idx: usize) -> Option<&Self::Input> {
+        None
+    }
+
+    /// The number of bytes the finished batch `output` takes on the wire,
+    /// for batches which know it. The byte size of the events is used
+    /// otherwise.
+    fn wire_size(_output: &Self::Output) -> Option<usize> {
+        None
+    }
+}
+
+/// The fill ratio of a batch, the highest of its events and bytes ratios.
+pub(super) fn fill_ratio(
+    num_items: usize,
+    max_events: usize,
+    num_bytes: usize,
+    max_bytes: usize,
+) -> f64 {
+    let ratio = |value: usize, max: usize| {
+        if max == 0 {
+            1.0
+        } else {
+            value as f64 / max as f64
+        }
+    };
+    ratio(num_items, max_events).max(ratio(num_bytes, max_bytes))
 }
 
 #[derive(Debug)]
@@ -339,6 +384,9 @@ pub struct EncodedBatch<I> {
     pub finalizers: EventFinalizers,
     pub count: usize,
     pub byte_size: usize,
+    /// The size of the batch as sent, including any framing added by the
+    /// batch itself.
+    pub wire_size: usize,
 }
 
 /// This is a batch construct that stores an set of event finalizers alongside the batch itself.
@@ -409,23 +457,39 @@ impl<B: Batch> Batch for FinalizersBatch<B> {
     }
 
     fn finish(self) -> Self::Output {
+        let items = self.inner.finish();
+        let wire_size = B::wire_size(&items).unwrap_or(self.byte_size);
         EncodedBatch {
-            items: self.inner.finish(),
+            items,
             finalizers: self.finalizers,
             count: self.count,
             byte_size: self.byte_size,
+            wire_size,
         }
     }
 
     fn num_items(&self) -> usize {
         self.inner.num_items()
     }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        self.inner.fill_ratio()
+    }
+
+    fn set_size(&mut self, max_events: usize, max_bytes: usize) {
+        self.inner.set_size(max_events, max_bytes);
+    }
+
+    fn wire_size(output: &Self::Output) -> Option<usize> {
+        Some(output.wire_size)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct StatefulBatch<B> {
     inner: B,
     was_full: bool,
+    was_warned: bool,
 }
 
 impl<B: Batch> From<B> for StatefulBatch<B> {
@@ -433,6 +497,7 @@ impl<B: Batch> From<B> for StatefulBatch<B> {
         Self {
             inner,
             was_full: false,
+            was_warned: false,
         }
     }
 }
@@ -442,6 +507,20 @@ impl<B> StatefulBatch<B> {
         self.was_full
     }
 
+    /// Returns the fill ratio of the batch the first time it reaches
+    /// `threshold`, `None` otherwise.
+    pub fn nearly_full(&mut self, threshold: f64) -> Option<f64>
+    where
+        B: Batch,
+    {
+        if self.was_warned {
+            return None;
+        }
+        let ratio = self.inner.fill_ratio()?;
+        self.was_warned = ratio >= threshold;
+        self.was_warned.then(|| ratio)
+    }
+
     #[allow(clippy::missing_const_for_fn)] // const cannot run destructor
     pub fn into_inner(self) -> B {
         self.inner
@@ -477,6 +556,7 @@ impl<B: Batch> Batch for StatefulBatch<B> {
         Self {
             inner: self.inner.fresh(),
             was_full: false,
+            was_warned: false,
         }
     }
 
@@ -487,4 +567,16 @@ impl<B: Batch> Batch for StatefulBatch<B> {
     fn num_items(&self) -> usize {
         self.inner.num_items()
     }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        self.inner.fill_ratio()
+    }
+
+    fn set_size(&mut self, max_events: usize, max_bytes: usize) {
+        self.inner.set_size(max_events, max_bytes);
+    }
+
+    fn wire_size(output: &Self::Output) -> Option<usize> {
+        B::wire_size(output)
+    }
 }
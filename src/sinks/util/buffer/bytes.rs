@@ -0,0 +1,111 @@
+use bytes::Bytes;
+
+use super::{err_event_too_large, fill_ratio, Batch, BatchSize, PushResult};
+
+/// A batch of events which are already encoded, sent as they are.
+#[derive(Clone, Debug)]
+pub struct BytesBuffer {
+    batch: Vec<Bytes>,
+    bytes: usize,
+    settings: BatchSize<Self>,
+}
+
+impl BytesBuffer {
+    pub const fn new(settings: BatchSize<Self>) -> Self {
+        Self {
+            batch: Vec::new(),
+            bytes: 0,
+            settings,
+        }
+    }
+}
+
+impl Batch for BytesBuffer {
+    type Input = Bytes;
+    type Output = Vec<Bytes>;
+
+    fn push(&mut self, item: Self::Input) -> PushResult<Self::Input> {
+        let new_bytes = self.bytes + item.len();
+        if self.is_empty() && item.len() > self.settings.bytes {
+            err_event_too_large(item.len(), self.settings.bytes)
+        } else if self.num_items() >= self.settings.events || new_bytes > self.settings.bytes {
+            PushResult::Overflow(item)
+        } else {
+            self.batch.push(item);
+            self.bytes = new_bytes;
+            PushResult::Ok(
+                self.batch.len() >= self.settings.events || new_bytes >= self.settings.bytes,
+            )
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+
+    fn fresh(&self) -> Self {
+        Self::new(self.settings)
+    }
+
+    fn finish(self) -> Self::Output {
+        self.batch
+    }
+
+    fn num_items(&self) -> usize {
+        self.batch.len()
+    }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        Some(fill_ratio(
+            self.num_items(),
+            self.settings.events,
+            self.bytes,
+            self.settings.bytes,
+        ))
+    }
+
+    fn set_size(&mut self, max_events: usize, max_bytes: usize) {
+        self.settings.events = max_events;
+        self.settings.bytes = max_bytes;
+    }
+
+    fn item_at(&self, idx: usize) -> Option<&Self::Input> {
+        self.batch.get(idx)
+    }
+
+    fn wire_size(output: &Self::Output) -> Option<usize> {
+        Some(output.iter().map(Bytes::len).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::util::BatchSettings;
+
+    #[test]
+    fn obeys_max_bytes() {
+        let mut batch_settings = BatchSettings::default();
+        batch_settings.size.bytes = 10;
+        batch_settings.size.events = 99;
+
+        let mut buffer = BytesBuffer::new(batch_settings.size);
+        let data = Bytes::from_static(b"hello");
+
+        assert_eq!(
+            buffer.push(Bytes::from_static(b"this payload is too long")),
+            PushResult::Ok(false)
+        );
+        assert!(buffer.is_empty());
+
+        assert_eq!(buffer.push(data.clone()), PushResult::Ok(false));
+        assert_eq!(buffer.push(data.clone()), PushResult::Ok(true));
+        assert_eq!(buffer.push(data.clone()), PushResult::Overflow(data));
+        assert_eq!(buffer.num_items(), 2);
+
+        assert_eq!(
+            buffer.finish(),
+            vec![Bytes::from_static(b"hello"), Bytes::from_static(b"hello")]
+        );
+    }
+}
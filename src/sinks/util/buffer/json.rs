@@ -1,6 +1,6 @@
 use serde_json::value::{to_raw_value, RawValue, Value};
 
-use super::super::batch::{err_event_too_large, Batch, BatchSize, PushResult};
+use super::super::batch::{err_event_too_large, fill_ratio, Batch, BatchSize, PushResult};
 
 pub type BoxedRawValue = Box<RawValue>;
 
@@ -58,6 +58,26 @@ impl Batch for JsonArrayBuffer {
     fn num_items(&self) -> usize {
         self.buffer.len()
     }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        Some(fill_ratio(
+            self.buffer.len(),
+            self.settings.events,
+            self.total_bytes,
+            self.settings.bytes,
+        ))
+    }
+
+    fn set_size(&mut self, max_events: usize, max_bytes: usize) {
+        self.settings.events = max_events;
+        self.settings.bytes = max_bytes;
+    }
+
+    /// The length of the array once serialized.
+    fn wire_size(output: &Self::Output) -> Option<usize> {
+        let values: usize = output.iter().map(|value| value.get().len()).sum();
+        Some(2 + values + output.len().saturating_sub(1))
+    }
 }
 
 #[cfg(test)]
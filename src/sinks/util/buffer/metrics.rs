@@ -84,6 +84,10 @@ impl Batch for MetricsBuffer {
             .map(|metrics| metrics.0.len())
             .unwrap_or(0)
     }
+
+    fn set_size(&mut self, max_events: usize, _max_bytes: usize) {
+        self.max_events = max_events;
+    }
 }
 
 /// This is a simple wrapper for using `MetricNormalize` with a
@@ -2,16 +2,22 @@ use std::io::Write;
 
 use flate2::write::GzEncoder;
 
-use super::batch::{err_event_too_large, Batch, BatchSize, PushResult};
+use super::batch::{err_event_too_large, fill_ratio, Batch, BatchSize, PushResult};
 
+pub mod bytes;
 pub mod compression;
 pub mod json;
 pub mod metrics;
 pub mod partition;
+pub mod per_partition;
+pub mod provenance;
 pub mod vec;
 
+pub use self::bytes::BytesBuffer;
 pub use compression::{Compression, GZIP_FAST};
-pub use partition::{Partition, PartitionBuffer, PartitionInnerBuffer};
+pub use partition::{Coalesce, MultiPartition, Partition, PartitionBuffer, PartitionInnerBuffer};
+pub use per_partition::{DynBatch, PerPartitionBatch};
+pub use provenance::{AnnotatedBatch, ProvenanceBuffer, Sourced};
 
 #[derive(Debug)]
 pub struct Buffer {
@@ -117,6 +123,25 @@ impl Batch for Buffer {
     fn num_items(&self) -> usize {
         self.num_items
     }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        Some(fill_ratio(
+            self.num_items,
+            self.settings.events,
+            self.num_bytes,
+            self.settings.bytes,
+        ))
+    }
+
+    fn set_size(&mut self, max_events: usize, max_bytes: usize) {
+        self.settings.events = max_events;
+        self.settings.bytes = max_bytes;
+    }
+
+    /// The size of the body once compressed.
+    fn wire_size(output: &Self::Output) -> Option<usize> {
+        Some(output.len())
+    }
 }
 
 #[cfg(test)]
@@ -9,6 +9,23 @@ use crate::sinks::util::{Merged, SinkBatchSettings};
 pub trait Partition<K> {
     fn partition(&self) -> K;
 }
+
+/// Like `Partition`, for events which belong to several partitions at once.
+pub trait MultiPartition<K> {
+    fn partitions(&self) -> Vec<K>;
+}
+
+/// Events which can be sent in a batch shared by several partitions, see
+/// `PartitionBatchSink::with_coalesce_threshold`.
+pub trait Coalesce<K>: Partition<K> {
+    /// The partition key of the shared batches, `"__coalesced__"`.
+    fn coalesced_partition() -> K;
+
+    /// Records `partition` in the encoded form of the event, as its
+    /// `__partition__` field, so that the events of a shared batch can be
+    /// told apart.
+    fn tag_partition(&mut self, partition: &K);
+}
 #[derive(Debug)]
 pub struct PartitionBuffer<T, K> {
     inner: T,
@@ -69,6 +86,18 @@ where
     fn num_items(&self) -> usize {
         self.inner.num_items()
     }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        self.inner.fill_ratio()
+    }
+
+    fn set_size(&mut self, max_events: usize, max_bytes: usize) {
+        self.inner.set_size(max_events, max_bytes);
+    }
+
+    fn wire_size(output: &Self::Output) -> Option<usize> {
+        T::wire_size(&output.inner)
+    }
 }
 
 impl<T, K> PartitionInnerBuffer<T, K> {
@@ -80,6 +109,10 @@ impl<T, K> PartitionInnerBuffer<T, K> {
     pub fn into_parts(self) -> (T, K) {
         (self.inner, self.key)
     }
+
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
 }
 
 impl<T, K> Partition<K> for PartitionInnerBuffer<T, K>
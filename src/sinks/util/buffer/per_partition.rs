@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use super::{
+    super::batch::{Batch, PushResult},
+    partition::Partition,
+};
+
+/// Object safe subset of `Batch`, letting batches of different types be
+/// used interchangeably.
+pub trait DynBatch<I, O>: Send {
+    fn push(&mut self, item: I) -> PushResult<I>;
+    fn is_empty(&self) -> bool;
+    fn num_items(&self) -> usize;
+    fn fill_ratio(&self) -> Option<f64>;
+    fn set_size(&mut self, max_events: usize, max_bytes: usize);
+    fn finish(self: Box<Self>) -> O;
+}
+
+impl<B> DynBatch<B::Input, B::Output> for B
+where
+    B: Batch + Send,
+{
+    fn push(&mut self, item: B::Input) -> PushResult<B::Input> {
+        Batch::push(self, item)
+    }
+
+    fn is_empty(&self) -> bool {
+        Batch::is_empty(self)
+    }
+
+    fn num_items(&self) -> usize {
+        Batch::num_items(self)
+    }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        Batch::fill_ratio(self)
+    }
+
+    fn set_size(&mut self, max_events: usize, max_bytes: usize) {
+        Batch::set_size(self, max_events, max_bytes)
+    }
+
+    fn finish(self: Box<Self>) -> B::Output {
+        Batch::finish(*self)
+    }
+}
+
+type BatchFactory<K, I, O> = Arc<dyn Fn(&K) -> Box<dyn DynBatch<I, O>> + Send + Sync>;
+
+/// A batch whose actual type, and so its encoding, depends on the partition
+/// of its events.
+///
+/// The inner batch is created by the factory once the first event, and so
+/// the partition of the batch, is known.
+pub struct PerPartitionBatch<K, I, O> {
+    factory: BatchFactory<K, I, O>,
+    inner: Option<Box<dyn DynBatch<I, O>>>,
+    size: Option<(usize, usize)>,
+}
+
+impl<K, I, O> PerPartitionBatch<K, I, O> {
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: Fn(&K) -> Box<dyn DynBatch<I, O>> + Send + Sync + 'static,
+    {
+        Self {
+            factory: Arc::new(factory),
+            inner: None,
+            size: None,
+        }
+    }
+}
+
+impl<K, I, O> Batch for PerPartitionBatch<K, I, O>
+where
+    I: Partition<K>,
+    O: Default,
+{
+    type Input = I;
+    type Output = O;
+
+    fn push(&mut self, item: Self::Input) -> PushResult<Self::Input> {
+        let factory = &self.factory;
+        let size = self.size;
+        let inner = self.inner.get_or_insert_with(|| {
+            let mut batch = factory(&item.partition());
+            if let Some((max_events, max_bytes)) = size {
+                batch.set_size(max_events, max_bytes);
+            }
+            batch
+        });
+        inner.push(item)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner.as_ref().map_or(true, |inner| inner.is_empty())
+    }
+
+    fn fresh(&self) -> Self {
+        Self {
+            factory: Arc::clone(&self.factory),
+            inner: None,
+            size: self.size,
+        }
+    }
+
+    fn finish(self) -> Self::Output {
+        self.inner.map(|inner| inner.finish()).unwrap_or_default()
+    }
+
+    fn num_items(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.num_items())
+    }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        self.inner.as_ref().and_then(|inner| inner.fill_ratio())
+    }
+
+    fn set_size(&mut self, max_events: usize, max_bytes: usize) {
+        self.size = Some((max_events, max_bytes));
+        if let Some(inner) = self.inner.as_mut() {
+            inner.set_size(max_events, max_bytes);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::{collections::HashSet, sync::Arc};
+
+use super::{
+    super::batch::{Batch, BatchConfig, BatchError, PushResult},
+    partition::Partition,
+};
+use crate::sinks::util::{Merged, SinkBatchSettings};
+
+/// Batches items along with the ids of the sources they were received by.
+#[derive(Debug)]
+pub struct ProvenanceBuffer<T> {
+    inner: T,
+    sources: HashSet<String>,
+}
+
+/// An item of a `ProvenanceBuffer`, with the id of the source of its event.
+#[derive(Debug, Clone)]
+pub struct Sourced<T> {
+    pub item: T,
+    pub source_id: Option<Arc<str>>,
+}
+
+/// A finished batch, with the ids of the sources its events came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotatedBatch<T> {
+    pub batch: T,
+    pub sources: HashSet<String>,
+}
+
+impl<T> ProvenanceBuffer<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            sources: HashSet::new(),
+        }
+    }
+}
+
+impl<T> Sourced<T> {
+    pub const fn new(item: T, source_id: Option<Arc<str>>) -> Self {
+        Self { item, source_id }
+    }
+}
+
+impl<T> Batch for ProvenanceBuffer<T>
+where
+    T: Batch,
+{
+    type Input = Sourced<T::Input>;
+    type Output = AnnotatedBatch<T::Output>;
+
+    fn get_settings_defaults<D: SinkBatchSettings>(
+        config: BatchConfig<D, Merged>,
+    ) -> Result<BatchConfig<D, Merged>, BatchError> {
+        T::get_settings_defaults(config)
+    }
+
+    fn push(&mut self, item: Self::Input) -> PushResult<Self::Input> {
+        let Sourced { item, source_id } = item;
+        match self.inner.push(item) {
+            PushResult::Ok(full) => {
+                if let Some(source_id) = source_id {
+                    if !self.sources.contains(&*source_id) {
+                        self.sources.insert(source_id.to_string());
+                    }
+                }
+                PushResult::Ok(full)
+            }
+            PushResult::Overflow(item) => PushResult::Overflow(Sourced { item, source_id }),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    fn fresh(&self) -> Self {
+        Self::new(self.inner.fresh())
+    }
+
+    fn finish(self) -> Self::Output {
+        AnnotatedBatch {
+            batch: self.inner.finish(),
+            sources: self.sources,
+        }
+    }
+
+    fn num_items(&self) -> usize {
+        self.inner.num_items()
+    }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        self.inner.fill_ratio()
+    }
+
+    fn set_size(&mut self, max_events: usize, max_bytes: usize) {
+        self.inner.set_size(max_events, max_bytes);
+    }
+
+    fn wire_size(output: &Self::Output) -> Option<usize> {
+        T::wire_size(&output.batch)
+    }
+}
+
+impl<T, K> Partition<K> for Sourced<T>
+where
+    T: Partition<K>,
+{
+    fn partition(&self) -> K {
+        self.item.partition()
+    }
+}
@@ -1,6 +1,6 @@
 use bytes::Bytes;
 
-use super::{err_event_too_large, Batch, BatchSize, PushResult};
+use super::{err_event_too_large, fill_ratio, Batch, BatchSize, PushResult};
 
 pub trait EncodedLength {
     fn encoded_length(&self) -> usize;
@@ -61,6 +61,28 @@ impl<T: EncodedLength> Batch for VecBuffer<T> {
     fn num_items(&self) -> usize {
         self.batch.as_ref().map(Vec::len).unwrap_or(0)
     }
+
+    fn fill_ratio(&self) -> Option<f64> {
+        Some(fill_ratio(
+            self.num_items(),
+            self.settings.events,
+            self.bytes,
+            self.settings.bytes,
+        ))
+    }
+
+    fn set_size(&mut self, max_events: usize, max_bytes: usize) {
+        self.settings.events = max_events;
+        self.settings.bytes = max_bytes;
+    }
+
+    fn item_at(&self, idx: usize) -> Option<&Self::Input> {
+        self.batch.as_ref().and_then(|batch| batch.get(idx))
+    }
+
+    fn wire_size(output: &Self::Output) -> Option<usize> {
+        Some(output.iter().map(EncodedLength::encoded_length).sum())
+    }
 }
 
 impl EncodedLength for Bytes {
@@ -139,4 +161,23 @@ mod tests {
 
         assert_eq!(buffer.finish().len(), 2);
     }
+
+    #[test]
+    fn peeks_without_consuming() {
+        let mut batch_settings = BatchSettings::default();
+        batch_settings.size.events = 3;
+
+        let mut buffer = VecBuffer::new(batch_settings.size);
+        assert!(buffer.peek_output().is_empty());
+        assert_eq!(buffer.item_at(0), None);
+
+        assert_eq!(buffer.push("first".to_string()), PushResult::Ok(false));
+        assert_eq!(buffer.push("second".to_string()), PushResult::Ok(false));
+        assert_eq!(buffer.peek_output(), vec!["first", "second"]);
+        assert_eq!(buffer.item_at(1), Some(&"second".to_string()));
+        assert_eq!(buffer.item_at(2), None);
+
+        assert_eq!(buffer.push("third".to_string()), PushResult::Ok(true));
+        assert_eq!(buffer.finish(), vec!["first", "second", "third"]);
+    }
 }
@@ -10,8 +10,9 @@ use std::{
 };
 
 use bytes::{Buf, Bytes};
+use chrono::{DateTime, Utc};
 use futures::{future::BoxFuture, ready, Sink};
-use http::StatusCode;
+use http::{header::RETRY_AFTER, HeaderValue, StatusCode};
 use hyper::{body, Body};
 use indexmap::IndexMap;
 use pin_project::pin_project;
@@ -442,6 +443,40 @@ impl<T: fmt::Debug> sink::Response for http::Response<T> {
     fn is_transient(&self) -> bool {
         self.status().is_server_error()
     }
+
+    fn retry_after(&self) -> Option<Duration> {
+        self.headers().get(RETRY_AFTER).and_then(parse_retry_after)
+    }
+
+    fn audit_summary(&self) -> Option<String> {
+        Some(self.status().to_string())
+    }
+}
+
+/// Longest delay accepted from a `Retry-After` header, regardless of the
+/// retry policy's own maximum backoff.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Parses a `Retry-After` header value, given either as a number of seconds
+/// or as an HTTP date. The result is capped at `MAX_RETRY_AFTER`; the retry
+/// policy further caps it at its maximum backoff.
+fn parse_retry_after(value: &HeaderValue) -> Option<Duration> {
+    parse_retry_after_uncapped(value).map(|delay| delay.min(MAX_RETRY_AFTER))
+}
+
+fn parse_retry_after_uncapped(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        date.with_timezone(&Utc)
+            .signed_duration_since(Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
 }
 
 #[derive(Debug, Default, Clone)]
@@ -470,6 +505,10 @@ impl RetryLogic for HttpRetryLogic {
             _ => RetryAction::DontRetry(format!("response status: {}", status).into()),
         }
     }
+
+    fn retry_after(&self, response: &Self::Response) -> Option<Duration> {
+        sink::Response::retry_after(response)
+    }
 }
 
 /// A more generic version of `HttpRetryLogic` that accepts anything that can be converted
@@ -0,0 +1,104 @@
+//! Leases on the partitions of a `PartitionBatchSink`, so that several
+//! instances receiving events of the same partitions don't each send a batch
+//! of them.
+
+#[cfg(feature = "sinks-redis")]
+use std::fmt;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+#[cfg(feature = "sinks-redis")]
+use futures::FutureExt;
+#[cfg(feature = "sinks-redis")]
+use rand::{thread_rng, Rng};
+#[cfg(feature = "sinks-redis")]
+use redis::aio::ConnectionManager;
+
+/// Decides which instance sends the batches of a partition.
+pub trait LeaseCoordinator<K>: Send + Sync {
+    /// Tries to take the lease of `key` for `ttl`, resolving to whether this
+    /// instance holds it. Taking a lease this instance already holds renews
+    /// it.
+    fn try_acquire(&self, key: &K, ttl: Duration) -> BoxFuture<'static, crate::Result<bool>>;
+
+    /// Gives up the lease of `key`, if this instance holds it.
+    fn release(&self, key: &K) -> BoxFuture<'static, crate::Result<()>>;
+}
+
+/// Takes the lease of `KEYS[1]` for `ARGV[1]`, for `ARGV[2]` milliseconds.
+#[cfg(feature = "sinks-redis")]
+const ACQUIRE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+end
+if redis.call("SET", KEYS[1], ARGV[1], "NX", "PX", ARGV[2]) then
+    return 1
+end
+return 0
+"#;
+
+/// Deletes `KEYS[1]` if it is the lease of `ARGV[1]`.
+#[cfg(feature = "sinks-redis")]
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+/// Keeps the leases in Redis, as keys set with `NX` which expire after their
+/// `ttl` unless they are renewed.
+#[cfg(feature = "sinks-redis")]
+pub struct RedisLeaseCoordinator {
+    connection: ConnectionManager,
+    /// Identifies this instance as the holder of its leases.
+    owner: String,
+    /// Prepended to the partitions to make the keys of their leases.
+    prefix: String,
+}
+
+#[cfg(feature = "sinks-redis")]
+impl RedisLeaseCoordinator {
+    pub async fn new(url: &str, prefix: impl Into<String>) -> crate::Result<Self> {
+        let connection = redis::Client::open(url)?
+            .get_tokio_connection_manager()
+            .await?;
+        Ok(Self {
+            connection,
+            owner: format!("{:016x}", thread_rng().gen::<u64>()),
+            prefix: prefix.into(),
+        })
+    }
+
+    fn eval(
+        &self,
+        script: &str,
+        key: &dyn fmt::Display,
+        ttl: Duration,
+    ) -> BoxFuture<'static, crate::Result<i64>> {
+        let mut connection = self.connection.clone();
+        let mut cmd = redis::cmd("EVAL");
+        cmd.arg(script)
+            .arg(1)
+            .arg(format!("{}{}", self.prefix, key))
+            .arg(&self.owner)
+            .arg(ttl.as_millis() as u64);
+        async move { Ok(cmd.query_async(&mut connection).await?) }.boxed()
+    }
+}
+
+#[cfg(feature = "sinks-redis")]
+impl<K: fmt::Display> LeaseCoordinator<K> for RedisLeaseCoordinator {
+    fn try_acquire(&self, key: &K, ttl: Duration) -> BoxFuture<'static, crate::Result<bool>> {
+        self.eval(ACQUIRE_SCRIPT, key, ttl)
+            .map(|result| result.map(|acquired| acquired == 1))
+            .boxed()
+    }
+
+    fn release(&self, key: &K) -> BoxFuture<'static, crate::Result<()>> {
+        // The script ignores the TTL when releasing a lease.
+        self.eval(RELEASE_SCRIPT, key, Duration::ZERO)
+            .map(|result| result.map(drop))
+            .boxed()
+    }
+}
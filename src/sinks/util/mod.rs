@@ -5,6 +5,7 @@ pub mod builder;
 pub mod compressor;
 pub mod encoding;
 pub mod http;
+mod lease;
 pub mod normalizer;
 pub mod partitioner;
 pub mod processed_event;
@@ -13,6 +14,7 @@ pub mod retries;
 pub mod service;
 pub mod sink;
 pub mod socket_bytes_sink;
+mod spill;
 pub mod statistic;
 pub mod tcp;
 #[cfg(test)]
@@ -21,6 +23,7 @@ pub mod udp;
 #[cfg(all(any(feature = "sinks-socket", feature = "sinks-statsd"), unix))]
 pub mod unix;
 pub mod uri;
+mod wal;
 
 use std::borrow::Cow;
 
@@ -31,14 +34,18 @@ pub use batch::{
 };
 pub use buffer::{
     json::{BoxedRawValue, JsonArrayBuffer},
-    partition::Partition,
+    partition::{Coalesce, MultiPartition, Partition},
     vec::{EncodedLength, VecBuffer},
-    Buffer, Compression, PartitionBuffer, PartitionInnerBuffer,
+    AnnotatedBatch, Buffer, BytesBuffer, Compression, PartitionBuffer, PartitionInnerBuffer,
+    ProvenanceBuffer, Sourced,
 };
 pub use builder::SinkBuilderExt;
 use bytes::Bytes;
 pub use compressor::Compressor;
 use encoding::{EncodingConfig, EncodingConfiguration};
+pub use lease::LeaseCoordinator;
+#[cfg(feature = "sinks-redis")]
+pub use lease::RedisLeaseCoordinator;
 pub use normalizer::Normalizer;
 pub use request_builder::{IncrementalRequestBuilder, RequestBuilder};
 use serde::{Deserialize, Serialize};
@@ -46,7 +53,13 @@ pub use service::{
     Concurrency, ServiceBuilderExt, TowerBatchedSink, TowerPartitionSink, TowerRequestConfig,
     TowerRequestLayer, TowerRequestSettings,
 };
-pub use sink::{BatchSink, PartitionBatchSink, StreamSink};
+#[cfg(feature = "sinks-utils-schema-validation")]
+pub use sink::ViolationPolicy;
+pub use sink::{
+    AuditRecord, BatchSink, FanOutPartitionBatchSink, Merger, MultiPartitionBatchSink,
+    PartitionBatchConfig, PartitionBatchSink, PartitionBatchSinkConfig, PartitionStats,
+    RetryBudget, ServiceSinkSnapshot, StreamSink,
+};
 use snafu::Snafu;
 pub use uri::UriSerde;
 
@@ -32,6 +32,12 @@ pub trait RetryLogic: Clone + Send + Sync + 'static {
         // Treat the default as the request is successful
         RetryAction::Successful
     }
+
+    /// The delay to wait for before retrying `response`, overriding the
+    /// backoff of the retry policy.
+    fn retry_after(&self, _response: &Self::Response) -> Option<Duration> {
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -81,11 +87,19 @@ impl<L: RetryLogic> FixedRetryPolicy<L> {
     }
 
     fn build_retry(&self) -> RetryPolicyFuture<L> {
+        self.build_retry_after(self.backoff())
+    }
+
+    fn build_retry_after(&self, delay: Duration) -> RetryPolicyFuture<L> {
+        // Never wait longer than the policy allows, whatever the server asked for.
+        let delay = cmp::min(delay, self.max_duration);
         let policy = self.advance();
-        let delay = Box::pin(sleep(self.backoff()));
 
-        debug!(message = "Retrying request.", delay_ms = %self.backoff().as_millis());
-        RetryPolicyFuture { delay, policy }
+        debug!(message = "Retrying request.", delay_ms = %delay.as_millis());
+        RetryPolicyFuture {
+            delay: Box::pin(sleep(delay)),
+            policy,
+        }
     }
 }
 
@@ -109,7 +123,10 @@ where
                     }
 
                     warn!(message = "Retrying after response.", reason = %reason);
-                    Some(self.build_retry())
+                    match self.logic.retry_after(response) {
+                        Some(delay) => Some(self.build_retry_after(delay)),
+                        None => Some(self.build_retry()),
+                    }
                 }
 
                 RetryAction::DontRetry(reason) => {
@@ -348,6 +365,94 @@ mod tests {
         assert_eq!(fut.await.unwrap(), "world");
     }
 
+    #[tokio::test]
+    async fn too_many_requests_retry_after() {
+        use bytes::Bytes;
+
+        use crate::sinks::util::http::HttpRetryLogic;
+
+        trace_init();
+
+        time::pause();
+
+        let policy = FixedRetryPolicy::new(
+            5,
+            Duration::from_secs(1),
+            Duration::from_secs(10),
+            HttpRetryLogic,
+        );
+
+        let (mut svc, mut handle) = mock::spawn_layer(RetryLayer::new(policy));
+
+        assert_ready_ok!(svc.poll_ready());
+
+        let mut fut = task::spawn(svc.call("hello"));
+        assert_request_eq!(handle, "hello").send_response(
+            http::Response::builder()
+                .status(429)
+                .header("Retry-After", "5")
+                .body(Bytes::new())
+                .unwrap(),
+        );
+        assert_pending!(fut.poll());
+
+        // The server asked to wait longer than the initial backoff.
+        time::advance(Duration::from_secs(4)).await;
+        assert_pending!(fut.poll());
+        assert_pending!(handle.poll_request());
+
+        time::advance(Duration::from_secs(1)).await;
+        assert_pending!(fut.poll());
+
+        assert_request_eq!(handle, "hello")
+            .send_response(http::Response::new(Bytes::from("world")));
+        assert_eq!(fut.await.unwrap().body(), "world");
+    }
+
+    #[tokio::test]
+    async fn too_many_requests_retry_after_is_capped() {
+        use bytes::Bytes;
+
+        use crate::sinks::util::http::HttpRetryLogic;
+
+        trace_init();
+
+        time::pause();
+
+        let policy = FixedRetryPolicy::new(
+            5,
+            Duration::from_secs(1),
+            Duration::from_secs(10),
+            HttpRetryLogic,
+        );
+
+        let (mut svc, mut handle) = mock::spawn_layer(RetryLayer::new(policy));
+
+        assert_ready_ok!(svc.poll_ready());
+
+        let mut fut = task::spawn(svc.call("hello"));
+        assert_request_eq!(handle, "hello").send_response(
+            http::Response::builder()
+                .status(429)
+                .header("Retry-After", "3600")
+                .body(Bytes::new())
+                .unwrap(),
+        );
+        assert_pending!(fut.poll());
+
+        // The delay is capped at the maximum backoff of the policy.
+        time::advance(Duration::from_secs(9)).await;
+        assert_pending!(fut.poll());
+        assert_pending!(handle.poll_request());
+
+        time::advance(Duration::from_secs(1)).await;
+        assert_pending!(fut.poll());
+
+        assert_request_eq!(handle, "hello")
+            .send_response(http::Response::new(Bytes::from("world")));
+        assert_eq!(fut.await.unwrap().body(), "world");
+    }
+
     #[test]
     fn backoff_grows_to_max() {
         let mut policy = FixedRetryPolicy::new(
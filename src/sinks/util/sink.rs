@@ -32,21 +32,37 @@
 //! it to notify the consumer that the request has succeeded.
 
 use std::{
-    collections::HashMap,
+    cmp::Reverse,
+    collections::{hash_map::DefaultHasher, BinaryHeap, HashMap, VecDeque},
     fmt,
-    hash::Hash,
+    hash::{Hash, Hasher},
     marker::PhantomData,
     pin::Pin,
-    task::{Context, Poll},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
 };
 
+use bytes::Bytes;
+use chrono::Utc;
 use futures::{
-    future::BoxFuture, ready, stream::FuturesUnordered, FutureExt, Sink, Stream, TryFutureExt,
+    future::{self, BoxFuture, Either},
+    ready,
+    stream::FuturesUnordered,
+    FutureExt, Sink, SinkExt, Stream, TryFutureExt,
 };
 use pin_project::pin_project;
+use rand::{thread_rng, Rng};
+use serde::Serialize;
 use tokio::{
-    sync::oneshot,
-    time::{sleep, Duration, Sleep},
+    sync::{
+        mpsc::{self, error::TrySendError},
+        oneshot, watch, OwnedSemaphorePermit, Semaphore,
+    },
+    task::JoinHandle,
+    time::{sleep, sleep_until, timeout, Duration, Instant, Sleep},
 };
 use tower::{Service, ServiceBuilder};
 use tracing_futures::Instrument;
@@ -55,15 +71,58 @@ pub use vector_core::sink::StreamSink;
 use vector_core::{buffers::Acker, internal_event::EventsSent};
 
 use super::{
-    batch::{Batch, EncodedBatch, FinalizersBatch, PushResult, StatefulBatch},
-    buffer::{Partition, PartitionBuffer, PartitionInnerBuffer},
+    batch::{Batch, BatchSize, EncodedBatch, FinalizersBatch, PushResult, StatefulBatch},
+    buffer::{
+        AnnotatedBatch, BytesBuffer, MultiPartition, Partition, PartitionBuffer,
+        PartitionInnerBuffer, ProvenanceBuffer,
+    },
+    retries::ExponentialBackoff,
     service::{Map, ServiceBuilderExt},
+    spill::OverflowSpill,
+    wal::WriteAheadLog,
     EncodedEvent,
 };
-use crate::event::EventStatus;
+#[cfg(feature = "sinks-utils-schema-validation")]
+pub use crate::sinks::util::sink::schema::ViolationPolicy;
+pub use crate::sinks::util::sink::{audit::AuditRecord, coalesce::Merger};
+use crate::{
+    event::{EventFinalizers, EventStatus},
+    internal_events::{
+        BatchBytesSent, BatchLatencySlaViolated, BatchSinkNearlyFull, PartitionBatchConfigUpdated,
+        PartitionBatchSinkGcCycle, PartitionBatchSinkMemoryPressure, ServiceAuditRecordDropped,
+        ServiceKeepaliveFailed, ServicePollReadyStalled, ServiceRetryBudgetExhausted,
+        ShadowServiceMismatch, SinkBackpressureActive, SinkBackpressureCleared,
+        SinkColdStartComplete,
+    },
+    sinks::util::sink::{
+        bulkhead::{finalize_in_lane, Bulkheads},
+        coalesce::Coalescing,
+        dependencies::PartitionDependencies,
+        failover::{lock_failover, RegionSelector, RegionalFailover},
+        leases::PartitionLeases,
+        metric_labels::MetricLabels,
+        shadow::ShadowCompare,
+        wal::consume_wal,
+    },
+};
+
+mod audit;
+mod bulkhead;
+mod coalesce;
+mod dependencies;
+mod failover;
+mod leases;
+mod metric_labels;
+#[cfg(feature = "sinks-utils-schema-validation")]
+mod schema;
+mod shadow;
+mod spill;
+mod wal;
 
 // === BatchSink ===
 
+const DEFAULT_WARN_THRESHOLD: f64 = 0.8;
+
 /// A `Sink` interface that wraps a `Service` and a
 /// `Batch`.
 ///
@@ -79,7 +138,8 @@ use crate::event::EventStatus;
 /// and r3 are dispatched and r2 and r3 complete, all events contained
 /// in all requests will not be acked until r1 has completed.
 #[pin_project]
-#[derive(Debug)]
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct BatchSink<S, B, L>
 where
     S: Service<B::Output>,
@@ -92,8 +152,21 @@ where
         (),
         L,
     >,
+    #[derivative(Debug = "ignore")]
+    transform: Option<BatchTransform<B::Input>>,
+    #[derivative(Debug = "ignore")]
+    validation: Option<BatchTransform<B::Input>>,
+    #[derivative(Debug = "ignore")]
+    spill: Option<Box<dyn OverflowSpill<B::Input>>>,
+    /// Whether the last `poll_ready` was pending, to report when the
+    /// backpressure starts and stops.
+    was_backpressured: bool,
 }
 
+/// Applied to each item before it is batched, returning the status of the
+/// items which are dropped instead.
+type BatchTransform<T> = Arc<dyn Fn(T) -> Result<T, EventStatus> + Send + Sync>;
+
 impl<S, B> BatchSink<S, B, StdServiceLogic<S::Response>>
 where
     S: Service<B::Output>,
@@ -107,6 +180,20 @@ where
     }
 }
 
+impl<S> BatchSink<S, BytesBuffer, StdServiceLogic<S::Response>>
+where
+    S: Service<Vec<Bytes>>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+{
+    /// Creates a sink batching events which are already encoded, which can
+    /// then be sent as `Bytes` instead of being wrapped in `EncodedEvent`.
+    pub fn raw(service: S, buffer: BytesBuffer, timeout: Duration, acker: Acker) -> Self {
+        Self::new(service, buffer, timeout, acker)
+    }
+}
+
 impl<S, B, SL> BatchSink<S, B, SL>
 where
     S: Service<B::Output>,
@@ -127,8 +214,66 @@ where
             .map(|req: PartitionInnerBuffer<B::Output, ()>| req.into_parts().0)
             .service(service);
         let batch = PartitionBuffer::new(batch);
-        let inner = PartitionBatchSink::new_with_logic(service, batch, timeout, acker, logic);
-        Self { inner }
+        let mut inner = PartitionBatchSink::new_with_logic(service, batch, timeout, acker, logic);
+        inner.nearly_full = Some((DEFAULT_WARN_THRESHOLD, |_| None));
+        Self {
+            inner,
+            transform: None,
+            validation: None,
+            spill: None,
+            was_backpressured: false,
+        }
+    }
+
+    /// Applies `transform` to each item right before it is inserted into the
+    /// batch. Items for which it returns `None` are dropped and acked as
+    /// delivered.
+    pub fn with_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(B::Input) -> Option<B::Input> + Send + Sync + 'static,
+    {
+        self.transform = Some(Arc::new(move |item| {
+            transform(item).ok_or(EventStatus::Delivered)
+        }));
+        self
+    }
+
+    /// Reports batches once they are filled up to `ratio`, 0.8 by default,
+    /// of their maximum size.
+    pub fn with_warn_threshold(mut self, ratio: f64) -> Self {
+        self.inner.nearly_full = Some((ratio, |_| None));
+        self
+    }
+
+    /// Sorts the events of each batch by `key`, right before the batch is
+    /// sent. Events with equal keys keep their order.
+    pub fn with_sort_key<F, O>(mut self, key: F) -> Self
+    where
+        B: Batch<Output = Vec<B::Input>>,
+        F: Fn(&B::Input) -> O + Send + Sync + 'static,
+        O: Ord,
+    {
+        self.inner.sort_batch = Some(Box::new(
+            move |batch: &mut PartitionInnerBuffer<Vec<B::Input>, ()>| {
+                batch.inner_mut().sort_by_key(|item| key(item))
+            },
+        ));
+        self
+    }
+
+    /// Reports the batches which the service isn't ready to take within
+    /// `threshold` of being due, either full or past their linger.
+    pub fn with_latency_sla(mut self, threshold: Duration) -> Self {
+        self.inner.latency_sla = Some(LatencySla {
+            threshold,
+            deadlines: HashMap::new(),
+        });
+        self
+    }
+
+    /// Returns the request bookkeeping of the underlying service.
+    pub fn service_snapshot(&self) -> ServiceSinkSnapshot {
+        self.inner.service_snapshot()
     }
 }
 
@@ -139,7 +284,13 @@ where
     B: Batch,
 {
     pub fn get_ref(&self) -> &S {
-        &self.inner.service.service.inner
+        &self
+            .inner
+            .service
+            .service
+            .as_ref()
+            .expect("Service is warming up")
+            .inner
     }
 }
 
@@ -154,27 +305,145 @@ where
 {
     type Error = crate::Error;
 
-    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.project().inner.poll_ready(cx)
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let poll = self.as_mut().poll_ready_inner(cx);
+        let this = self.project();
+        match poll {
+            Poll::Pending if !*this.was_backpressured => {
+                *this.was_backpressured = true;
+                emit!(&SinkBackpressureActive);
+            }
+            Poll::Ready(_) if *this.was_backpressured => {
+                *this.was_backpressured = false;
+                emit!(&SinkBackpressureCleared);
+            }
+            _ => {}
+        }
+        poll
     }
 
     fn start_send(self: Pin<&mut Self>, item: EncodedEvent<B::Input>) -> Result<(), Self::Error> {
-        self.project()
-            .inner
+        let mut this = self.project();
+        let mut item = item;
+        for transform in [this.transform.as_ref(), this.validation.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            let EncodedEvent {
+                item: inner,
+                finalizers,
+                byte_size,
+            } = item;
+            match transform(inner) {
+                Ok(inner) => {
+                    item = EncodedEvent {
+                        item: inner,
+                        finalizers,
+                        byte_size,
+                    }
+                }
+                Err(status) => {
+                    finalizers.update_status(status);
+                    this.inner.service.ack_dropped(1);
+                    return Ok(());
+                }
+            }
+        }
+        if let Some(spill) = this.spill {
+            // Keep spilling until everything has been replayed to preserve ordering.
+            if this.inner.buffer.is_some() || !spill.is_empty() {
+                return spill.push(item);
+            }
+        }
+        this.inner
+            .as_mut()
             .start_send(item.map(|item| PartitionInnerBuffer::new(item, ())))
     }
 
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        loop {
+            self.as_mut().replay_spill()?;
+
+            let mut this = self.as_mut().project();
+
+            let poll = this.inner.as_mut().poll_flush(cx)?;
+            let replayed = this.spill.as_ref().map_or(true, |spill| spill.is_empty());
+            if replayed || this.inner.buffer.is_some() {
+                return poll.map(Ok);
+            }
+        }
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.spill.is_none() {
+            return self.project().inner.poll_close(cx);
+        }
+
+        trace!("Closing batch sink.");
+        self.as_mut().project().inner.closing = true;
+        self.poll_flush(cx)
+    }
+}
+
+/// Events already encoded are batched as they are. They have no finalizers,
+/// and their size is their encoded length.
+impl<S, SL> Sink<Bytes> for BatchSink<S, BytesBuffer, SL>
+where
+    S: Service<Vec<Bytes>>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    type Error = crate::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Sink::<EncodedEvent<Bytes>>::poll_ready(self, cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> Result<(), Self::Error> {
+        let byte_size = item.len();
+        Sink::<EncodedEvent<Bytes>>::start_send(self, EncodedEvent::new(item, byte_size))
+    }
+
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.project().inner.poll_flush(cx)
+        Sink::<EncodedEvent<Bytes>>::poll_flush(self, cx)
     }
 
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.project().inner.poll_close(cx)
+        Sink::<EncodedEvent<Bytes>>::poll_close(self, cx)
     }
 }
 
 // === PartitionBatchSink ===
 
+/// Batch settings of a `PartitionBatchSink` which can be updated at runtime.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PartitionBatchConfig {
+    pub timeout: Duration,
+    pub max_events: usize,
+    pub max_bytes: usize,
+}
+
+/// Settings a `PartitionBatchSink` is built from, see
+/// `PartitionBatchSink::from_config`.
+#[derive(Debug, Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct PartitionBatchSinkConfig<B> {
+    /// Size limits of each partition batch.
+    pub batch_size: BatchSize<B>,
+    /// How long a partition batch is held before being sent.
+    pub linger: Duration,
+    /// Upper bound on the requests in flight at once. When unset, a new
+    /// request is only dispatched once the service reports being ready.
+    pub max_in_flight: Option<usize>,
+    /// Upper bound on a random delay added to the linger of each partition
+    /// batch, so that batches started together don't all flush at once.
+    pub linger_jitter: Option<Duration>,
+    /// Whether requests of the same partition are sent one at a time.
+    pub ordered: bool,
+}
+
 /// A partition based batcher, given some `Service` and `Batch` where the
 /// input is partitionable via the `Partition` trait, it will hold many
 /// in flight batches.
@@ -209,9 +478,155 @@ where
     batch: StatefulBatch<FinalizersBatch<B>>,
     partitions: HashMap<K, StatefulBatch<FinalizersBatch<B>>>,
     timeout: Duration,
+    linger_jitter: Option<Duration>,
     lingers: HashMap<K, Pin<Box<Sleep>>>,
+    partition_ttl: Option<Duration>,
+    ttl_timers: HashMap<K, Pin<Box<Sleep>>>,
     in_flight: Option<HashMap<K, BoxFuture<'static, ()>>>,
     closing: bool,
+    waker: Option<Waker>,
+    /// Number of events held in `partitions`.
+    buffered_items: usize,
+    memory_limit: Option<MemoryLimit>,
+    nearly_full: Option<(f64, fn(&K) -> Option<String>)>,
+    key_display: Option<Box<dyn Fn(&K) -> String + Send + Sync>>,
+    metric_labels: Option<MetricLabels>,
+    config_updates: Option<(PartitionBatchConfig, watch::Receiver<PartitionBatchConfig>)>,
+    overflow: Option<OverflowSink<B::Input>>,
+    sort_batch: Option<Box<dyn Fn(&mut B::Output) + Send + Sync>>,
+    /// Whether `sort_batch` was set by `with_timestamp_ordering`.
+    timestamp_ordering: bool,
+    coalesce: Option<Coalescing<K, B::Output>>,
+    normalize_key: Option<Box<dyn Fn(K) -> K + Send + Sync>>,
+    priority: Option<Box<dyn Fn(&K) -> u8 + Send + Sync>>,
+    idle_gc: Option<IdleGc<K>>,
+    latency_sla: Option<LatencySla<K>>,
+    wal: Option<Box<dyn WriteAheadLog<K, B::Input>>>,
+    stats: Arc<Mutex<PartitionStats<K>>>,
+    dependencies: Option<PartitionDependencies<K>>,
+    leases: Option<PartitionLeases<K>>,
+}
+
+type OverflowSink<T> = Pin<Box<dyn Sink<EncodedEvent<T>, Error = crate::Error> + Send>>;
+
+/// An event in the heap draining a batch in timestamp order, see
+/// `PartitionBatchSink::with_timestamp_ordering`. Events with equal
+/// timestamps keep their insertion order.
+struct Timestamped<T> {
+    timestamp: i64,
+    index: usize,
+    item: T,
+}
+
+impl<T> PartialEq for Timestamped<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.timestamp, self.index) == (other.timestamp, other.index)
+    }
+}
+
+impl<T> Eq for Timestamped<T> {}
+
+impl<T> PartialOrd for Timestamped<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Timestamped<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.timestamp, self.index).cmp(&(other.timestamp, other.index))
+    }
+}
+
+/// Periodic removal of the state kept for partitions which went idle.
+struct IdleGc<K> {
+    interval: Duration,
+    idle_threshold: Duration,
+    timer: Pin<Box<Sleep>>,
+    /// Time of the last insertion in each partition.
+    last_active: HashMap<K, Instant>,
+}
+
+/// Reports the batches which wait for the service for longer than
+/// `threshold` once they are due to be sent.
+struct LatencySla<K> {
+    threshold: Duration,
+    /// Deadline of the batch of each partition, set when it became due.
+    deadlines: HashMap<K, Instant>,
+}
+
+impl<K: Hash + Eq + Clone> LatencySla<K> {
+    fn start(&mut self, partition: &K) {
+        if !self.deadlines.contains_key(partition) {
+            self.deadlines
+                .insert(partition.clone(), Instant::now() + self.threshold);
+        }
+    }
+
+    /// Reports the batch of `partition` as sent, if it missed its deadline.
+    fn finish(&mut self, partition: &K, batch_size: usize) {
+        if let Some(deadline) = self.deadlines.remove(partition) {
+            let now = Instant::now();
+            if now > deadline {
+                emit!(&BatchLatencySlaViolated {
+                    exceeded_by_ms: (now - deadline).as_millis() as u64,
+                    batch_size,
+                });
+            }
+        }
+    }
+}
+
+/// Describes `partition` with the key display of a `PartitionBatchSink`, or
+/// else with the label of its nearly full events.
+fn describe_partition<K>(
+    key_display: &Option<Box<dyn Fn(&K) -> String + Send + Sync>>,
+    nearly_full: &Option<(f64, fn(&K) -> Option<String>)>,
+    partition: &K,
+) -> Option<String> {
+    match key_display {
+        Some(display) => Some(display(partition)),
+        None => nearly_full.and_then(|(_, label)| label(partition)),
+    }
+}
+
+/// Estimated bound on the bytes held by the batches of a `PartitionBatchSink`.
+#[derive(Debug)]
+struct MemoryLimit {
+    max_bytes: usize,
+    avg_item_bytes: usize,
+    paused: bool,
+}
+
+/// Events received by each partition of a `PartitionBatchSink`, shared with
+/// the tasks watching it through `PartitionBatchSink::stats_handle`.
+#[derive(Debug)]
+pub struct PartitionStats<K> {
+    lifetime_event_count: HashMap<K, usize>,
+}
+
+impl<K> Default for PartitionStats<K> {
+    fn default() -> Self {
+        Self {
+            lifetime_event_count: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> PartitionStats<K> {
+    /// Number of events received by each partition since the sink was
+    /// created, or since the counts were last reset.
+    pub fn events_per_partition(&self) -> HashMap<K, usize> {
+        self.lifetime_event_count.clone()
+    }
+
+    pub fn reset_event_counts(&mut self) {
+        self.lifetime_event_count.clear();
+    }
+
+    fn record(&mut self, partition: K) {
+        *self.lifetime_event_count.entry(partition).or_insert(0) += 1;
+    }
 }
 
 impl<S, B, K> PartitionBatchSink<S, B, K, StdServiceLogic<S::Response>>
@@ -227,6 +642,51 @@ where
     pub fn new(service: S, batch: B, timeout: Duration, acker: Acker) -> Self {
         Self::new_with_logic(service, batch, timeout, acker, StdServiceLogic::default())
     }
+
+    /// Creates a sink with all of the settings of `config` applied.
+    pub fn from_config(
+        service: S,
+        batch: B,
+        config: PartitionBatchSinkConfig<B>,
+        acker: Acker,
+    ) -> Self {
+        let mut sink = Self::new(service, batch, config.linger, acker);
+        sink.batch
+            .set_size(config.batch_size.events, config.batch_size.bytes);
+        if let Some(max_in_flight) = config.max_in_flight {
+            sink = sink.with_concurrent_dispatch(max_in_flight);
+        }
+        if let Some(jitter) = config.linger_jitter {
+            sink = sink.with_linger_jitter(jitter);
+        }
+        if config.ordered {
+            sink.ordered();
+        }
+        sink
+    }
+}
+
+impl<S, B, K> PartitionBatchSink<S, ProvenanceBuffer<B>, K, StdServiceLogic<S::Response>>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<AnnotatedBatch<B::Output>>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+{
+    /// Creates a sink which sends each batch to the service as an
+    /// `AnnotatedBatch`, along with the ids of the sources its events were
+    /// received by.
+    ///
+    /// The source id of each event is taken from the `Sourced` items it is
+    /// sent as, usually filled from `EventMetadata::source_id`. The topology
+    /// only sets it when the config of the sink returns `true` from
+    /// `SinkConfig::tracks_provenance`.
+    pub fn with_provenance_tracking(service: S, batch: B, timeout: Duration, acker: Acker) -> Self {
+        Self::new(service, ProvenanceBuffer::new(batch), timeout, acker)
+    }
 }
 
 impl<S, B, K, SL> PartitionBatchSink<S, B, K, SL>
@@ -255,9 +715,31 @@ where
             batch: StatefulBatch::from(FinalizersBatch::from(batch)),
             partitions: HashMap::new(),
             timeout,
+            linger_jitter: None,
             lingers: HashMap::new(),
+            partition_ttl: None,
+            ttl_timers: HashMap::new(),
             in_flight: None,
             closing: false,
+            waker: None,
+            buffered_items: 0,
+            memory_limit: None,
+            nearly_full: None,
+            key_display: None,
+            metric_labels: None,
+            config_updates: None,
+            overflow: None,
+            sort_batch: None,
+            timestamp_ordering: false,
+            coalesce: None,
+            normalize_key: None,
+            priority: None,
+            idle_gc: None,
+            latency_sla: None,
+            wal: None,
+            stats: Arc::new(Mutex::new(PartitionStats::default())),
+            dependencies: None,
+            leases: None,
         }
     }
 
@@ -265,129 +747,856 @@ where
     pub fn ordered(&mut self) {
         self.in_flight = Some(HashMap::new());
     }
-}
 
-impl<S, B, K, SL> Sink<EncodedEvent<B::Input>> for PartitionBatchSink<S, B, K, SL>
-where
-    B: Batch,
-    B::Input: Partition<K>,
-    K: Hash + Eq + Clone + Send + 'static,
-    S: Service<B::Output>,
-    S::Future: Send + 'static,
-    S::Error: Into<crate::Error> + Send + 'static,
-    S::Response: Response + Send + 'static,
-    SL: ServiceLogic<Response = S::Response> + Send + 'static,
-{
-    type Error = crate::Error;
+    /// Adds a random delay of up to `jitter` to the linger timeout of each
+    /// new partition batch.
+    pub fn with_linger_jitter(mut self, jitter: Duration) -> Self {
+        self.linger_jitter = Some(jitter);
+        self
+    }
 
-    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        if self.buffer.is_some() {
-            match self.as_mut().poll_flush(cx) {
-                Poll::Ready(Ok(())) => {}
-                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
-                Poll::Pending => {
-                    if self.buffer.is_some() {
-                        return Poll::Pending;
-                    }
-                }
-            }
-        }
+    /// Reports the service as stalled once `poll_ready` has been pending for
+    /// longer than `threshold`. Defaults to five seconds.
+    pub fn with_poll_ready_warn_threshold(mut self, threshold: Duration) -> Self {
+        self.service.poll_ready_warn_threshold = threshold;
+        self
+    }
 
-        Poll::Ready(Ok(()))
+    /// Lets up to `max_concurrent` requests be in flight at once.
+    ///
+    /// This is meant for services which handle concurrency themselves, like
+    /// `tower::buffer::Buffer`. The service readiness is still checked before
+    /// each request, on top of the `max_concurrent` limit.
+    pub fn with_concurrent_dispatch(mut self, max_concurrent: usize) -> Self {
+        self.service = self.service.with_concurrent_dispatch(max_concurrent);
+        self
     }
 
-    fn start_send(
-        mut self: Pin<&mut Self>,
-        item: EncodedEvent<B::Input>,
-    ) -> Result<(), Self::Error> {
-        let partition = item.item.partition();
+    /// Returns the request bookkeeping of the underlying service.
+    pub fn service_snapshot(&self) -> ServiceSinkSnapshot {
+        self.service.snapshot()
+    }
 
-        let batch = loop {
-            if let Some(batch) = self.partitions.get_mut(&partition) {
-                break batch;
-            }
+    /// Returns the number of events each partition received, see
+    /// `PartitionStats::events_per_partition`.
+    pub fn events_per_partition(&self) -> HashMap<K, usize> {
+        self.lock_stats().events_per_partition()
+    }
 
-            let batch = self.batch.fresh();
-            self.partitions.insert(partition.clone(), batch);
+    pub fn reset_event_counts(&mut self) {
+        self.lock_stats().reset_event_counts();
+    }
 
-            let delay = sleep(self.timeout);
-            self.lingers.insert(partition.clone(), Box::pin(delay));
-        };
+    /// Returns a handle on the partition statistics, to read them from
+    /// another task while the sink is in use.
+    pub fn stats_handle(&self) -> Arc<Mutex<PartitionStats<K>>> {
+        Arc::clone(&self.stats)
+    }
 
-        if let PushResult::Overflow(item) = batch.push(item) {
-            self.buffer = Some((partition, item));
-        }
+    /// Logs the body of each request at `level` before sending it, formatted
+    /// with `Debug` and truncated to 1KiB by default.
+    pub fn with_debug_logging(mut self, level: tracing::Level) -> Self
+    where
+        B::Output: fmt::Debug,
+    {
+        self.service = self.service.with_debug_logging(level);
+        self
+    }
 
-        Ok(())
+    /// Like `with_debug_logging`, for services taking raw bytes: the bodies
+    /// are logged as UTF-8 text, with invalid sequences replaced.
+    pub fn with_debug_logging_bytes(mut self, level: tracing::Level) -> Self
+    where
+        B::Output: AsRef<[u8]>,
+    {
+        self.service = self.service.with_debug_logging_bytes(level);
+        self
     }
 
-    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        loop {
-            // Poll inner service while not ready, if we don't have buffer or any batch.
-            if self.buffer.is_none() && self.partitions.is_empty() {
-                ready!(self.service.poll_complete(cx));
-                return Poll::Ready(Ok(()));
-            }
+    /// Calls `inspect` with every response returned by the service, for
+    /// example to log the rate limit headers of throttled HTTP requests.
+    pub fn with_response_inspector<F>(mut self, inspect: F) -> Self
+    where
+        F: Fn(&S::Response) + Send + Sync + 'static,
+    {
+        self.service = self.service.with_response_inspector(inspect);
+        self
+    }
 
-            // Try send batches.
-            let this = self.as_mut().project();
-            let mut partitions_ready = vec![];
-            for (partition, batch) in this.partitions.iter() {
-                if ((*this.closing && !batch.is_empty())
-                    || batch.was_full()
-                    || matches!(
-                        this.lingers
-                            .get_mut(partition)
-                            .expect("linger should exists for poll_flush")
-                            .poll_unpin(cx),
-                        Poll::Ready(())
-                    ))
-                    && this
-                        .in_flight
-                        .as_mut()
-                        .and_then(|map| map.get_mut(partition))
-                        .map(|req| matches!(req.poll_unpin(cx), Poll::Ready(())))
-                        .unwrap_or(true)
-                {
-                    partitions_ready.push(partition.clone());
-                }
-            }
-            let mut batch_consumed = false;
-            for partition in partitions_ready.iter() {
-                let service_ready = match this.service.poll_ready(cx) {
-                    Poll::Ready(Ok(())) => true,
-                    Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
-                    Poll::Pending => false,
-                };
-                if service_ready {
-                    trace!("Service ready; Sending batch.");
+    /// Sends `heartbeat` every `interval` while no batch is in flight, to keep
+    /// the connections of the service from being closed as idle.
+    pub fn with_keepalive(mut self, interval: Duration, heartbeat: B::Output) -> Self
+    where
+        S: Clone + Send + 'static,
+        B::Output: Clone + Send + 'static,
+    {
+        self.service = self.service.with_keepalive(interval, heartbeat);
+        self
+    }
 
-                    let batch = this.partitions.remove(partition).unwrap();
-                    this.lingers.remove(partition);
+    /// Retries the failed batches for as long as `budget`, which may be
+    /// shared with other sinks, has retries left.
+    pub fn with_retry_budget(mut self, budget: Arc<RetryBudget>) -> Self
+    where
+        S: Clone + Send + 'static,
+        B::Output: Clone + Send + 'static,
+    {
+        self.service = self.service.with_retry_budget(budget);
+        self
+    }
 
-                    let batch_size = batch.num_items();
-                    let batch = batch.finish();
-                    let future = tokio::spawn(this.service.call(batch, batch_size));
+    /// Truncates the request bodies logged by `with_debug_logging` to
+    /// `max_bytes`.
+    pub fn with_debug_log_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.service.debug_log_max_bytes = max_bytes;
+        self
+    }
 
-                    if let Some(map) = this.in_flight.as_mut() {
-                        map.insert(partition.clone(), future.map(|_| ()).fuse().boxed());
-                    }
+    /// Starts polling a clone of the service for readiness right away, in a
+    /// separate task, so that its connections are established before the
+    /// first batch.
+    ///
+    /// The warm-up gives up quietly if the service isn't ready after three
+    /// attempts, one second apart.
+    pub fn with_warmup(mut self) -> Self
+    where
+        S: Clone + Send + 'static,
+        B::Output: 'static,
+    {
+        self.service = self.service.with_warmup();
+        self
+    }
 
-                    batch_consumed = true;
-                } else {
-                    break;
-                }
-            }
-            if batch_consumed {
-                continue;
-            }
+    /// Flushes, then forgets about, partitions which haven't received any
+    /// event for `ttl`.
+    ///
+    /// Each insertion resets the partition timer. Once it expires, the batch
+    /// of the partition is sent without waiting for its linger, and the state
+    /// kept for the partition is dropped once the batch is dispatched.
+    pub fn with_partition_ttl(mut self, ttl: Duration) -> Self {
+        self.partition_ttl = Some(ttl);
+        self
+    }
 
-            // Cleanup of in flight futures
-            if let Some(in_flight) = this.in_flight.as_mut() {
-                if in_flight.len() > this.partitions.len() {
-                    // There is at least one in flight future without a partition to check it
-                    // so we will do it here.
-                    let partitions = this.partitions;
+    /// Every `interval`, drops the state kept for the partitions which haven't
+    /// received any event for `idle_threshold` and have nothing left to send.
+    pub fn with_idle_gc(mut self, interval: Duration, idle_threshold: Duration) -> Self {
+        self.idle_gc = Some(IdleGc {
+            interval,
+            idle_threshold,
+            timer: Box::pin(sleep(interval)),
+            last_active: HashMap::new(),
+        });
+        self
+    }
+
+    /// Stops accepting events while the batches hold more than `max_bytes`,
+    /// estimating each event to weigh `avg_item_bytes`.
+    ///
+    /// Events are accepted again once the batches shrink below 80% of
+    /// `max_bytes`.
+    pub fn with_memory_limit(mut self, max_bytes: usize, avg_item_bytes: usize) -> Self {
+        self.memory_limit = Some(MemoryLimit {
+            max_bytes,
+            avg_item_bytes,
+            paused: false,
+        });
+        self
+    }
+
+    /// Reports batches once they are filled up to `ratio` of their maximum
+    /// size.
+    pub fn with_warn_threshold(mut self, ratio: f64) -> Self
+    where
+        K: fmt::Debug,
+    {
+        self.nearly_full = Some((ratio, |partition| Some(format!("{:?}", partition))));
+        self
+    }
+
+    /// Describes partitions in logs and internal events with `display`,
+    /// instead of their `Debug` representation.
+    pub fn with_key_display<F>(mut self, display: F) -> Self
+    where
+        F: Fn(&K) -> String + Send + Sync + 'static,
+    {
+        self.key_display = Some(Box::new(display));
+        self
+    }
+
+    /// Applies the batch settings received on `updates`, starting with the
+    /// current one.
+    ///
+    /// Updates are picked up on flush. Batches already started keep the
+    /// settings they were created with, only new ones use the updated settings.
+    pub fn with_config_updates(
+        mut self,
+        mut updates: watch::Receiver<PartitionBatchConfig>,
+    ) -> Self {
+        let config = *updates.borrow_and_update();
+        self.apply_config(config);
+        self.config_updates = Some((config, updates));
+        self
+    }
+
+    /// Hands the events which don't fit in their batch over to `overflow`,
+    /// instead of holding on to them until that batch has been sent.
+    ///
+    /// Events are only accepted while `overflow` is ready, so it applies
+    /// backpressure to this sink as well.
+    pub fn with_overflow_sink<O>(mut self, overflow: O) -> Self
+    where
+        O: Sink<EncodedEvent<B::Input>> + Send + 'static,
+        O::Error: Into<crate::Error>,
+    {
+        self.overflow = Some(Box::pin(overflow.sink_map_err(Into::into)));
+        self
+    }
+
+    /// Sorts the events of each batch by `key`, right before the batch is
+    /// sent. Events with equal keys keep their order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sink already orders its batches by timestamp, see
+    /// `with_timestamp_ordering`.
+    pub fn with_sort_key<F, O>(mut self, key: F) -> Self
+    where
+        B: Batch<Output = Vec<B::Input>>,
+        F: Fn(&B::Input) -> O + Send + Sync + 'static,
+        O: Ord,
+    {
+        assert!(
+            !self.timestamp_ordering,
+            "Batches are already ordered by timestamp."
+        );
+        self.sort_batch = Some(Box::new(move |items: &mut Vec<B::Input>| {
+            items.sort_by_key(|item| key(item))
+        }));
+        self
+    }
+
+    /// Delivers the events of each partition batch in timestamp order, where
+    /// `key` returns the event timestamp in epoch milliseconds.
+    ///
+    /// The events of a batch are drained from a heap ordered by timestamp
+    /// when the batch is sent, events with equal timestamps keeping their
+    /// order. Events arriving after their batch was sent land in the next
+    /// batch of the partition, so ordering is not guaranteed across batches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the batches are already sorted by `with_sort_key`.
+    pub fn with_timestamp_ordering<F>(mut self, key: F) -> Self
+    where
+        B: Batch<Output = Vec<B::Input>>,
+        F: Fn(&B::Input) -> i64 + Send + Sync + 'static,
+    {
+        assert!(
+            self.sort_batch.is_none(),
+            "Batches are already sorted by another key."
+        );
+        self.sort_batch = Some(Box::new(move |items: &mut Vec<B::Input>| {
+            let mut heap = items
+                .drain(..)
+                .enumerate()
+                .map(|(index, item)| {
+                    Reverse(Timestamped {
+                        timestamp: key(&item),
+                        index,
+                        item,
+                    })
+                })
+                .collect::<BinaryHeap<_>>();
+            while let Some(Reverse(timestamped)) = heap.pop() {
+                items.push(timestamped.item);
+            }
+        }));
+        self.timestamp_ordering = true;
+        self
+    }
+
+    /// Applies `normalize` to the partition key of each event before looking
+    /// up its batch, so that keys differing only slightly, say by their case,
+    /// share the same partition.
+    pub fn with_key_normalizer<F>(mut self, normalize: F) -> Self
+    where
+        F: Fn(K) -> K + Send + Sync + 'static,
+    {
+        self.normalize_key = Some(Box::new(normalize));
+        self
+    }
+
+    /// Dispatches the ready batches of the partitions with the highest
+    /// `priority` first, from 255 down to 0, so that they aren't held back
+    /// by less important ones while the service is busy.
+    pub fn with_priority<F>(mut self, priority: F) -> Self
+    where
+        F: Fn(&K) -> u8 + Send + Sync + 'static,
+    {
+        self.priority = Some(Box::new(priority));
+        self
+    }
+
+    /// Reports the batches which the service isn't ready to take within
+    /// `threshold` of being due, either full or past their linger.
+    pub fn with_latency_sla(mut self, threshold: Duration) -> Self {
+        self.latency_sla = Some(LatencySla {
+            threshold,
+            deadlines: HashMap::new(),
+        });
+        self
+    }
+
+    /// Flushes the overflow sink, or closes it once this sink is closing.
+    fn poll_overflow(&mut self, cx: &mut Context<'_>) -> Poll<crate::Result<()>> {
+        let closing = self.closing;
+        match self.overflow.as_mut() {
+            Some(overflow) if closing => overflow.as_mut().poll_close(cx),
+            Some(overflow) => overflow.as_mut().poll_flush(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    /// Runs a garbage collection pass of the idle partitions once the
+    /// interval elapsed.
+    fn poll_idle_gc(&mut self, cx: &mut Context<'_>) {
+        let gc = match self.idle_gc.as_mut() {
+            Some(gc) => gc,
+            None => return,
+        };
+        if gc.timer.poll_unpin(cx).is_pending() {
+            return;
+        }
+        let now = Instant::now();
+        gc.timer.as_mut().reset(now + gc.interval);
+        // Registers the task to be woken up for the next pass.
+        let _ = gc.timer.poll_unpin(cx);
+
+        let partitions = &mut self.partitions;
+        let lingers = &mut self.lingers;
+        let ttl_timers = &mut self.ttl_timers;
+        let in_flight = &mut self.in_flight;
+        let metric_labels = &mut self.metric_labels;
+        let leases = &mut self.leases;
+        let key_display = &self.key_display;
+        let nearly_full = &self.nearly_full;
+        let buffered = self.buffer.as_ref().map(|(partition, _)| partition);
+        let idle_threshold = gc.idle_threshold;
+        let before = gc.last_active.len();
+        gc.last_active.retain(|partition, last_active| {
+            if now.duration_since(*last_active) < idle_threshold
+                || buffered == Some(partition)
+                || partitions
+                    .get(partition)
+                    .map_or(false, |batch| !batch.is_empty())
+            {
+                return true;
+            }
+            if let Some(in_flight) = in_flight.as_mut() {
+                let done = in_flight
+                    .get_mut(partition)
+                    .map_or(true, |req| req.poll_unpin(cx).is_ready());
+                if !done {
+                    return true;
+                }
+                in_flight.remove(partition);
+            }
+
+            partitions.remove(partition);
+            lingers.remove(partition);
+            ttl_timers.remove(partition);
+            if let Some(leases) = leases.as_mut() {
+                leases.forget(partition, cx);
+            }
+            if let Some(labels) = metric_labels.as_mut() {
+                if let Some(label) = describe_partition(key_display, nearly_full, partition) {
+                    labels.forget(&label);
+                }
+            }
+            false
+        });
+        let partitions_removed = before - gc.last_active.len();
+
+        // Removing entries doesn't give their memory back.
+        gc.last_active.shrink_to_fit();
+        partitions.shrink_to_fit();
+        lingers.shrink_to_fit();
+        ttl_timers.shrink_to_fit();
+        if let Some(in_flight) = in_flight.as_mut() {
+            in_flight.shrink_to_fit();
+        }
+
+        emit!(&PartitionBatchSinkGcCycle { partitions_removed });
+    }
+
+    fn apply_config(&mut self, config: PartitionBatchConfig) {
+        self.timeout = config.timeout;
+        self.batch.set_size(config.max_events, config.max_bytes);
+    }
+
+    fn poll_config_updates(&mut self) {
+        let (current, updates) = match self.config_updates.as_mut() {
+            Some((current, updates)) => (current, updates),
+            None => return,
+        };
+        let changed = updates.changed().now_or_never();
+        if !matches!(changed, Some(Ok(()))) {
+            return;
+        }
+
+        let old = *current;
+        let new = *updates.borrow();
+        *current = new;
+        if old != new {
+            self.apply_config(new);
+            emit!(&PartitionBatchConfigUpdated { old, new });
+        }
+    }
+
+    /// Whether intake is paused because of the memory limit.
+    fn under_memory_pressure(&mut self) -> bool {
+        let limit = match self.memory_limit.as_mut() {
+            Some(limit) => limit,
+            None => return false,
+        };
+
+        let buffered_bytes = self.buffered_items * limit.avg_item_bytes;
+        if limit.paused {
+            limit.paused = buffered_bytes >= limit.max_bytes / 10 * 8;
+        } else if buffered_bytes > limit.max_bytes {
+            limit.paused = true;
+            emit!(&PartitionBatchSinkMemoryPressure {
+                buffered_bytes,
+                limit_bytes: limit.max_bytes,
+            });
+        }
+        limit.paused
+    }
+
+    /// Inserts `item` in the batch of its partition, returns whether the sink
+    /// needs to be flushed again: either a batch is now full or a new
+    /// partition, with a linger yet to be polled, was created.
+    fn insert(&mut self, item: EncodedEvent<B::Input>) -> bool {
+        let partition = self.partition_of(&item.item);
+
+        if let Some(gc) = self.idle_gc.as_mut() {
+            gc.last_active.insert(partition.clone(), Instant::now());
+        }
+
+        if let Some(ttl) = self.partition_ttl {
+            let deadline = Instant::now() + ttl;
+            match self.ttl_timers.get_mut(&partition) {
+                Some(timer) => timer.as_mut().reset(deadline),
+                None => {
+                    self.ttl_timers
+                        .insert(partition.clone(), Box::pin(sleep_until(deadline)));
+                }
+            }
+        }
+
+        let nearly_full = self.nearly_full;
+        let mut created = false;
+        let batch = loop {
+            if let Some(batch) = self.partitions.get_mut(&partition) {
+                break batch;
+            }
+
+            let batch = self.batch.fresh();
+            self.partitions.insert(partition.clone(), batch);
+
+            let jitter = self.linger_jitter.map_or(Duration::ZERO, |jitter| {
+                thread_rng().gen_range(Duration::ZERO..=jitter)
+            });
+            let delay = sleep(self.timeout + jitter);
+            self.lingers.insert(partition.clone(), Box::pin(delay));
+            created = true;
+        };
+
+        match batch.push(item) {
+            PushResult::Ok(full) => {
+                self.buffered_items += 1;
+                if let Some((threshold, _)) = nearly_full {
+                    if let Some(fill_ratio) = batch.nearly_full(threshold) {
+                        let partition = self.partition_label(&partition);
+                        let metric_label = match (&partition, self.metric_labels.as_mut()) {
+                            (Some(partition), Some(labels)) => Some(labels.label(partition)),
+                            _ => None,
+                        };
+                        emit!(&BatchSinkNearlyFull {
+                            fill_ratio,
+                            partition,
+                            metric_label,
+                        });
+                    }
+                }
+                full || created
+            }
+            PushResult::Overflow(item) => {
+                self.buffer = Some((partition, item));
+                true
+            }
+        }
+    }
+
+    fn lock_stats(&self) -> std::sync::MutexGuard<'_, PartitionStats<K>> {
+        self.stats.lock().expect("partition stats lock poisoned")
+    }
+
+    /// The partition `item` is batched in.
+    fn partition_of(&self, item: &B::Input) -> K {
+        let partition = item.partition();
+        match self.normalize_key.as_ref() {
+            Some(normalize_key) => normalize_key(partition),
+            None => partition,
+        }
+    }
+
+    /// Describes `partition` in internal events, if it is described at all.
+    fn partition_label(&self, partition: &K) -> Option<String> {
+        describe_partition(&self.key_display, &self.nearly_full, partition)
+    }
+
+    /// Remembers the task driving the sink, so that it is only woken up
+    /// again once there is something to flush.
+    fn register_waker(&mut self, cx: &Context<'_>) {
+        match &self.waker {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            _ => self.waker = Some(cx.waker().clone()),
+        }
+    }
+}
+
+impl<S, B, K, SL> Sink<EncodedEvent<B::Input>> for PartitionBatchSink<S, B, K, SL>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    type Error = crate::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Replayed events go before any new one.
+        self.replay_wal()?;
+
+        if let Some(overflow) = self.overflow.as_mut() {
+            match overflow.as_mut().poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                poll => return poll,
+            }
+        }
+
+        if self.buffer.is_some() {
+            match self.as_mut().poll_flush(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => {
+                    if self.buffer.is_some() {
+                        return Poll::Pending;
+                    }
+                }
+            }
+        }
+
+        if self.under_memory_pressure() {
+            // Flushing sends what it can and registers the task to be woken
+            // up once more batches can be sent.
+            if let Poll::Ready(Err(error)) = self.as_mut().poll_flush(cx) {
+                return Poll::Ready(Err(error));
+            }
+            if self.under_memory_pressure() {
+                return Poll::Pending;
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(
+        mut self: Pin<&mut Self>,
+        item: EncodedEvent<B::Input>,
+    ) -> Result<(), Self::Error> {
+        let partition = self.partition_of(&item.item);
+        if let Some(wal) = self.wal.as_mut() {
+            wal.append(&partition, &item)?;
+        }
+        self.lock_stats().record(partition);
+        let wake = self.insert(item);
+
+        // An overflowing event goes to the overflow sink right away, rather
+        // than waiting for its batch to be sent.
+        let this = &mut *self;
+        if let Some(overflow) = this.overflow.as_mut() {
+            if let Some((partition, item)) = this.buffer.take() {
+                if let Some(wal) = this.wal.as_mut() {
+                    wal.consume_last(&partition)?;
+                }
+                return overflow.as_mut().start_send(item);
+            }
+        }
+
+        // Wake up the task waiting on a flush, if any, only when there is
+        // something new for it to do.
+        if wake {
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_config_updates();
+        let overflow_flushed = match self.poll_overflow(cx) {
+            Poll::Ready(Ok(())) => true,
+            Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+            Poll::Pending => false,
+        };
+
+        self.poll_idle_gc(cx);
+        if let Some(leases) = self.leases.as_mut() {
+            let _ = leases.poll_released(cx);
+        }
+
+        loop {
+            self.replay_wal()?;
+
+            // Drop expired partitions once their batch has been dispatched.
+            let this = self.as_mut().project();
+            if !this.ttl_timers.is_empty() {
+                let partitions = this.partitions;
+                let lingers = this.lingers;
+                let in_flight = this.in_flight;
+                let latency_sla = this.latency_sla;
+                let metric_labels = this.metric_labels;
+                let leases = this.leases;
+                let key_display = &*this.key_display;
+                let nearly_full = &*this.nearly_full;
+                this.ttl_timers.retain(|partition, timer| {
+                    if timer.poll_unpin(cx).is_pending()
+                        || partitions
+                            .get(partition)
+                            .map_or(false, |batch| !batch.is_empty())
+                    {
+                        return true;
+                    }
+
+                    partitions.remove(partition);
+                    lingers.remove(partition);
+                    if let Some(leases) = leases.as_mut() {
+                        leases.forget(partition, cx);
+                    }
+                    if let Some(labels) = metric_labels.as_mut() {
+                        if let Some(label) = describe_partition(key_display, nearly_full, partition)
+                        {
+                            labels.forget(&label);
+                        }
+                    }
+                    if let Some(latency_sla) = latency_sla.as_mut() {
+                        latency_sla.deadlines.remove(partition);
+                    }
+                    if let Some(in_flight) = in_flight.as_mut() {
+                        let done = in_flight
+                            .get_mut(partition)
+                            .map_or(false, |req| req.poll_unpin(cx).is_ready());
+                        if done {
+                            in_flight.remove(partition);
+                        }
+                    }
+                    false
+                });
+            }
+
+            let coalesced = match self.poll_coalesced(cx) {
+                Poll::Ready(Ok(())) => true,
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => false,
+            };
+
+            // Poll inner service while not ready, if we don't have buffer or any batch.
+            if self.buffer.is_none() && self.partitions.is_empty() && coalesced {
+                if self.service.poll_complete(cx).is_pending() {
+                    self.register_waker(cx);
+                    return Poll::Pending;
+                }
+                let closing = self.closing;
+                if let Some(leases) = self.leases.as_mut() {
+                    if closing {
+                        leases.forget_all(cx);
+                    }
+                    if leases.poll_released(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                }
+                self.waker = None;
+                // The overflow sink wakes the task up once it has been flushed.
+                if !overflow_flushed {
+                    return Poll::Pending;
+                }
+                return Poll::Ready(Ok(()));
+            }
+
+            // Try send batches.
+            let this = self.as_mut().project();
+            let mut partitions_ready = vec![];
+            for (partition, batch) in this.partitions.iter() {
+                if ((*this.closing && !batch.is_empty())
+                    || batch.was_full()
+                    || (!batch.is_empty()
+                        && this
+                            .ttl_timers
+                            .get_mut(partition)
+                            .map_or(false, |timer| timer.poll_unpin(cx).is_ready()))
+                    || matches!(
+                        this.lingers
+                            .get_mut(partition)
+                            .expect("linger should exists for poll_flush")
+                            .poll_unpin(cx),
+                        Poll::Ready(())
+                    ))
+                    && this
+                        .in_flight
+                        .as_mut()
+                        .and_then(|map| map.get_mut(partition))
+                        .map(|req| matches!(req.poll_unpin(cx), Poll::Ready(())))
+                        .unwrap_or(true)
+                    && this.dependencies.as_ref().map_or(true, |dependencies| {
+                        dependencies.are_met(partition, |dependency| {
+                            !*this.closing
+                                || this.partitions.contains_key(dependency)
+                                || matches!(this.buffer, Some((buffered, _)) if buffered == dependency)
+                        })
+                    })
+                    && this
+                        .leases
+                        .as_mut()
+                        .map_or(true, |leases| leases.poll_acquire(partition, cx))
+                {
+                    partitions_ready.push(partition.clone());
+                }
+            }
+            if let Some(latency_sla) = this.latency_sla.as_mut() {
+                for partition in &partitions_ready {
+                    latency_sla.start(partition);
+                }
+            }
+            if let Some(priority) = this.priority.as_ref() {
+                partitions_ready.sort_by_key(|partition| std::cmp::Reverse(priority(partition)));
+            }
+
+            // Hold the batches which may be sent together, the next pass sends
+            // them once they are due.
+            if let Some(coalesce) = this.coalesce.as_mut() {
+                let (mut held, mut fill_ratio) = coalesce.held();
+                let mut selected = Vec::new();
+                for partition in &partitions_ready {
+                    if coalesce
+                        .max_batches
+                        .map_or(false, |max_batches| held >= max_batches)
+                    {
+                        break;
+                    }
+                    let batch = &this.partitions[partition];
+                    if let Some(min_items) = coalesce.min_items {
+                        if batch.num_items() >= min_items {
+                            continue;
+                        }
+                        let ratio = batch.fill_ratio().unwrap_or_default();
+                        if fill_ratio + ratio > 1.0 {
+                            continue;
+                        }
+                        fill_ratio += ratio;
+                    }
+                    held += 1;
+                    selected.push(partition.clone());
+                }
+                // Without a window, only batches ready at once are coalesced.
+                if selected.len() > 1 || (!selected.is_empty() && coalesce.window.is_some()) {
+                    match this.service.poll_ready(cx) {
+                        Poll::Ready(Ok(())) => {
+                            for partition in selected {
+                                let batch = this.partitions.remove(&partition).unwrap();
+                                this.lingers.remove(&partition);
+                                consume_wal(this.wal, this.buffer, &partition)?;
+
+                                let batch_size = batch.num_items();
+                                *this.buffered_items -= batch_size;
+                                if let Some(latency_sla) = this.latency_sla.as_mut() {
+                                    latency_sla.finish(&partition, batch_size);
+                                }
+                                let fill_ratio = batch.fill_ratio().unwrap_or_default();
+                                coalesce.hold(partition, batch.finish(), batch_size, fill_ratio);
+                            }
+                            continue;
+                        }
+                        Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                        Poll::Pending => {}
+                    }
+                }
+            }
+
+            let mut batch_consumed = false;
+            for partition in partitions_ready.iter() {
+                let service_ready = match this.service.poll_ready(cx) {
+                    Poll::Ready(Ok(())) => true,
+                    Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                    Poll::Pending => false,
+                };
+                if service_ready {
+                    trace!("Service ready; Sending batch.");
+
+                    let batch = this.partitions.remove(partition).unwrap();
+                    this.lingers.remove(partition);
+                    consume_wal(this.wal, this.buffer, partition)?;
+
+                    let batch_size = batch.num_items();
+                    *this.buffered_items -= batch_size;
+                    if let Some(latency_sla) = this.latency_sla.as_mut() {
+                        latency_sla.finish(partition, batch_size);
+                    }
+                    let mut batch = batch.finish();
+                    if let Some(sort_batch) = this.sort_batch.as_ref() {
+                        sort_batch(&mut batch.items);
+                    }
+                    let label = this.service.shadow.as_ref().and_then(|_| {
+                        describe_partition(this.key_display, this.nearly_full, partition)
+                    });
+                    let mut request = this
+                        .service
+                        .call_partition(partition, label, batch, batch_size);
+                    if let Some(leases) = this.leases.as_ref() {
+                        request = leases.release_after(vec![partition.clone()], request);
+                    }
+                    let future = tokio::spawn(request);
+                    if let Some(dependencies) = this.dependencies.as_mut() {
+                        dependencies.dispatched(partition);
+                    }
+
+                    if let Some(map) = this.in_flight.as_mut() {
+                        map.insert(partition.clone(), future.map(|_| ()).fuse().boxed());
+                    }
+
+                    batch_consumed = true;
+                } else {
+                    break;
+                }
+            }
+            if batch_consumed {
+                continue;
+            }
+
+            // Cleanup of in flight futures
+            if let Some(in_flight) = this.in_flight.as_mut() {
+                if in_flight.len() > this.partitions.len() {
+                    // There is at least one in flight future without a partition to check it
+                    // so we will do it here.
+                    let partitions = this.partitions;
                     in_flight.retain(|partition, req| {
                         partitions.contains_key(partition) || req.poll_unpin(cx).is_pending()
                     });
@@ -399,7 +1608,7 @@ where
                 if self.partitions.contains_key(&partition) {
                     self.buffer = Some((partition, item));
                 } else {
-                    self.as_mut().start_send(item)?;
+                    self.insert(item);
 
                     if self.buffer.is_some() {
                         unreachable!("Empty buffer overflowed.");
@@ -409,47 +1618,646 @@ where
                 }
             }
 
-            // Only poll inner service and return `Poll::Pending` anyway.
-            ready!(self.service.poll_complete(cx));
-            return Poll::Pending;
+            // Only poll inner service and return `Poll::Pending` anyway.
+            let _ = self.service.poll_complete(cx);
+            self.register_waker(cx);
+            return Poll::Pending;
+        }
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        trace!("Closing partition batch sink.");
+        self.closing = true;
+        self.poll_flush(cx)
+    }
+}
+
+impl<S, B, K, SL> fmt::Debug for PartitionBatchSink<S, B, K, SL>
+where
+    S: Service<B::Output> + fmt::Debug,
+    B: Batch + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PartitionBatchSink")
+            .field("service", &self.service)
+            .field("batch", &self.batch)
+            .field("timeout", &self.timeout)
+            .field("linger_jitter", &self.linger_jitter)
+            .finish()
+    }
+}
+
+// === FanOutPartitionBatchSink ===
+
+/// A sink that mirrors every event into two `PartitionBatchSink`s, each
+/// driving its own service.
+///
+/// # Acking
+///
+/// An event is only acked once both sinks have acked it, so the slower of the
+/// two services determines the acking progress.
+#[pin_project]
+pub struct FanOutPartitionBatchSink<S1, S2, B, K>
+where
+    B: Batch,
+    S1: Service<B::Output>,
+    S2: Service<B::Output>,
+{
+    #[pin]
+    primary: PartitionBatchSink<S1, B, K, StdServiceLogic<S1::Response>>,
+    #[pin]
+    secondary: PartitionBatchSink<S2, B, K, StdServiceLogic<S2::Response>>,
+    acks: Arc<FanOutAcks>,
+}
+
+impl<S1, S2, B, K> FanOutPartitionBatchSink<S1, S2, B, K>
+where
+    B: Batch,
+    B::Input: Partition<K> + Clone,
+    K: Hash + Eq + Clone + Send + 'static,
+    S1: Service<B::Output>,
+    S1::Future: Send + 'static,
+    S1::Error: Into<crate::Error> + Send + 'static,
+    S1::Response: Response + Send + 'static,
+    S2: Service<B::Output>,
+    S2::Future: Send + 'static,
+    S2::Error: Into<crate::Error> + Send + 'static,
+    S2::Response: Response + Send + 'static,
+{
+    pub fn new(primary: S1, secondary: S2, batch: B, timeout: Duration, acker: Acker) -> Self {
+        let acks = Arc::new(FanOutAcks::new(acker));
+        let secondary_batch = batch.fresh();
+        let primary = PartitionBatchSink::new(primary, batch, timeout, acks.acker(0));
+        let secondary = PartitionBatchSink::new(secondary, secondary_batch, timeout, acks.acker(1));
+
+        Self {
+            primary,
+            secondary,
+            acks,
+        }
+    }
+}
+
+impl<S1, S2, B, K> Sink<EncodedEvent<B::Input>> for FanOutPartitionBatchSink<S1, S2, B, K>
+where
+    B: Batch,
+    B::Input: Partition<K> + Clone,
+    K: Hash + Eq + Clone + Send + 'static,
+    S1: Service<B::Output>,
+    S1::Future: Send + 'static,
+    S1::Error: Into<crate::Error> + Send + 'static,
+    S1::Response: Response + Send + 'static,
+    S2: Service<B::Output>,
+    S2::Future: Send + 'static,
+    S2::Error: Into<crate::Error> + Send + 'static,
+    S2::Response: Response + Send + 'static,
+{
+    type Error = crate::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.project();
+        let primary = this.primary.poll_ready(cx)?;
+        let secondary = this.secondary.poll_ready(cx)?;
+        if primary.is_ready() && secondary.is_ready() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: EncodedEvent<B::Input>) -> Result<(), Self::Error> {
+        let this = self.project();
+        this.acks.push();
+        let mirrored = EncodedEvent {
+            item: item.item.clone(),
+            finalizers: item.finalizers.clone(),
+            byte_size: item.byte_size,
+        };
+        this.primary.start_send(item)?;
+        this.secondary.start_send(mirrored)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.project();
+        let primary = this.primary.poll_flush(cx)?;
+        let secondary = this.secondary.poll_flush(cx)?;
+        if primary.is_ready() && secondary.is_ready() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.project();
+        let primary = this.primary.poll_close(cx)?;
+        let secondary = this.secondary.poll_close(cx)?;
+        if primary.is_ready() && secondary.is_ready() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<S1, S2, B, K> fmt::Debug for FanOutPartitionBatchSink<S1, S2, B, K>
+where
+    S1: Service<B::Output> + fmt::Debug,
+    S2: Service<B::Output> + fmt::Debug,
+    B: Batch + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FanOutPartitionBatchSink")
+            .field("primary", &self.primary)
+            .field("secondary", &self.secondary)
+            .finish()
+    }
+}
+
+/// Tracks how many of the fanned out sinks have acked each event, acking
+/// events with the upstream `Acker` once both sinks did.
+struct FanOutAcks {
+    acker: Acker,
+    state: Mutex<FanOutAcksState>,
+}
+
+struct FanOutAcksState {
+    /// Number of sinks that acked each pending event, oldest first.
+    counters: VecDeque<usize>,
+    /// Position of the next event to be acked by each sink in `counters`.
+    cursors: [usize; 2],
+}
+
+impl FanOutAcks {
+    fn new(acker: Acker) -> Self {
+        Self {
+            acker,
+            state: Mutex::new(FanOutAcksState {
+                counters: VecDeque::new(),
+                cursors: [0, 0],
+            }),
+        }
+    }
+
+    fn acker(self: &Arc<Self>, sink: usize) -> Acker {
+        let acks = Arc::clone(self);
+        Acker::segmented(move |num| acks.ack(sink, num))
+    }
+
+    fn push(&self) {
+        let mut state = self.state.lock().expect("fan out acks lock poisoned");
+        state.counters.push_back(0);
+    }
+
+    fn ack(&self, sink: usize, num: usize) {
+        let mut state = self.state.lock().expect("fan out acks lock poisoned");
+        let state = &mut *state;
+
+        let start = state.cursors[sink];
+        let end = (start + num).min(state.counters.len());
+        for counter in state.counters.range_mut(start..end) {
+            *counter += 1;
+        }
+        state.cursors[sink] = end;
+
+        let sinks = state.cursors.len();
+        let mut num_to_ack = 0;
+        while state.counters.front() == Some(&sinks) {
+            state.counters.pop_front();
+            num_to_ack += 1;
+        }
+        if num_to_ack > 0 {
+            for cursor in &mut state.cursors {
+                *cursor -= num_to_ack;
+            }
+            self.acker.ack(num_to_ack);
+        }
+    }
+}
+
+// === MultiPartitionBatchSink ===
+
+/// A sink that inserts every event into the batch of each of its partitions,
+/// the service receives the batches tagged with their partition key.
+///
+/// # Acking
+///
+/// An event is only acked once the batches holding all of its copies were
+/// acked. Its finalizers are shared by the copies, so its status is only
+/// updated once the last of them is dropped.
+///
+/// The copies are handed to the inner sink one at a time, waiting for it to
+/// be ready again in between, and the sink isn't ready for another event
+/// until all of them were.
+#[pin_project]
+pub struct MultiPartitionBatchSink<S, B, K>
+where
+    B: Batch,
+    S: Service<PartitionInnerBuffer<B::Output, K>>,
+{
+    #[pin]
+    inner: PartitionBatchSink<S, PartitionBuffer<B, K>, K, StdServiceLogic<S::Response>>,
+    acks: Arc<MultiPartitionAcks>,
+    /// Copies of the last event which the inner sink didn't take yet.
+    pending: VecDeque<EncodedEvent<PartitionInnerBuffer<B::Input, K>>>,
+}
+
+impl<S, B, K> MultiPartitionBatchSink<S, B, K>
+where
+    B: Batch,
+    B::Input: MultiPartition<K> + Clone,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<PartitionInnerBuffer<B::Output, K>>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+{
+    pub fn new(service: S, batch: B, timeout: Duration, acker: Acker) -> Self {
+        let acks = Arc::new(MultiPartitionAcks::new(acker));
+        let inner =
+            PartitionBatchSink::new(service, PartitionBuffer::new(batch), timeout, acks.acker());
+
+        Self {
+            inner,
+            acks,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl<S, B, K> Sink<EncodedEvent<B::Input>> for MultiPartitionBatchSink<S, B, K>
+where
+    B: Batch,
+    B::Input: MultiPartition<K> + Clone,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<PartitionInnerBuffer<B::Output, K>>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+{
+    type Error = crate::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.as_mut().poll_send_pending(cx))?;
+        self.project().inner.poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: EncodedEvent<B::Input>) -> Result<(), Self::Error> {
+        let this = self.project();
+        let mut partitions = item.item.partitions();
+        this.acks.push(partitions.len());
+
+        // The last partition takes the event itself, the others get copies.
+        if let Some(last) = partitions.pop() {
+            for key in partitions {
+                this.pending.push_back(EncodedEvent {
+                    item: PartitionInnerBuffer::new(item.item.clone(), key),
+                    finalizers: item.finalizers.clone(),
+                    byte_size: item.byte_size,
+                });
+            }
+            this.pending.push_back(EncodedEvent {
+                item: PartitionInnerBuffer::new(item.item, last),
+                finalizers: item.finalizers,
+                byte_size: item.byte_size,
+            });
+        }
+
+        // The inner sink is ready for the first copy, since this sink was.
+        match this.pending.pop_front() {
+            Some(copy) => this.inner.start_send(copy),
+            None => Ok(()),
         }
     }
 
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.as_mut().poll_send_pending(cx))?;
+        self.project().inner.poll_flush(cx)
+    }
+
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        trace!("Closing partition batch sink.");
-        self.closing = true;
-        self.poll_flush(cx)
+        ready!(self.as_mut().poll_send_pending(cx))?;
+        self.project().inner.poll_close(cx)
     }
 }
 
-impl<S, B, K, SL> fmt::Debug for PartitionBatchSink<S, B, K, SL>
+impl<S, B, K> MultiPartitionBatchSink<S, B, K>
 where
-    S: Service<B::Output> + fmt::Debug,
+    B: Batch,
+    B::Input: MultiPartition<K> + Clone,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<PartitionInnerBuffer<B::Output, K>>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+{
+    /// Hands the pending copies to the inner sink as soon as it is ready for
+    /// each of them.
+    fn poll_send_pending(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<crate::Result<()>> {
+        let mut this = self.project();
+        while !this.pending.is_empty() {
+            ready!(this.inner.as_mut().poll_ready(cx))?;
+            if let Some(copy) = this.pending.pop_front() {
+                this.inner.as_mut().start_send(copy)?;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S, B, K> fmt::Debug for MultiPartitionBatchSink<S, B, K>
+where
+    S: Service<PartitionInnerBuffer<B::Output, K>> + fmt::Debug,
     B: Batch + fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("PartitionBatchSink")
-            .field("service", &self.service)
-            .field("batch", &self.batch)
-            .field("timeout", &self.timeout)
+        f.debug_struct("MultiPartitionBatchSink")
+            .field("inner", &self.inner)
             .finish()
     }
 }
 
+/// Converts the acks of event copies into acks of the original events, an
+/// event being acked with the upstream `Acker` once all of its copies were.
+struct MultiPartitionAcks {
+    acker: Acker,
+    state: Mutex<MultiPartitionAcksState>,
+}
+
+struct MultiPartitionAcksState {
+    /// Number of copies of each pending event, oldest first.
+    copies: VecDeque<usize>,
+    /// Number of copies acked which weren't attributed to an event yet.
+    acked: usize,
+}
+
+impl MultiPartitionAcks {
+    fn new(acker: Acker) -> Self {
+        Self {
+            acker,
+            state: Mutex::new(MultiPartitionAcksState {
+                copies: VecDeque::new(),
+                acked: 0,
+            }),
+        }
+    }
+
+    fn acker(self: &Arc<Self>) -> Acker {
+        let acks = Arc::clone(self);
+        Acker::segmented(move |num| acks.ack(num))
+    }
+
+    /// Tracks a new event, events without any copy are acked as soon as the
+    /// events before them are.
+    fn push(&self, copies: usize) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("multi partition acks lock poisoned");
+        state.copies.push_back(copies);
+        self.settle(&mut state);
+    }
+
+    fn ack(&self, num: usize) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("multi partition acks lock poisoned");
+        state.acked += num;
+        self.settle(&mut state);
+    }
+
+    fn settle(&self, state: &mut MultiPartitionAcksState) {
+        let mut num_to_ack = 0;
+        while let Some(&copies) = state.copies.front() {
+            if copies > state.acked {
+                break;
+            }
+            state.acked -= copies;
+            state.copies.pop_front();
+            num_to_ack += 1;
+        }
+        if num_to_ack > 0 {
+            self.acker.ack(num_to_ack);
+        }
+    }
+}
+
 // === ServiceSink ===
 
-struct ServiceSink<S, Request, SL> {
+const DEFAULT_POLL_READY_WARN_THRESHOLD: Duration = Duration::from_secs(5);
+const DEFAULT_DEBUG_LOG_MAX_BYTES: usize = 1024;
+const WARMUP_ATTEMPTS: usize = 3;
+const WARMUP_BACKOFF: Duration = Duration::from_secs(1);
+const RETRY_BACKOFF: ExponentialBackoff = ExponentialBackoff::from_millis(2)
+    .factor(250)
+    .max_delay(Duration::from_secs(60));
+
+struct ServiceSink<S, Request, SL>
+where
+    S: Service<Request>,
+{
     service: S,
+    /// Completions of the dispatched requests, as `(seqno, event count)`.
     in_flight: FuturesUnordered<oneshot::Receiver<(usize, usize)>>,
     acker: Acker,
-    seq_head: usize,
-    seq_tail: usize,
-    pending_acks: HashMap<usize, usize>,
+    /// The `Acker` counts events, so the requests are acked in the order
+    /// they were dispatched, whichever partition they are for.
+    sequence: SequenceState,
+    bulkheads: Option<Bulkheads>,
     next_request_id: usize,
     logic: SL,
+    poll_ready_warn_threshold: Duration,
+    pending_since: Option<Instant>,
+    stall_timer: Option<Pin<Box<Sleep>>>,
+    concurrent_dispatch: Option<ConcurrentDispatch>,
+    debug_logging: Option<(tracing::Level, fn(&Request) -> String)>,
+    debug_log_max_bytes: usize,
+    /// Called with every response the service returns, before its status is
+    /// evaluated.
+    inspect_response: Option<Arc<dyn Fn(&S::Response) + Send + Sync>>,
+    /// Feeds the task writing the audit records to the audit sink.
+    audit_log: Option<mpsc::Sender<AuditRecord>>,
+    shadow: Option<Arc<dyn Fn(&Request) -> ShadowCompare<S::Response> + Send + Sync>>,
+    failover: Option<RegionalFailover<Request, S::Response>>,
+    /// Number of requests which haven't completed yet, shared with the
+    /// keepalive task.
+    active_requests: Arc<AtomicUsize>,
+    keepalive: Option<KeepaliveTask>,
+    retry: Option<Retry<Request, S::Response>>,
+    /// When the sink was built, to report how long it took to dispatch its
+    /// first request.
+    created_at: Instant,
+    first_call_done: bool,
     _pd: PhantomData<Request>,
 }
 
+/// Requests acked in the order they were dispatched, holding what is acked
+/// for each of the completed requests, by default its number of events.
+#[derive(Debug)]
+struct SequenceState<T = usize> {
+    seq_head: usize,
+    seq_tail: usize,
+    pending_acks: HashMap<usize, T>,
+}
+
+impl<T> Default for SequenceState<T> {
+    fn default() -> Self {
+        Self {
+            seq_head: 0,
+            seq_tail: 0,
+            pending_acks: HashMap::new(),
+        }
+    }
+}
+
+impl<T> SequenceState<T> {
+    fn next_seqno(&mut self) -> usize {
+        let seqno = self.seq_head;
+        self.seq_head += 1;
+        seqno
+    }
+
+    /// Removes the completed requests which no longer wait on older ones, in
+    /// order.
+    fn take_completed(&mut self) -> Vec<T> {
+        let mut completed = Vec::new();
+        while let Some(ack) = self.pending_acks.remove(&self.seq_tail) {
+            completed.push(ack);
+            self.seq_tail += 1;
+        }
+        completed
+    }
+
+    const fn is_settled(&self) -> bool {
+        self.seq_head == self.seq_tail
+    }
+}
+
+impl SequenceState {
+    /// Removes the completed requests which no longer wait on older ones,
+    /// returning their number of events.
+    fn take_acks(&mut self) -> usize {
+        self.take_completed().into_iter().sum()
+    }
+}
+
+/// Sends a request again, as many times as it is called.
+type Resend<R> = Box<dyn FnMut() -> BoxFuture<'static, crate::Result<R>> + Send>;
+
+/// Resends the requests which failed, for as long as `budget` allows it.
+struct Retry<Request, R> {
+    budget: Arc<RetryBudget>,
+    backoff: ExponentialBackoff,
+    /// Keeps what is needed to resend a request, before it is first sent.
+    ///
+    /// The service takes the request by value, so this clones every request
+    /// up front, whether it ends up failing or not. The copy is dropped once
+    /// the request completes.
+    prepare: Arc<dyn Fn(&Request) -> Resend<R> + Send + Sync>,
+}
+
+/// A token bucket bounding the rate of the retries of the `ServiceSink`s
+/// sharing it, so that they don't all hammer a recovering service at once.
+#[derive(Debug)]
+pub struct RetryBudget {
+    max_retries_per_second: u32,
+    state: Mutex<RetryBudgetState>,
+}
+
+#[derive(Debug)]
+struct RetryBudgetState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RetryBudget {
+    pub fn new(max_retries_per_second: u32) -> Self {
+        Self {
+            max_retries_per_second,
+            state: Mutex::new(RetryBudgetState {
+                tokens: f64::from(max_retries_per_second),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a retry out of the budget, returning whether there was one left.
+    pub fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().expect("retry budget lock poisoned");
+        let now = Instant::now();
+        let rate = f64::from(self.max_retries_per_second);
+        let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * rate).min(rate);
+        state.refilled_at = now;
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The task sending the keepalive heartbeats, stopped along with the sink.
+struct KeepaliveTask(JoinHandle<()>);
+
+impl Drop for KeepaliveTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Point in time view of the acking state of a `ServiceSink`, for debugging.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ServiceSinkSnapshot {
+    /// Sequence number of the next request.
+    pub seq_head: usize,
+    /// Sequence number of the oldest request which hasn't been acked yet.
+    pub seq_tail: usize,
+    pub in_flight_count: usize,
+    /// Completed requests waiting on older ones to be acked, as
+    /// `(seqno, event count)` pairs ordered by sequence number.
+    pub pending_acks: Vec<(usize, usize)>,
+}
+
+/// Bounds the number of in flight requests of a `ServiceSink`.
+struct ConcurrentDispatch {
+    semaphore: Arc<Semaphore>,
+    acquiring: Option<BoxFuture<'static, OwnedSemaphorePermit>>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl ConcurrentDispatch {
+    fn new(max_concurrent: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            acquiring: None,
+            permit: None,
+        }
+    }
+
+    fn poll_acquire(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.permit.is_none() {
+            let semaphore = Arc::clone(&self.semaphore);
+            let acquiring = self.acquiring.get_or_insert_with(|| {
+                semaphore
+                    .acquire_owned()
+                    .map(|permit| permit.expect("Semaphore has been closed"))
+                    .boxed()
+            });
+            match acquiring.poll_unpin(cx) {
+                Poll::Ready(permit) => {
+                    self.acquiring = None;
+                    self.permit = Some(permit);
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
 impl<S, Request> ServiceSink<S, Request, StdServiceLogic<S::Response>>
 where
     S: Service<Request>,
@@ -476,95 +2284,603 @@ where
             service,
             in_flight: FuturesUnordered::new(),
             acker,
-            seq_head: 0,
-            seq_tail: 0,
-            pending_acks: HashMap::new(),
+            sequence: SequenceState::default(),
+            bulkheads: None,
             next_request_id: 0,
             logic,
+            poll_ready_warn_threshold: DEFAULT_POLL_READY_WARN_THRESHOLD,
+            pending_since: None,
+            stall_timer: None,
+            concurrent_dispatch: None,
+            debug_logging: None,
+            debug_log_max_bytes: DEFAULT_DEBUG_LOG_MAX_BYTES,
+            inspect_response: None,
+            audit_log: None,
+            shadow: None,
+            failover: None,
+            active_requests: Arc::new(AtomicUsize::new(0)),
+            keepalive: None,
+            retry: None,
+            created_at: Instant::now(),
+            first_call_done: false,
             _pd: PhantomData,
         }
     }
 
+    fn with_concurrent_dispatch(mut self, max_concurrent: usize) -> Self {
+        self.concurrent_dispatch = Some(ConcurrentDispatch::new(max_concurrent));
+        self
+    }
+
+    fn with_debug_logging(mut self, level: tracing::Level) -> Self
+    where
+        Request: fmt::Debug,
+    {
+        self.debug_logging = Some((level, |request| format!("{:?}", request)));
+        self
+    }
+
+    fn with_debug_logging_bytes(mut self, level: tracing::Level) -> Self
+    where
+        Request: AsRef<[u8]>,
+    {
+        self.debug_logging = Some((level, |request| {
+            String::from_utf8_lossy(request.as_ref()).into_owned()
+        }));
+        self
+    }
+
+    fn with_response_inspector<F>(mut self, inspect: F) -> Self
+    where
+        F: Fn(&S::Response) + Send + Sync + 'static,
+    {
+        self.inspect_response = Some(Arc::new(inspect));
+        self
+    }
+
+    /// Polls a clone of the service until it is ready in a separate task, to
+    /// establish its connections before the first batch is sent.
+    fn with_warmup(self) -> Self
+    where
+        S: Clone + Send + 'static,
+        Request: 'static,
+    {
+        tokio::spawn(warm_up(self.service.clone()));
+        self
+    }
+
+    /// Sends `heartbeat` through a clone of the service every `interval`
+    /// while no request is in flight, to keep idle connections open.
+    ///
+    /// Failed heartbeats are reported but don't affect the sink.
+    fn with_keepalive(mut self, interval: Duration, heartbeat: Request) -> Self
+    where
+        S: Clone + Send + 'static,
+        Request: Clone + Send + 'static,
+    {
+        let service = self.service.clone();
+        let task = keepalive(
+            service,
+            heartbeat,
+            interval,
+            self.logic.clone(),
+            Arc::clone(&self.active_requests),
+        );
+        self.keepalive = Some(KeepaliveTask(tokio::spawn(task)));
+        self
+    }
+
+    /// Resends the requests which failed through a clone of the service, as
+    /// long as `budget` has retries left. Requests which can't be retried
+    /// are marked as errored.
+    fn with_retry_budget(mut self, budget: Arc<RetryBudget>) -> Self
+    where
+        S: Clone + Send + 'static,
+        Request: Clone + Send + 'static,
+    {
+        let service = Mutex::new(self.service.clone());
+        self.retry = Some(Retry {
+            budget,
+            backoff: RETRY_BACKOFF,
+            prepare: Arc::new(move |request: &Request| -> Resend<S::Response> {
+                let service = service.lock().expect("retry service lock poisoned").clone();
+                let request = request.clone();
+                Box::new(move || {
+                    tower::ServiceExt::oneshot(service.clone(), request.clone())
+                        .err_into()
+                        .boxed()
+                })
+            }),
+        });
+        self
+    }
+
+    fn snapshot(&self) -> ServiceSinkSnapshot {
+        let mut pending_acks: Vec<_> = self
+            .sequence
+            .pending_acks
+            .iter()
+            .map(|(&seqno, &count)| (seqno, count))
+            .collect();
+        pending_acks.sort_unstable();
+        ServiceSinkSnapshot {
+            seq_head: self.sequence.seq_head,
+            seq_tail: self.sequence.seq_tail,
+            in_flight_count: self.in_flight.len(),
+            pending_acks,
+        }
+    }
+
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<crate::Result<()>> {
-        self.service.poll_ready(cx).map_err(Into::into)
+        if let Some(dispatch) = self.concurrent_dispatch.as_mut() {
+            if dispatch.poll_acquire(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+
+        // The primary service is only polled if the next request goes to it,
+        // so a stalled primary region does not hold back the secondary one.
+        let route = match self.failover.as_mut() {
+            Some(failover) => match failover.route {
+                Some(route) => route,
+                None => *failover
+                    .route
+                    .insert(lock_failover(&failover.state).route(failover.probe_interval)),
+            },
+            None => return self.poll_service_ready(cx),
+        };
+        match route {
+            (RegionSelector::Secondary, _) => Poll::Ready(Ok(())),
+            (RegionSelector::Primary, false) => self.poll_service_ready(cx),
+            (RegionSelector::Primary, true) => match self.poll_service_ready(cx) {
+                Poll::Ready(Ok(())) => Poll::Ready(Ok(())),
+                _ => {
+                    // A primary region that is not ready fails its probe.
+                    self.pending_since = None;
+                    self.stall_timer = None;
+                    let failover = self.failover.as_mut().expect("failover is set");
+                    lock_failover(&failover.state).record(route, false, failover.health_threshold);
+                    failover.route = Some((RegionSelector::Secondary, false));
+                    Poll::Ready(Ok(()))
+                }
+            },
+        }
+    }
+
+    fn poll_service_ready(&mut self, cx: &mut Context<'_>) -> Poll<crate::Result<()>> {
+        let poll = self.service.poll_ready(cx).map_err(Into::into);
+
+        if poll.is_pending() {
+            let pending_since = match self.pending_since {
+                Some(pending_since) => pending_since,
+                None => {
+                    // The timer makes sure we get polled again to report the stall.
+                    self.stall_timer = Some(Box::pin(sleep(self.poll_ready_warn_threshold)));
+                    *self.pending_since.insert(Instant::now())
+                }
+            };
+            if let Some(timer) = self.stall_timer.as_mut() {
+                if timer.poll_unpin(cx).is_ready() {
+                    self.stall_timer = None;
+                    emit!(&ServicePollReadyStalled {
+                        duration_ms: pending_since.elapsed().as_millis() as u64,
+                    });
+                }
+            }
+        } else {
+            self.pending_since = None;
+            self.stall_timer = None;
+        }
+
+        poll
+    }
+
+    fn call(&mut self, batch: EncodedBatch<Request>, batch_size: usize) -> BoxFuture<'static, ()> {
+        self.dispatch(None, None, batch, batch_size)
+    }
+
+    /// Sends the batch of a single partition, reporting its events
+    /// independently of the other partitions if bulkheads are enabled. The
+    /// partition is described as `label` in the mismatches with the shadow
+    /// service.
+    fn call_partition<K: Hash>(
+        &mut self,
+        partition: &K,
+        label: Option<String>,
+        batch: EncodedBatch<Request>,
+        batch_size: usize,
+    ) -> BoxFuture<'static, ()> {
+        let lane = self.bulkheads.as_ref().map(|_| {
+            let mut hasher = DefaultHasher::new();
+            partition.hash(&mut hasher);
+            hasher.finish()
+        });
+        self.dispatch(lane, label, batch, batch_size)
+    }
+
+    /// Sends `batch`, described as `label` in the mismatches with the shadow
+    /// service. The events are reported in the order of the bulkhead `lane`,
+    /// if any, or else as soon as the request completes.
+    fn dispatch(
+        &mut self,
+        lane: Option<u64>,
+        label: Option<String>,
+        batch: EncodedBatch<Request>,
+        batch_size: usize,
+    ) -> BoxFuture<'static, ()> {
+        let EncodedBatch {
+            items,
+            finalizers,
+            count,
+            byte_size,
+            wire_size,
+        } = batch;
+        let seqno = self.sequence.next_seqno();
+        let lane = lane.and_then(|lane| {
+            let bulkheads = self.bulkheads.as_ref()?;
+            let lane_seqno = bulkheads
+                .lock()
+                .expect("bulkheads lock poisoned")
+                .entry(lane)
+                .or_default()
+                .next_seqno();
+            Some((Arc::clone(bulkheads), lane, lane_seqno))
+        });
+
+        if !self.first_call_done {
+            self.first_call_done = true;
+            emit!(&SinkColdStartComplete {
+                elapsed_ms: self.created_at.elapsed().as_millis() as u64,
+            });
+        }
+
+        let (tx, rx) = oneshot::channel();
+
+        self.in_flight.push(rx);
+
+        let request_id = self.next_request_id;
+        self.next_request_id = request_id.wrapping_add(1);
+
+        trace!(
+            message = "Submitting service request.",
+            in_flight_requests = self.in_flight.len()
+        );
+        if let Some((level, format_request)) = self.debug_logging {
+            // Only pay for the formatting when the event is going to be logged.
+            if tracing::level_enabled!(level) {
+                let mut body = format_request(&items);
+                truncate_at_char_boundary(&mut body, self.debug_log_max_bytes);
+                log_request_body(level, request_id, &body);
+            }
+        }
+        let retry = self.retry.as_ref().map(|retry| {
+            (
+                Arc::clone(&retry.budget),
+                (retry.prepare)(&items),
+                retry.backoff.clone(),
+            )
+        });
+        let logic = self.logic.clone();
+        let inspect_response = self.inspect_response.clone();
+        let audit_log = self.audit_log.clone();
+        let shadow = self.shadow.as_ref().map(|shadow| shadow(&items));
+        let active_requests = Arc::clone(&self.active_requests);
+        active_requests.fetch_add(1, Ordering::AcqRel);
+        // Held until the request completes.
+        let permit = self
+            .concurrent_dispatch
+            .as_mut()
+            .and_then(|dispatch| dispatch.permit.take());
+        let route = self.failover.as_mut().map(|failover| {
+            let route = failover
+                .route
+                .take()
+                .unwrap_or_else(|| lock_failover(&failover.state).route(failover.probe_interval));
+            (
+                Arc::clone(&failover.state),
+                failover.health_threshold,
+                route,
+            )
+        });
+        let response = match (&self.failover, &route) {
+            (Some(failover), Some((_, _, (RegionSelector::Secondary, _)))) => {
+                Either::Right((failover.secondary)(items))
+            }
+            _ => Either::Left(self.service.call(items).err_into()),
+        };
+        response
+            .then(move |result| retry_failed(result, retry))
+            .map(move |result| {
+                // The logic takes the result, so whatever needs the response
+                // is taken from it first.
+                let mut response_summary = None;
+                let mut compare_shadow = None;
+                if let Ok(response) = &result {
+                    if let Some(inspect) = &inspect_response {
+                        inspect(response);
+                    }
+                    if response.is_successful() {
+                        logic.on_success(response, &finalizers);
+                    }
+                    if audit_log.is_some() {
+                        response_summary = response.audit_summary();
+                    }
+                    compare_shadow = shadow.map(|compare| compare(response));
+                }
+                let status = logic.result_status(result);
+                if let Some((state, health_threshold, route)) = route {
+                    let healthy = status != EventStatus::Errored;
+                    lock_failover(&state).record(route, healthy, health_threshold);
+                }
+                match lane {
+                    Some((bulkheads, lane, lane_seqno)) => {
+                        finalize_in_lane(&bulkheads, lane, lane_seqno, finalizers, status)
+                    }
+                    None => finalizers.update_status(status),
+                }
+                if let Some(audit_log) = audit_log {
+                    let record = AuditRecord {
+                        timestamp: Utc::now(),
+                        request_id,
+                        batch_size,
+                        outcome: status,
+                        response_summary,
+                    };
+                    // The audit task only stops early once the audit sink
+                    // failed, which was already reported.
+                    if let Err(TrySendError::Full(_)) = audit_log.try_send(record) {
+                        emit!(&ServiceAuditRecordDropped { request_id });
+                    }
+                }
+                if status == EventStatus::Delivered {
+                    emit!(&EventsSent { count, byte_size });
+                    emit!(&BatchBytesSent {
+                        batch_size: count,
+                        wire_size,
+                    });
+                }
+
+                // If the rx end is dropped we still completed
+                // the request so this is a weird case that we can
+                // ignore for now.
+                let _ = tx.send((seqno, batch_size));
+                drop(permit);
+                active_requests.fetch_sub(1, Ordering::AcqRel);
+
+                if let Some(compare) = compare_shadow {
+                    tokio::spawn(async move {
+                        if compare.await == Some(false) {
+                            emit!(&ShadowServiceMismatch {
+                                partition: label.unwrap_or_default(),
+                                batch_size,
+                            });
+                        }
+                    });
+                }
+            })
+            .instrument(info_span!("request", %request_id))
+            .boxed()
+    }
+
+    /// Acks `count` events which were dropped instead of being sent, once
+    /// all previously dispatched requests have been acked.
+    fn ack_dropped(&mut self, count: usize) {
+        let seqno = self.sequence.next_seqno();
+        self.sequence.pending_acks.insert(seqno, count);
+        self.ack_pending();
+    }
+
+    fn ack_pending(&mut self) {
+        let num_to_ack = self.sequence.take_acks();
+        if num_to_ack > 0 {
+            trace!(message = "Acking events.", acking_num = num_to_ack);
+            self.acker.ack(num_to_ack);
+        }
+    }
+
+    fn poll_complete(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        // Collect every completed request first so that all of them are acked
+        // with a single call to the acker.
+        let mut poll = Poll::Ready(());
+        while !self.in_flight.is_empty() {
+            match Pin::new(&mut self.in_flight).poll_next(cx) {
+                Poll::Ready(Some(Ok((seqno, batch_size)))) => {
+                    self.sequence.pending_acks.insert(seqno, batch_size);
+                }
+                Poll::Ready(Some(Err(_))) => panic!("ServiceSink service sender dropped."),
+                Poll::Ready(None) => break,
+                Poll::Pending => {
+                    poll = Poll::Pending;
+                    break;
+                }
+            }
+        }
+        self.ack_pending();
+
+        poll
+    }
+}
+
+impl<S, Request, SL> Drop for ServiceSink<S, Request, SL>
+where
+    S: Service<Request>,
+{
+    /// Hands the requests still in flight over to a task which acks them once
+    /// they complete, so that their events aren't left unacked.
+    fn drop(&mut self) {
+        if self.in_flight.is_empty() {
+            return;
+        }
+        // Without a runtime the requests can't complete anyway.
+        let runtime = match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => runtime,
+            Err(_) => return,
+        };
+
+        debug!(
+            message = "Waiting for in flight requests to complete.",
+            in_flight_requests = self.in_flight.len()
+        );
+        runtime.spawn(ack_in_flight(
+            std::mem::take(&mut self.in_flight),
+            std::mem::take(&mut self.sequence),
+            std::mem::replace(&mut self.acker, Acker::passthrough()),
+        ));
+    }
+}
+
+/// Acks the requests of a dropped `ServiceSink` in order, once all of them
+/// have completed.
+async fn ack_in_flight(
+    mut in_flight: FuturesUnordered<oneshot::Receiver<(usize, usize)>>,
+    mut sequence: SequenceState,
+    acker: Acker,
+) {
+    while let Some(result) = futures::StreamExt::next(&mut in_flight).await {
+        match result {
+            Ok((seqno, batch_size)) => {
+                sequence.pending_acks.insert(seqno, batch_size);
+            }
+            // The request task was cancelled, the runtime is going away.
+            Err(_) => break,
+        }
     }
 
-    fn call(&mut self, batch: EncodedBatch<Request>, batch_size: usize) -> BoxFuture<'static, ()> {
-        let EncodedBatch {
-            items,
-            finalizers,
-            count,
-            byte_size,
-        } = batch;
-        let seqno = self.seq_head;
-        self.seq_head += 1;
+    let num_to_ack = sequence.take_acks();
+    if num_to_ack > 0 {
+        trace!(message = "Acking events.", acking_num = num_to_ack);
+        acker.ack(num_to_ack);
+    }
+}
 
-        let (tx, rx) = oneshot::channel();
+fn truncate_at_char_boundary(body: &mut String, max_bytes: usize) {
+    if body.len() > max_bytes {
+        let mut end = max_bytes;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+        body.push_str("...");
+    }
+}
 
-        self.in_flight.push(rx);
+fn log_request_body(level: tracing::Level, request_id: usize, body: &str) {
+    use tracing::Level;
+
+    if level == Level::ERROR {
+        error!(message = "Sending request.", %request_id, %body);
+    } else if level == Level::WARN {
+        warn!(message = "Sending request.", %request_id, %body);
+    } else if level == Level::INFO {
+        info!(message = "Sending request.", %request_id, %body);
+    } else if level == Level::DEBUG {
+        debug!(message = "Sending request.", %request_id, %body);
+    } else {
+        trace!(message = "Sending request.", %request_id, %body);
+    }
+}
 
-        let request_id = self.next_request_id;
-        self.next_request_id = request_id.wrapping_add(1);
+/// Waits for `service` to be ready, giving up after a few attempts.
+async fn warm_up<S, Request>(mut service: S)
+where
+    S: Service<Request>,
+{
+    for attempt in 0..WARMUP_ATTEMPTS {
+        match timeout(
+            WARMUP_BACKOFF,
+            futures::future::poll_fn(|cx| service.poll_ready(cx)),
+        )
+        .await
+        {
+            Ok(Ok(())) => return,
+            Ok(Err(_)) if attempt + 1 < WARMUP_ATTEMPTS => sleep(WARMUP_BACKOFF).await,
+            Ok(Err(_)) | Err(_) => {}
+        }
+    }
+    debug!(message = "Service is not ready after warm-up, giving up.");
+}
 
-        trace!(
-            message = "Submitting service request.",
-            in_flight_requests = self.in_flight.len()
-        );
-        let logic = self.logic.clone();
-        self.service
-            .call(items)
-            .err_into()
-            .map(move |result| {
-                let status = logic.result_status(result);
-                finalizers.update_status(status);
-                if status == EventStatus::Delivered {
-                    emit!(&EventsSent { count, byte_size });
-                    // TODO: Emit a BytesSent event here too
-                }
+/// Resends a request which failed transiently for as long as the retry
+/// budget allows it, waiting in between for as long as the response asks to,
+/// or else for the next delay of `backoff`.
+async fn retry_failed<R: Response>(
+    mut result: crate::Result<R>,
+    retry: Option<(Arc<RetryBudget>, Resend<R>, ExponentialBackoff)>,
+) -> crate::Result<R> {
+    if let Some((budget, mut resend, mut backoff)) = retry {
+        while is_transient_failure(&result) {
+            if !budget.try_acquire() {
+                emit!(&ServiceRetryBudgetExhausted);
+                break;
+            }
+            let delay = result.as_ref().ok().and_then(Response::retry_after);
+            sleep(delay.or_else(|| backoff.next()).unwrap_or_default()).await;
+            result = resend().await;
+        }
+    }
+    result
+}
 
-                // If the rx end is dropped we still completed
-                // the request so this is a weird case that we can
-                // ignore for now.
-                let _ = tx.send((seqno, batch_size));
-            })
-            .instrument(info_span!("request", %request_id))
-            .boxed()
+/// Whether `result` is a failure worth retrying, that is a failed request or
+/// a response which isn't successful but is transient.
+fn is_transient_failure<R: Response>(result: &crate::Result<R>) -> bool {
+    match result {
+        Ok(response) => !response.is_successful() && response.is_transient(),
+        Err(_) => true,
     }
+}
 
-    fn poll_complete(&mut self, cx: &mut Context<'_>) -> Poll<()> {
-        while !self.in_flight.is_empty() {
-            match ready!(Pin::new(&mut self.in_flight).poll_next(cx)) {
-                Some(Ok((seqno, batch_size))) => {
-                    self.pending_acks.insert(seqno, batch_size);
-
-                    let mut num_to_ack = 0;
-                    while let Some(ack_size) = self.pending_acks.remove(&self.seq_tail) {
-                        num_to_ack += ack_size;
-                        self.seq_tail += 1
-                    }
-                    trace!(message = "Acking events.", acking_num = num_to_ack);
-                    self.acker.ack(num_to_ack);
+/// Sends `heartbeat` every `interval` unless requests are in flight.
+async fn keepalive<S, Request, SL>(
+    mut service: S,
+    heartbeat: Request,
+    interval: Duration,
+    logic: SL,
+    active_requests: Arc<AtomicUsize>,
+) where
+    S: Service<Request>,
+    S::Error: Into<crate::Error>,
+    Request: Clone,
+    SL: ServiceLogic<Response = S::Response>,
+{
+    let mut ticks = tokio::time::interval(interval);
+    // The first tick completes right away.
+    ticks.tick().await;
+    loop {
+        ticks.tick().await;
+        if active_requests.load(Ordering::Acquire) > 0 {
+            continue;
+        }
+
+        trace!("Sending keepalive heartbeat.");
+        let result = match futures::future::poll_fn(|cx| service.poll_ready(cx)).await {
+            Ok(()) => service.call(heartbeat.clone()).await,
+            Err(error) => Err(error),
+        }
+        .map_err(Into::into);
+        match result {
+            Ok(response) => {
+                if logic.result_status(Ok(response)) != EventStatus::Delivered {
+                    emit!(&ServiceKeepaliveFailed { error: None });
                 }
-                Some(Err(_)) => panic!("ServiceSink service sender dropped."),
-                None => break,
             }
+            Err(error) => emit!(&ServiceKeepaliveFailed {
+                error: Some(&error),
+            }),
         }
-
-        Poll::Ready(())
     }
 }
 
 impl<S, Request, SL> fmt::Debug for ServiceSink<S, Request, SL>
 where
-    S: fmt::Debug,
+    S: Service<Request> + fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ServiceSink")
             .field("service", &self.service)
             .field("acker", &self.acker)
-            .field("seq_head", &self.seq_head)
-            .field("seq_tail", &self.seq_tail)
-            .field("pending_acks", &self.pending_acks)
+            .field("sequence", &self.sequence)
+            .field("bulkheads", &self.bulkheads)
             .finish()
     }
 }
@@ -574,6 +2890,11 @@ where
 pub trait ServiceLogic: Clone {
     type Response: Response;
     fn result_status(&self, result: crate::Result<Self::Response>) -> EventStatus;
+
+    /// Called with the response of a request when it reports success, before
+    /// `result_status` and before the finalizers are updated, to allow
+    /// success-side processing of the response.
+    fn on_success(&self, _response: &Self::Response, _finalizers: &EventFinalizers) {}
 }
 
 #[derive(Derivative)]
@@ -626,618 +2947,21 @@ pub trait Response: fmt::Debug {
     fn is_transient(&self) -> bool {
         true
     }
-}
-
-impl Response for () {}
-
-impl<'a> Response for &'a str {}
-
-#[cfg(test)]
-mod tests {
-    use std::{
-        convert::Infallible,
-        sync::{atomic::Ordering::Relaxed, Arc, Mutex},
-    };
-
-    use bytes::Bytes;
-    use futures::{future, stream, task::noop_waker_ref, SinkExt, StreamExt};
-    use tokio::{task::yield_now, time::Instant};
-    use vector_buffers::Acker;
-
-    use super::*;
-    use crate::{
-        sinks::util::{BatchSettings, EncodedLength, VecBuffer},
-        test_util::trace_init,
-    };
-
-    const TIMEOUT: Duration = Duration::from_secs(10);
-
-    impl EncodedLength for usize {
-        fn encoded_length(&self) -> usize {
-            22
-        }
-    }
-
-    async fn advance_time(duration: Duration) {
-        tokio::time::pause();
-        tokio::time::advance(duration).await;
-        tokio::time::resume();
-    }
-
-    #[tokio::test]
-    async fn batch_sink_acking_sequential() {
-        let (acker, ack_counter) = Acker::basic();
-
-        let svc = tower::service_fn(|_| future::ok::<_, std::io::Error>(()));
-        let mut batch_settings = BatchSettings::default();
-        batch_settings.size.bytes = 9999;
-        batch_settings.size.events = 10;
-
-        let buffered = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
-
-        let _ = buffered
-            .sink_map_err(drop)
-            .send_all(&mut stream::iter(0..22).map(|item| Ok(EncodedEvent::new(item, 0))))
-            .await
-            .unwrap();
-
-        assert_eq!(ack_counter.load(Relaxed), 22);
-    }
-
-    #[tokio::test]
-    async fn batch_sink_acking_unordered() {
-        trace_init();
-
-        // Services future will be spawned and work between `yield_now` calls.
-        let (acker, ack_counter) = Acker::basic();
-
-        let svc = tower::service_fn(|req: Vec<usize>| async move {
-            let duration = match req[0] {
-                0 => Duration::from_secs(1),
-                1 => Duration::from_secs(1),
-                2 => Duration::from_secs(1),
-
-                // The 4th request will introduce some sort of
-                // latency spike to ensure later events don't
-                // get acked.
-                3 => Duration::from_secs(5),
-                4 => Duration::from_secs(1),
-                5 => Duration::from_secs(1),
-                _ => unreachable!(),
-            };
-
-            sleep(duration).await;
-            Ok::<(), Infallible>(())
-        });
-
-        let mut batch_settings = BatchSettings::default();
-        batch_settings.size.bytes = 9999;
-        batch_settings.size.events = 1;
-
-        let mut sink = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
-
-        let mut cx = Context::from_waker(noop_waker_ref());
-        assert!(matches!(
-            sink.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.start_send_unpin(EncodedEvent::new(0, 0)),
-            Ok(())
-        ));
-        assert!(matches!(
-            sink.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.start_send_unpin(EncodedEvent::new(1, 0)),
-            Ok(())
-        ));
-        assert!(matches!(
-            sink.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.start_send_unpin(EncodedEvent::new(2, 0)),
-            Ok(())
-        ));
-
-        // Clear internal buffer
-        assert!(matches!(sink.poll_flush_unpin(&mut cx), Poll::Pending));
-        assert_eq!(ack_counter.load(Relaxed), 0);
-
-        yield_now().await;
-        advance_time(Duration::from_secs(3)).await;
-        yield_now().await;
-
-        assert!(matches!(
-            sink.poll_flush_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.poll_flush_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.poll_flush_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-
-        assert_eq!(ack_counter.load(Relaxed), 3);
-
-        assert!(matches!(
-            sink.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.start_send_unpin(EncodedEvent::new(3, 0)),
-            Ok(())
-        ));
-        assert!(matches!(
-            sink.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.start_send_unpin(EncodedEvent::new(4, 0)),
-            Ok(())
-        ));
-        assert!(matches!(
-            sink.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.start_send_unpin(EncodedEvent::new(5, 0)),
-            Ok(())
-        ));
-
-        // Clear internal buffer
-        assert!(matches!(sink.poll_flush_unpin(&mut cx), Poll::Pending));
-        assert_eq!(ack_counter.load(Relaxed), 3);
-
-        yield_now().await;
-        advance_time(Duration::from_secs(2)).await;
-        yield_now().await;
-
-        assert!(matches!(sink.poll_flush_unpin(&mut cx), Poll::Pending));
-
-        // Check that events 3,4,5 have not been acked yet
-        // only the three previous should be acked.
-        assert_eq!(ack_counter.load(Relaxed), 3);
-
-        yield_now().await;
-        advance_time(Duration::from_secs(5)).await;
-        yield_now().await;
-
-        assert!(matches!(
-            sink.poll_flush_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.poll_flush_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.poll_flush_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-
-        assert_eq!(ack_counter.load(Relaxed), 6);
-    }
-
-    #[tokio::test]
-    async fn batch_sink_buffers_messages_until_limit() {
-        let (acker, _) = Acker::basic();
-        let sent_requests = Arc::new(Mutex::new(Vec::new()));
-
-        let svc = tower::service_fn(|req| {
-            let sent_requests = Arc::clone(&sent_requests);
-
-            sent_requests.lock().unwrap().push(req);
-
-            future::ok::<_, std::io::Error>(())
-        });
-
-        let mut batch_settings = BatchSettings::default();
-        batch_settings.size.bytes = 9999;
-        batch_settings.size.events = 10;
-        let buffered = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
-
-        let _ = buffered
-            .sink_map_err(drop)
-            .send_all(&mut stream::iter(0..22).map(|item| Ok(EncodedEvent::new(item, 0))))
-            .await
-            .unwrap();
-
-        let output = sent_requests.lock().unwrap();
-        assert_eq!(
-            &*output,
-            &vec![
-                vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
-                vec![10, 11, 12, 13, 14, 15, 16, 17, 18, 19],
-                vec![20, 21]
-            ]
-        );
-    }
-
-    #[tokio::test]
-    async fn batch_sink_flushes_below_min_on_close() {
-        let (acker, _) = Acker::basic();
-        let sent_requests = Arc::new(Mutex::new(Vec::new()));
-
-        let svc = tower::service_fn(|req| {
-            let sent_requests = Arc::clone(&sent_requests);
-            sent_requests.lock().unwrap().push(req);
-            future::ok::<_, std::io::Error>(())
-        });
-
-        let mut batch_settings = BatchSettings::default();
-        batch_settings.size.bytes = 9999;
-        batch_settings.size.events = 10;
-        let mut buffered = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
-
-        let mut cx = Context::from_waker(noop_waker_ref());
-        assert!(matches!(
-            buffered.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            buffered.start_send_unpin(EncodedEvent::new(0, 0)),
-            Ok(())
-        ));
-        assert!(matches!(
-            buffered.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            buffered.start_send_unpin(EncodedEvent::new(1, 0)),
-            Ok(())
-        ));
-
-        buffered.close().await.unwrap();
-
-        let output = sent_requests.lock().unwrap();
-        assert_eq!(&*output, &vec![vec![0, 1]]);
-    }
-
-    #[tokio::test]
-    async fn batch_sink_expired_linger() {
-        let (acker, _) = Acker::basic();
-        let sent_requests = Arc::new(Mutex::new(Vec::new()));
-
-        let svc = tower::service_fn(|req| {
-            let sent_requests = Arc::clone(&sent_requests);
-            sent_requests.lock().unwrap().push(req);
-            future::ok::<_, std::io::Error>(())
-        });
-
-        let mut batch_settings = BatchSettings::default();
-        batch_settings.size.bytes = 9999;
-        batch_settings.size.events = 10;
-        let mut buffered = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
-
-        let mut cx = Context::from_waker(noop_waker_ref());
-        assert!(matches!(
-            buffered.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            buffered.start_send_unpin(EncodedEvent::new(0, 0)),
-            Ok(())
-        ));
-        assert!(matches!(
-            buffered.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            buffered.start_send_unpin(EncodedEvent::new(1, 0)),
-            Ok(())
-        ));
-
-        // Move clock forward by linger timeout + 1 sec
-        advance_time(TIMEOUT + Duration::from_secs(1)).await;
-
-        // Flush buffer and make sure that this didn't take long time (because linger elapsed).
-        let start = Instant::now();
-        buffered.flush().await.unwrap();
-        let elapsed = start.duration_since(start);
-        assert!(elapsed < Duration::from_millis(200));
-
-        let output = sent_requests.lock().unwrap();
-        assert_eq!(&*output, &vec![vec![0, 1]]);
-    }
-
-    #[tokio::test]
-    async fn partition_batch_sink_buffers_messages_until_limit() {
-        let (acker, _) = Acker::basic();
-        let sent_requests = Arc::new(Mutex::new(Vec::new()));
-
-        let svc = tower::service_fn(|req| {
-            let sent_requests = Arc::clone(&sent_requests);
-            sent_requests.lock().unwrap().push(req);
-            future::ok::<_, std::io::Error>(())
-        });
-
-        let mut batch_settings = BatchSettings::default();
-        batch_settings.size.bytes = 9999;
-        batch_settings.size.events = 10;
-
-        let sink =
-            PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
-
-        sink.sink_map_err(drop)
-            .send_all(&mut stream::iter(0..22).map(|item| Ok(EncodedEvent::new(item, 0))))
-            .await
-            .unwrap();
-
-        let output = sent_requests.lock().unwrap();
-        assert_eq!(
-            &*output,
-            &vec![
-                vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
-                vec![10, 11, 12, 13, 14, 15, 16, 17, 18, 19],
-                vec![20, 21]
-            ]
-        );
-    }
-
-    #[tokio::test]
-    async fn partition_batch_sink_buffers_by_partition_buffer_size_one() {
-        let (acker, _) = Acker::basic();
-        let sent_requests = Arc::new(Mutex::new(Vec::new()));
-
-        let svc = tower::service_fn(|req| {
-            let sent_requests = Arc::clone(&sent_requests);
-            sent_requests.lock().unwrap().push(req);
-            future::ok::<_, std::io::Error>(())
-        });
-
-        let mut batch_settings = BatchSettings::default();
-        batch_settings.size.bytes = 9999;
-        batch_settings.size.events = 1;
-
-        let sink =
-            PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
-
-        let input = vec![Partitions::A, Partitions::B];
-        sink.sink_map_err(drop)
-            .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
-            .await
-            .unwrap();
-
-        let mut output = sent_requests.lock().unwrap();
-        output[..].sort();
-        assert_eq!(&*output, &vec![vec![Partitions::A], vec![Partitions::B]]);
-    }
-
-    #[tokio::test]
-    async fn partition_batch_sink_buffers_by_partition_buffer_size_two() {
-        let (acker, _) = Acker::basic();
-        let sent_requests = Arc::new(Mutex::new(Vec::new()));
-
-        let svc = tower::service_fn(|req| {
-            let sent_requests = Arc::clone(&sent_requests);
-            sent_requests.lock().unwrap().push(req);
-            future::ok::<_, std::io::Error>(())
-        });
-
-        let mut batch_settings = BatchSettings::default();
-        batch_settings.size.bytes = 9999;
-        batch_settings.size.events = 2;
-
-        let sink =
-            PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
-
-        let input = vec![Partitions::A, Partitions::B, Partitions::A, Partitions::B];
-        sink.sink_map_err(drop)
-            .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
-            .await
-            .unwrap();
-
-        let mut output = sent_requests.lock().unwrap();
-        output[..].sort();
-        assert_eq!(
-            &*output,
-            &vec![
-                vec![Partitions::A, Partitions::A],
-                vec![Partitions::B, Partitions::B]
-            ]
-        );
-    }
-
-    #[tokio::test]
-    async fn partition_batch_sink_submits_after_linger() {
-        let (acker, _) = Acker::basic();
-        let sent_requests = Arc::new(Mutex::new(Vec::new()));
-
-        let svc = tower::service_fn(|req| {
-            let sent_requests = Arc::clone(&sent_requests);
-            sent_requests.lock().unwrap().push(req);
-            future::ok::<_, std::io::Error>(())
-        });
-
-        let mut batch_settings = BatchSettings::default();
-        batch_settings.size.bytes = 9999;
-        batch_settings.size.events = 10;
-
-        let mut sink =
-            PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
-
-        let mut cx = Context::from_waker(noop_waker_ref());
-        assert!(matches!(
-            sink.poll_ready_unpin(&mut cx),
-            Poll::Ready(Ok(()))
-        ));
-        assert!(matches!(
-            sink.start_send_unpin(EncodedEvent::new(1, 0)),
-            Ok(())
-        ));
-        assert!(matches!(sink.poll_flush_unpin(&mut cx), Poll::Pending));
-
-        advance_time(TIMEOUT + Duration::from_secs(1)).await;
-
-        let start = Instant::now();
-        sink.flush().await.unwrap();
-        let elapsed = start.duration_since(start);
-        assert!(elapsed < Duration::from_millis(200));
-
-        let output = sent_requests.lock().unwrap();
-        assert_eq!(&*output, &vec![vec![1]]);
-    }
-
-    #[tokio::test]
-    async fn service_sink_doesnt_propagate_error() {
-        // We need a mock executor here because we need to ensure
-        // that we poll the service futures within the mock clock
-        // context. This allows us to manually advance the time on the
-        // "spawned" futures.
-        let (acker, ack_counter) = Acker::basic();
-
-        let svc = tower::service_fn(|req: u8| {
-            if req == 3 {
-                future::err("bad")
-            } else {
-                future::ok("good")
-            }
-        });
-        let mut sink = ServiceSink::new(svc, acker);
-        let req = |items: u8| EncodedBatch {
-            items,
-            finalizers: Default::default(),
-            count: items as usize,
-            byte_size: 1,
-        };
-
-        // send some initial requests
-        let mut fut1 = sink.call(req(1), 1);
-        let mut fut2 = sink.call(req(2), 2);
-
-        assert_eq!(ack_counter.load(Relaxed), 0);
-
-        let mut cx = Context::from_waker(noop_waker_ref());
-        assert!(matches!(fut1.poll_unpin(&mut cx), Poll::Ready(())));
-        assert!(matches!(fut2.poll_unpin(&mut cx), Poll::Ready(())));
-        assert!(matches!(sink.poll_complete(&mut cx), Poll::Ready(())));
-
-        assert_eq!(ack_counter.load(Relaxed), 3);
-
-        // send one request that will error and one normal
-        let mut fut3 = sink.call(req(3), 3); // I will error
-        let mut fut4 = sink.call(req(4), 4);
-
-        // make sure they all "worked"
-        assert!(matches!(fut3.poll_unpin(&mut cx), Poll::Ready(())));
-        assert!(matches!(fut4.poll_unpin(&mut cx), Poll::Ready(())));
-        assert!(matches!(sink.poll_complete(&mut cx), Poll::Ready(())));
-
-        assert_eq!(ack_counter.load(Relaxed), 10);
-    }
-
-    #[tokio::test]
-    async fn partition_batch_sink_ordering_per_partition() {
-        let (acker, _) = Acker::basic();
-        let sent_requests = Arc::new(Mutex::new(Vec::new()));
-
-        let mut delay = true;
-        let svc = tower::service_fn(|req| {
-            let sent_requests = Arc::clone(&sent_requests);
-            if delay {
-                // Delay and then error
-                delay = false;
-                sleep(Duration::from_secs(1))
-                    .map(move |_| {
-                        sent_requests.lock().unwrap().push(req);
-                        Result::<_, std::io::Error>::Ok(())
-                    })
-                    .boxed()
-            } else {
-                sent_requests.lock().unwrap().push(req);
-                future::ok::<_, std::io::Error>(()).boxed()
-            }
-        });
-
-        let mut batch_settings = BatchSettings::default();
-        batch_settings.size.bytes = 9999;
-        batch_settings.size.events = 10;
-
-        let mut sink =
-            PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
-        sink.ordered();
-
-        let input = (0..20)
-            .into_iter()
-            .map(|i| (0, i))
-            .chain((0..20).into_iter().map(|i| (1, i)));
-        sink.sink_map_err(drop)
-            .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
-            .await
-            .unwrap();
-
-        let output = sent_requests.lock().unwrap();
-        // We sended '0' partition first and delayed sending only first request, first 10 events,
-        // which should delay sending the second batch of events in the same partition until
-        // the first one succeeds.
-        assert_eq!(
-            &*output,
-            &vec![
-                (0..10).into_iter().map(|i| (1, i)).collect::<Vec<_>>(),
-                (10..20).into_iter().map(|i| (1, i)).collect(),
-                (0..10).into_iter().map(|i| (0, i)).collect(),
-                (10..20).into_iter().map(|i| (0, i)).collect(),
-            ]
-        );
-    }
-
-    #[derive(Debug, PartialEq, Eq, Ord, PartialOrd)]
-    enum Partitions {
-        A,
-        B,
-    }
-
-    impl EncodedLength for Partitions {
-        fn encoded_length(&self) -> usize {
-            10 // Dummy value
-        }
-    }
-
-    impl Partition<Bytes> for Partitions {
-        fn partition(&self) -> Bytes {
-            format!("{:?}", self).into()
-        }
-    }
 
-    impl Partition<Bytes> for usize {
-        fn partition(&self) -> Bytes {
-            "key".into()
-        }
-    }
-
-    impl Partition<Bytes> for u8 {
-        fn partition(&self) -> Bytes {
-            "key".into()
-        }
+    /// The delay the downstream service asked to wait for before retrying.
+    fn retry_after(&self) -> Option<Duration> {
+        None
     }
 
-    impl Partition<Bytes> for i32 {
-        fn partition(&self) -> Bytes {
-            "key".into()
-        }
+    /// A short description of the response for the audit records.
+    fn audit_summary(&self) -> Option<String> {
+        None
     }
+}
 
-    impl Partition<Bytes> for Vec<i32> {
-        fn partition(&self) -> Bytes {
-            "key".into()
-        }
-    }
+impl Response for () {}
 
-    impl Partition<Bytes> for (usize, usize) {
-        fn partition(&self) -> Bytes {
-            format!("{}", self.0).into()
-        }
-    }
+impl<'a> Response for &'a str {}
 
-    impl EncodedLength for (usize, usize) {
-        fn encoded_length(&self) -> usize {
-            16
-        }
-    }
-}
+#[cfg(test)]
+mod tests;
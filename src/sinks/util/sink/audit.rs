@@ -0,0 +1,92 @@
+//! Records of the requests sent by a `ServiceSink`, see
+//! `PartitionBatchSink::with_audit_log`.
+
+use std::{fmt, hash::Hash};
+
+use chrono::{DateTime, Utc};
+use futures::{Sink, SinkExt};
+use tokio::sync::mpsc;
+use tower::Service;
+
+use super::{PartitionBatchSink, Response, ServiceLogic, ServiceSink};
+use crate::{
+    event::EventStatus,
+    internal_events::ServiceAuditLogFailed,
+    sinks::util::{batch::Batch, buffer::Partition},
+};
+
+pub(super) const AUDIT_LOG_BUFFER_SIZE: usize = 1024;
+
+/// Record of a request sent by a `ServiceSink`, see
+/// `PartitionBatchSink::with_audit_log`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditRecord {
+    /// When the response was received.
+    pub timestamp: DateTime<Utc>,
+    pub request_id: usize,
+    /// Number of events in the request.
+    pub batch_size: usize,
+    pub outcome: EventStatus,
+    pub response_summary: Option<String>,
+}
+
+impl<S, Request, SL> ServiceSink<S, Request, SL>
+where
+    S: Service<Request>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Writes the audit records of the requests to `audit_sink` from a
+    /// separate task, which runs until the sink and all of its requests are
+    /// gone.
+    ///
+    /// Up to `AUDIT_LOG_BUFFER_SIZE` records wait for the audit sink, the
+    /// following ones are dropped until it catches up.
+    pub(super) fn with_audit_log<A>(mut self, mut audit_sink: A) -> Self
+    where
+        A: Sink<AuditRecord> + Unpin + Send + 'static,
+        A::Error: fmt::Debug + Send,
+    {
+        let (tx, mut rx) = mpsc::channel(AUDIT_LOG_BUFFER_SIZE);
+        self.audit_log = Some(tx);
+        tokio::spawn(async move {
+            let forwarded = async {
+                while let Some(record) = rx.recv().await {
+                    audit_sink.send(record).await?;
+                }
+                audit_sink.close().await
+            };
+            if let Err(error) = forwarded.await {
+                emit!(&ServiceAuditLogFailed {
+                    error: format!("{:?}", error),
+                });
+            }
+        });
+        self
+    }
+}
+
+impl<S, B, K, SL> PartitionBatchSink<S, B, K, SL>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Writes an `AuditRecord` to `audit_sink` for every request once its
+    /// response has been received.
+    pub fn with_audit_log<A>(mut self, audit_sink: A) -> Self
+    where
+        A: Sink<AuditRecord> + Unpin + Send + 'static,
+        A::Error: fmt::Debug + Send,
+    {
+        self.service = self.service.with_audit_log(audit_sink);
+        self
+    }
+}
@@ -0,0 +1,89 @@
+//! Reporting the events of each partition independently of the others, see
+//! `PartitionBatchSink::with_bulkhead`.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+use tower::Service;
+
+use super::{PartitionBatchSink, Response, SequenceState, ServiceLogic, ServiceSink};
+use crate::{
+    event::{EventFinalizers, EventStatus},
+    sinks::util::{batch::Batch, buffer::Partition},
+};
+
+/// Separate sequences for the requests of each partition, keyed by the hash
+/// of the partition, holding the finalizers of the completed requests along
+/// with their status until the older requests of the partition complete.
+///
+/// Shared with the request tasks, so that the events are reported as soon as
+/// their turn comes, even once the sink is dropped.
+pub(super) type Bulkheads = Arc<Mutex<HashMap<u64, SequenceState<(EventFinalizers, EventStatus)>>>>;
+
+/// Reports the status of a completed request of the bulkhead `lane` once
+/// all of the older requests of the lane have been reported.
+pub(super) fn finalize_in_lane(
+    bulkheads: &Bulkheads,
+    lane: u64,
+    seqno: usize,
+    finalizers: EventFinalizers,
+    status: EventStatus,
+) {
+    let completed = {
+        let mut lanes = bulkheads.lock().expect("bulkheads lock poisoned");
+        let sequence = lanes.entry(lane).or_default();
+        sequence.pending_acks.insert(seqno, (finalizers, status));
+        let completed = sequence.take_completed();
+        // The sequence of a partition starts over once all of its requests
+        // are reported.
+        if sequence.is_settled() {
+            lanes.remove(&lane);
+        }
+        completed
+    };
+    for (finalizers, status) in completed {
+        finalizers.update_status(status);
+    }
+}
+
+impl<S, Request, SL> ServiceSink<S, Request, SL>
+where
+    S: Service<Request>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    pub(super) fn with_bulkhead(mut self) -> Self {
+        self.bulkheads = Some(Arc::default());
+        self
+    }
+}
+
+impl<S, B, K, SL> PartitionBatchSink<S, B, K, SL>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Reports the delivery of the events of each partition in the order its
+    /// batches were dispatched, independently of the other partitions: a
+    /// partition whose requests are slow or retried only holds back its own
+    /// events, through their finalizers.
+    ///
+    /// The acker still counts the events of all partitions in dispatch
+    /// order, as it can't tell them apart. Batches coalesced across
+    /// partitions are reported as soon as their request completes.
+    pub fn with_bulkhead(mut self) -> Self {
+        self.service = self.service.with_bulkhead();
+        self
+    }
+}
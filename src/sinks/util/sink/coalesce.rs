@@ -0,0 +1,301 @@
+//! Sending several batches of a `PartitionBatchSink` as a single request, see
+//! `PartitionBatchSink::with_coalesce_threshold` and
+//! `PartitionBatchSink::with_coalesce_merger`.
+
+use std::{
+    hash::Hash,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::FutureExt;
+use tokio::time::{sleep, Duration, Sleep};
+use tower::Service;
+
+use super::{describe_partition, PartitionBatchSink, Response, ServiceLogic};
+use crate::{
+    event::EventFinalizers,
+    sinks::util::{
+        batch::{Batch, EncodedBatch},
+        buffer::{Coalesce, Partition},
+    },
+};
+
+/// Merges the outputs of several batches into a single request, for services
+/// taking batches of batches.
+pub trait Merger<T>: Send + Sync {
+    fn merge(&self, items: Vec<T>) -> T;
+}
+
+impl<T, F> Merger<T> for F
+where
+    F: Fn(Vec<T>) -> T + Send + Sync,
+{
+    fn merge(&self, items: Vec<T>) -> T {
+        self(items)
+    }
+}
+
+/// Sends several ready batches as a single request, see
+/// `PartitionBatchSink::with_coalesce_threshold` and
+/// `PartitionBatchSink::with_coalesce_merger`.
+pub(super) struct Coalescing<K, O> {
+    merger: Box<dyn Merger<O>>,
+    /// Only the batches of fewer events are coalesced, and only as many of
+    /// them as fit in a single batch.
+    pub(super) min_items: Option<usize>,
+    /// The partition the coalesced batches are sent as, along with the
+    /// function tagging the events of each batch with their own partition.
+    shared_partition: Option<(K, fn(&mut O, &K))>,
+    /// How long the ready batches are held for others to join them. Without
+    /// it, batches are only coalesced when several are ready at once.
+    pub(super) window: Option<Duration>,
+    /// Number of batches held after which they are sent right away.
+    pub(super) max_batches: Option<usize>,
+    pending: Option<CoalescedBatch<K, O>>,
+}
+
+struct CoalescedBatch<K, O> {
+    batches: Vec<EncodedBatch<O>>,
+    batch_size: usize,
+    fill_ratio: f64,
+    partitions: Vec<K>,
+    deadline: Option<Pin<Box<Sleep>>>,
+}
+
+impl<K, O> Coalescing<K, O> {
+    fn new(merger: Box<dyn Merger<O>>) -> Self {
+        Self {
+            merger,
+            min_items: None,
+            shared_partition: None,
+            window: None,
+            max_batches: None,
+            pending: None,
+        }
+    }
+
+    /// Holds `batch` along with the pending ones, starting the window if
+    /// there are none yet.
+    pub(super) fn hold(
+        &mut self,
+        partition: K,
+        mut batch: EncodedBatch<O>,
+        batch_size: usize,
+        fill_ratio: f64,
+    ) {
+        if let Some((_, tag)) = self.shared_partition.as_ref() {
+            tag(&mut batch.items, &partition);
+        }
+        let window = self.window;
+        let pending = self.pending.get_or_insert_with(|| CoalescedBatch {
+            batches: Vec::new(),
+            batch_size: 0,
+            fill_ratio: 0.0,
+            partitions: Vec::new(),
+            deadline: window.map(|window| Box::pin(sleep(window))),
+        });
+        pending.batches.push(batch);
+        pending.batch_size += batch_size;
+        pending.fill_ratio += fill_ratio;
+        pending.partitions.push(partition);
+    }
+
+    /// Number of batches held and how much of a batch they fill together.
+    pub(super) fn held(&self) -> (usize, f64) {
+        self.pending.as_ref().map_or((0, 0.0), |pending| {
+            (pending.batches.len(), pending.fill_ratio)
+        })
+    }
+
+    /// Whether as many batches as allowed are held.
+    fn is_full(&self) -> bool {
+        self.max_batches
+            .map_or(false, |max_batches| self.held().0 >= max_batches)
+    }
+
+    /// Merges the held batches into the one to send.
+    fn merge(&self, batches: Vec<EncodedBatch<O>>) -> EncodedBatch<O> {
+        let mut items = Vec::with_capacity(batches.len());
+        let mut finalizers = EventFinalizers::default();
+        let (mut count, mut byte_size, mut wire_size) = (0, 0, 0);
+        for batch in batches {
+            items.push(batch.items);
+            finalizers.merge(batch.finalizers);
+            count += batch.count;
+            byte_size += batch.byte_size;
+            wire_size += batch.wire_size;
+        }
+        EncodedBatch {
+            items: self.merger.merge(items),
+            finalizers,
+            count,
+            byte_size,
+            wire_size,
+        }
+    }
+}
+
+impl<S, B, K, SL> PartitionBatchSink<S, B, K, SL>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Sends the batches of the partitions holding fewer than `min_items`
+    /// events in a single request, when several of them are due at once.
+    ///
+    /// The shared batch is sent as the partition given by
+    /// `Coalesce::coalesced_partition`, and each of its events is tagged with
+    /// its own partition. It never holds more than a batch worth of events:
+    /// the small batches which don't fit are sent on their own.
+    ///
+    /// Along with `with_coalesce_merger`, only the small batches are held
+    /// for its window.
+    pub fn with_coalesce_threshold(mut self, min_items: usize) -> Self
+    where
+        B: Batch<Output = Vec<B::Input>>,
+        B::Input: Coalesce<K>,
+    {
+        let coalesce = self.coalesce.get_or_insert_with(|| {
+            Coalescing::new(Box::new(|batches: Vec<Vec<B::Input>>| {
+                batches.into_iter().flatten().collect::<Vec<_>>()
+            }))
+        });
+        coalesce.min_items = Some(min_items);
+        coalesce.shared_partition = Some((
+            <B::Input as Coalesce<K>>::coalesced_partition(),
+            |items, partition| {
+                for item in items {
+                    item.tag_partition(partition);
+                }
+            },
+        ));
+        self
+    }
+
+    /// Holds each batch for up to `window` once it is ready to be sent, and
+    /// sends it along with the batches which became ready in the meantime as
+    /// a single request holding all of their events.
+    ///
+    /// Batches are merged before being dispatched, so no event is sent twice.
+    pub fn with_coalesce_window(self, window: Duration) -> Self
+    where
+        B: Batch<Output = Vec<B::Input>>,
+    {
+        self.with_coalesce_merger(
+            |batches: Vec<Vec<B::Input>>| batches.into_iter().flatten().collect::<Vec<_>>(),
+            window,
+        )
+    }
+
+    /// Holds each batch for up to `window` once it is ready to be sent, and
+    /// sends it along with the batches which became ready in the meantime,
+    /// as a single request merged by `merger`.
+    pub fn with_coalesce_merger<M>(mut self, merger: M, window: Duration) -> Self
+    where
+        M: Merger<B::Output> + 'static,
+    {
+        let merger: Box<dyn Merger<B::Output>> = Box::new(merger);
+        let coalesce = match self.coalesce.take() {
+            Some(coalesce) => Coalescing { merger, ..coalesce },
+            None => Coalescing::new(merger),
+        };
+        self.coalesce = Some(Coalescing {
+            window: Some(window),
+            ..coalesce
+        });
+        self
+    }
+
+    /// Sends the coalesced batches as soon as `max_batches` of them are
+    /// held, without waiting for the end of their window.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless coalescing was set up first, with
+    /// `with_coalesce_threshold`, `with_coalesce_window` or
+    /// `with_coalesce_merger`.
+    pub fn with_max_coalesced_batches(mut self, max_batches: usize) -> Self {
+        self.coalesce
+            .as_mut()
+            .expect("with_max_coalesced_batches requires coalescing to be set up first")
+            .max_batches = Some(max_batches);
+        self
+    }
+
+    /// Sends the coalesced batches, once their window is over or the sink is
+    /// closing.
+    pub(super) fn poll_coalesced(&mut self, cx: &mut Context<'_>) -> Poll<crate::Result<()>> {
+        let coalesce = match self.coalesce.as_mut() {
+            Some(coalesce) => coalesce,
+            None => return Poll::Ready(Ok(())),
+        };
+        let full = coalesce.is_full();
+        let due = match coalesce.pending.as_mut() {
+            Some(pending) => {
+                self.closing
+                    || full
+                    || pending
+                        .deadline
+                        .as_mut()
+                        .map_or(true, |deadline| deadline.poll_unpin(cx).is_ready())
+            }
+            None => return Poll::Ready(Ok(())),
+        };
+        if !due {
+            return Poll::Pending;
+        }
+        match self.service.poll_ready(cx) {
+            Poll::Ready(Ok(())) => {}
+            poll => return poll,
+        }
+
+        let CoalescedBatch {
+            batches,
+            batch_size,
+            partitions,
+            ..
+        } = coalesce
+            .pending
+            .take()
+            .expect("Pending batch was just checked");
+        let mut batch = coalesce.merge(batches);
+        trace!(
+            message = "Service ready; Sending coalesced batch.",
+            partitions = partitions.len()
+        );
+        if let Some(sort_batch) = self.sort_batch.as_ref() {
+            sort_batch(&mut batch.items);
+        }
+        let mut request = match coalesce.shared_partition.as_ref() {
+            Some((partition, _)) => {
+                let label = self.service.shadow.as_ref().and_then(|_| {
+                    describe_partition(&self.key_display, &self.nearly_full, partition)
+                });
+                self.service.dispatch(None, label, batch, batch_size)
+            }
+            None => self.service.call(batch, batch_size),
+        };
+        if let Some(leases) = self.leases.as_ref() {
+            request = leases.release_after(partitions.clone(), request);
+        }
+        let future = tokio::spawn(request).map(|_| ()).shared();
+        if let Some(dependencies) = self.dependencies.as_mut() {
+            for partition in &partitions {
+                dependencies.dispatched(partition);
+            }
+        }
+        if let Some(map) = self.in_flight.as_mut() {
+            for partition in partitions {
+                map.insert(partition, future.clone().boxed());
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,105 @@
+//! Ordering of the batches of a `PartitionBatchSink` across partitions, see
+//! `PartitionBatchSink::with_dependencies`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use tower::Service;
+
+use super::{PartitionBatchSink, Response, ServiceLogic};
+use crate::sinks::util::{batch::Batch, buffer::Partition};
+
+/// Partitions whose batches are only sent once a batch of each of the
+/// partitions they depend on has been.
+pub(super) struct PartitionDependencies<K> {
+    depends_on: HashMap<K, Vec<K>>,
+    dispatched_partitions: HashSet<K>,
+}
+
+impl<K: Hash + Eq + Clone> PartitionDependencies<K> {
+    fn new(depends_on: HashMap<K, Vec<K>>) -> Self {
+        assert!(
+            !has_cycle(&depends_on),
+            "Partition dependencies are circular."
+        );
+        Self {
+            depends_on,
+            dispatched_partitions: HashSet::new(),
+        }
+    }
+
+    pub(super) fn dispatched(&mut self, partition: &K) {
+        if !self.dispatched_partitions.contains(partition) {
+            self.dispatched_partitions.insert(partition.clone());
+        }
+    }
+
+    /// Whether the batch of `partition` can be sent, given whether each of
+    /// its dependencies may still send something.
+    pub(super) fn are_met(&self, partition: &K, pending: impl Fn(&K) -> bool) -> bool {
+        self.depends_on.get(partition).map_or(true, |dependencies| {
+            dependencies.iter().all(|dependency| {
+                self.dispatched_partitions.contains(dependency) || !pending(dependency)
+            })
+        })
+    }
+}
+
+/// Whether following `dependencies` from any partition leads back to it.
+fn has_cycle<K: Hash + Eq>(dependencies: &HashMap<K, Vec<K>>) -> bool {
+    fn visit<'a, K: Hash + Eq>(
+        partition: &'a K,
+        dependencies: &'a HashMap<K, Vec<K>>,
+        visiting: &mut HashSet<&'a K>,
+        visited: &mut HashSet<&'a K>,
+    ) -> bool {
+        if visited.contains(partition) {
+            return false;
+        }
+        if !visiting.insert(partition) {
+            return true;
+        }
+        let cyclic = dependencies.get(partition).map_or(false, |next| {
+            next.iter()
+                .any(|dependency| visit(dependency, dependencies, visiting, visited))
+        });
+        visiting.remove(partition);
+        visited.insert(partition);
+        cyclic
+    }
+
+    let mut visiting = HashSet::new();
+    let mut visited = HashSet::new();
+    dependencies
+        .keys()
+        .any(|partition| visit(partition, dependencies, &mut visiting, &mut visited))
+}
+
+impl<S, B, K, SL> PartitionBatchSink<S, B, K, SL>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Only sends the batches of a partition once a batch of each of the
+    /// partitions it depends on in `deps` has been dispatched, for instance
+    /// so that a dimension table is loaded before its fact table.
+    ///
+    /// When closing, dependencies with nothing left to send don't hold back
+    /// the partitions depending on them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dependencies are circular.
+    pub fn with_dependencies(mut self, deps: HashMap<K, Vec<K>>) -> Self {
+        self.dependencies = Some(PartitionDependencies::new(deps));
+        self
+    }
+}
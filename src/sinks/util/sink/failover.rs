@@ -0,0 +1,207 @@
+//! Routing the requests of a `ServiceSink` to a secondary service while the
+//! primary one is unhealthy, see `PartitionBatchSink::with_regional_failover`.
+
+use std::{
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+use futures::{future::BoxFuture, FutureExt, TryFutureExt};
+use tokio::time::{Duration, Instant};
+use tower::Service;
+
+use super::{PartitionBatchSink, Response, ServiceLogic, ServiceSink};
+use crate::{
+    internal_events::{RegionalFailoverActivated, RegionalFailoverRecovered},
+    sinks::util::{batch::Batch, buffer::Partition},
+};
+
+/// The service a `ServiceSink` with regional failover sends a request to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum RegionSelector {
+    Primary,
+    Secondary,
+}
+
+impl RegionSelector {
+    const fn as_str(self) -> &'static str {
+        match self {
+            RegionSelector::Primary => "primary",
+            RegionSelector::Secondary => "secondary",
+        }
+    }
+}
+
+/// Routing of the requests of a `ServiceSink` with regional failover.
+#[derive(Debug)]
+pub(super) struct FailoverState {
+    consecutive_failures: u32,
+    active: RegionSelector,
+    /// When the requests were routed to the secondary service, or when the
+    /// primary service last failed a probe since.
+    failed_over_at: Option<Instant>,
+    /// Whether a request probing the primary service is in flight.
+    probing: bool,
+}
+
+impl FailoverState {
+    /// Picks the service of the next request, probing the primary service
+    /// with a single request once `probe_interval` has passed since it
+    /// failed. Also returns whether the request is that probe.
+    pub(super) fn route(&mut self, probe_interval: Duration) -> (RegionSelector, bool) {
+        match self.active {
+            RegionSelector::Primary => (RegionSelector::Primary, false),
+            RegionSelector::Secondary => {
+                let due = self
+                    .failed_over_at
+                    .map_or(true, |at| at.elapsed() >= probe_interval);
+                if due && !self.probing {
+                    self.probing = true;
+                    (RegionSelector::Primary, true)
+                } else {
+                    (RegionSelector::Secondary, false)
+                }
+            }
+        }
+    }
+
+    /// Records whether a request routed by `route` succeeded.
+    pub(super) fn record(
+        &mut self,
+        route: (RegionSelector, bool),
+        healthy: bool,
+        health_threshold: u32,
+    ) {
+        match (self.active, route) {
+            (RegionSelector::Primary, (RegionSelector::Primary, _)) => {
+                if healthy {
+                    self.consecutive_failures = 0;
+                    return;
+                }
+                self.consecutive_failures += 1;
+                if self.consecutive_failures >= health_threshold {
+                    self.active = RegionSelector::Secondary;
+                    self.failed_over_at = Some(Instant::now());
+                    emit!(&RegionalFailoverActivated {
+                        from_region: RegionSelector::Primary.as_str().to_owned(),
+                        to_region: RegionSelector::Secondary.as_str().to_owned(),
+                    });
+                }
+            }
+            (RegionSelector::Secondary, (RegionSelector::Primary, true)) => {
+                self.probing = false;
+                if healthy {
+                    self.active = RegionSelector::Primary;
+                    self.consecutive_failures = 0;
+                    self.failed_over_at = None;
+                    emit!(&RegionalFailoverRecovered);
+                } else {
+                    self.failed_over_at = Some(Instant::now());
+                }
+            }
+            // Requests sent to the primary service before it was failed over
+            // from, and requests sent to the secondary service.
+            _ => {}
+        }
+    }
+}
+
+/// Sends the requests of a `ServiceSink` to a secondary service while the
+/// primary one is unhealthy.
+pub(super) struct RegionalFailover<Request, R> {
+    pub(super) state: Arc<Mutex<FailoverState>>,
+    pub(super) health_threshold: u32,
+    pub(super) probe_interval: Duration,
+    pub(super) secondary:
+        Arc<dyn Fn(Request) -> BoxFuture<'static, crate::Result<R>> + Send + Sync>,
+    /// The route picked by `poll_ready` for the next request.
+    pub(super) route: Option<(RegionSelector, bool)>,
+}
+
+pub(super) fn lock_failover(
+    state: &Mutex<FailoverState>,
+) -> std::sync::MutexGuard<'_, FailoverState> {
+    state.lock().expect("failover state lock poisoned")
+}
+
+impl<S, Request, SL> ServiceSink<S, Request, SL>
+where
+    S: Service<Request>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Routes all the requests to a clone of `secondary` once the service
+    /// failed `health_threshold` requests in a row. After `probe_interval`, a
+    /// single request is sent to the service again, and the requests are
+    /// routed back to it if it succeeds.
+    pub(super) fn with_regional_failover<S2>(
+        mut self,
+        secondary: S2,
+        health_threshold: u32,
+        probe_interval: Duration,
+    ) -> Self
+    where
+        S2: Service<Request, Response = S::Response> + Clone + Send + 'static,
+        S2::Future: Send + 'static,
+        S2::Error: Into<crate::Error> + Send + 'static,
+        Request: Send + 'static,
+    {
+        let secondary = Mutex::new(secondary);
+        self.failover = Some(RegionalFailover {
+            state: Arc::new(Mutex::new(FailoverState {
+                consecutive_failures: 0,
+                active: RegionSelector::Primary,
+                failed_over_at: None,
+                probing: false,
+            })),
+            health_threshold,
+            probe_interval,
+            secondary: Arc::new(move |request: Request| {
+                let service = secondary
+                    .lock()
+                    .expect("secondary service lock poisoned")
+                    .clone();
+                tower::ServiceExt::oneshot(service, request)
+                    .err_into()
+                    .boxed()
+            }),
+            route: None,
+        });
+        self
+    }
+}
+
+impl<S, B, K, SL> PartitionBatchSink<S, B, K, SL>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Routes the batches to `secondary` once the service failed
+    /// `health_threshold` times in a row, until a single batch sent to the
+    /// service again after `probe_interval` succeeds.
+    pub fn with_regional_failover<S2>(
+        mut self,
+        secondary: S2,
+        health_threshold: u32,
+        probe_interval: Duration,
+    ) -> Self
+    where
+        S2: Service<B::Output, Response = S::Response> + Clone + Send + 'static,
+        S2::Future: Send + 'static,
+        S2::Error: Into<crate::Error> + Send + 'static,
+        B::Output: Send + 'static,
+    {
+        self.service =
+            self.service
+                .with_regional_failover(secondary, health_threshold, probe_interval);
+        self
+    }
+}
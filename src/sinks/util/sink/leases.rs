@@ -0,0 +1,266 @@
+//! Leases taken by a `PartitionBatchSink` on its partitions before sending
+//! their batches, see `PartitionBatchSink::with_lease_coordinator`.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::{
+    future::{self, BoxFuture},
+    ready,
+    stream::FuturesUnordered,
+    FutureExt, Stream,
+};
+use tokio::time::{sleep, Duration, Instant, Sleep};
+use tower::Service;
+
+use super::{PartitionBatchSink, Response, ServiceLogic};
+use crate::{
+    internal_events::LeaseCoordinatorFailed,
+    sinks::util::{batch::Batch, buffer::Partition, lease::LeaseCoordinator},
+};
+
+/// Leases of the partitions of a `PartitionBatchSink`, taken before sending
+/// their batches.
+pub(super) struct PartitionLeases<K> {
+    coordinator: Arc<dyn LeaseCoordinator<K>>,
+    ttl: Duration,
+    /// Partitions whose lease is held, or whose requests are still in
+    /// flight. Shared with the requests, which release the lease once the
+    /// last of them completes.
+    held: Arc<Mutex<HashMap<K, HeldLease>>>,
+    /// Partitions whose lease is being taken or renewed.
+    acquiring: HashMap<K, BoxFuture<'static, crate::Result<bool>>>,
+    /// Partitions whose lease could not be taken, until their lease is tried
+    /// again.
+    waits: HashMap<K, Pin<Box<Sleep>>>,
+    /// Leases being given up by partitions which won't send a batch.
+    releasing: FuturesUnordered<BoxFuture<'static, ()>>,
+}
+
+struct HeldLease {
+    /// Number of requests of the partition in flight.
+    in_flight: usize,
+    /// When the lease was last taken or renewed. `None` once it has been
+    /// lost while requests of the partition were still in flight.
+    acquired_at: Option<Instant>,
+}
+
+impl<K: Hash + Eq + Clone + Send + 'static> PartitionLeases<K> {
+    fn new(coordinator: Arc<dyn LeaseCoordinator<K>>, ttl: Duration) -> Self {
+        Self {
+            coordinator,
+            ttl,
+            held: Arc::new(Mutex::new(HashMap::new())),
+            acquiring: HashMap::new(),
+            waits: HashMap::new(),
+            releasing: FuturesUnordered::new(),
+        }
+    }
+
+    /// Whether the lease of `partition` is held, trying to take it unless
+    /// it could not be taken less than `ttl` ago.
+    ///
+    /// A held lease is renewed once half of its `ttl` has elapsed, and is
+    /// treated as lost if it can't be renewed. A lease which could not be
+    /// taken because the coordinator failed is not held either, its batches
+    /// are kept until it is tried again.
+    pub(super) fn poll_acquire(&mut self, partition: &K, cx: &mut Context<'_>) -> bool {
+        let elapsed = lock_leases(&self.held)
+            .get(partition)
+            .and_then(|lease| lease.acquired_at)
+            .map(|acquired_at| acquired_at.elapsed());
+        if elapsed.map_or(false, |elapsed| elapsed < self.ttl / 2) {
+            return true;
+        }
+        // The lease can still be used while it is being renewed.
+        let held = elapsed.map_or(false, |elapsed| elapsed < self.ttl);
+        if !held {
+            if let Some(wait) = self.waits.get_mut(partition) {
+                if wait.poll_unpin(cx).is_pending() {
+                    return false;
+                }
+                self.waits.remove(partition);
+            }
+        }
+        let acquire = self
+            .acquiring
+            .entry(partition.clone())
+            .or_insert_with(|| self.coordinator.try_acquire(partition, self.ttl));
+        let acquired = match acquire.poll_unpin(cx) {
+            Poll::Pending => return held,
+            Poll::Ready(acquired) => acquired,
+        };
+        self.acquiring.remove(partition);
+        match acquired {
+            Ok(true) => {
+                lock_leases(&self.held)
+                    .entry(partition.clone())
+                    .or_insert(HeldLease {
+                        in_flight: 0,
+                        acquired_at: None,
+                    })
+                    .acquired_at = Some(Instant::now());
+                return true;
+            }
+            Ok(false) => (),
+            Err(error) => emit!(&LeaseCoordinatorFailed {
+                error: error.to_string(),
+            }),
+        }
+        self.lose(partition);
+        let mut wait = Box::pin(sleep(self.ttl));
+        // Registers the task to be woken up once the wait is over.
+        let _ = wait.poll_unpin(cx);
+        self.waits.insert(partition.clone(), wait);
+        false
+    }
+
+    /// Forgets the lease of `partition`, which is no longer held, keeping
+    /// track of its requests in flight.
+    fn lose(&mut self, partition: &K) {
+        let mut held = lock_leases(&self.held);
+        if let Some(lease) = held.get_mut(partition) {
+            lease.acquired_at = None;
+            if lease.in_flight == 0 {
+                held.remove(partition);
+            }
+        }
+    }
+
+    /// Gives up the lease of `partition` if none of its requests are in
+    /// flight, as when it is evicted from the sink.
+    pub(super) fn forget(&mut self, partition: &K, cx: &mut Context<'_>) {
+        self.acquiring.remove(partition);
+        self.waits.remove(partition);
+        let mut held = lock_leases(&self.held);
+        if held
+            .get(partition)
+            .map_or(false, |lease| lease.in_flight == 0)
+        {
+            let acquired_at = held.remove(partition).and_then(|lease| lease.acquired_at);
+            if acquired_at.map_or(false, |acquired_at| acquired_at.elapsed() < self.ttl) {
+                self.releasing
+                    .push(release_lease(&*self.coordinator, partition));
+            }
+        }
+        drop(held);
+        // Registers the task to be woken up once the lease is released.
+        let _ = self.poll_released(cx);
+    }
+
+    /// Gives up the leases of all partitions without requests in flight, as
+    /// when the sink is closing.
+    pub(super) fn forget_all(&mut self, cx: &mut Context<'_>) {
+        let partitions = lock_leases(&self.held).keys().cloned().collect::<Vec<_>>();
+        for partition in partitions {
+            self.forget(&partition, cx);
+        }
+        self.acquiring.clear();
+        self.waits.clear();
+    }
+
+    /// Whether all the leases given up have been released.
+    pub(super) fn poll_released(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        while ready!(Pin::new(&mut self.releasing).poll_next(cx)).is_some() {}
+        Poll::Ready(())
+    }
+
+    /// Releases the leases of `partitions` once `request`, and the other
+    /// requests of these partitions, complete.
+    pub(super) fn release_after(
+        &self,
+        partitions: Vec<K>,
+        request: BoxFuture<'static, ()>,
+    ) -> BoxFuture<'static, ()> {
+        {
+            let mut held = lock_leases(&self.held);
+            for partition in &partitions {
+                held.entry(partition.clone())
+                    .or_insert(HeldLease {
+                        in_flight: 0,
+                        acquired_at: None,
+                    })
+                    .in_flight += 1;
+            }
+        }
+        let coordinator = Arc::clone(&self.coordinator);
+        let held = Arc::clone(&self.held);
+        request
+            .then(move |()| {
+                let mut held = lock_leases(&held);
+                let releases = partitions
+                    .into_iter()
+                    .filter_map(|partition| {
+                        let lease = held.get_mut(&partition)?;
+                        lease.in_flight -= 1;
+                        if lease.in_flight > 0 {
+                            return None;
+                        }
+                        // A lost lease isn't ours to release anymore.
+                        held.remove(&partition)?.acquired_at?;
+                        Some(release_lease(&*coordinator, &partition))
+                    })
+                    .collect::<Vec<_>>();
+                future::join_all(releases).map(drop)
+            })
+            .boxed()
+    }
+}
+
+/// Releases the lease of `partition`, reporting the coordinator failures.
+fn release_lease<K>(
+    coordinator: &dyn LeaseCoordinator<K>,
+    partition: &K,
+) -> BoxFuture<'static, ()> {
+    coordinator
+        .release(partition)
+        .map(|result| {
+            if let Err(error) = result {
+                emit!(&LeaseCoordinatorFailed {
+                    error: error.to_string(),
+                });
+            }
+        })
+        .boxed()
+}
+
+fn lock_leases<K>(
+    held: &Mutex<HashMap<K, HeldLease>>,
+) -> std::sync::MutexGuard<'_, HashMap<K, HeldLease>> {
+    held.lock().expect("partition leases lock poisoned")
+}
+
+impl<S, B, K, SL> PartitionBatchSink<S, B, K, SL>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Only sends the batches of a partition while holding its lease from
+    /// `coordinator`, so that several instances receiving events of the same
+    /// partition don't each send them.
+    ///
+    /// Leases are taken for `ttl`, and renewed when a batch is sent once half
+    /// of it has elapsed. The batches of a partition whose lease could not be
+    /// taken or renewed, because another instance holds it or the coordinator
+    /// failed, are held for `ttl` before trying to take the lease again.
+    /// Leases are released once the requests of their partition complete, or
+    /// once the partition is evicted or the sink closes.
+    pub fn with_lease_coordinator<L>(mut self, coordinator: L, ttl: Duration) -> Self
+    where
+        L: LeaseCoordinator<K> + 'static,
+    {
+        self.leases = Some(PartitionLeases::new(Arc::new(coordinator), ttl));
+        self
+    }
+}
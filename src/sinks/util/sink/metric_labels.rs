@@ -0,0 +1,128 @@
+//! Partition labels of the internal metrics of a `PartitionBatchSink`, see
+//! `PartitionBatchSink::with_metrics_cardinality_cap`.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hash,
+};
+
+use tower::Service;
+
+use super::{PartitionBatchSink, Response, ServiceLogic};
+use crate::sinks::util::{batch::Batch, buffer::Partition};
+
+/// Label value of the partitions which aren't told apart in metrics.
+pub(super) const OTHER_PARTITIONS_LABEL: &str = "__other__";
+
+/// Caps the number of label values partitions are reported under in metrics.
+///
+/// Only `max_label_values` partitions at a time get a label value of their
+/// own, the following ones are reported as `"__other__"`. A partition gives
+/// its label value back once it is evicted from the sink. The label values of
+/// the partitions seen most recently are remembered.
+#[derive(Debug)]
+pub(super) struct MetricLabels {
+    max_label_values: usize,
+    /// Partitions which were given a label value of their own.
+    granted: HashSet<String>,
+    /// Label value of each partition, along with when it was last seen.
+    labels: HashMap<String, (String, u64)>,
+    /// Sightings of the partitions of `labels`, least recent first. Those
+    /// superseded by a later sighting are skipped.
+    recency: VecDeque<(u64, String)>,
+    seen: u64,
+}
+
+impl MetricLabels {
+    pub(super) fn new(max_label_values: usize) -> Self {
+        Self {
+            max_label_values,
+            granted: HashSet::new(),
+            labels: HashMap::new(),
+            recency: VecDeque::new(),
+            seen: 0,
+        }
+    }
+
+    pub(super) fn label(&mut self, partition: &str) -> String {
+        self.seen += 1;
+        let seen = self.seen;
+        if let Some((label, last_seen)) = self.labels.get_mut(partition) {
+            *last_seen = seen;
+            let label = label.clone();
+            self.recency.push_back((seen, partition.to_owned()));
+            self.compact();
+            return label;
+        }
+
+        let label =
+            if self.granted.contains(partition) || self.granted.len() < self.max_label_values {
+                self.granted.insert(partition.to_owned());
+                partition.to_owned()
+            } else {
+                OTHER_PARTITIONS_LABEL.to_owned()
+            };
+        if self.labels.len() >= self.max_label_values {
+            self.evict_least_recent();
+        }
+        self.labels
+            .insert(partition.to_owned(), (label.clone(), seen));
+        self.recency.push_back((seen, partition.to_owned()));
+        self.compact();
+        label
+    }
+
+    /// Gives back the label value of a partition evicted from the sink.
+    pub(super) fn forget(&mut self, partition: &str) {
+        self.granted.remove(partition);
+        self.labels.remove(partition);
+    }
+
+    fn evict_least_recent(&mut self) {
+        while let Some((seen, partition)) = self.recency.pop_front() {
+            if let Some((_, last_seen)) = self.labels.get(&partition) {
+                if *last_seen == seen {
+                    self.labels.remove(&partition);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Drops the superseded sightings once they outnumber the others.
+    fn compact(&mut self) {
+        if self.recency.len() <= 2 * self.labels.len() {
+            return;
+        }
+        let labels = &self.labels;
+        self.recency.retain(|(seen, partition)| {
+            labels
+                .get(partition)
+                .map_or(false, |(_, last_seen)| last_seen == seen)
+        });
+    }
+}
+
+impl<S, B, K, SL> PartitionBatchSink<S, B, K, SL>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Labels internal metrics with the partition they are about, using at
+    /// most `max_label_values` distinct label values at a time. Partitions
+    /// beyond them are labelled `"__other__"`, until the partitions with a
+    /// label value of their own are evicted by `with_idle_gc` or
+    /// `with_partition_ttl`.
+    ///
+    /// Partitions are described as in logs, see `with_key_display`.
+    pub fn with_metrics_cardinality_cap(mut self, max_label_values: usize) -> Self {
+        self.metric_labels = Some(MetricLabels::new(max_label_values));
+        self
+    }
+}
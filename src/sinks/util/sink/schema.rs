@@ -0,0 +1,92 @@
+//! JSON Schema validation of the events of a `BatchSink`, see
+//! `BatchSink::with_schema_validation`.
+
+use std::sync::Arc;
+
+use jsonschema::JSONSchema;
+use serde::{de::DeserializeOwned, Serialize};
+use tower::Service;
+
+use super::{BatchSink, Response, ServiceLogic};
+use crate::{
+    event::EventStatus, internal_events::BatchSinkSchemaViolation, sinks::util::batch::Batch,
+};
+
+/// What a `BatchSink` does with the events which don't match its schema.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViolationPolicy {
+    /// Drops them, acking them as delivered.
+    Drop,
+    /// Drops them, marking them as rejected.
+    Error,
+    /// Adds the validation errors to the given field and sends them anyway.
+    Tag(String),
+}
+
+impl<S, B, SL> BatchSink<S, B, SL>
+where
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    B: Batch,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Validates each item against the JSON `schema`, after `with_transform`,
+    /// handling the items which don't match it as `on_violation` says.
+    pub fn with_schema_validation(
+        mut self,
+        schema: serde_json::Value,
+        on_violation: ViolationPolicy,
+    ) -> crate::Result<Self>
+    where
+        B::Input: Serialize + DeserializeOwned,
+    {
+        let schema = JSONSchema::compile(&schema)
+            .map_err(|error| format!("Invalid JSON schema: {}", error))?;
+        self.validation = Some(Arc::new(move |item| {
+            check_schema(&schema, &on_violation, item)
+        }));
+        Ok(self)
+    }
+}
+
+/// Validates `item` against `schema`, applying `on_violation` if it doesn't
+/// match it.
+fn check_schema<T>(
+    schema: &JSONSchema,
+    on_violation: &ViolationPolicy,
+    item: T,
+) -> Result<T, EventStatus>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut value = match serde_json::to_value(&item) {
+        Ok(value) => value,
+        Err(error) => {
+            emit!(&BatchSinkSchemaViolation {
+                errors: &[error.to_string()],
+                dropped: true,
+            });
+            return Err(EventStatus::Rejected);
+        }
+    };
+    let errors = match schema.validate(&value) {
+        Ok(()) => return Ok(item),
+        Err(errors) => errors.map(|error| error.to_string()).collect::<Vec<_>>(),
+    };
+
+    emit!(&BatchSinkSchemaViolation {
+        errors: &errors,
+        dropped: !matches!(on_violation, ViolationPolicy::Tag(_)),
+    });
+    match on_violation {
+        ViolationPolicy::Drop => Err(EventStatus::Delivered),
+        ViolationPolicy::Error => Err(EventStatus::Rejected),
+        ViolationPolicy::Tag(field) => {
+            let object = value.as_object_mut().ok_or(EventStatus::Rejected)?;
+            object.insert(field.clone(), errors.into());
+            serde_json::from_value(value).map_err(|_| EventStatus::Rejected)
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Sending the requests of a `ServiceSink` to a shadow service as well, see
+//! `PartitionBatchSink::with_shadow_service`.
+
+use std::{
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use tower::Service;
+
+use super::{PartitionBatchSink, Response, ServiceLogic, ServiceSink};
+use crate::sinks::util::{batch::Batch, buffer::Partition};
+
+/// Compares the response of the service to a request with the one of the
+/// shadow service, once it arrives. `None` if the shadow request failed.
+pub(super) type ShadowCompare<R> = Box<dyn FnOnce(&R) -> BoxFuture<'static, Option<bool>> + Send>;
+
+impl<S, Request, SL> ServiceSink<S, Request, SL>
+where
+    S: Service<Request>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Sends every request to a clone of `shadow` as well, in a separate task,
+    /// and reports the requests for which `compare` finds that the responses
+    /// differ.
+    pub(super) fn with_shadow_service<S2, C>(mut self, shadow: S2, compare: C) -> Self
+    where
+        S2: Service<Request> + Clone + Send + 'static,
+        S2::Future: Send + 'static,
+        S2::Response: Send + 'static,
+        S2::Error: Send + 'static,
+        S::Response: Clone + Send + 'static,
+        Request: Clone + Send + 'static,
+        C: Fn(&Request, &S::Response, &S2::Response) -> bool + Send + Sync + 'static,
+    {
+        let shadow = Mutex::new(shadow);
+        let compare = Arc::new(compare);
+        self.shadow = Some(Arc::new(
+            move |request: &Request| -> ShadowCompare<S::Response> {
+                let service = shadow.lock().expect("shadow service lock poisoned").clone();
+                let request = request.clone();
+                let response = tokio::spawn(tower::ServiceExt::oneshot(service, request.clone()));
+                let compare = Arc::clone(&compare);
+                Box::new(move |primary: &S::Response| {
+                    let primary = primary.clone();
+                    async move {
+                        match response.await {
+                            Ok(Ok(shadow)) => Some(compare(&request, &primary, &shadow)),
+                            _ => None,
+                        }
+                    }
+                    .boxed()
+                })
+            },
+        ));
+        self
+    }
+}
+
+impl<S, B, K, SL> PartitionBatchSink<S, B, K, SL>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Sends every batch to `shadow` as well, alongside the service, and
+    /// reports a `ShadowServiceMismatch` whenever `compare` finds that their
+    /// responses differ.
+    ///
+    /// Only the response of the service decides whether the events are
+    /// delivered, failures of the shadow service are ignored. Batches
+    /// coalesced across partitions are reported without a partition.
+    pub fn with_shadow_service<S2, C>(mut self, shadow: S2, compare: C) -> Self
+    where
+        S2: Service<B::Output> + Clone + Send + 'static,
+        S2::Future: Send + 'static,
+        S2::Response: Send + 'static,
+        S2::Error: Send + 'static,
+        S::Response: Clone,
+        B::Output: Clone + Send + 'static,
+        C: Fn(&B::Output, &S::Response, &S2::Response) -> bool + Send + Sync + 'static,
+    {
+        self.service = self.service.with_shadow_service(shadow, compare);
+        self
+    }
+}
@@ -0,0 +1,79 @@
+//! Spilling the events a `BatchSink` can't hold in memory to disk, see
+//! `BatchSink::with_overflow_spill`.
+
+use std::{
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Sink;
+use serde::{de::DeserializeOwned, Serialize};
+use tower::Service;
+
+use super::{BatchSink, Response, ServiceLogic};
+use crate::sinks::util::{batch::Batch, buffer::PartitionInnerBuffer, spill::DiskSpill};
+
+impl<S, B, SL> BatchSink<S, B, SL>
+where
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    B: Batch,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Spills the events which don't fit in memory, while the service isn't
+    /// ready, to an append-only file at `path` instead of applying backpressure.
+    ///
+    /// Spilled events are replayed in order once the batch has room again.
+    /// Backpressure is only applied once `max_spill_bytes` have been spilled.
+    pub fn with_overflow_spill(mut self, path: PathBuf, max_spill_bytes: u64) -> Self
+    where
+        B::Input: Serialize + DeserializeOwned + 'static,
+    {
+        self.spill = Some(Box::new(DiskSpill::new(path, max_spill_bytes)));
+        self
+    }
+
+    /// Whether the sink is ready for more events, which are spilled while
+    /// the inner sink is full.
+    pub(super) fn poll_ready_inner(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), crate::Error>> {
+        let spill_is_empty = match &self.spill {
+            Some(spill) => spill.is_empty(),
+            None => return self.project().inner.poll_ready(cx),
+        };
+
+        if self.inner.buffer.is_some() || !spill_is_empty {
+            if let Poll::Ready(Err(error)) = self.as_mut().poll_flush(cx) {
+                return Poll::Ready(Err(error));
+            }
+        }
+        if self.spill.as_ref().map_or(false, |spill| spill.is_full()) {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Hands the spilled events back to the inner sink, for as long as they
+    /// fit in its batches.
+    pub(super) fn replay_spill(self: Pin<&mut Self>) -> crate::Result<()> {
+        let mut this = self.project();
+        if let Some(spill) = this.spill.as_mut() {
+            while this.inner.buffer.is_none() {
+                match spill.pop()? {
+                    Some(item) => this
+                        .inner
+                        .as_mut()
+                        .start_send(item.map(|item| PartitionInnerBuffer::new(item, ())))?,
+                    None => break,
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,3686 @@
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+        Arc, Mutex,
+    },
+};
+
+use bytes::Bytes;
+use futures::{
+    future, stream,
+    task::{noop_waker_ref, waker, ArcWake},
+    SinkExt, StreamExt,
+};
+use tokio::{task::yield_now, time::Instant};
+use vector_buffers::Acker;
+use vector_core::event_test_util;
+
+use super::{audit::AUDIT_LOG_BUFFER_SIZE, metric_labels::OTHER_PARTITIONS_LABEL, *};
+use crate::{
+    event::{metric::MetricValue, Event},
+    metrics::Controller,
+    sinks::util::{
+        buffer::{Coalesce, DynBatch, PerPartitionBatch, Sourced},
+        lease::LeaseCoordinator,
+        BatchSettings, EncodedLength, VecBuffer,
+    },
+    test_util::{components::init_test, temp_dir, temp_file, trace_init},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+impl EncodedLength for usize {
+    fn encoded_length(&self) -> usize {
+        22
+    }
+}
+
+async fn advance_time(duration: Duration) {
+    tokio::time::pause();
+    tokio::time::advance(duration).await;
+    tokio::time::resume();
+}
+
+#[tokio::test]
+async fn batch_sink_acking_sequential() {
+    let (acker, ack_counter) = Acker::basic();
+
+    let svc = tower::service_fn(|_| future::ok::<_, std::io::Error>(()));
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let buffered = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    let _ = buffered
+        .sink_map_err(drop)
+        .send_all(&mut stream::iter(0..22).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    assert_eq!(ack_counter.load(Relaxed), 22);
+}
+
+#[tokio::test]
+async fn batch_sink_sorts_batches() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+    let svc = tower::service_fn(|req: Vec<usize>| {
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 4;
+
+    let sink = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_sort_key(|timestamp: &usize| *timestamp);
+
+    sink.sink_map_err(drop)
+        .send_all(
+            &mut stream::iter(vec![30, 10, 40, 20, 70, 50])
+                .map(|item| Ok(EncodedEvent::new(item, 0))),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        &*sent_requests.lock().unwrap(),
+        &vec![vec![10, 20, 30, 40], vec![50, 70]]
+    );
+}
+
+#[tokio::test]
+async fn batch_sink_acking_unordered() {
+    trace_init();
+
+    // Services future will be spawned and work between `yield_now` calls.
+    let (acker, ack_counter) = Acker::basic();
+
+    let svc = tower::service_fn(|req: Vec<usize>| async move {
+        let duration = match req[0] {
+            0 => Duration::from_secs(1),
+            1 => Duration::from_secs(1),
+            2 => Duration::from_secs(1),
+
+            // The 4th request will introduce some sort of
+            // latency spike to ensure later events don't
+            // get acked.
+            3 => Duration::from_secs(5),
+            4 => Duration::from_secs(1),
+            5 => Duration::from_secs(1),
+            _ => unreachable!(),
+        };
+
+        sleep(duration).await;
+        Ok::<(), Infallible>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    let mut sink = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.start_send_unpin(EncodedEvent::new(0, 0)),
+        Ok(())
+    ));
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.start_send_unpin(EncodedEvent::new(1, 0)),
+        Ok(())
+    ));
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.start_send_unpin(EncodedEvent::new(2, 0)),
+        Ok(())
+    ));
+
+    // Clear internal buffer
+    assert!(matches!(sink.poll_flush_unpin(&mut cx), Poll::Pending));
+    assert_eq!(ack_counter.load(Relaxed), 0);
+
+    yield_now().await;
+    advance_time(Duration::from_secs(3)).await;
+    yield_now().await;
+
+    assert!(matches!(
+        sink.poll_flush_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.poll_flush_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.poll_flush_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+
+    assert_eq!(ack_counter.load(Relaxed), 3);
+
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.start_send_unpin(EncodedEvent::new(3, 0)),
+        Ok(())
+    ));
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.start_send_unpin(EncodedEvent::new(4, 0)),
+        Ok(())
+    ));
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.start_send_unpin(EncodedEvent::new(5, 0)),
+        Ok(())
+    ));
+
+    // Clear internal buffer
+    assert!(matches!(sink.poll_flush_unpin(&mut cx), Poll::Pending));
+    assert_eq!(ack_counter.load(Relaxed), 3);
+
+    yield_now().await;
+    advance_time(Duration::from_secs(2)).await;
+    yield_now().await;
+
+    assert!(matches!(sink.poll_flush_unpin(&mut cx), Poll::Pending));
+
+    // Check that events 3,4,5 have not been acked yet
+    // only the three previous should be acked.
+    assert_eq!(ack_counter.load(Relaxed), 3);
+
+    yield_now().await;
+    advance_time(Duration::from_secs(5)).await;
+    yield_now().await;
+
+    assert!(matches!(
+        sink.poll_flush_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.poll_flush_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.poll_flush_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+
+    assert_eq!(ack_counter.load(Relaxed), 6);
+}
+
+#[tokio::test]
+async fn batch_sink_buffers_messages_until_limit() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+
+        sent_requests.lock().unwrap().push(req);
+
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+    let buffered = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    let _ = buffered
+        .sink_map_err(drop)
+        .send_all(&mut stream::iter(0..22).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let output = sent_requests.lock().unwrap();
+    assert_eq!(
+        &*output,
+        &vec![
+            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+            vec![10, 11, 12, 13, 14, 15, 16, 17, 18, 19],
+            vec![20, 21]
+        ]
+    );
+}
+
+#[tokio::test]
+async fn batch_sink_raw_sends_bytes_unmodified() {
+    let (acker, ack_counter) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req: Vec<Bytes>| {
+        let sent_requests = Arc::clone(&sent_requests);
+
+        sent_requests.lock().unwrap().push(req);
+
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+    let buffered = BatchSink::raw(svc, BytesBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    let payloads = vec![
+        Bytes::from_static(b"{\"message\":\"one\"}"),
+        Bytes::from_static(b"\x00\x01binary"),
+        Bytes::from_static(b"three\n"),
+    ];
+    let _ = buffered
+        .sink_map_err(drop)
+        .send_all(&mut stream::iter(payloads.clone()).map(Ok))
+        .await
+        .unwrap();
+
+    let output = sent_requests.lock().unwrap();
+    assert_eq!(
+        &*output,
+        &vec![payloads[..2].to_vec(), payloads[2..].to_vec()]
+    );
+    assert_eq!(ack_counter.load(Relaxed), 3);
+}
+
+#[tokio::test]
+async fn batch_sink_applies_transform() {
+    let (acker, ack_counter) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+
+        sent_requests.lock().unwrap().push(req);
+
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 5;
+
+    let counter = AtomicUsize::new(0);
+    let buffered = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_transform(move |(_, item): (usize, usize)| {
+            (item % 3 != 2).then(|| (counter.fetch_add(1, Relaxed), item))
+        });
+
+    let _ = buffered
+        .sink_map_err(drop)
+        .send_all(&mut stream::iter(0..15).map(|item| Ok(EncodedEvent::new((0, item), 0))))
+        .await
+        .unwrap();
+
+    let output = sent_requests.lock().unwrap();
+    assert_eq!(
+        &*output,
+        &vec![
+            vec![(0, 0), (1, 1), (2, 3), (3, 4), (4, 6)],
+            vec![(5, 7), (6, 9), (7, 10), (8, 12), (9, 13)],
+        ]
+    );
+    assert_eq!(ack_counter.load(Relaxed), 15);
+}
+
+impl EncodedLength for serde_json::Value {
+    fn encoded_length(&self) -> usize {
+        10 // Dummy value
+    }
+}
+
+#[cfg(feature = "sinks-utils-schema-validation")]
+#[tokio::test]
+async fn batch_sink_validates_events_against_schema() {
+    use crate::event::{BatchNotifier, BatchStatus, EventFinalizer};
+
+    let schema = serde_json::json!({
+        "type": "object",
+        "required": ["name"],
+    });
+    let policies = vec![
+        (ViolationPolicy::Drop, BatchStatus::Delivered),
+        (ViolationPolicy::Error, BatchStatus::Rejected),
+        (
+            ViolationPolicy::Tag("schema_error".to_owned()),
+            BatchStatus::Delivered,
+        ),
+    ];
+
+    for (policy, expected_status) in policies {
+        let (acker, ack_counter) = Acker::basic();
+        let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+        let svc = tower::service_fn(|req: Vec<serde_json::Value>| {
+            let sent_requests = Arc::clone(&sent_requests);
+            sent_requests.lock().unwrap().push(req);
+            future::ok::<_, std::io::Error>(())
+        });
+
+        let mut batch_settings = BatchSettings::default();
+        batch_settings.size.bytes = 9999;
+        batch_settings.size.events = 5;
+
+        let sink = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_schema_validation(schema.clone(), policy.clone())
+            .unwrap();
+
+        let (batch, receiver) = BatchNotifier::new_with_receiver();
+        let invalid = EncodedEvent {
+            item: serde_json::json!({"value": 2}),
+            finalizers: EventFinalizers::new(EventFinalizer::new(batch)),
+            byte_size: 0,
+        };
+        let events = vec![
+            EncodedEvent::new(serde_json::json!({"name": "first", "value": 1}), 0),
+            invalid,
+            EncodedEvent::new(serde_json::json!({"name": "third", "value": 3}), 0),
+        ];
+        sink.sink_map_err(drop)
+            .send_all(&mut stream::iter(events).map(Ok))
+            .await
+            .unwrap();
+
+        assert_eq!(receiver.await, expected_status, "{:?}", policy);
+        assert_eq!(ack_counter.load(Relaxed), 3, "{:?}", policy);
+
+        let output = sent_requests.lock().unwrap().concat();
+        let names: Vec<_> = output.iter().map(|event| event.get("name")).collect();
+        match policy {
+            ViolationPolicy::Tag(field) => {
+                assert_eq!(output.len(), 3);
+                assert_eq!(output[1]["value"], 2);
+                assert!(!output[1][&field].as_array().unwrap().is_empty());
+                assert!(output[0].get(&field).is_none());
+            }
+            _ => assert_eq!(names, vec![Some(&"first".into()), Some(&"third".into())]),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GatedService {
+    open: Arc<AtomicBool>,
+    requests: Arc<Mutex<Vec<Vec<usize>>>>,
+}
+
+impl Service<Vec<usize>> for GatedService {
+    type Response = ();
+    type Error = std::io::Error;
+    type Future = future::Ready<Result<(), std::io::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.open.load(Relaxed) {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: Vec<usize>) -> Self::Future {
+        self.requests.lock().unwrap().push(req);
+        future::ok(())
+    }
+}
+
+#[tokio::test]
+async fn batch_sink_warns_when_nearly_full() {
+    init_test();
+    let nearly_full_warnings = || {
+        Controller::get()
+            .unwrap()
+            .capture_metrics()
+            .find(|metric| metric.name() == "batch_nearly_full_total")
+            .map_or(0.0, |metric| match metric.value() {
+                MetricValue::Counter { value } => *value,
+                _ => panic!("batch_nearly_full_total should be a counter"),
+            })
+    };
+
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_| future::ok::<_, std::io::Error>(()));
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let mut sink = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_warn_threshold(0.8);
+
+    for i in 0..7 {
+        sink.start_send_unpin(EncodedEvent::new(i, 0)).unwrap();
+    }
+    assert_eq!(nearly_full_warnings(), 0.0);
+
+    sink.start_send_unpin(EncodedEvent::new(7, 0)).unwrap();
+    assert_eq!(nearly_full_warnings(), 1.0);
+
+    sink.start_send_unpin(EncodedEvent::new(8, 0)).unwrap();
+    assert_eq!(nearly_full_warnings(), 1.0);
+}
+
+#[tokio::test]
+async fn batch_sink_reports_cold_start_once() {
+    init_test();
+    tokio::time::pause();
+    let cold_starts = || {
+        Controller::get()
+            .unwrap()
+            .capture_metrics()
+            .find(|metric| metric.name() == "sink_cold_start_ms")
+            .map(|metric| match metric.value() {
+                MetricValue::AggregatedHistogram { count, sum, .. } => (*count, *sum),
+                _ => panic!("sink_cold_start_ms should be a histogram"),
+            })
+    };
+
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_| future::ok::<_, std::io::Error>(()));
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    let mut sink = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+    tokio::time::advance(Duration::from_millis(25)).await;
+
+    sink.send(EncodedEvent::new(0, 0)).await.unwrap();
+    let (count, elapsed_ms) = cold_starts().expect("cold start should be reported");
+    assert_eq!(count, 1);
+    assert!(elapsed_ms > 0.0);
+
+    sink.send(EncodedEvent::new(1, 0)).await.unwrap();
+    assert_eq!(cold_starts().map(|(count, _)| count), Some(1));
+}
+
+#[tokio::test]
+async fn batch_sink_reports_latency_sla_violations() {
+    init_test();
+    tokio::time::pause();
+    let violations = || {
+        Controller::get()
+            .unwrap()
+            .capture_metrics()
+            .find(|metric| metric.name() == "sink_latency_sla_violations_total")
+            .map_or(0.0, |metric| match metric.value() {
+                MetricValue::Counter { value } => *value,
+                _ => panic!("sink_latency_sla_violations_total should be a counter"),
+            })
+    };
+
+    let (acker, _) = Acker::basic();
+    let svc = GatedService {
+        open: Arc::new(AtomicBool::new(false)),
+        requests: Arc::new(Mutex::new(Vec::new())),
+    };
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+
+    let mut sink = BatchSink::new(
+        svc.clone(),
+        VecBuffer::new(batch_settings.size),
+        TIMEOUT,
+        acker,
+    )
+    .with_latency_sla(Duration::from_millis(10));
+
+    // The third event fills the first batch, which is then due.
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for item in 0..3 {
+        sink.start_send_unpin(EncodedEvent::new(item, 0)).unwrap();
+    }
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+
+    tokio::time::advance(Duration::from_millis(50)).await;
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert_eq!(violations(), 0.0);
+
+    svc.open.store(true, Relaxed);
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert_eq!(&*svc.requests.lock().unwrap(), &vec![vec![0, 1]]);
+    assert_eq!(violations(), 1.0);
+}
+
+#[tokio::test]
+async fn batch_sink_reports_latency_sla_violations_only_when_late() {
+    init_test();
+    tokio::time::pause();
+
+    let (acker, _) = Acker::basic();
+    let svc = GatedService {
+        open: Arc::new(AtomicBool::new(true)),
+        requests: Arc::new(Mutex::new(Vec::new())),
+    };
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+
+    let mut sink = BatchSink::new(
+        svc.clone(),
+        VecBuffer::new(batch_settings.size),
+        TIMEOUT,
+        acker,
+    )
+    .with_latency_sla(Duration::from_millis(10));
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for item in 0..3 {
+        sink.start_send_unpin(EncodedEvent::new(item, 0)).unwrap();
+    }
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert_eq!(&*svc.requests.lock().unwrap(), &vec![vec![0, 1]]);
+    assert!(!Controller::get()
+        .unwrap()
+        .capture_metrics()
+        .any(|metric| metric.name() == "sink_latency_sla_violations_total"));
+}
+
+#[tokio::test]
+async fn batch_sink_reports_backpressure() {
+    init_test();
+    let counter = |name: &str| {
+        Controller::get()
+            .unwrap()
+            .capture_metrics()
+            .find(|metric| metric.name() == name)
+            .map_or(0.0, |metric| match metric.value() {
+                MetricValue::Counter { value } => *value,
+                _ => panic!("{} should be a counter", name),
+            })
+    };
+
+    let (acker, _) = Acker::basic();
+    let svc = GatedService {
+        open: Arc::new(AtomicBool::new(false)),
+        requests: Arc::new(Mutex::new(Vec::new())),
+    };
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+
+    let mut sink = BatchSink::new(
+        svc.clone(),
+        VecBuffer::new(batch_settings.size),
+        TIMEOUT,
+        acker,
+    );
+
+    // The third event doesn't fit in the batch, which can't be sent while
+    // the service isn't ready.
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for item in 0..3 {
+        assert!(matches!(
+            sink.poll_ready_unpin(&mut cx),
+            Poll::Ready(Ok(()))
+        ));
+        sink.start_send_unpin(EncodedEvent::new(item, 0)).unwrap();
+    }
+    assert!(sink.poll_ready_unpin(&mut cx).is_pending());
+    assert!(sink.poll_ready_unpin(&mut cx).is_pending());
+    assert_eq!(counter("sink_backpressure_events_total"), 1.0);
+    assert_eq!(counter("sink_backpressure_cleared_total"), 0.0);
+
+    svc.open.store(true, Relaxed);
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert_eq!(counter("sink_backpressure_events_total"), 1.0);
+    assert_eq!(counter("sink_backpressure_cleared_total"), 1.0);
+    assert_eq!(&*svc.requests.lock().unwrap(), &vec![vec![0, 1]]);
+}
+
+#[tokio::test]
+async fn batch_sink_spills_overflow_to_disk() {
+    let (acker, ack_counter) = Acker::basic();
+    let svc = GatedService {
+        open: Arc::new(AtomicBool::new(false)),
+        requests: Arc::new(Mutex::new(Vec::new())),
+    };
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+
+    let path = temp_file();
+    let mut sink = BatchSink::new(
+        svc.clone(),
+        VecBuffer::new(batch_settings.size),
+        TIMEOUT,
+        acker,
+    )
+    .with_overflow_spill(path.clone(), 1024 * 1024);
+
+    // The service never gets ready, so the events that don't fit in the
+    // batch are spilled instead of applying backpressure.
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for item in 0..10 {
+        assert!(matches!(
+            sink.poll_ready_unpin(&mut cx),
+            Poll::Ready(Ok(()))
+        ));
+        assert!(matches!(
+            sink.start_send_unpin(EncodedEvent::new(item, 0)),
+            Ok(())
+        ));
+    }
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert!(svc.requests.lock().unwrap().is_empty());
+    assert!(std::fs::metadata(&path).unwrap().len() > 0);
+
+    svc.open.store(true, Relaxed);
+    sink.close().await.unwrap();
+
+    let output = svc.requests.lock().unwrap();
+    assert_eq!(
+        output.iter().flatten().copied().collect::<Vec<_>>(),
+        (0..10).collect::<Vec<_>>()
+    );
+    assert_eq!(ack_counter.load(Relaxed), 10);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn batch_sink_flushes_below_min_on_close() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+    let mut buffered = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(matches!(
+        buffered.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        buffered.start_send_unpin(EncodedEvent::new(0, 0)),
+        Ok(())
+    ));
+    assert!(matches!(
+        buffered.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        buffered.start_send_unpin(EncodedEvent::new(1, 0)),
+        Ok(())
+    ));
+
+    buffered.close().await.unwrap();
+
+    let output = sent_requests.lock().unwrap();
+    assert_eq!(&*output, &vec![vec![0, 1]]);
+}
+
+#[tokio::test]
+async fn batch_sink_expired_linger() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+    let mut buffered = BatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(matches!(
+        buffered.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        buffered.start_send_unpin(EncodedEvent::new(0, 0)),
+        Ok(())
+    ));
+    assert!(matches!(
+        buffered.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        buffered.start_send_unpin(EncodedEvent::new(1, 0)),
+        Ok(())
+    ));
+
+    // Move clock forward by linger timeout + 1 sec
+    advance_time(TIMEOUT + Duration::from_secs(1)).await;
+
+    // Flush buffer and make sure that this didn't take long time (because linger elapsed).
+    let start = Instant::now();
+    buffered.flush().await.unwrap();
+    let elapsed = start.duration_since(start);
+    assert!(elapsed < Duration::from_millis(200));
+
+    let output = sent_requests.lock().unwrap();
+    assert_eq!(&*output, &vec![vec![0, 1]]);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_buffers_messages_until_limit() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(0..22).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let output = sent_requests.lock().unwrap();
+    assert_eq!(
+        &*output,
+        &vec![
+            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+            vec![10, 11, 12, 13, 14, 15, 16, 17, 18, 19],
+            vec![20, 21]
+        ]
+    );
+}
+
+#[tokio::test]
+async fn partition_batch_sink_normalizes_keys() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_key_normalizer(|key: String| key.trim().to_lowercase());
+
+    let input = vec![Keyed("A"), Keyed("a"), Keyed(" A")];
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        &*sent_requests.lock().unwrap(),
+        &vec![vec![Keyed("A"), Keyed("a"), Keyed(" A")]]
+    );
+}
+
+#[tokio::test]
+async fn partition_batch_sink_counts_events_per_partition() {
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_req: Vec<Keyed>| future::ok::<_, std::io::Error>(()));
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+    let stats = sink.stats_handle();
+
+    let input = vec![
+        Keyed("a"),
+        Keyed("b"),
+        Keyed("a"),
+        Keyed("c"),
+        Keyed("a"),
+        Keyed("b"),
+    ];
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let expected = [("a", 3), ("b", 2), ("c", 1)]
+        .into_iter()
+        .map(|(partition, count)| (partition.to_owned(), count))
+        .collect::<HashMap<_, _>>();
+    assert_eq!(stats.lock().unwrap().events_per_partition(), expected);
+
+    stats.lock().unwrap().reset_event_counts();
+    assert!(stats.lock().unwrap().events_per_partition().is_empty());
+}
+
+#[tokio::test]
+async fn partition_batch_sink_compares_shadow_responses() {
+    init_test();
+    let (acker, ack_counter) = Acker::basic();
+    let svc = tower::service_fn(|_req: Vec<Keyed>| future::ok::<_, std::io::Error>("ok"));
+    // The shadow request of partition "c" fails, which isn't a mismatch.
+    let shadow = tower::service_fn(|req: Vec<Keyed>| match req[0].0 {
+        "a" => future::ok("ok"),
+        "b" => future::ok("changed"),
+        _ => future::err("bad"),
+    });
+    let mismatches = Arc::new(Mutex::new(Vec::new()));
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_key_display(|key: &String| key.clone())
+        .with_shadow_service(shadow, {
+            let mismatches = Arc::clone(&mismatches);
+            move |req: &Vec<Keyed>, primary: &&str, shadow: &&str| {
+                if primary != shadow {
+                    mismatches.lock().unwrap().push(req[0].0);
+                }
+                primary == shadow
+            }
+        });
+
+    let input = vec![Keyed("a"), Keyed("b"), Keyed("c"), Keyed("a"), Keyed("b")];
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    // The mismatch doesn't keep the events from being acked.
+    assert_eq!(ack_counter.load(Relaxed), 5);
+
+    timeout(TIMEOUT, async {
+        while mismatches.lock().unwrap().is_empty() {
+            yield_now().await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(*mismatches.lock().unwrap(), vec!["b"]);
+    assert!(event_test_util::contains_name("ShadowServiceMismatch"));
+}
+
+#[tokio::test]
+async fn partition_batch_sink_sends_dependencies_first() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    // The fact table "b" is loaded after the dimension table "a".
+    let dependencies = [("b".to_owned(), vec!["a".to_owned()])]
+        .into_iter()
+        .collect();
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_dependencies(dependencies);
+
+    // Both batches are only due once the sink closes.
+    let input = vec![Keyed("b"), Keyed("b"), Keyed("a"), Keyed("b"), Keyed("a")];
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        &*sent_requests.lock().unwrap(),
+        &vec![
+            vec![Keyed("a"), Keyed("a")],
+            vec![Keyed("b"), Keyed("b"), Keyed("b")],
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Partition dependencies are circular.")]
+fn partition_batch_sink_rejects_circular_dependencies() {
+    let svc = tower::service_fn(|_req: Vec<Keyed>| future::ok::<_, std::io::Error>(()));
+    let dependencies = [
+        ("a".to_owned(), vec!["b".to_owned()]),
+        ("b".to_owned(), vec!["c".to_owned()]),
+        ("c".to_owned(), vec!["a".to_owned()]),
+    ]
+    .into_iter()
+    .collect();
+
+    let _ = PartitionBatchSink::new(
+        svc,
+        VecBuffer::new(BatchSettings::default().size),
+        TIMEOUT,
+        Acker::basic().0,
+    )
+    .with_dependencies(dependencies);
+}
+
+/// Leases held by other instances, as set by the test.
+#[derive(Clone, Default)]
+struct MockLeases {
+    held_elsewhere: Arc<Mutex<HashSet<String>>>,
+    failing: Arc<AtomicBool>,
+    acquired: Arc<AtomicUsize>,
+    released: Arc<Mutex<Vec<String>>>,
+}
+
+impl LeaseCoordinator<String> for MockLeases {
+    fn try_acquire(&self, key: &String, _: Duration) -> BoxFuture<'static, crate::Result<bool>> {
+        self.acquired.fetch_add(1, Relaxed);
+        let acquired = if self.failing.load(Relaxed) {
+            Err("coordinator unavailable".into())
+        } else {
+            Ok(!self.held_elsewhere.lock().unwrap().contains(key))
+        };
+        future::ready(acquired).boxed()
+    }
+
+    fn release(&self, key: &String) -> BoxFuture<'static, crate::Result<()>> {
+        self.released.lock().unwrap().push(key.clone());
+        future::ok(()).boxed()
+    }
+}
+
+#[tokio::test]
+async fn partition_batch_sink_waits_for_partition_leases() {
+    init_test();
+    let (acker, ack_counter) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+    let svc = tower::service_fn(|req: Vec<Keyed>| {
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+    let leases = MockLeases::default();
+    leases.held_elsewhere.lock().unwrap().insert("a".to_owned());
+    leases.failing.store(true, Relaxed);
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_lease_coordinator(leases.clone(), Duration::from_secs(5));
+    for item in [Keyed("a"), Keyed("b"), Keyed("a"), Keyed("b")] {
+        sink.start_send_unpin(EncodedEvent::new(item, 0)).unwrap();
+    }
+
+    // The batches are held while the coordinator fails.
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert!(sent_requests.lock().unwrap().is_empty());
+    assert!(event_test_util::contains_name("LeaseCoordinatorFailed"));
+
+    // The batch of "a" is held while another instance holds its lease.
+    leases.failing.store(false, Relaxed);
+    advance_time(Duration::from_secs(5)).await;
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert_eq!(
+        *sent_requests.lock().unwrap(),
+        vec![vec![Keyed("b"), Keyed("b")]]
+    );
+
+    // Once released, it is sent when its lease is tried again.
+    leases.held_elsewhere.lock().unwrap().clear();
+    advance_time(Duration::from_secs(5)).await;
+    timeout(TIMEOUT, sink.flush()).await.unwrap().unwrap();
+    assert_eq!(
+        *sent_requests.lock().unwrap(),
+        vec![vec![Keyed("b"), Keyed("b")], vec![Keyed("a"), Keyed("a")]]
+    );
+    assert_eq!(ack_counter.load(Relaxed), 4);
+
+    let mut released = leases.released.lock().unwrap().clone();
+    released.sort();
+    assert_eq!(released, vec!["a".to_owned(), "b".to_owned()]);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_renews_partition_leases() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+    let (release, released) = oneshot::channel::<()>();
+    let mut released = Some(released);
+    // The first request stays in flight until released.
+    let svc = tower::service_fn(|req: Vec<Keyed>| {
+        sent_requests.lock().unwrap().push(req);
+        let released = released.take();
+        async move {
+            if let Some(released) = released {
+                let _ = released.await;
+            }
+            Ok::<_, std::io::Error>(())
+        }
+    });
+    let leases = MockLeases::default();
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_lease_coordinator(leases.clone(), Duration::from_secs(10));
+    let mut cx = Context::from_waker(noop_waker_ref());
+
+    sink.start_send_unpin(EncodedEvent::new(Keyed("a"), 0))
+        .unwrap();
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert_eq!(sent_requests.lock().unwrap().len(), 1);
+    assert_eq!(leases.acquired.load(Relaxed), 1);
+
+    // Within half of its TTL, the lease is used as is.
+    advance_time(Duration::from_secs(4)).await;
+    sink.start_send_unpin(EncodedEvent::new(Keyed("a"), 0))
+        .unwrap();
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert_eq!(sent_requests.lock().unwrap().len(), 2);
+    assert_eq!(leases.acquired.load(Relaxed), 1);
+
+    // Past half of its TTL, the lease is renewed.
+    advance_time(Duration::from_secs(2)).await;
+    sink.start_send_unpin(EncodedEvent::new(Keyed("a"), 0))
+        .unwrap();
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert_eq!(sent_requests.lock().unwrap().len(), 3);
+    assert_eq!(leases.acquired.load(Relaxed), 2);
+
+    // Once another instance took it over, the lease is lost as soon as
+    // the renewal fails, even though requests are still in flight.
+    leases.held_elsewhere.lock().unwrap().insert("a".to_owned());
+    advance_time(Duration::from_secs(6)).await;
+    sink.start_send_unpin(EncodedEvent::new(Keyed("a"), 0))
+        .unwrap();
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert_eq!(sent_requests.lock().unwrap().len(), 3);
+    assert_eq!(leases.acquired.load(Relaxed), 3);
+
+    // A lost lease isn't released by the requests which were using it.
+    release.send(()).unwrap();
+    yield_now().await;
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert!(leases.released.lock().unwrap().is_empty());
+
+    // The lease is taken again once it is free.
+    leases.held_elsewhere.lock().unwrap().clear();
+    advance_time(Duration::from_secs(10)).await;
+    timeout(TIMEOUT, sink.flush()).await.unwrap().unwrap();
+    assert_eq!(sent_requests.lock().unwrap().len(), 4);
+    assert_eq!(*leases.released.lock().unwrap(), vec!["a".to_owned()]);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_tracks_provenance() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 3;
+
+    let sink = PartitionBatchSink::with_provenance_tracking(
+        svc,
+        VecBuffer::new(batch_settings.size),
+        TIMEOUT,
+        acker,
+    );
+
+    let input = vec![
+        Sourced::new(Keyed("A"), Some(Arc::from("in_a"))),
+        Sourced::new(Keyed("A"), Some(Arc::from("in_b"))),
+        Sourced::new(Keyed("A"), Some(Arc::from("in_a"))),
+    ];
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let sent_requests = sent_requests.lock().unwrap();
+    assert_eq!(sent_requests.len(), 1);
+    assert_eq!(
+        sent_requests[0].batch,
+        vec![Keyed("A"), Keyed("A"), Keyed("A")]
+    );
+    assert_eq!(
+        sent_requests[0].sources,
+        ["in_a", "in_b"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<HashSet<_>>()
+    );
+}
+
+#[tokio::test]
+async fn partition_batch_sink_dispatches_by_priority() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_priority(|key: &String| match key.as_str() {
+                "alerts" => 255,
+                "audit" => 128,
+                _ => 0,
+            });
+    let mut cx = Context::from_waker(noop_waker_ref());
+
+    for item in [Keyed("analytics"), Keyed("audit"), Keyed("alerts")] {
+        assert!(sink.poll_ready_unpin(&mut cx).is_ready());
+        sink.start_send_unpin(EncodedEvent::new(item, 0)).unwrap();
+    }
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+
+    assert_eq!(
+        &*sent_requests.lock().unwrap(),
+        &vec![
+            vec![Keyed("alerts")],
+            vec![Keyed("audit")],
+            vec![Keyed("analytics")]
+        ]
+    );
+}
+
+#[tokio::test]
+async fn partition_batch_sink_from_config() {
+    tokio::time::pause();
+
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_size = BatchSize::default();
+    batch_size.bytes = 9999;
+    batch_size.events = 2;
+    let config = PartitionBatchSinkConfig {
+        batch_size,
+        linger: Duration::from_secs(1),
+        max_in_flight: Some(4),
+        linger_jitter: Some(Duration::from_millis(500)),
+        ordered: true,
+    };
+
+    let mut sink = PartitionBatchSink::from_config(
+        svc,
+        VecBuffer::new(BatchSettings::default().size),
+        config,
+        acker,
+    );
+    assert_eq!(sink.timeout, Duration::from_secs(1));
+    assert_eq!(sink.linger_jitter, Some(Duration::from_millis(500)));
+    assert!(sink.in_flight.is_some());
+    let permits = sink
+        .service
+        .concurrent_dispatch
+        .as_ref()
+        .map(|dispatch| dispatch.semaphore.available_permits());
+    assert_eq!(permits, Some(4));
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for item in [Keyed("A"), Keyed("A"), Keyed("B")] {
+        assert!(sink.poll_ready_unpin(&mut cx).is_ready());
+        sink.start_send_unpin(EncodedEvent::new(item, 0)).unwrap();
+    }
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    yield_now().await;
+
+    // Only the full batch is sent before the linger expires.
+    assert_eq!(
+        &*sent_requests.lock().unwrap(),
+        &vec![vec![Keyed("A"), Keyed("A")]]
+    );
+
+    tokio::time::advance(Duration::from_millis(1501)).await;
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    yield_now().await;
+
+    assert_eq!(
+        &*sent_requests.lock().unwrap(),
+        &vec![vec![Keyed("A"), Keyed("A")], vec![Keyed("B")]]
+    );
+}
+
+#[tokio::test]
+async fn partition_batch_sink_coalesces_batches_within_window() {
+    tokio::time::pause();
+
+    let (acker, ack_counter) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_coalesce_window(Duration::from_millis(10));
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+
+    // The second batch is ready 5ms after the first one.
+    sink.start_send_unpin(EncodedEvent::new((0, 0), 0)).unwrap();
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    tokio::time::advance(Duration::from_millis(5)).await;
+    sink.start_send_unpin(EncodedEvent::new((1, 1), 0)).unwrap();
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert!(sent_requests.lock().unwrap().is_empty());
+
+    sink.flush().await.unwrap();
+
+    assert_eq!(&*sent_requests.lock().unwrap(), &vec![vec![(0, 0), (1, 1)]]);
+    assert_eq!(ack_counter.load(Relaxed), 2);
+}
+
+#[test]
+#[should_panic(expected = "requires coalescing to be set up first")]
+fn partition_batch_sink_rejects_max_coalesced_batches_without_coalescing() {
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_: Vec<(usize, usize)>| future::ok::<_, std::io::Error>(()));
+    let batch_settings = BatchSettings::default();
+    let _ = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_max_coalesced_batches(2);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_replays_wal_after_crash() {
+    let dir = temp_dir();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+    let new_sink = || {
+        let sent_requests = Arc::clone(&sent_requests);
+        let svc = tower::service_fn(move |req| {
+            sent_requests.lock().unwrap().push(req);
+            future::ok::<_, std::io::Error>(())
+        });
+        let (acker, _) = Acker::basic();
+        let mut batch_settings = BatchSettings::default();
+        batch_settings.size.bytes = 9999;
+        batch_settings.size.events = 10;
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_wal(dir.clone())
+    };
+
+    // Crash while the events are still waiting in their batch.
+    let mut sink = new_sink();
+    for item in [(0, 0), (0, 1), (1, 2)] {
+        sink.start_send_unpin(EncodedEvent::new(item, 0)).unwrap();
+    }
+    drop(sink);
+    assert!(sent_requests.lock().unwrap().is_empty());
+
+    new_sink().close().await.unwrap();
+    let mut output = sent_requests.lock().unwrap().clone();
+    output.sort();
+    assert_eq!(output, vec![vec![(0, 0), (0, 1)], vec![(1, 2)]]);
+
+    // Dispatched events aren't replayed again.
+    sent_requests.lock().unwrap().clear();
+    new_sink().close().await.unwrap();
+    assert!(sent_requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn partition_batch_sink_rotates_wal_files() {
+    let dir = temp_dir();
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_: Vec<(usize, usize)>| future::ok::<_, std::io::Error>(()));
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    // Each event gets a file of its own.
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_wal_limits(dir.clone(), 2, 1);
+    sink.sink_map_err(drop)
+        .send_all(
+            &mut stream::iter((0..5).map(|item| (item, item)))
+                .map(|item| Ok(EncodedEvent::new(item, 0))),
+        )
+        .await
+        .unwrap();
+
+    let wal_files = std::fs::read_dir(&dir)
+        .unwrap()
+        .filter(|entry| {
+            entry
+                .as_ref()
+                .unwrap()
+                .path()
+                .extension()
+                .map_or(false, |extension| extension == "wal")
+        })
+        .count();
+    assert_eq!(wal_files, 2);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_merges_coalesced_batches() {
+    tokio::time::pause();
+
+    let (acker, ack_counter) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+    let merged = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+    let merger = {
+        let merged = Arc::clone(&merged);
+        move |batches: Vec<Vec<(usize, usize)>>| {
+            merged.lock().unwrap().push(batches.len());
+            batches.into_iter().rev().flatten().collect::<Vec<_>>()
+        }
+    };
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_coalesce_merger(merger, Duration::from_millis(10));
+    let mut cx = Context::from_waker(noop_waker_ref());
+
+    for partition in 0..3 {
+        sink.start_send_unpin(EncodedEvent::new((partition, partition), 0))
+            .unwrap();
+        assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+        tokio::time::advance(Duration::from_millis(2)).await;
+    }
+    assert!(sent_requests.lock().unwrap().is_empty());
+
+    tokio::time::advance(Duration::from_millis(5)).await;
+    sink.flush().await.unwrap();
+
+    assert_eq!(&*merged.lock().unwrap(), &vec![3]);
+    assert_eq!(
+        &*sent_requests.lock().unwrap(),
+        &vec![vec![(2, 2), (1, 1), (0, 0)]]
+    );
+    assert_eq!(ack_counter.load(Relaxed), 3);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_sends_coalesced_batches_once_max_is_held() {
+    tokio::time::pause();
+
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_coalesce_merger(
+                |batches: Vec<Vec<(usize, usize)>>| {
+                    batches.into_iter().flatten().collect::<Vec<_>>()
+                },
+                Duration::from_millis(10),
+            )
+            .with_max_coalesced_batches(2);
+    let mut cx = Context::from_waker(noop_waker_ref());
+
+    for partition in 0..3 {
+        sink.start_send_unpin(EncodedEvent::new((partition, partition), 0))
+            .unwrap();
+        let _ = sink.poll_flush_unpin(&mut cx);
+    }
+    yield_now().await;
+    assert_eq!(&*sent_requests.lock().unwrap(), &vec![vec![(0, 0), (1, 1)]]);
+
+    tokio::time::advance(Duration::from_millis(10)).await;
+    sink.flush().await.unwrap();
+    assert_eq!(
+        &*sent_requests.lock().unwrap(),
+        &vec![vec![(0, 0), (1, 1)], vec![(2, 2)]]
+    );
+}
+
+#[tokio::test]
+async fn partition_batch_sink_coalesces_small_partitions() {
+    tokio::time::pause();
+
+    let (acker, ack_counter) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_coalesce_threshold(2);
+
+    let input = (0..5).map(Small::new);
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let mut output = sent_requests.lock().unwrap().clone();
+    assert_eq!(output.len(), 1);
+    output[0].sort_unstable();
+    let expected = (0..5)
+        .map(|key| Small {
+            key,
+            partition: Some(key.to_string().into()),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(output[0], expected);
+    assert_eq!(ack_counter.load(Relaxed), 5);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_bounds_coalesced_batches() {
+    tokio::time::pause();
+
+    let (acker, ack_counter) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 4;
+
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_coalesce_threshold(2);
+
+    let input = (0..5).map(Small::new);
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let mut output = sent_requests.lock().unwrap().clone();
+    output.sort_by_key(Vec::len);
+    assert_eq!(output.iter().map(Vec::len).collect::<Vec<_>>(), vec![1, 4]);
+    // Batches sent on their own aren't tagged.
+    assert_eq!(output[0][0].partition, None);
+    assert!(output[1].iter().all(|item| item.partition.is_some()));
+    assert_eq!(ack_counter.load(Relaxed), 5);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_orders_by_timestamp() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 4;
+
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_timestamp_ordering(|(_, timestamp): &(usize, usize)| *timestamp as i64);
+
+    let input = vec![(0, 300), (0, 100), (0, 400), (0, 200), (0, 600), (0, 500)];
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let output = sent_requests.lock().unwrap();
+    assert_eq!(
+        &*output,
+        &vec![
+            vec![(0, 100), (0, 200), (0, 300), (0, 400)],
+            vec![(0, 500), (0, 600)],
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Batches are already sorted by another key.")]
+fn partition_batch_sink_rejects_conflicting_orderings() {
+    let svc = tower::service_fn(|_req: Vec<usize>| future::ok::<_, std::io::Error>(()));
+
+    let _ = PartitionBatchSink::new(
+        svc,
+        VecBuffer::new(BatchSettings::default().size),
+        TIMEOUT,
+        Acker::basic().0,
+    )
+    .with_sort_key(|item: &usize| *item)
+    .with_timestamp_ordering(|item: &usize| *item as i64);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_buffers_by_partition_buffer_size_one() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    let input = vec![Partitions::A, Partitions::B];
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let mut output = sent_requests.lock().unwrap();
+    output[..].sort();
+    assert_eq!(&*output, &vec![vec![Partitions::A], vec![Partitions::B]]);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_displays_partition_keys() {
+    init_test();
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+    let new_sink = || {
+        let (acker, _) = Acker::basic();
+        let svc = tower::service_fn(|_: Vec<Labeled>| future::ok::<_, std::io::Error>(()));
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_warn_threshold(0.5)
+    };
+    let key = MyKey { field: "value" };
+
+    let sink = new_sink();
+    assert_eq!(
+        sink.partition_label(&key).as_deref(),
+        Some(r#"MyKey { field: "value" }"#)
+    );
+
+    let mut sink = new_sink().with_key_display(|key: &MyKey| format!("MyKey(field={})", key.field));
+    assert_eq!(
+        sink.partition_label(&key).as_deref(),
+        Some("MyKey(field=value)")
+    );
+
+    sink.start_send_unpin(EncodedEvent::new(Labeled("value"), 0))
+        .unwrap();
+    assert!(event_test_util::contains_name("BatchSinkNearlyFull"));
+}
+
+#[tokio::test]
+async fn partition_batch_sink_caps_partition_labels_in_metrics() {
+    init_test();
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_: Vec<Labeled>| future::ok::<_, std::io::Error>(()));
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_warn_threshold(0.5)
+            .with_key_display(|key: &MyKey| key.field.to_owned())
+            .with_metrics_cardinality_cap(2);
+
+    for field in ["a", "b", "c", "d", "e"] {
+        sink.start_send_unpin(EncodedEvent::new(Labeled(field), 0))
+            .unwrap();
+    }
+
+    let labels = Controller::get()
+        .unwrap()
+        .capture_metrics()
+        .filter(|metric| metric.name() == "batch_nearly_full_total")
+        .map(|metric| metric.tag_value("partition").unwrap())
+        .collect::<HashSet<_>>();
+    assert_eq!(
+        labels,
+        ["a", "b", "__other__"]
+            .iter()
+            .map(|label| label.to_string())
+            .collect::<HashSet<_>>()
+    );
+}
+
+#[test]
+fn metric_labels_are_given_back_by_evicted_partitions() {
+    let mut labels = MetricLabels::new(2);
+    assert_eq!(labels.label("a"), "a");
+    assert_eq!(labels.label("b"), "b");
+    assert_eq!(labels.label("c"), OTHER_PARTITIONS_LABEL);
+
+    labels.forget("a");
+    assert_eq!(labels.label("d"), "d");
+    assert_eq!(labels.label("b"), "b");
+    assert_eq!(labels.label("e"), OTHER_PARTITIONS_LABEL);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_buffers_by_partition_buffer_size_two() {
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+
+    let mut harness = PartitionBatchSinkTestHarness::new(batch_settings, TIMEOUT);
+    harness
+        .send_events(vec![
+            Partitions::A,
+            Partitions::B,
+            Partitions::A,
+            Partitions::B,
+        ])
+        .await;
+
+    harness.assert_dispatched_for_partition(&Bytes::from("A"), &[Partitions::A, Partitions::A]);
+    harness.assert_dispatched_for_partition(&Bytes::from("B"), &[Partitions::B, Partitions::B]);
+    assert_eq!(harness.sent_requests.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_submits_after_linger() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.start_send_unpin(EncodedEvent::new(1, 0)),
+        Ok(())
+    ));
+    assert!(matches!(sink.poll_flush_unpin(&mut cx), Poll::Pending));
+
+    advance_time(TIMEOUT + Duration::from_secs(1)).await;
+
+    let start = Instant::now();
+    sink.flush().await.unwrap();
+    let elapsed = start.duration_since(start);
+    assert!(elapsed < Duration::from_millis(200));
+
+    let output = sent_requests.lock().unwrap();
+    assert_eq!(&*output, &vec![vec![1]]);
+}
+
+#[tokio::test]
+async fn service_sink_doesnt_propagate_error() {
+    // We need a mock executor here because we need to ensure
+    // that we poll the service futures within the mock clock
+    // context. This allows us to manually advance the time on the
+    // "spawned" futures.
+    let (acker, ack_counter) = Acker::basic();
+
+    let svc = tower::service_fn(|req: u8| {
+        if req == 3 {
+            future::err("bad")
+        } else {
+            future::ok("good")
+        }
+    });
+    let mut sink = ServiceSink::new(svc, acker);
+    let req = |items: u8| EncodedBatch {
+        items,
+        finalizers: Default::default(),
+        count: items as usize,
+        byte_size: 1,
+        wire_size: 1,
+    };
+
+    // send some initial requests
+    let mut fut1 = sink.call(req(1), 1);
+    let mut fut2 = sink.call(req(2), 2);
+
+    assert_eq!(ack_counter.load(Relaxed), 0);
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(matches!(fut1.poll_unpin(&mut cx), Poll::Ready(())));
+    assert!(matches!(fut2.poll_unpin(&mut cx), Poll::Ready(())));
+    assert!(matches!(sink.poll_complete(&mut cx), Poll::Ready(())));
+
+    assert_eq!(ack_counter.load(Relaxed), 3);
+
+    // send one request that will error and one normal
+    let mut fut3 = sink.call(req(3), 3); // I will error
+    let mut fut4 = sink.call(req(4), 4);
+
+    // make sure they all "worked"
+    assert!(matches!(fut3.poll_unpin(&mut cx), Poll::Ready(())));
+    assert!(matches!(fut4.poll_unpin(&mut cx), Poll::Ready(())));
+    assert!(matches!(sink.poll_complete(&mut cx), Poll::Ready(())));
+
+    assert_eq!(ack_counter.load(Relaxed), 10);
+}
+
+#[tokio::test]
+async fn service_sink_acks_in_order_across_partitions() {
+    use crate::event::{BatchNotifier, BatchStatus, EventFinalizer};
+
+    let (acker, ack_counter) = Acker::basic();
+    let (release, released) = oneshot::channel::<()>();
+    let mut released = Some(released);
+
+    // Requests of partition "a" fail, once released.
+    let svc = tower::service_fn(move |req: &'static str| {
+        if req == "a" {
+            let released = released.take().expect("Partition a is only sent once");
+            released.then(|_| future::err("bad")).boxed()
+        } else {
+            future::ok("good").boxed()
+        }
+    });
+    let mut sink = ServiceSink::new(svc, acker);
+    let batch = |items: &'static str, count: usize, finalizers| EncodedBatch {
+        items,
+        finalizers,
+        count,
+        byte_size: 1,
+        wire_size: 1,
+    };
+    let mut cx = Context::from_waker(noop_waker_ref());
+
+    let request_a = tokio::spawn(sink.dispatch(
+        None,
+        Some("a".to_owned()),
+        batch("a", 1, EventFinalizers::default()),
+        1,
+    ));
+    let (notifier, receiver) = BatchNotifier::new_with_receiver();
+    let finalizers = EventFinalizers::new(EventFinalizer::new(notifier));
+    sink.dispatch(None, Some("b".to_owned()), batch("b", 2, finalizers), 2)
+        .await;
+
+    // The events of "b" are delivered right away, but the acker counts
+    // events so they are only acked after the ones of "a".
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+    assert!(sink.poll_complete(&mut cx).is_pending());
+    assert_eq!(ack_counter.load(Relaxed), 0);
+
+    release.send(()).unwrap();
+    request_a.await.unwrap();
+    assert!(sink.poll_complete(&mut cx).is_ready());
+    assert_eq!(ack_counter.load(Relaxed), 3);
+}
+
+#[tokio::test]
+async fn service_sink_bulkhead_reports_partitions_independently() {
+    use crate::event::{BatchNotifier, BatchStatus, EventFinalizer};
+
+    let (acker, ack_counter) = Acker::basic();
+    let (release, released) = oneshot::channel::<()>();
+    let mut released = Some(released);
+
+    // The first request of partition "a" fails, once released.
+    let svc = tower::service_fn(move |req: &'static str| {
+        if req == "a" {
+            if let Some(released) = released.take() {
+                return released.then(|_| future::err("bad")).boxed();
+            }
+        }
+        future::ok("good").boxed()
+    });
+    let mut sink = ServiceSink::new(svc, acker).with_bulkhead();
+    let batch = |items: &'static str, count: usize| {
+        let (notifier, receiver) = BatchNotifier::new_with_receiver();
+        let batch = EncodedBatch {
+            items,
+            finalizers: EventFinalizers::new(EventFinalizer::new(notifier)),
+            count,
+            byte_size: 1,
+            wire_size: 1,
+        };
+        (batch, receiver)
+    };
+    let mut cx = Context::from_waker(noop_waker_ref());
+
+    let (first_a, first_a_status) = batch("a", 1);
+    let request_a = tokio::spawn(sink.call_partition(&"a", None, first_a, 1));
+    let (second_a, mut second_a_status) = batch("a", 1);
+    sink.call_partition(&"a", None, second_a, 1).await;
+    let (b, b_status) = batch("b", 2);
+    sink.call_partition(&"b", None, b, 2).await;
+
+    // The events of "b" are reported right away, while the second batch
+    // of "a" waits for the first one.
+    assert_eq!(b_status.await, BatchStatus::Delivered);
+    assert!(second_a_status.try_recv().is_err());
+    assert!(sink.poll_complete(&mut cx).is_pending());
+    assert_eq!(ack_counter.load(Relaxed), 0);
+
+    release.send(()).unwrap();
+    request_a.await.unwrap();
+    assert_eq!(first_a_status.await, BatchStatus::Errored);
+    assert_eq!(second_a_status.await, BatchStatus::Delivered);
+    assert!(sink.poll_complete(&mut cx).is_ready());
+    assert_eq!(ack_counter.load(Relaxed), 4);
+    assert!(sink.bulkheads.as_ref().unwrap().lock().unwrap().is_empty());
+}
+
+/// Response of a service storing the events it receives, which hands
+/// them back along with the location they are stored at.
+#[derive(Debug)]
+struct StoredResponse {
+    status: http::StatusCode,
+    location: http::HeaderValue,
+    events: Arc<Mutex<Vec<Event>>>,
+}
+
+impl Response for StoredResponse {
+    fn is_successful(&self) -> bool {
+        self.status.is_success()
+    }
+}
+
+#[derive(Clone)]
+struct LocationServiceLogic;
+
+impl ServiceLogic for LocationServiceLogic {
+    type Response = StoredResponse;
+
+    fn result_status(&self, result: crate::Result<Self::Response>) -> EventStatus {
+        StdServiceLogic::default().result_status(result)
+    }
+
+    fn on_success(&self, response: &Self::Response, _finalizers: &EventFinalizers) {
+        let location = response.location.to_str().unwrap();
+        for event in response.events.lock().unwrap().iter_mut() {
+            event
+                .metadata_mut()
+                .set_delivery_location(Some(location.to_owned()));
+        }
+    }
+}
+
+#[tokio::test]
+async fn service_sink_calls_on_success() {
+    let (acker, ack_counter) = Acker::basic();
+
+    let svc = tower::service_fn(|(id, events): (u8, Arc<Mutex<Vec<Event>>>)| {
+        let status = if id == 2 {
+            http::StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            http::StatusCode::CREATED
+        };
+        future::ok::<_, std::io::Error>(StoredResponse {
+            status,
+            location: http::HeaderValue::from_str(&format!("/items/{}", id)).unwrap(),
+            events,
+        })
+    });
+    let mut sink = ServiceSink::new_with_logic(svc, acker, LocationServiceLogic);
+    let requests = (1..=3)
+        .map(|id| (id, Arc::new(Mutex::new(vec![Event::from("message")]))))
+        .collect::<Vec<_>>();
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for (id, events) in &requests {
+        let batch = EncodedBatch {
+            items: (*id, Arc::clone(events)),
+            finalizers: Default::default(),
+            count: 1,
+            byte_size: 1,
+            wire_size: 1,
+        };
+        assert!(matches!(
+            sink.call(batch, 1).poll_unpin(&mut cx),
+            Poll::Ready(())
+        ));
+    }
+    assert!(matches!(sink.poll_complete(&mut cx), Poll::Ready(())));
+
+    assert_eq!(ack_counter.load(Relaxed), 3);
+    // Only the events of the delivered requests get their location.
+    let locations = requests
+        .iter()
+        .map(|(_, events)| {
+            events.lock().unwrap()[0]
+                .metadata()
+                .delivery_location()
+                .clone()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        locations,
+        vec![
+            Some("/items/1".to_owned()),
+            None,
+            Some("/items/3".to_owned())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn partition_batch_sink_ordering_per_partition() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let mut delay = true;
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        if delay {
+            // Delay and then error
+            delay = false;
+            sleep(Duration::from_secs(1))
+                .map(move |_| {
+                    sent_requests.lock().unwrap().push(req);
+                    Result::<_, std::io::Error>::Ok(())
+                })
+                .boxed()
+        } else {
+            sent_requests.lock().unwrap().push(req);
+            future::ok::<_, std::io::Error>(()).boxed()
+        }
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+    sink.ordered();
+
+    let input = (0..20)
+        .into_iter()
+        .map(|i| (0, i))
+        .chain((0..20).into_iter().map(|i| (1, i)));
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let output = sent_requests.lock().unwrap();
+    // We sended '0' partition first and delayed sending only first request, first 10 events,
+    // which should delay sending the second batch of events in the same partition until
+    // the first one succeeds.
+    assert_eq!(
+        &*output,
+        &vec![
+            (0..10).into_iter().map(|i| (1, i)).collect::<Vec<_>>(),
+            (10..20).into_iter().map(|i| (1, i)).collect(),
+            (0..10).into_iter().map(|i| (0, i)).collect(),
+            (10..20).into_iter().map(|i| (0, i)).collect(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn partition_batch_sink_avoids_spurious_wakeups() {
+    struct CountingWaker(AtomicUsize);
+
+    impl ArcWake for CountingWaker {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Relaxed);
+        }
+    }
+
+    tokio::task::unconstrained(async {
+        tokio::time::pause();
+
+        let (acker, _) = Acker::basic();
+        let svc = tower::service_fn(|_| future::ok::<_, std::io::Error>(()));
+
+        let mut batch_settings = BatchSettings::default();
+        batch_settings.size.bytes = 9999;
+        batch_settings.size.events = 10;
+
+        let mut sink =
+            PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+        let wakes = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = waker(Arc::clone(&wakes));
+        let mut cx = Context::from_waker(&waker);
+
+        sink.start_send_unpin(EncodedEvent::new((0, 0), 0)).unwrap();
+        sink.start_send_unpin(EncodedEvent::new((1, 1), 0)).unwrap();
+
+        // Nothing changed between polls, nobody should wake the task up.
+        for _ in 0..10 {
+            assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+        }
+        assert_eq!(wakes.0.load(Relaxed), 0);
+
+        // Both lingers fire.
+        tokio::time::advance(TIMEOUT).await;
+        assert!(wakes.0.load(Relaxed) <= 2);
+
+        // Both requests are sent and complete.
+        while sink.poll_flush_unpin(&mut cx).is_pending() {
+            yield_now().await;
+        }
+        assert!(wakes.0.load(Relaxed) <= 4);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn partition_batch_sink_expires_idle_partitions() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_partition_ttl(TIMEOUT * 2);
+    sink.ordered();
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.start_send_unpin(EncodedEvent::new(1, 0)),
+        Ok(())
+    ));
+    assert_eq!(sink.ttl_timers.len(), 1);
+
+    // The batch is still sent once the linger expires.
+    advance_time(TIMEOUT + Duration::from_secs(1)).await;
+    sink.flush().await.unwrap();
+    assert_eq!(&*sent_requests.lock().unwrap(), &vec![vec![1]]);
+    assert_eq!(sink.ttl_timers.len(), 1);
+    assert_eq!(sink.in_flight.as_ref().unwrap().len(), 1);
+
+    advance_time(TIMEOUT).await;
+    assert!(matches!(
+        sink.poll_flush_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(sink.ttl_timers.is_empty());
+    assert!(sink.partitions.is_empty());
+    assert!(sink.lingers.is_empty());
+    assert!(sink.in_flight.as_ref().unwrap().is_empty());
+    assert_eq!(sent_requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_flushes_expired_partitions() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_partition_ttl(Duration::from_secs(1));
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert!(matches!(
+        sink.start_send_unpin(EncodedEvent::new(1, 0)),
+        Ok(())
+    ));
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert!(sent_requests.lock().unwrap().is_empty());
+
+    // The batch goes out long before its linger expires.
+    advance_time(Duration::from_secs(1)).await;
+    sink.flush().await.unwrap();
+    assert_eq!(&*sent_requests.lock().unwrap(), &vec![vec![1]]);
+    assert!(sink.ttl_timers.is_empty());
+    assert!(sink.partitions.is_empty());
+    assert!(sink.lingers.is_empty());
+}
+
+#[tokio::test]
+async fn partition_batch_sink_collects_idle_partitions() {
+    tokio::time::pause();
+
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_idle_gc(Duration::from_secs(60), Duration::from_secs(30));
+    sink.ordered();
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for item in [Partitions::A, Partitions::B] {
+        assert!(matches!(
+            sink.poll_ready_unpin(&mut cx),
+            Poll::Ready(Ok(()))
+        ));
+        sink.start_send_unpin(EncodedEvent::new(item, 0)).unwrap();
+    }
+
+    // Both batches are sent once their linger expires.
+    tokio::time::advance(TIMEOUT + Duration::from_secs(1)).await;
+    yield_now().await;
+    let _ = sink.poll_flush_unpin(&mut cx);
+    assert_eq!(sent_requests.lock().unwrap().len(), 2);
+
+    // Only `A` keeps receiving events.
+    tokio::time::advance(Duration::from_secs(20)).await;
+    sink.start_send_unpin(EncodedEvent::new(Partitions::A, 0))
+        .unwrap();
+    let _ = sink.poll_flush_unpin(&mut cx);
+    assert_eq!(sink.idle_gc.as_ref().unwrap().last_active.len(), 2);
+
+    // The pass at 60 seconds collects `B`, idle for longer than 30 seconds.
+    tokio::time::advance(Duration::from_secs(29)).await;
+    let _ = sink.poll_flush_unpin(&mut cx);
+    let last_active = &sink.idle_gc.as_ref().unwrap().last_active;
+    assert_eq!(
+        last_active.keys().cloned().collect::<Vec<_>>(),
+        vec![Bytes::from("A")]
+    );
+    assert!(!sink
+        .in_flight
+        .as_ref()
+        .unwrap()
+        .contains_key(&Bytes::from("B")));
+}
+
+#[tokio::test]
+async fn fan_out_partition_batch_sink_acks_after_both_sinks() {
+    tokio::time::pause();
+
+    let (acker, ack_counter) = Acker::basic();
+    let primary_requests = Arc::new(Mutex::new(Vec::new()));
+    let secondary_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let primary = tower::service_fn(|req| {
+        let primary_requests = Arc::clone(&primary_requests);
+        primary_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+    let secondary = tower::service_fn(|req| {
+        let secondary_requests = Arc::clone(&secondary_requests);
+        sleep(Duration::from_secs(1)).map(move |_| {
+            secondary_requests.lock().unwrap().push(req);
+            Result::<_, std::io::Error>::Ok(())
+        })
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 2;
+
+    let mut sink = FanOutPartitionBatchSink::new(
+        primary,
+        secondary,
+        VecBuffer::new(batch_settings.size),
+        TIMEOUT,
+        acker,
+    );
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for item in 0..2 {
+        assert!(matches!(
+            sink.poll_ready_unpin(&mut cx),
+            Poll::Ready(Ok(()))
+        ));
+        assert!(matches!(
+            sink.start_send_unpin(EncodedEvent::new((0, item), 0)),
+            Ok(())
+        ));
+    }
+
+    // Both batches are dispatched, only the primary service completes.
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    yield_now().await;
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert_eq!(primary_requests.lock().unwrap().len(), 1);
+    assert_eq!(ack_counter.load(Relaxed), 0);
+
+    tokio::time::advance(Duration::from_secs(1)).await;
+    yield_now().await;
+    assert!(sink.poll_flush_unpin(&mut cx).is_pending());
+    assert_eq!(ack_counter.load(Relaxed), 2);
+
+    assert_eq!(
+        &*primary_requests.lock().unwrap(),
+        &vec![vec![(0, 0), (0, 1)]]
+    );
+    assert_eq!(
+        &*secondary_requests.lock().unwrap(),
+        &vec![vec![(0, 0), (0, 1)]]
+    );
+}
+
+#[tokio::test]
+async fn multi_partition_batch_sink_inserts_into_each_partition() {
+    let (acker, ack_counter) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req: PartitionInnerBuffer<Vec<Sharded>, &'static str>| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req.into_parts());
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let sink =
+        MultiPartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    let input = vec![Sharded(0, vec!["a", "b"])];
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let mut output = sent_requests.lock().unwrap();
+    output.sort_by_key(|(_, key)| *key);
+    assert_eq!(
+        &*output,
+        &vec![
+            (vec![Sharded(0, vec!["a", "b"])], "a"),
+            (vec![Sharded(0, vec!["a", "b"])], "b"),
+        ]
+    );
+    assert_eq!(ack_counter.load(Relaxed), 1);
+}
+
+#[tokio::test]
+async fn multi_partition_batch_sink_waits_for_each_copy() {
+    let (acker, ack_counter) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+
+    let svc = tower::service_fn(|req: PartitionInnerBuffer<Vec<Sharded>, &'static str>| {
+        let sent_requests = Arc::clone(&sent_requests);
+        sent_requests.lock().unwrap().push(req.into_parts());
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    let sink =
+        MultiPartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker);
+
+    let input = vec![Sharded(0, vec!["a", "b", "c"])];
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(input).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    let mut output = sent_requests.lock().unwrap();
+    output.sort_by_key(|(_, key)| *key);
+    assert_eq!(
+        &*output,
+        &vec![
+            (vec![Sharded(0, vec!["a", "b", "c"])], "a"),
+            (vec![Sharded(0, vec!["a", "b", "c"])], "b"),
+            (vec![Sharded(0, vec!["a", "b", "c"])], "c"),
+        ]
+    );
+    assert_eq!(ack_counter.load(Relaxed), 1);
+}
+
+#[tokio::test]
+async fn service_sink_acks_completed_requests_at_once() {
+    let ack_calls = Arc::new(Mutex::new(Vec::new()));
+    let acker = {
+        let ack_calls = Arc::clone(&ack_calls);
+        Acker::segmented(move |num| ack_calls.lock().unwrap().push(num))
+    };
+    let svc = tower::service_fn(|_: Vec<usize>| future::ok::<_, std::io::Error>(()));
+    let mut sink = ServiceSink::new(svc, acker);
+
+    for items in [vec![0, 1], vec![2], vec![3, 4, 5]] {
+        let count = items.len();
+        let batch = EncodedBatch {
+            items,
+            finalizers: EventFinalizers::default(),
+            count,
+            byte_size: 0,
+            wire_size: 0,
+        };
+        tokio::spawn(sink.call(batch, count)).await.unwrap();
+    }
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(sink.poll_complete(&mut cx).is_ready());
+    assert_eq!(&*ack_calls.lock().unwrap(), &vec![6]);
+}
+
+#[tokio::test]
+async fn service_sink_shares_retry_budget() {
+    tokio::time::pause();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let svc = {
+        let calls = Arc::clone(&calls);
+        tower::service_fn(move |_: Vec<usize>| {
+            calls.fetch_add(1, Relaxed);
+            future::err::<(), _>(std::io::Error::new(std::io::ErrorKind::Other, "down"))
+        })
+    };
+    let budget = Arc::new(RetryBudget::new(1));
+    let mut sinks = [
+        ServiceSink::new(svc.clone(), Acker::basic().0).with_retry_budget(Arc::clone(&budget)),
+        ServiceSink::new(svc, Acker::basic().0).with_retry_budget(budget),
+    ];
+    let batch = || EncodedBatch {
+        items: vec![0],
+        finalizers: EventFinalizers::default(),
+        count: 1,
+        byte_size: 0,
+        wire_size: 0,
+    };
+
+    // Both requests fail, but only one of them gets retried.
+    let requests: Vec<_> = sinks.iter_mut().map(|sink| sink.call(batch(), 1)).collect();
+    future::join_all(requests).await;
+    assert_eq!(calls.load(Relaxed), 3);
+
+    // The retry waited 500ms for its backoff, and the budget stays
+    // exhausted until a second has passed.
+    let requests: Vec<_> = sinks.iter_mut().map(|sink| sink.call(batch(), 1)).collect();
+    future::join_all(requests).await;
+    assert_eq!(calls.load(Relaxed), 5);
+
+    tokio::time::advance(Duration::from_millis(500)).await;
+    let requests: Vec<_> = sinks.iter_mut().map(|sink| sink.call(batch(), 1)).collect();
+    future::join_all(requests).await;
+    assert_eq!(calls.load(Relaxed), 8);
+}
+
+#[tokio::test]
+async fn service_sink_retries_after_requested_delay() {
+    #[derive(Debug)]
+    struct Throttled(Option<Duration>);
+
+    impl Response for Throttled {
+        fn is_successful(&self) -> bool {
+            self.0.is_none()
+        }
+
+        fn retry_after(&self) -> Option<Duration> {
+            self.0
+        }
+    }
+
+    tokio::time::pause();
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let svc = {
+        let calls = Arc::clone(&calls);
+        tower::service_fn(move |_: Vec<usize>| {
+            let mut calls = calls.lock().unwrap();
+            // Only the first request gets throttled.
+            let retry_after = calls.is_empty().then(|| Duration::from_secs(3));
+            calls.push(Instant::now());
+            future::ok::<_, std::io::Error>(Throttled(retry_after))
+        })
+    };
+    let mut sink =
+        ServiceSink::new(svc, Acker::basic().0).with_retry_budget(Arc::new(RetryBudget::new(1)));
+    let batch = EncodedBatch {
+        items: vec![0],
+        finalizers: EventFinalizers::default(),
+        count: 1,
+        byte_size: 0,
+        wire_size: 0,
+    };
+    sink.call(batch, 1).await;
+
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[1] - calls[0], Duration::from_secs(3));
+}
+
+#[tokio::test]
+async fn service_sink_acks_in_flight_requests_after_drop() {
+    tokio::time::pause();
+
+    let (acker, ack_counter) = Acker::basic();
+    // Each request completes after as many seconds as its first item.
+    let svc = tower::service_fn(|items: Vec<u64>| async move {
+        sleep(Duration::from_secs(items[0])).await;
+        Ok::<_, std::io::Error>(())
+    });
+    let mut sink = ServiceSink::new(svc, acker);
+
+    for items in [vec![2, 0], vec![1], vec![0, 1, 2]] {
+        let count = items.len();
+        let batch = EncodedBatch {
+            items,
+            finalizers: EventFinalizers::default(),
+            count,
+            byte_size: 0,
+            wire_size: 0,
+        };
+        tokio::spawn(sink.call(batch, count));
+    }
+    yield_now().await;
+    drop(sink);
+    assert_eq!(ack_counter.load(Relaxed), 0);
+
+    tokio::time::advance(Duration::from_secs(3)).await;
+    for _ in 0..10 {
+        yield_now().await;
+    }
+    assert_eq!(ack_counter.load(Relaxed), 6);
+}
+
+#[tokio::test]
+async fn service_sink_snapshot() {
+    tokio::time::pause();
+
+    let (acker, ack_counter) = Acker::basic();
+    // Each request completes after as many seconds as its first item.
+    let svc = tower::service_fn(|items: Vec<u64>| async move {
+        sleep(Duration::from_secs(items[0])).await;
+        Ok::<_, std::io::Error>(())
+    });
+    let mut sink = ServiceSink::new(svc, acker);
+
+    for items in [vec![10, 0], vec![0], vec![0, 1, 2], vec![20]] {
+        let count = items.len();
+        let batch = EncodedBatch {
+            items,
+            finalizers: EventFinalizers::default(),
+            count,
+            byte_size: 0,
+            wire_size: 0,
+        };
+        tokio::spawn(sink.call(batch, count));
+    }
+    yield_now().await;
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(sink.poll_complete(&mut cx).is_pending());
+    assert_eq!(
+        sink.snapshot(),
+        ServiceSinkSnapshot {
+            seq_head: 4,
+            seq_tail: 0,
+            in_flight_count: 2,
+            pending_acks: vec![(1, 1), (2, 3)],
+        }
+    );
+
+    tokio::time::advance(Duration::from_secs(10)).await;
+    yield_now().await;
+    assert!(sink.poll_complete(&mut cx).is_pending());
+    assert_eq!(ack_counter.load(Relaxed), 6);
+    assert_eq!(
+        sink.snapshot(),
+        ServiceSinkSnapshot {
+            seq_head: 4,
+            seq_tail: 3,
+            in_flight_count: 1,
+            pending_acks: vec![],
+        }
+    );
+}
+
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn service_sink_logs_request_bodies() {
+    let logs = CapturedLogs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .with_writer({
+            let logs = logs.clone();
+            move || logs.clone()
+        })
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_: Vec<usize>| future::ok::<_, std::io::Error>(()));
+    let mut sink = ServiceSink::new(svc, acker).with_debug_logging(tracing::Level::TRACE);
+    sink.debug_log_max_bytes = 10;
+
+    for items in [vec![1, 2, 3], vec![1234567, 89]] {
+        let count = items.len();
+        let batch = EncodedBatch {
+            items,
+            finalizers: EventFinalizers::default(),
+            count,
+            byte_size: 0,
+            wire_size: 0,
+        };
+        sink.call(batch, count).await;
+    }
+
+    let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(output.contains("body=[1, 2, 3]"));
+    assert!(output.contains("body=[1234567, ..."));
+    assert!(!output.contains("[1234567, 89]"));
+}
+
+#[tokio::test]
+async fn service_sink_logs_byte_request_bodies() {
+    let logs = CapturedLogs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .with_writer({
+            let logs = logs.clone();
+            move || logs.clone()
+        })
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_: Vec<u8>| future::ok::<_, std::io::Error>(()));
+    let mut sink = ServiceSink::new(svc, acker).with_debug_logging_bytes(tracing::Level::TRACE);
+
+    for items in [b"hello".to_vec(), vec![b'a', 0xff, b'b']] {
+        let batch = EncodedBatch {
+            items,
+            finalizers: EventFinalizers::default(),
+            count: 1,
+            byte_size: 0,
+            wire_size: 0,
+        };
+        sink.call(batch, 1).await;
+    }
+
+    let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(output.contains("body=hello"));
+    assert!(output.contains("body=a\u{fffd}b"));
+}
+
+#[tokio::test]
+async fn service_sink_sends_keepalive_heartbeats() {
+    tokio::time::pause();
+
+    let (acker, _) = Acker::basic();
+    let heartbeats = Arc::new(Mutex::new(Vec::new()));
+    let svc = {
+        let heartbeats = Arc::clone(&heartbeats);
+        tower::service_fn(move |req: u8| {
+            heartbeats.lock().unwrap().push(req);
+            future::ok::<_, std::io::Error>(())
+        })
+    };
+
+    let _sink = ServiceSink::new(svc, acker).with_keepalive(Duration::from_millis(100), 42);
+
+    // The sink stays idle, so a heartbeat is sent on each tick.
+    sleep(Duration::from_millis(350)).await;
+    assert_eq!(&*heartbeats.lock().unwrap(), &vec![42, 42, 42]);
+}
+
+#[tokio::test]
+async fn service_sink_inspects_responses() {
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_: Vec<usize>| {
+        let response = http::Response::builder()
+            .status(429)
+            .header("Retry-After", "10")
+            .body(Bytes::new())
+            .unwrap();
+        future::ok::<_, std::io::Error>(response)
+    });
+    let retry_after = Arc::new(Mutex::new(Vec::new()));
+    let mut sink = ServiceSink::new(svc, acker).with_response_inspector({
+        let retry_after = Arc::clone(&retry_after);
+        move |response: &http::Response<Bytes>| {
+            retry_after.lock().unwrap().push((
+                response.status().as_u16(),
+                response.headers()["Retry-After"]
+                    .to_str()
+                    .unwrap()
+                    .to_owned(),
+            ));
+        }
+    });
+
+    let batch = EncodedBatch {
+        items: vec![1, 2, 3],
+        finalizers: EventFinalizers::default(),
+        count: 3,
+        byte_size: 0,
+        wire_size: 0,
+    };
+    sink.call(batch, 3).await;
+
+    assert_eq!(&*retry_after.lock().unwrap(), &vec![(429, "10".to_owned())]);
+}
+
+#[tokio::test]
+async fn service_sink_fails_over_to_secondary_region() {
+    init_test();
+    let (acker, _) = Acker::basic();
+    let primary_healthy = Arc::new(AtomicBool::new(false));
+    let sent_to = Arc::new(Mutex::new(Vec::new()));
+    let service = |region: &'static str| {
+        let primary_healthy = Arc::clone(&primary_healthy);
+        let sent_to = Arc::clone(&sent_to);
+        tower::service_fn(move |_: Vec<usize>| {
+            sent_to.lock().unwrap().push(region);
+            if region == "secondary" || primary_healthy.load(Relaxed) {
+                future::ok("good")
+            } else {
+                future::err("bad")
+            }
+        })
+    };
+    let mut sink = ServiceSink::new(service("primary"), acker).with_regional_failover(
+        service("secondary"),
+        3,
+        Duration::from_secs(60),
+    );
+    let batch = || EncodedBatch {
+        items: vec![1],
+        finalizers: EventFinalizers::default(),
+        count: 1,
+        byte_size: 0,
+        wire_size: 0,
+    };
+
+    for _ in 0..4 {
+        sink.call(batch(), 1).await;
+    }
+    assert_eq!(
+        *sent_to.lock().unwrap(),
+        vec!["primary", "primary", "primary", "secondary"]
+    );
+    assert!(event_test_util::contains_name("RegionalFailoverActivated"));
+
+    // Once the probe interval is over, a single successful request to the
+    // primary region routes the requests back to it.
+    primary_healthy.store(true, Relaxed);
+    advance_time(Duration::from_secs(60)).await;
+    sent_to.lock().unwrap().clear();
+    for _ in 0..2 {
+        sink.call(batch(), 1).await;
+    }
+    assert_eq!(*sent_to.lock().unwrap(), vec!["primary", "primary"]);
+    assert!(event_test_util::contains_name("RegionalFailoverRecovered"));
+}
+
+#[tokio::test]
+async fn service_sink_skips_readiness_of_failed_over_primary() {
+    #[derive(Clone)]
+    struct UnreadyPrimary {
+        ready: Arc<AtomicBool>,
+        sent_to: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl Service<Vec<usize>> for UnreadyPrimary {
+        type Response = &'static str;
+        type Error = &'static str;
+        type Future = future::Ready<Result<&'static str, &'static str>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.ready.load(Relaxed) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, _: Vec<usize>) -> Self::Future {
+            self.sent_to.lock().unwrap().push("primary");
+            future::err("bad")
+        }
+    }
+
+    init_test();
+    let (acker, _) = Acker::basic();
+    let ready = Arc::new(AtomicBool::new(true));
+    let sent_to = Arc::new(Mutex::new(Vec::new()));
+    let primary = UnreadyPrimary {
+        ready: Arc::clone(&ready),
+        sent_to: Arc::clone(&sent_to),
+    };
+    let secondary = {
+        let sent_to = Arc::clone(&sent_to);
+        tower::service_fn(move |_: Vec<usize>| {
+            sent_to.lock().unwrap().push("secondary");
+            future::ok::<_, &'static str>("good")
+        })
+    };
+    let mut sink = ServiceSink::new(primary, acker).with_regional_failover(
+        secondary,
+        2,
+        Duration::from_secs(60),
+    );
+    let batch = || EncodedBatch {
+        items: vec![1],
+        finalizers: EventFinalizers::default(),
+        count: 1,
+        byte_size: 0,
+        wire_size: 0,
+    };
+    let mut cx = Context::from_waker(noop_waker_ref());
+
+    for _ in 0..2 {
+        assert!(matches!(sink.poll_ready(&mut cx), Poll::Ready(Ok(()))));
+        sink.call(batch(), 1).await;
+    }
+
+    // Once failed over, the stalled primary region does not hold back
+    // the requests, not even the one probing it.
+    ready.store(false, Relaxed);
+    assert!(matches!(sink.poll_ready(&mut cx), Poll::Ready(Ok(()))));
+    sink.call(batch(), 1).await;
+    advance_time(Duration::from_secs(60)).await;
+    assert!(matches!(sink.poll_ready(&mut cx), Poll::Ready(Ok(()))));
+    sink.call(batch(), 1).await;
+    assert_eq!(
+        *sent_to.lock().unwrap(),
+        vec!["primary", "primary", "secondary", "secondary"]
+    );
+}
+
+#[tokio::test]
+async fn service_sink_writes_audit_records() {
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|status: u16| {
+        let response = http::Response::builder()
+            .status(status)
+            .body(Bytes::new())
+            .unwrap();
+        future::ok::<_, std::io::Error>(response)
+    });
+    let (audit_tx, audit_rx) = futures::channel::mpsc::unbounded();
+    let mut sink = ServiceSink::new(svc, acker).with_audit_log(audit_tx);
+
+    for (status, count) in [(200, 1), (503, 2), (400, 3)] {
+        let batch = EncodedBatch {
+            items: status,
+            finalizers: EventFinalizers::default(),
+            count,
+            byte_size: 0,
+            wire_size: 0,
+        };
+        sink.call(batch, count).await;
+    }
+    drop(sink);
+
+    let records = timeout(TIMEOUT, audit_rx.collect::<Vec<_>>())
+        .await
+        .unwrap();
+    let records = records
+        .into_iter()
+        .map(|record| {
+            (
+                record.request_id,
+                record.batch_size,
+                record.outcome,
+                record.response_summary.unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        records,
+        vec![
+            (0, 1, EventStatus::Delivered, "200 OK".to_owned()),
+            (
+                1,
+                2,
+                EventStatus::Errored,
+                "503 Service Unavailable".to_owned()
+            ),
+            (2, 3, EventStatus::Rejected, "400 Bad Request".to_owned()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn service_sink_drops_audit_records_of_slow_audit_sink() {
+    init_test();
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_: Vec<usize>| future::ok::<_, std::io::Error>(()));
+    // The audit sink never takes more than a record.
+    let (audit_tx, _audit_rx) = futures::channel::mpsc::channel(0);
+    let mut sink = ServiceSink::new(svc, acker).with_audit_log(audit_tx);
+
+    for _ in 0..AUDIT_LOG_BUFFER_SIZE + 2 {
+        let batch = EncodedBatch {
+            items: vec![0],
+            finalizers: EventFinalizers::default(),
+            count: 1,
+            byte_size: 0,
+            wire_size: 0,
+        };
+        sink.call(batch, 1).await;
+    }
+    assert!(event_test_util::contains_name("ServiceAuditRecordDropped"));
+}
+
+const FRAME_OVERHEAD: usize = 12;
+
+/// Adds a fixed framing overhead to each batch, like a message set header.
+#[derive(Clone)]
+struct FramedBatch(VecBuffer<usize>);
+
+impl Batch for FramedBatch {
+    type Input = usize;
+    type Output = Vec<usize>;
+
+    fn push(&mut self, item: Self::Input) -> PushResult<Self::Input> {
+        self.0.push(item)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn fresh(&self) -> Self {
+        Self(self.0.fresh())
+    }
+
+    fn finish(self) -> Self::Output {
+        self.0.finish()
+    }
+
+    fn num_items(&self) -> usize {
+        self.0.num_items()
+    }
+
+    fn wire_size(output: &Self::Output) -> Option<usize> {
+        <VecBuffer<usize> as Batch>::wire_size(output).map(|size| size + FRAME_OVERHEAD)
+    }
+}
+
+#[tokio::test]
+async fn service_sink_reports_wire_size() {
+    let logs = CapturedLogs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .with_writer({
+            let logs = logs.clone();
+            move || logs.clone()
+        })
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+    let mut batch = FinalizersBatch::from(FramedBatch(VecBuffer::new(batch_settings.size)));
+    for item in 0..3 {
+        assert!(matches!(
+            batch.push(EncodedEvent::new(item, 8)),
+            PushResult::Ok(false)
+        ));
+    }
+    let batch = batch.finish();
+    assert_eq!(batch.byte_size, 24);
+    assert_eq!(batch.wire_size, 3 * 22 + FRAME_OVERHEAD);
+
+    let (acker, _) = Acker::basic();
+    let svc = tower::service_fn(|_: Vec<usize>| future::ok::<_, std::io::Error>(()));
+    let mut sink = ServiceSink::new(svc, acker);
+    sink.call(batch, 3).await;
+
+    let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(output.contains("batch_size=3 wire_size=78"));
+}
+
+#[derive(Clone)]
+struct CountingService {
+    poll_ready_calls: Arc<AtomicUsize>,
+}
+
+impl Service<Vec<usize>> for CountingService {
+    type Response = ();
+    type Error = std::io::Error;
+    type Future = future::Ready<Result<(), std::io::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_ready_calls.fetch_add(1, Relaxed);
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: Vec<usize>) -> Self::Future {
+        future::ok(())
+    }
+}
+
+#[tokio::test]
+async fn service_sink_warms_up_service() {
+    let (acker, _) = Acker::basic();
+    let poll_ready_calls = Arc::new(AtomicUsize::new(0));
+    let svc = CountingService {
+        poll_ready_calls: Arc::clone(&poll_ready_calls),
+    };
+    let mut sink = ServiceSink::new(svc, acker).with_warmup();
+    while poll_ready_calls.load(Relaxed) == 0 {
+        yield_now().await;
+    }
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for (i, expected_calls) in [2, 3].into_iter().enumerate() {
+        assert!(matches!(sink.poll_ready(&mut cx), Poll::Ready(Ok(()))));
+        assert_eq!(poll_ready_calls.load(Relaxed), expected_calls);
+        let batch = EncodedBatch {
+            items: vec![i],
+            finalizers: EventFinalizers::default(),
+            count: 1,
+            byte_size: 0,
+            wire_size: 0,
+        };
+        tokio::spawn(sink.call(batch, 1)).await.unwrap();
+    }
+}
+
+struct ConcurrentService {
+    poll_ready_calls: Arc<AtomicUsize>,
+    in_flight: Arc<AtomicUsize>,
+    gate: Arc<tokio::sync::Notify>,
+}
+
+impl Service<Vec<usize>> for ConcurrentService {
+    type Response = ();
+    type Error = std::io::Error;
+    type Future = BoxFuture<'static, Result<(), std::io::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_ready_calls.fetch_add(1, Relaxed);
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: Vec<usize>) -> Self::Future {
+        let in_flight = Arc::clone(&self.in_flight);
+        let gate = Arc::clone(&self.gate);
+        Box::pin(async move {
+            in_flight.fetch_add(1, Relaxed);
+            gate.notified().await;
+            in_flight.fetch_sub(1, Relaxed);
+            Ok(())
+        })
+    }
+}
+
+#[tokio::test]
+async fn service_sink_concurrent_dispatch() {
+    let (acker, ack_counter) = Acker::basic();
+    let poll_ready_calls = Arc::new(AtomicUsize::new(0));
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let gate = Arc::new(tokio::sync::Notify::new());
+    let svc = ConcurrentService {
+        poll_ready_calls: Arc::clone(&poll_ready_calls),
+        in_flight: Arc::clone(&in_flight),
+        gate: Arc::clone(&gate),
+    };
+    let mut sink = ServiceSink::new(svc, acker).with_concurrent_dispatch(10);
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for i in 0..10 {
+        assert!(matches!(sink.poll_ready(&mut cx), Poll::Ready(Ok(()))));
+        let batch = EncodedBatch {
+            items: vec![i],
+            finalizers: EventFinalizers::default(),
+            count: 1,
+            byte_size: 0,
+            wire_size: 0,
+        };
+        tokio::spawn(sink.call(batch, 1));
+    }
+    yield_now().await;
+
+    assert_eq!(in_flight.load(Relaxed), 10);
+    assert_eq!(poll_ready_calls.load(Relaxed), 10);
+    // All permits are taken.
+    assert!(sink.poll_ready(&mut cx).is_pending());
+
+    gate.notify_waiters();
+    while sink.poll_complete(&mut cx).is_pending() {
+        yield_now().await;
+    }
+    assert_eq!(in_flight.load(Relaxed), 0);
+    assert_eq!(ack_counter.load(Relaxed), 10);
+    assert!(matches!(sink.poll_ready(&mut cx), Poll::Ready(Ok(()))));
+    assert_eq!(poll_ready_calls.load(Relaxed), 11);
+}
+
+#[tokio::test]
+async fn service_sink_concurrent_dispatch_to_buffered_service() {
+    let (acker, ack_counter) = Acker::basic();
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let gate = Arc::new(tokio::sync::Notify::new());
+    let svc = ConcurrentService {
+        poll_ready_calls: Arc::new(AtomicUsize::new(0)),
+        in_flight: Arc::clone(&in_flight),
+        gate: Arc::clone(&gate),
+    };
+    // Both panic when called without being ready first.
+    let svc = tower::buffer::Buffer::new(tower::limit::ConcurrencyLimit::new(svc, 10), 10);
+    let mut sink = ServiceSink::new(svc, acker).with_concurrent_dispatch(10);
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for i in 0..3 {
+        assert!(matches!(sink.poll_ready(&mut cx), Poll::Ready(Ok(()))));
+        let batch = EncodedBatch {
+            items: vec![i],
+            finalizers: EventFinalizers::default(),
+            count: 1,
+            byte_size: 0,
+            wire_size: 0,
+        };
+        tokio::spawn(sink.call(batch, 1));
+    }
+    while in_flight.load(Relaxed) < 3 {
+        yield_now().await;
+    }
+
+    gate.notify_waiters();
+    while sink.poll_complete(&mut cx).is_pending() {
+        yield_now().await;
+    }
+    assert_eq!(ack_counter.load(Relaxed), 3);
+}
+
+struct PendingService {
+    ready_at: Instant,
+}
+
+impl Service<u8> for PendingService {
+    type Response = ();
+    type Error = std::io::Error;
+    type Future = future::Ready<Result<(), std::io::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if Instant::now() >= self.ready_at {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, _req: u8) -> Self::Future {
+        future::ok(())
+    }
+}
+
+#[tokio::test]
+async fn service_sink_reports_stalled_poll_ready() {
+    init_test();
+    tokio::time::pause();
+
+    let (acker, _) = Acker::basic();
+    let svc = PendingService {
+        ready_at: Instant::now() + Duration::from_secs(10),
+    };
+    let mut sink = ServiceSink::new(svc, acker);
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(sink.poll_ready(&mut cx).is_pending());
+    assert!(sink.pending_since.is_some());
+    assert!(sink.stall_timer.is_some());
+
+    tokio::time::advance(Duration::from_secs(3)).await;
+    assert!(sink.poll_ready(&mut cx).is_pending());
+    assert!(sink.stall_timer.is_some());
+
+    tokio::time::advance(Duration::from_secs(3)).await;
+    assert!(sink.poll_ready(&mut cx).is_pending());
+    // The stall is only reported once.
+    assert!(sink.stall_timer.is_none());
+    assert!(Controller::get().unwrap().capture_metrics().any(|metric| {
+        metric.name() == "component_errors_total"
+            && metric.tags().map_or(false, |tags| {
+                tags.get("error_type").map(String::as_str) == Some("poll_ready_stalled")
+            })
+    }));
+
+    tokio::time::advance(Duration::from_secs(5)).await;
+    assert!(matches!(sink.poll_ready(&mut cx), Poll::Ready(Ok(()))));
+    assert!(sink.pending_since.is_none());
+}
+
+#[derive(Debug, PartialEq, Eq, Ord, PartialOrd)]
+enum Partitions {
+    A,
+    B,
+}
+
+impl EncodedLength for Partitions {
+    fn encoded_length(&self) -> usize {
+        10 // Dummy value
+    }
+}
+
+impl Partition<Bytes> for Partitions {
+    fn partition(&self) -> Bytes {
+        format!("{:?}", self).into()
+    }
+}
+
+/// An event partitioned by its raw key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Keyed(&'static str);
+
+impl EncodedLength for Keyed {
+    fn encoded_length(&self) -> usize {
+        10 // Dummy value
+    }
+}
+
+impl Partition<String> for Keyed {
+    fn partition(&self) -> String {
+        self.0.to_owned()
+    }
+}
+
+/// A partition key which isn't a plain string.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct MyKey {
+    field: &'static str,
+}
+
+/// An event partitioned by a `MyKey`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Labeled(&'static str);
+
+impl EncodedLength for Labeled {
+    fn encoded_length(&self) -> usize {
+        10 // Dummy value
+    }
+}
+
+impl Partition<MyKey> for Labeled {
+    fn partition(&self) -> MyKey {
+        MyKey { field: self.0 }
+    }
+}
+
+/// An event delivered to each of its shards.
+#[derive(Clone, Debug, PartialEq)]
+struct Sharded(usize, Vec<&'static str>);
+
+impl EncodedLength for Sharded {
+    fn encoded_length(&self) -> usize {
+        10 // Dummy value
+    }
+}
+
+impl MultiPartition<&'static str> for Sharded {
+    fn partitions(&self) -> Vec<&'static str> {
+        self.1.clone()
+    }
+}
+
+impl Partition<Bytes> for usize {
+    fn partition(&self) -> Bytes {
+        "key".into()
+    }
+}
+
+impl Partition<Bytes> for u8 {
+    fn partition(&self) -> Bytes {
+        "key".into()
+    }
+}
+
+impl Partition<Bytes> for i32 {
+    fn partition(&self) -> Bytes {
+        "key".into()
+    }
+}
+
+impl Partition<Bytes> for Vec<i32> {
+    fn partition(&self) -> Bytes {
+        "key".into()
+    }
+}
+
+/// A service which records the requests it is sent.
+struct RecordingService<R> {
+    requests: Arc<Mutex<Vec<R>>>,
+}
+
+impl<R> Service<R> for RecordingService<R> {
+    type Response = ();
+    type Error = std::io::Error;
+    type Future = future::Ready<Result<(), std::io::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        self.requests.lock().unwrap().push(req);
+        future::ok(())
+    }
+}
+
+/// Runs a `PartitionBatchSink` over a `RecordingService`, to check what
+/// it dispatched for each partition.
+struct PartitionBatchSinkTestHarness<B, K>
+where
+    B: Batch,
+{
+    sink: PartitionBatchSink<RecordingService<B::Output>, B, K, StdServiceLogic<()>>,
+    sent_requests: Arc<Mutex<Vec<B::Output>>>,
+}
+
+impl<T, K> PartitionBatchSinkTestHarness<VecBuffer<T>, K>
+where
+    T: Partition<K> + EncodedLength + Send + 'static,
+    K: Hash + Eq + Clone + Send + 'static,
+{
+    fn new(batch_settings: BatchSettings<VecBuffer<T>>, timeout: Duration) -> Self {
+        let (acker, _) = Acker::basic();
+        let sent_requests = Arc::new(Mutex::new(Vec::new()));
+        let service = RecordingService {
+            requests: Arc::clone(&sent_requests),
+        };
+        let sink =
+            PartitionBatchSink::new(service, VecBuffer::new(batch_settings.size), timeout, acker);
+        Self {
+            sink,
+            sent_requests,
+        }
+    }
+}
+
+impl<B, K> PartitionBatchSinkTestHarness<B, K>
+where
+    B: Batch<Output = Vec<<B as Batch>::Input>>,
+    B::Input: Partition<K> + fmt::Debug + PartialEq,
+    K: Hash + Eq + Clone + Send + 'static,
+{
+    /// Sends all of `events` and flushes the sink.
+    async fn send_events(&mut self, events: Vec<B::Input>) {
+        self.sink
+            .send_all(&mut stream::iter(events).map(|item| Ok(EncodedEvent::new(item, 0))))
+            .await
+            .unwrap();
+    }
+
+    /// Checks that the events dispatched for `key`, across all of its
+    /// batches, are `expected` in that order.
+    fn assert_dispatched_for_partition(&self, key: &K, expected: &[B::Input]) {
+        let sent_requests = self.sent_requests.lock().unwrap();
+        let dispatched: Vec<&B::Input> = sent_requests
+            .iter()
+            .flatten()
+            .filter(|item| item.partition() == *key)
+            .collect();
+        assert_eq!(dispatched, expected.iter().collect::<Vec<_>>());
+    }
+}
+
+#[tokio::test]
+async fn partition_batch_sink_applies_config_updates() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+    let svc = tower::service_fn(|req| {
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let config = PartitionBatchConfig {
+        timeout: TIMEOUT,
+        max_events: 10,
+        max_bytes: 9999,
+    };
+    let (tx, rx) = watch::channel(config);
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_config_updates(rx);
+
+    sink.send_all(&mut stream::iter(0..10).map(|i| Ok(EncodedEvent::new((0, i), 0))))
+        .await
+        .unwrap();
+
+    tx.send(PartitionBatchConfig {
+        max_events: 2,
+        ..config
+    })
+    .unwrap();
+    sink.flush().await.unwrap();
+
+    sink.send_all(&mut stream::iter(10..14).map(|i| Ok(EncodedEvent::new((0, i), 0))))
+        .await
+        .unwrap();
+
+    let output = sent_requests.lock().unwrap();
+    let sizes = output.iter().map(Vec::len).collect::<Vec<_>>();
+    assert_eq!(sizes, vec![10, 2, 2]);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_routes_overflow_to_overflow_sink() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+    let svc = tower::service_fn(|req| {
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 1;
+
+    let (overflow_tx, overflow_rx) = futures::channel::mpsc::unbounded();
+    let sink = PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+        .with_overflow_sink(overflow_tx);
+
+    sink.sink_map_err(drop)
+        .send_all(&mut stream::iter(0..3).map(|item| Ok(EncodedEvent::new(item, 0))))
+        .await
+        .unwrap();
+
+    assert_eq!(&*sent_requests.lock().unwrap(), &vec![vec![0]]);
+    let overflowed: Vec<_> = overflow_rx.map(|event| event.item).collect().await;
+    assert_eq!(overflowed, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn partition_batch_sink_pauses_under_memory_pressure() {
+    tokio::time::pause();
+
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+    let svc = tower::service_fn(|req| {
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let mut batch_settings = BatchSettings::default();
+    batch_settings.size.bytes = 9999;
+    batch_settings.size.events = 10;
+
+    let mut sink =
+        PartitionBatchSink::new(svc, VecBuffer::new(batch_settings.size), TIMEOUT, acker)
+            .with_memory_limit(50, 10);
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for i in 0..6 {
+        assert!(matches!(
+            sink.poll_ready_unpin(&mut cx),
+            Poll::Ready(Ok(()))
+        ));
+        sink.start_send_unpin(EncodedEvent::new((i % 2, i), 0))
+            .unwrap();
+    }
+
+    // 60 bytes are buffered, above the 50 bytes limit.
+    assert!(sink.poll_ready_unpin(&mut cx).is_pending());
+    assert!(sink.poll_ready_unpin(&mut cx).is_pending());
+    assert!(sent_requests.lock().unwrap().is_empty());
+
+    // Lingering batches are sent, relieving the pressure.
+    tokio::time::advance(TIMEOUT).await;
+    assert!(matches!(
+        sink.poll_ready_unpin(&mut cx),
+        Poll::Ready(Ok(()))
+    ));
+    assert_eq!(sent_requests.lock().unwrap().len(), 2);
+}
+
+/// Test batch encoding its items with `encode` once finished.
+struct EncodingBatch {
+    items: Vec<(usize, usize)>,
+    encode: fn(&[(usize, usize)]) -> Bytes,
+}
+
+impl Batch for EncodingBatch {
+    type Input = (usize, usize);
+    type Output = Bytes;
+
+    fn push(&mut self, item: Self::Input) -> PushResult<Self::Input> {
+        self.items.push(item);
+        PushResult::Ok(false)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    fn fresh(&self) -> Self {
+        Self {
+            items: Vec::new(),
+            encode: self.encode,
+        }
+    }
+
+    fn finish(self) -> Self::Output {
+        (self.encode)(&self.items)
+    }
+
+    fn num_items(&self) -> usize {
+        self.items.len()
+    }
+}
+
+#[tokio::test]
+async fn partition_batch_sink_encodes_per_partition() {
+    let (acker, _) = Acker::basic();
+    let sent_requests = Arc::new(Mutex::new(Vec::new()));
+    let svc = tower::service_fn(|req: Bytes| {
+        sent_requests.lock().unwrap().push(req);
+        future::ok::<_, std::io::Error>(())
+    });
+
+    let batch = PerPartitionBatch::new(
+        |partition: &Bytes| -> Box<dyn DynBatch<(usize, usize), Bytes>> {
+            let encode: fn(&[(usize, usize)]) -> Bytes = if &partition[..] == b"0" {
+                |items: &[(usize, usize)]| serde_json::to_vec(items).unwrap().into()
+            } else {
+                |items: &[(usize, usize)]| {
+                    items
+                        .iter()
+                        .map(|(partition, value)| format!("{},{}\n", partition, value))
+                        .collect::<String>()
+                        .into()
+                }
+            };
+            Box::new(EncodingBatch {
+                items: Vec::new(),
+                encode,
+            })
+        },
+    );
+    let sink = PartitionBatchSink::new(svc, batch, TIMEOUT, acker);
+
+    sink.sink_map_err(drop)
+        .send_all(
+            &mut stream::iter(vec![(0, 1), (1, 2), (0, 3), (1, 4)])
+                .map(|item| Ok(EncodedEvent::new(item, 0))),
+        )
+        .await
+        .unwrap();
+
+    let mut output = sent_requests.lock().unwrap().clone();
+    output.sort();
+    assert_eq!(
+        output,
+        vec![Bytes::from("1,2\n1,4\n"), Bytes::from("[[0,1],[0,3]]")]
+    );
+}
+
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct Small {
+    key: usize,
+    partition: Option<Bytes>,
+}
+
+impl Small {
+    const fn new(key: usize) -> Self {
+        Self {
+            key,
+            partition: None,
+        }
+    }
+}
+
+impl Partition<Bytes> for Small {
+    fn partition(&self) -> Bytes {
+        self.key.to_string().into()
+    }
+}
+
+impl Coalesce<Bytes> for Small {
+    fn coalesced_partition() -> Bytes {
+        Bytes::from_static(b"__coalesced__")
+    }
+
+    fn tag_partition(&mut self, partition: &Bytes) {
+        self.partition = Some(partition.clone());
+    }
+}
+
+impl EncodedLength for Small {
+    fn encoded_length(&self) -> usize {
+        16
+    }
+}
+
+impl Partition<Bytes> for (usize, usize) {
+    fn partition(&self) -> Bytes {
+        format!("{}", self.0).into()
+    }
+}
+
+impl EncodedLength for (usize, usize) {
+    fn encoded_length(&self) -> usize {
+        16
+    }
+}
@@ -0,0 +1,98 @@
+//! Logging the events held in the batches of a `PartitionBatchSink` until
+//! they are dispatched, see `PartitionBatchSink::with_wal`.
+
+use std::{hash::Hash, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tower::Service;
+
+use super::{PartitionBatchSink, Response, ServiceLogic};
+use crate::sinks::util::{
+    batch::Batch,
+    buffer::Partition,
+    wal::{DiskWal, WriteAheadLog},
+    EncodedEvent,
+};
+
+const DEFAULT_MAX_WAL_FILES: usize = 4;
+const DEFAULT_MAX_WAL_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+impl<S, B, K, SL> PartitionBatchSink<S, B, K, SL>
+where
+    B: Batch,
+    B::Input: Partition<K>,
+    K: Hash + Eq + Clone + Send + 'static,
+    S: Service<B::Output>,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::Error> + Send + 'static,
+    S::Response: Response + Send + 'static,
+    SL: ServiceLogic<Response = S::Response> + Send + 'static,
+{
+    /// Logs each event to files in the `path` directory until its batch has
+    /// been dispatched, so that the events held in batches survive a crash.
+    /// The events a previous run left in there are sent again first.
+    ///
+    /// Replayed events have no finalizers, their delivery isn't acked.
+    pub fn with_wal(self, path: PathBuf) -> Self
+    where
+        K: Serialize + DeserializeOwned,
+        B::Input: Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.with_wal_limits(path, DEFAULT_MAX_WAL_FILES, DEFAULT_MAX_WAL_FILE_BYTES)
+    }
+
+    /// Like `with_wal`, starting a new file once the current one reaches
+    /// `max_file_bytes`. The oldest files are deleted once there are more than
+    /// `max_wal_files` of them and all of their events have been dispatched.
+    pub fn with_wal_limits(
+        mut self,
+        path: PathBuf,
+        max_wal_files: usize,
+        max_file_bytes: u64,
+    ) -> Self
+    where
+        K: Serialize + DeserializeOwned,
+        B::Input: Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.wal = Some(Box::new(DiskWal::new(path, max_wal_files, max_file_bytes)));
+        self
+    }
+
+    /// Inserts the events a previous run left in the write-ahead log, for as
+    /// long as they fit in their batch.
+    pub(super) fn replay_wal(&mut self) -> crate::Result<()> {
+        while self.buffer.is_none() {
+            let item = match self.wal.as_mut() {
+                Some(wal) => wal.replay()?,
+                None => None,
+            };
+            let item = match item {
+                Some(item) => item,
+                None => break,
+            };
+            let partition = self.partition_of(&item.item);
+            if let Some(wal) = self.wal.as_mut() {
+                wal.append(&partition, &item)?;
+            }
+            self.insert(item);
+        }
+        Ok(())
+    }
+}
+
+/// Marks the events logged for `partition` as consumed once its batch is
+/// dispatched, except for the one waiting in `buffer` for the next batch.
+pub(super) fn consume_wal<K: PartialEq, T>(
+    wal: &mut Option<Box<dyn WriteAheadLog<K, T>>>,
+    buffer: &Option<(K, EncodedEvent<T>)>,
+    partition: &K,
+) -> crate::Result<()> {
+    if let Some(wal) = wal.as_mut() {
+        let keep = match buffer {
+            Some((buffered, _)) if buffered == partition => 1,
+            _ => 0,
+        };
+        wal.consume(partition, keep)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+//! Disk backed queue holding the events a `BatchSink` couldn't fit in memory.
+
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    marker::PhantomData,
+    path::PathBuf,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::EncodedEvent;
+use crate::event::EventFinalizers;
+
+pub(crate) trait OverflowSpill<T>: Send {
+    /// Appends `item` at the end of the queue.
+    fn push(&mut self, item: EncodedEvent<T>) -> crate::Result<()>;
+
+    /// Takes the oldest item out of the queue.
+    fn pop(&mut self) -> crate::Result<Option<EncodedEvent<T>>>;
+
+    fn is_empty(&self) -> bool;
+
+    /// Whether the queue reached its size limit, in which case no more items
+    /// should be pushed until it has been emptied.
+    fn is_full(&self) -> bool;
+}
+
+/// Spills items as newline delimited JSON to an append-only file, which is
+/// truncated once all of them have been taken back out.
+///
+/// Only the items are written to disk, their finalizers stay in memory.
+pub(crate) struct DiskSpill<T> {
+    path: PathBuf,
+    max_bytes: u64,
+    writer: Option<File>,
+    reader: Option<BufReader<File>>,
+    written_bytes: u64,
+    pending: VecDeque<(EventFinalizers, usize)>,
+    _pd: PhantomData<fn() -> T>,
+}
+
+impl<T> DiskSpill<T> {
+    pub(crate) fn new(path: PathBuf, max_bytes: u64) -> Self {
+        Self {
+            path,
+            max_bytes,
+            writer: None,
+            reader: None,
+            written_bytes: 0,
+            pending: VecDeque::new(),
+            _pd: PhantomData,
+        }
+    }
+
+    fn writer(&mut self) -> io::Result<&mut File> {
+        if self.writer.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&self.path)?;
+            self.writer = Some(file);
+        }
+        Ok(self.writer.as_mut().expect("writer was just opened"))
+    }
+
+    fn reader(&mut self) -> io::Result<&mut BufReader<File>> {
+        if self.reader.is_none() {
+            self.reader = Some(BufReader::new(File::open(&self.path)?));
+        }
+        Ok(self.reader.as_mut().expect("reader was just opened"))
+    }
+
+    fn truncate(&mut self) -> io::Result<()> {
+        self.reader = None;
+        self.written_bytes = 0;
+        if let Some(writer) = self.writer.take() {
+            writer.set_len(0)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> OverflowSpill<T> for DiskSpill<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn push(&mut self, item: EncodedEvent<T>) -> crate::Result<()> {
+        let mut line = serde_json::to_vec(&item.item)?;
+        line.push(b'\n');
+        self.writer()?.write_all(&line)?;
+        self.written_bytes += line.len() as u64;
+        self.pending.push_back((item.finalizers, item.byte_size));
+        Ok(())
+    }
+
+    fn pop(&mut self) -> crate::Result<Option<EncodedEvent<T>>> {
+        let (finalizers, byte_size) = match self.pending.pop_front() {
+            Some(pending) => pending,
+            None => return Ok(None),
+        };
+
+        let mut line = String::new();
+        if self.reader()?.read_line(&mut line)? == 0 {
+            return Err(format!("Spill file {:?} ended unexpectedly.", self.path).into());
+        }
+        let item = serde_json::from_str(&line)?;
+
+        if self.pending.is_empty() {
+            self.truncate()?;
+        }
+
+        Ok(Some(EncodedEvent {
+            item,
+            finalizers,
+            byte_size,
+        }))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    fn is_full(&self) -> bool {
+        self.written_bytes >= self.max_bytes
+    }
+}
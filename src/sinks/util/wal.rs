@@ -0,0 +1,312 @@
+//! Write-ahead log of the events held in the batches of a
+//! `PartitionBatchSink`, so that they survive a crash until their batch has
+//! been dispatched.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs::{self, File, OpenOptions},
+    hash::Hash,
+    io::{self, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::EncodedEvent;
+
+pub(crate) trait WriteAheadLog<K, T>: Send {
+    /// Records `item`, which is about to be inserted in the batch of
+    /// `partition`.
+    fn append(&mut self, partition: &K, item: &EncodedEvent<T>) -> crate::Result<()>;
+
+    /// Marks the records of `partition` as consumed, except for the `keep`
+    /// most recent ones.
+    fn consume(&mut self, partition: &K, keep: usize) -> crate::Result<()>;
+
+    /// Marks the most recent record of `partition` as consumed.
+    fn consume_last(&mut self, partition: &K) -> crate::Result<()>;
+
+    /// Takes the next event a previous run left unconsumed. Its record has to
+    /// be appended again, the files of the previous run being deleted once
+    /// all of them have been taken.
+    fn replay(&mut self) -> crate::Result<Option<EncodedEvent<T>>>;
+}
+
+#[derive(Deserialize, Serialize)]
+struct WalRecord<K, T> {
+    seq: u64,
+    partition: K,
+    byte_size: usize,
+    item: T,
+}
+
+/// A file the records are appended to, along with the log of the records of
+/// it which have been consumed.
+struct Segment {
+    id: u64,
+    /// Unset once the segment has been rotated.
+    writer: Option<File>,
+    consume_log: File,
+    written_bytes: u64,
+    records: usize,
+    consumed: usize,
+}
+
+/// Logs records to files in `dir`, each of them being a length prefixed JSON
+/// document. A new file is started once the current one reaches
+/// `max_file_bytes`, and the oldest files are deleted once there are more
+/// than `max_files` of them and all of their records have been consumed.
+///
+/// Only the items are written to disk, replayed events have no finalizers.
+pub(crate) struct DiskWal<K, T> {
+    dir: PathBuf,
+    max_files: usize,
+    max_file_bytes: u64,
+    opened: bool,
+    segments: VecDeque<Segment>,
+    next_segment: u64,
+    next_seq: u64,
+    /// Records of the events of each partition which haven't been consumed,
+    /// as `(segment, seq)` pairs from oldest to most recent.
+    pending: HashMap<K, VecDeque<(u64, u64)>>,
+    replay: VecDeque<EncodedEvent<T>>,
+    /// Files of the previous run, deleted once all of its events have been
+    /// replayed.
+    replayed_files: Vec<PathBuf>,
+    _pd: PhantomData<fn() -> T>,
+}
+
+impl<K, T> DiskWal<K, T> {
+    pub(crate) fn new(dir: PathBuf, max_files: usize, max_file_bytes: u64) -> Self {
+        Self {
+            dir,
+            max_files,
+            max_file_bytes,
+            opened: false,
+            segments: VecDeque::new(),
+            next_segment: 0,
+            next_seq: 0,
+            pending: HashMap::new(),
+            replay: VecDeque::new(),
+            replayed_files: Vec::new(),
+            _pd: PhantomData,
+        }
+    }
+
+    fn wal_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{:020}.wal", id))
+    }
+
+    fn consume_log_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{:020}.consumed", id))
+    }
+
+    /// Returns the segment to append to, starting a new one if the current
+    /// one is full.
+    fn writable_segment(&mut self) -> io::Result<&mut Segment> {
+        let full = self.segments.back().map_or(true, |segment| {
+            segment.writer.is_none() || segment.written_bytes >= self.max_file_bytes
+        });
+        if full {
+            if let Some(segment) = self.segments.back_mut() {
+                segment.writer = None;
+            }
+            let id = self.next_segment;
+            self.next_segment += 1;
+            let open = |path: PathBuf| OpenOptions::new().create(true).append(true).open(path);
+            self.segments.push_back(Segment {
+                id,
+                writer: Some(open(self.wal_path(id))?),
+                consume_log: open(self.consume_log_path(id))?,
+                written_bytes: 0,
+                records: 0,
+                consumed: 0,
+            });
+            self.remove_consumed_segments()?;
+        }
+        Ok(self.segments.back_mut().expect("segment was just checked"))
+    }
+
+    /// Appends `seq` to the consume log of segment `id`.
+    fn mark_consumed(&mut self, id: u64, seq: u64) -> io::Result<()> {
+        if let Some(segment) = self.segments.iter_mut().find(|segment| segment.id == id) {
+            writeln!(segment.consume_log, "{}", seq)?;
+            segment.consumed += 1;
+        }
+        Ok(())
+    }
+
+    /// Deletes the oldest segments while there are more than `max_files` of
+    /// them and all of their records have been consumed.
+    fn remove_consumed_segments(&mut self) -> io::Result<()> {
+        while self.segments.len() > self.max_files.max(1) {
+            let consumed = self.segments.front().map_or(false, |segment| {
+                segment.writer.is_none() && segment.consumed >= segment.records
+            });
+            if !consumed {
+                break;
+            }
+            let segment = self.segments.pop_front().expect("segment was just checked");
+            remove_file(&self.wal_path(segment.id))?;
+            remove_file(&self.consume_log_path(segment.id))?;
+        }
+        Ok(())
+    }
+}
+
+impl<K, T> DiskWal<K, T>
+where
+    K: DeserializeOwned,
+    T: DeserializeOwned,
+{
+    /// Reads the events the files of a previous run left unconsumed.
+    fn open(&mut self) -> crate::Result<()> {
+        if self.opened {
+            return Ok(());
+        }
+        self.opened = true;
+        fs::create_dir_all(&self.dir)?;
+
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .map_or(false, |extension| extension == "wal")
+            {
+                if let Some(id) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u64>().ok())
+                {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort_unstable();
+
+        for id in ids {
+            let (wal_path, consume_log_path) = (self.wal_path(id), self.consume_log_path(id));
+            let consumed = match fs::read_to_string(&consume_log_path) {
+                Ok(log) => log
+                    .lines()
+                    .filter_map(|line| line.parse::<u64>().ok())
+                    .collect::<HashSet<_>>(),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => HashSet::new(),
+                Err(error) => return Err(error.into()),
+            };
+            for record in read_records::<K, T>(&wal_path)? {
+                self.next_seq = self.next_seq.max(record.seq + 1);
+                if !consumed.contains(&record.seq) {
+                    self.replay
+                        .push_back(EncodedEvent::new(record.item, record.byte_size));
+                }
+            }
+            self.next_segment = self.next_segment.max(id + 1);
+            self.replayed_files.push(wal_path);
+            self.replayed_files.push(consume_log_path);
+        }
+        Ok(())
+    }
+}
+
+impl<K, T> WriteAheadLog<K, T> for DiskWal<K, T>
+where
+    K: Serialize + DeserializeOwned + Hash + Eq + Clone + Send,
+    T: Serialize + DeserializeOwned + Send,
+{
+    fn append(&mut self, partition: &K, item: &EncodedEvent<T>) -> crate::Result<()> {
+        self.open()?;
+        let seq = self.next_seq;
+        let record = serde_json::to_vec(&WalRecord {
+            seq,
+            partition,
+            byte_size: item.byte_size,
+            item: &item.item,
+        })?;
+
+        let segment = self.writable_segment()?;
+        let writer = segment.writer.as_mut().expect("segment is writable");
+        writer.write_all(&(record.len() as u32).to_be_bytes())?;
+        writer.write_all(&record)?;
+        segment.written_bytes += 4 + record.len() as u64;
+        segment.records += 1;
+        let id = segment.id;
+
+        self.next_seq += 1;
+        self.pending
+            .entry(partition.clone())
+            .or_default()
+            .push_back((id, seq));
+        Ok(())
+    }
+
+    fn consume(&mut self, partition: &K, keep: usize) -> crate::Result<()> {
+        let records = match self.pending.get_mut(partition) {
+            Some(records) => {
+                let count = records.len().saturating_sub(keep);
+                records.drain(..count).collect::<Vec<_>>()
+            }
+            None => return Ok(()),
+        };
+        if self.pending[partition].is_empty() {
+            self.pending.remove(partition);
+        }
+        for (id, seq) in records {
+            self.mark_consumed(id, seq)?;
+        }
+        self.remove_consumed_segments()?;
+        Ok(())
+    }
+
+    fn consume_last(&mut self, partition: &K) -> crate::Result<()> {
+        let record = self
+            .pending
+            .get_mut(partition)
+            .and_then(|records| records.pop_back());
+        if let Some((id, seq)) = record {
+            self.mark_consumed(id, seq)?;
+        }
+        Ok(())
+    }
+
+    fn replay(&mut self) -> crate::Result<Option<EncodedEvent<T>>> {
+        self.open()?;
+        let item = self.replay.pop_front();
+        if item.is_none() {
+            for path in self.replayed_files.drain(..) {
+                remove_file(&path)?;
+            }
+        }
+        Ok(item)
+    }
+}
+
+/// Reads the records of a file, up to the first one which was only partially
+/// written.
+fn read_records<K, T>(path: &Path) -> crate::Result<Vec<WalRecord<K, T>>>
+where
+    K: DeserializeOwned,
+    T: DeserializeOwned,
+{
+    let data = fs::read(path)?;
+    let mut records = Vec::new();
+    let mut rest = data.as_slice();
+    while rest.len() >= 4 {
+        let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        if rest.len() < 4 + len {
+            break;
+        }
+        records.push(serde_json::from_slice(&rest[4..4 + len])?);
+        rest = &rest[4 + len..];
+    }
+    Ok(records)
+}
+
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
@@ -0,0 +1,107 @@
+//! Restriction of the clients allowed to submit data to the intake.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use http::StatusCode;
+use ipnet::IpNet;
+use warp::{filters::BoxedFilter, reply::Response, Filter, Reply};
+
+use crate::internal_events::DatadogAgentRequestDenied;
+
+/// Address of the client a request was received from, inserted into the
+/// extensions of every request by the server.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PeerAddr(pub SocketAddr);
+
+/// Address of the client behind the proxies, inserted into the extensions of
+/// the requests by the server when it can be determined.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ClientIp(pub IpAddr);
+
+/// Extracts the `ClientIp` of the request, if any.
+pub(crate) fn client_ip() -> BoxedFilter<(Option<IpAddr>,)> {
+    warp::ext::optional::<ClientIp>()
+        .map(|client_ip: Option<ClientIp>| client_ip.map(|client_ip| client_ip.0))
+        .boxed()
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct IpAccess {
+    pub allowed_ips: Option<Vec<IpNet>>,
+    pub denied_ips: Option<Vec<IpNet>>,
+    pub behind_proxy: bool,
+    pub trusted_proxy_count: u32,
+}
+
+impl IpAccess {
+    /// Whether any restriction is configured at all.
+    pub(crate) const fn is_enabled(&self) -> bool {
+        self.allowed_ips.is_some() || self.denied_ips.is_some() || self.trusted_proxy_count > 0
+    }
+
+    /// The address of the client.
+    ///
+    /// Behind `trusted_proxy_count` proxies, it is the rightmost
+    /// `X-Forwarded-For` entry which wasn't added by them, or `None` if there
+    /// are not enough entries. Otherwise, when running behind a proxy, it is
+    /// the rightmost entry, which was appended by the proxy: any entry left of
+    /// it may have been set by the client itself.
+    pub(crate) fn client_ip(
+        &self,
+        peer_addr: SocketAddr,
+        forwarded_for: Option<&str>,
+    ) -> Option<IpAddr> {
+        if self.trusted_proxy_count > 0 {
+            let entries = forwarded_for
+                .map(|forwarded_for| forwarded_for.split(',').collect::<Vec<_>>())
+                .unwrap_or_default();
+            let index = entries
+                .len()
+                .checked_sub(self.trusted_proxy_count as usize + 1)?;
+            return entries[index].trim().parse().ok();
+        }
+
+        let ip = forwarded_for
+            .filter(|_| self.behind_proxy)
+            .and_then(|forwarded_for| forwarded_for.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok())
+            .unwrap_or_else(|| peer_addr.ip());
+        Some(ip)
+    }
+
+    fn is_allowed(&self, ip: IpAddr) -> bool {
+        let matches = |nets: &Vec<IpNet>| nets.iter().any(|net| net.contains(&ip));
+        self.allowed_ips.as_ref().map_or(true, matches)
+            && !self.denied_ips.as_ref().map_or(false, matches)
+    }
+
+    /// Answers `403 Forbidden` to the requests of clients which aren't
+    /// allowed, or whose address can't be determined, before they reach
+    /// `filter`.
+    pub(crate) fn wrap(
+        self: &Arc<Self>,
+        filter: BoxedFilter<(Response,)>,
+    ) -> BoxedFilter<(Response,)> {
+        let access = Arc::clone(self);
+        warp::ext::get::<PeerAddr>()
+            .and(client_ip())
+            .and_then(move |peer_addr: PeerAddr, client_ip: Option<IpAddr>| {
+                let allowed = client_ip.map_or(false, |ip| access.is_allowed(ip));
+                let ip = client_ip.unwrap_or_else(|| peer_addr.0.ip());
+                async move {
+                    if allowed {
+                        Err(warp::reject())
+                    } else {
+                        emit!(&DatadogAgentRequestDenied { ip });
+                        Ok(StatusCode::FORBIDDEN.into_response())
+                    }
+                }
+            })
+            .or(filter)
+            .unify()
+            .boxed()
+    }
+}
@@ -0,0 +1,189 @@
+use std::{collections::BTreeMap, fmt, str::FromStr, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event, LogEvent, Value,
+    },
+    internal_events::{DatadogAgentParseError, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// The output of `datadog-agent info --json`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct AgentInfo {
+    pub agent_version: String,
+    #[serde(default)]
+    pub python_version: Option<String>,
+    #[serde(default)]
+    pub checks: BTreeMap<String, AgentCheck>,
+    #[serde(default)]
+    pub logs_agent: Option<serde_json::Value>,
+    #[serde(default)]
+    pub apm_agent: Option<serde_json::Value>,
+    #[serde(default)]
+    pub forwarder_status: Option<serde_json::Value>,
+    /// The other sections of the output, kept as they are.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct AgentCheck {
+    pub status: String,
+    #[serde(flatten)]
+    pub details: serde_json::Map<String, serde_json::Value>,
+}
+
+impl From<AgentCheck> for Value {
+    fn from(check: AgentCheck) -> Self {
+        let mut details = check.details;
+        details.insert("status".to_owned(), check.status.into());
+        Value::from(serde_json::Value::Object(details))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CheckStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl CheckStatus {
+    const fn gauge_value(self) -> f64 {
+        match self {
+            Self::Ok => 0.0,
+            Self::Warning => 1.0,
+            Self::Error => 2.0,
+        }
+    }
+}
+
+impl FromStr for CheckStatus {
+    type Err = UnknownCheckStatus;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status.to_ascii_uppercase().as_str() {
+            "OK" => Ok(Self::Ok),
+            "WARNING" => Ok(Self::Warning),
+            "ERROR" => Ok(Self::Error),
+            _ => Err(UnknownCheckStatus(status.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UnknownCheckStatus(String);
+
+impl fmt::Display for UnknownCheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown check status {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCheckStatus {}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "agent_info",
+        path!("api" / "v1" / "agent_info" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_agent_info,
+    )
+}
+
+/// Turns the agent info into a log, along with a `datadog.agent.check.status`
+/// gauge for each check: 0 when it is OK, 1 on warning and 2 on error.
+fn decode_agent_info(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let info: AgentInfo = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let mut events = Vec::with_capacity(1 + info.checks.len());
+    for (name, check) in &info.checks {
+        let status = check.status.parse::<CheckStatus>().map_err(|error| {
+            emit!(&DatadogAgentParseError {
+                endpoint: "agent_info",
+                error: &error,
+            });
+            ErrorMessage::new(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid check {:?}: {}", name, error),
+            )
+        })?;
+        let tags = std::iter::once(("check_name".to_owned(), name.clone())).collect();
+        let mut metric = Metric::new(
+            "datadog.agent.check.status",
+            MetricKind::Absolute,
+            MetricValue::Gauge {
+                value: status.gauge_value(),
+            },
+        )
+        .with_timestamp(Some(now))
+        .with_tags(Some(tags));
+        if let Some(k) = &api_key {
+            metric
+                .metadata_mut()
+                .set_datadog_api_key(Some(Arc::clone(k)));
+        }
+        events.push(metric.into());
+    }
+
+    let mut log = LogEvent::default();
+    for (key, value) in info.other {
+        log.insert_flat(key, Value::from(value));
+    }
+    log.insert_flat("agent_version", info.agent_version);
+    if let Some(python_version) = info.python_version {
+        log.insert_flat("python_version", python_version);
+    }
+    let checks = info
+        .checks
+        .into_iter()
+        .map(|(name, check)| (name, Value::from(check)))
+        .collect::<BTreeMap<_, _>>();
+    log.insert_flat("checks", Value::from(checks));
+    let sections = [
+        ("logs_agent", info.logs_agent),
+        ("apm_agent", info.apm_agent),
+        ("forwarder_status", info.forwarder_status),
+    ];
+    for (key, value) in sections {
+        if let Some(value) = value {
+            log.insert_flat(key, Value::from(value));
+        }
+    }
+    events.insert(0, source.finish_log(log, now, &api_key));
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
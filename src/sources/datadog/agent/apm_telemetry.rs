@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// Telemetry a tracing library sends about itself.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct TelemetryRequest {
+    pub api_version: String,
+    pub seq_id: i64,
+    pub runtime_id: String,
+    pub service: String,
+    #[serde(default)]
+    pub env: Option<String>,
+    pub tracer_version: String,
+    #[serde(default)]
+    pub language_name: Option<String>,
+    #[serde(default)]
+    pub language_version: Option<String>,
+    pub payload: Vec<TelemetryItem>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct TelemetryItem {
+    pub request_type: String,
+    #[serde(default)]
+    pub payload: Option<serde_json::Value>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "apm_telemetry",
+        path!("api" / "v2" / "apm" / "telemetry" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_telemetry,
+    )
+}
+
+/// Turns each telemetry item into a log, along with the tracer and service
+/// it is about.
+fn decode_telemetry(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let request: TelemetryRequest = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let events = request
+        .payload
+        .into_iter()
+        .map(|item| {
+            let mut log = LogEvent::default();
+            log.insert_flat("request_type", item.request_type);
+            if let Some(payload) = item.payload {
+                log.insert_flat("payload", Value::from(payload));
+            }
+            log.insert_flat("api_version", request.api_version.clone());
+            log.insert_flat("seq_id", request.seq_id);
+            log.insert_flat("runtime_id", request.runtime_id.clone());
+            log.insert_flat("service", request.service.clone());
+            log.insert_flat("tracer_version", request.tracer_version.clone());
+            let optional = [
+                ("env", &request.env),
+                ("language_name", &request.language_name),
+                ("language_version", &request.language_version),
+            ];
+            for (key, value) in optional {
+                if let Some(value) = value {
+                    log.insert_flat(key, value.clone());
+                }
+            }
+            source.finish_log(log, now, &api_key)
+        })
+        .collect::<Vec<_>>();
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
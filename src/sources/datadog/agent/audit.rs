@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct AuditPayload {
+    pub data: Vec<AuditEvent>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct AuditEvent {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "audit",
+        path!("api" / "v2" / "audit" / "events" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_audit_events,
+    )
+}
+
+/// Turns each audit event into a log, its nested attributes are flattened so
+/// that `evt.name` becomes the `evt_name` field.
+fn decode_audit_events(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let payload: AuditPayload = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let events: Vec<Event> = payload
+        .data
+        .into_iter()
+        .map(|event| {
+            let mut log = LogEvent::default();
+            if let Some(id) = event.id {
+                log.insert_flat("id", id);
+            }
+            log.insert_flat("type", event.kind);
+            flatten_attributes(&mut log, None, event.attributes);
+            source.finish_log(log, now, &api_key)
+        })
+        .collect();
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
+
+fn flatten_attributes(
+    log: &mut LogEvent,
+    prefix: Option<&str>,
+    attributes: serde_json::Map<String, serde_json::Value>,
+) {
+    for (key, value) in attributes {
+        let key = match prefix {
+            Some(prefix) => format!("{}_{}", prefix, key),
+            None => key,
+        };
+        match value {
+            serde_json::Value::Object(object) => flatten_attributes(log, Some(&key), object),
+            value => {
+                log.insert_flat(key, Value::from(value));
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Simulation of a slow and flaky intake, meant for testing only.
+
+use std::time::Duration;
+
+use http::StatusCode;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use warp::{filters::BoxedFilter, reply::Response, Filter, Reply};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SimulateDelay {
+    min_ms: u64,
+    max_ms: u64,
+    #[serde(default)]
+    error_rate: f64,
+}
+
+impl SimulateDelay {
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if !(0.0..=1.0).contains(&self.error_rate) {
+            return Err(format!(
+                "`simulate_delay.error_rate` must be between 0 and 1, got {}",
+                self.error_rate
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Delays the requests handled by `filter` by a random duration, failing
+    /// them with `503 Service Unavailable` at the configured error rate.
+    ///
+    /// Failed requests never reach `filter`, so their events aren't sent
+    /// downstream before the client retries them.
+    pub(crate) fn wrap(self, filter: BoxedFilter<(Response,)>) -> BoxedFilter<(Response,)> {
+        warp::any()
+            .and_then(move || {
+                let (delay, fail) = {
+                    let mut rng = thread_rng();
+                    let delay = rng.gen_range(self.min_ms..=self.max_ms.max(self.min_ms));
+                    (delay, rng.gen_bool(self.error_rate))
+                };
+                async move {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    if fail {
+                        Ok(StatusCode::SERVICE_UNAVAILABLE.into_response())
+                    } else {
+                        Err(warp::reject())
+                    }
+                }
+            })
+            .or(filter)
+            .unify()
+            .boxed()
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::{TimeZone, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource, EventRoute, LOG_PIPELINE_CHECKS};
+use crate::{
+    event::{Event, LogEvent},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct CheckRun {
+    pub check: String,
+    pub host_name: String,
+    pub timestamp: i64,
+    pub status: u8,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    let route = source
+        .log_pipeline_check_pattern
+        .is_some()
+        .then(|| EventRoute {
+            output: LOG_PIPELINE_CHECKS,
+            matches: is_log_pipeline_check,
+        });
+    source.routed_intake_filter(
+        "check_run",
+        path!("api" / "v1" / "check_run" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        route,
+        decode_check_runs,
+    )
+}
+
+/// Whether `event` is a check run whose name matches the configured log
+/// pipeline check pattern.
+fn is_log_pipeline_check(source: &DatadogAgentSource, event: &Event) -> bool {
+    match (&source.log_pipeline_check_pattern, event) {
+        (Some(pattern), Event::Log(log)) => log
+            .get_flat("check")
+            .map_or(false, |check| pattern.is_match(&check.to_string_lossy())),
+        _ => false,
+    }
+}
+
+const fn status_name(status: u8) -> &'static str {
+    match status {
+        0 => "ok",
+        1 => "warning",
+        2 => "critical",
+        _ => "unknown",
+    }
+}
+
+fn decode_check_runs(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let check_runs: Vec<CheckRun> = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let events = check_runs
+        .into_iter()
+        .map(|check_run| {
+            let mut log = LogEvent::default();
+            log.insert_flat("check", check_run.check);
+            log.insert_flat("host", check_run.host_name);
+            log.insert_flat("status", status_name(check_run.status));
+            log.insert_flat("message", check_run.message);
+            if source.parse_ddtags {
+                for (key, value) in parse_tags(check_run.tags.iter().map(String::as_str)) {
+                    match value {
+                        Some(value) => log.try_insert_flat(key, value),
+                        None => log.try_insert_flat(key, true),
+                    }
+                }
+            } else {
+                log.insert_flat("tags", check_run.tags);
+            }
+            let timestamp = Utc.timestamp(check_run.timestamp, 0);
+            source.finish_log(log, timestamp, &api_key)
+        })
+        .collect::<Vec<_>>();
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
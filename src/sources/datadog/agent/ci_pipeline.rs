@@ -0,0 +1,124 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct PipelineExecution {
+    pub ci_provider_name: String,
+    pub pipeline_name: String,
+    pub pipeline_id: String,
+    pub start: String,
+    pub end: String,
+    pub status: String,
+    pub git: PipelineGit,
+    #[serde(default)]
+    pub stages: Vec<BTreeMap<String, serde_json::Value>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct PipelineGit {
+    pub repository_url: String,
+    pub commit: PipelineGitCommit,
+    pub author: PipelineGitAuthor,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct PipelineGitCommit {
+    pub sha: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct PipelineGitAuthor {
+    pub email: String,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "ci_pipeline",
+        path!("api" / "v2" / "ci" / "pipeline" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_pipeline_execution,
+    )
+}
+
+/// Turns a pipeline execution into a log holding all of its stages, followed
+/// by one log per stage, tagged with the `pipeline_id` of the execution.
+///
+/// The `level` field tells pipeline logs apart from stage logs.
+fn decode_pipeline_execution(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let pipeline: PipelineExecution = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let mut events = Vec::with_capacity(pipeline.stages.len() + 1);
+
+    let mut log = LogEvent::default();
+    log.insert_flat("level", "pipeline");
+    log.insert_flat("ci_provider_name", pipeline.ci_provider_name);
+    log.insert_flat("pipeline_name", pipeline.pipeline_name);
+    log.insert_flat("pipeline_id", pipeline.pipeline_id.clone());
+    log.insert_flat("start", pipeline.start);
+    log.insert_flat("end", pipeline.end);
+    log.insert_flat("status", pipeline.status);
+    log.insert("git.repository_url", pipeline.git.repository_url);
+    log.insert("git.commit.sha", pipeline.git.commit.sha);
+    log.insert("git.author.email", pipeline.git.author.email);
+    let stages: Vec<Value> = pipeline
+        .stages
+        .iter()
+        .map(|stage| {
+            stage
+                .iter()
+                .map(|(key, value)| (key.clone(), Value::from(value.clone())))
+                .collect::<BTreeMap<_, _>>()
+                .into()
+        })
+        .collect();
+    log.insert_flat("stages", stages);
+    events.push(source.finish_log(log, now, &api_key));
+
+    for stage in pipeline.stages {
+        let mut log = LogEvent::default();
+        for (key, value) in stage {
+            log.insert_flat(key, Value::from(value));
+        }
+        log.insert_flat("level", "stage");
+        log.insert_flat("pipeline_id", pipeline.pipeline_id.clone());
+        events.push(source.finish_log(log, now, &api_key));
+    }
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,184 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::{TimeZone, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{check_run::CheckRun, into_vector_metric, parse_tags, DatadogAgentSource};
+use crate::{
+    common::datadog::DatadogSeriesMetric,
+    event::{Event, LogEvent, Value},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// The result of a cluster check, as reported by the node agent that ran it.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ClusterCheck {
+    pub check_name: String,
+    pub node_name: String,
+    pub result: ClusterCheckResult,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ClusterCheckResult {
+    pub status: String,
+    #[serde(default)]
+    pub metrics: Vec<DatadogSeriesMetric>,
+    #[serde(default)]
+    pub service_checks: Vec<CheckRun>,
+    #[serde(default)]
+    pub events: Vec<AgentEvent>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct AgentEvent {
+    pub title: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub alert_type: Option<String>,
+    #[serde(default)]
+    pub aggregation_key: Option<String>,
+    #[serde(default)]
+    pub source_type_name: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "cluster_checks",
+        path!("api" / "v1" / "cluster_checks" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_cluster_checks,
+    )
+}
+
+/// Turns each cluster check result into a summary log, followed by the
+/// metrics it reported and a log for each of its events.
+fn decode_cluster_checks(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let checks: Vec<ClusterCheck> = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let mut events = Vec::new();
+    for check in checks {
+        let ClusterCheck {
+            check_name,
+            node_name,
+            result,
+        } = check;
+
+        let mut log = LogEvent::default();
+        log.insert_flat("check_name", check_name.clone());
+        log.insert_flat("node_name", node_name.clone());
+        log.insert_flat("status", result.status);
+        log.insert_flat("metric_count", result.metrics.len() as i64);
+        log.insert_flat("event_count", result.events.len() as i64);
+        let service_checks = result
+            .service_checks
+            .into_iter()
+            .map(service_check_value)
+            .collect::<Vec<_>>();
+        log.insert_flat("service_checks", service_checks);
+        events.push(source.finish_log(log, now, &api_key));
+
+        events.extend(
+            result
+                .metrics
+                .into_iter()
+                .flat_map(|metric| into_vector_metric(metric, api_key.clone())),
+        );
+
+        for event in result.events {
+            let timestamp = event
+                .timestamp
+                .map_or(now, |timestamp| Utc.timestamp(timestamp, 0));
+            let log = decode_event(source, event, &check_name, &node_name);
+            events.push(source.finish_log(log, timestamp, &api_key));
+        }
+    }
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
+
+fn service_check_value(check: CheckRun) -> Value {
+    let mut fields = BTreeMap::new();
+    fields.insert("check".to_owned(), Value::from(check.check));
+    fields.insert("host_name".to_owned(), Value::from(check.host_name));
+    fields.insert("status".to_owned(), Value::from(i64::from(check.status)));
+    fields.insert("message".to_owned(), Value::from(check.message));
+    fields.insert("tags".to_owned(), Value::from(check.tags));
+    fields.insert(
+        "timestamp".to_owned(),
+        Value::from(Utc.timestamp(check.timestamp, 0)),
+    );
+    Value::from(fields)
+}
+
+fn decode_event(
+    source: &DatadogAgentSource,
+    event: AgentEvent,
+    check_name: &str,
+    node_name: &str,
+) -> LogEvent {
+    let mut log = LogEvent::default();
+    log.insert_flat("check_name", check_name);
+    log.insert_flat("node_name", node_name);
+    log.insert_flat("title", event.title);
+    log.insert_flat("text", event.text);
+    let fields = [
+        ("priority", event.priority),
+        ("host", event.host),
+        ("alert_type", event.alert_type),
+        ("aggregation_key", event.aggregation_key),
+        ("source_type_name", event.source_type_name),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            log.insert_flat(key, value);
+        }
+    }
+    if source.parse_ddtags {
+        for (key, value) in parse_tags(event.tags.iter().map(String::as_str)) {
+            match value {
+                Some(value) => log.try_insert_flat(key, value),
+                None => log.try_insert_flat(key, true),
+            }
+        }
+    } else {
+        log.insert_flat("tags", event.tags);
+    }
+    log
+}
@@ -0,0 +1,99 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter, Reply};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::{DatadogAgentConfigUpdateReceived, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// A runtime configuration update of a check.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ConfigUpdate {
+    pub check_name: String,
+    #[serde(default)]
+    pub config_updates: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+/// Builds the filter for `api/v1/config/update`, answering accepted updates
+/// with `{"status": "ok"}` if `acknowledge_updates` is set.
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+    acknowledge_updates: bool,
+) -> BoxedFilter<(Response,)> {
+    let filter = source.intake_filter(
+        "config_update",
+        path!("api" / "v1" / "config" / "update" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_config_update,
+    );
+    if !acknowledge_updates {
+        return filter;
+    }
+
+    filter
+        .map(|response: Response| {
+            if response.status().is_success() {
+                let body = warp::reply::json(&serde_json::json!({"status": "ok"}));
+                warp::reply::with_status(body, response.status()).into_response()
+            } else {
+                response
+            }
+        })
+        .boxed()
+}
+
+fn decode_config_update(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let update: ConfigUpdate = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    emit!(&DatadogAgentConfigUpdateReceived {
+        check_name: update.check_name.clone(),
+    });
+
+    let mut log = LogEvent::default();
+    log.insert_flat("check_name", update.check_name);
+    let config_updates = update
+        .config_updates
+        .into_iter()
+        .map(|(field, value)| (field, Value::from(value)))
+        .collect::<BTreeMap<_, _>>();
+    log.insert_flat("config_updates", Value::from(config_updates));
+    if let Some(key) = update
+        .api_key
+        .or_else(|| api_key.as_deref().map(str::to_owned))
+    {
+        log.insert_flat("api_key", key);
+    }
+    let events = vec![source.finish_log(log, Utc::now(), &api_key)];
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
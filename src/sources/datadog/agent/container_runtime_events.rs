@@ -0,0 +1,162 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::{DateTime, TimeZone, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event, LogEvent, Value,
+    },
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// An event of the container runtime, as reported by Docker.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ContainerRuntimeEvent {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub action: String,
+    #[serde(default)]
+    pub status: Option<String>,
+    pub actor: Actor,
+    /// Unix timestamp in seconds.
+    pub time: i64,
+    /// Unix timestamp in nanoseconds, more precise than `time`.
+    #[serde(rename = "timeNano", default)]
+    pub time_nano: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct Actor {
+    #[serde(rename = "ID")]
+    pub id: String,
+    /// Attributes of the container, such as its `name`, `image` and the
+    /// `exitCode` of `die` events.
+    #[serde(rename = "Attributes", default)]
+    pub attributes: BTreeMap<String, serde_json::Value>,
+}
+
+impl ContainerRuntimeEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        match self.time_nano {
+            Some(time_nano) => Utc.timestamp_nanos(time_nano),
+            None => Utc.timestamp(self.time, 0),
+        }
+    }
+
+    /// The exit code of the container, which the attributes hold as a string.
+    fn exit_code(&self) -> Option<f64> {
+        match self.actor.attributes.get("exitCode")? {
+            serde_json::Value::Number(code) => code.as_f64(),
+            serde_json::Value::String(code) => code.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "container_runtime_events",
+        path!("api" / "v1" / "container_runtime_events" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_container_runtime_events,
+    )
+}
+
+/// Turns each event into a log, along with a `container.exit_code` gauge for
+/// the containers which died.
+fn decode_container_runtime_events(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let runtime_events: Vec<ContainerRuntimeEvent> =
+        serde_json::from_slice(&body).map_err(|error| {
+            ErrorMessage::new(
+                StatusCode::BAD_REQUEST,
+                format!("Error parsing JSON: {:?}", error),
+            )
+        })?;
+
+    let mut events = Vec::with_capacity(runtime_events.len());
+    for runtime_event in runtime_events {
+        let timestamp = runtime_event.timestamp();
+        let exit_code = match runtime_event.action.as_str() {
+            "die" => runtime_event.exit_code(),
+            _ => None,
+        };
+        let image = runtime_event
+            .actor
+            .attributes
+            .get("image")
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_owned);
+        let container_id = runtime_event.actor.id.clone();
+
+        let mut log = LogEvent::default();
+        if let Some(id) = runtime_event.id {
+            log.insert_flat("id", id);
+        }
+        log.insert_flat("type", runtime_event.kind);
+        log.insert_flat("action", runtime_event.action);
+        if let Some(status) = runtime_event.status {
+            log.insert_flat("status", status);
+        }
+        let attributes = runtime_event
+            .actor
+            .attributes
+            .into_iter()
+            .map(|(key, value)| (key, Value::from(value)))
+            .collect::<BTreeMap<_, _>>();
+        let actor = BTreeMap::from([
+            ("id".to_owned(), Value::from(runtime_event.actor.id)),
+            ("attributes".to_owned(), Value::from(attributes)),
+        ]);
+        log.insert_flat("actor", Value::from(actor));
+        events.push(source.finish_log(log, timestamp, &api_key));
+
+        if let Some(value) = exit_code {
+            let mut tags = BTreeMap::from([("container_id".to_owned(), container_id)]);
+            if let Some(image) = image {
+                tags.insert("image".to_owned(), image);
+            }
+            let mut metric = Metric::new(
+                "container.exit_code",
+                MetricKind::Absolute,
+                MetricValue::Gauge { value },
+            )
+            .with_timestamp(Some(timestamp))
+            .with_tags(Some(tags));
+            if let Some(k) = &api_key {
+                metric
+                    .metadata_mut()
+                    .set_datadog_api_key(Some(Arc::clone(k)));
+            }
+            events.push(metric.into());
+        }
+    }
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,163 @@
+use std::{collections::BTreeMap, fmt, str::FromStr, sync::Arc};
+
+use bytes::Bytes;
+use chrono::{TimeZone, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::{DatadogAgentParseError, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct Container {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub state: String,
+    pub status: String,
+    /// Unix timestamp in seconds.
+    pub created: i64,
+    #[serde(default)]
+    pub cpu_limit: Option<f64>,
+    /// Memory limit in bytes.
+    #[serde(default)]
+    pub memory_limit: Option<i64>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub networks: Vec<serde_json::Value>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ContainerState {
+    Running,
+    Stopped,
+    Paused,
+    Exited,
+    Dead,
+}
+
+impl ContainerState {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Stopped => "stopped",
+            Self::Paused => "paused",
+            Self::Exited => "exited",
+            Self::Dead => "dead",
+        }
+    }
+}
+
+impl FromStr for ContainerState {
+    type Err = UnknownContainerState;
+
+    fn from_str(state: &str) -> Result<Self, Self::Err> {
+        match state {
+            "running" => Ok(Self::Running),
+            "stopped" => Ok(Self::Stopped),
+            "paused" => Ok(Self::Paused),
+            "exited" => Ok(Self::Exited),
+            "dead" => Ok(Self::Dead),
+            _ => Err(UnknownContainerState(state.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UnknownContainerState(String);
+
+impl fmt::Display for UnknownContainerState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown container state {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownContainerState {}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "containers",
+        path!("api" / "v1" / "container" / "list" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_containers,
+    )
+}
+
+fn decode_containers(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let containers: Vec<Container> = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let events = containers
+        .into_iter()
+        .map(|container| {
+            let state = container.state.parse::<ContainerState>().map_err(|error| {
+                emit!(&DatadogAgentParseError {
+                    endpoint: "containers",
+                    error: &error,
+                });
+                ErrorMessage::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid container {:?}: {}", container.id, error),
+                )
+            })?;
+
+            let mut log = LogEvent::default();
+            log.insert_flat("id", container.id);
+            log.insert_flat("name", container.name);
+            log.insert_flat("image", container.image);
+            log.insert_flat("state", state.as_str());
+            log.insert_flat("status", container.status);
+            log.insert_flat("created", Utc.timestamp(container.created, 0));
+            if let Some(cpu_limit) = container.cpu_limit {
+                log.insert_flat("cpu_limit", cpu_limit);
+            }
+            if let Some(memory_limit) = container.memory_limit {
+                log.insert_flat("memory_limit", memory_limit);
+            }
+            let labels = container
+                .labels
+                .into_iter()
+                .map(|(key, value)| (key, Value::from(value)))
+                .collect::<BTreeMap<_, _>>();
+            log.insert_flat("labels", labels);
+            let networks = container
+                .networks
+                .into_iter()
+                .map(Value::from)
+                .collect::<Vec<_>>();
+            log.insert_flat("networks", networks);
+            Ok(source.finish_log(log, now, &api_key))
+        })
+        .collect::<Result<Vec<_>, ErrorMessage>>()?;
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,149 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event, LogEvent,
+    },
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct BudgetAlertsRequest {
+    pub data: BudgetAlertsData,
+}
+
+/// Budget alerts are sent one at a time, or several at once.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub(crate) enum BudgetAlertsData {
+    Alert(Box<BudgetAlert>),
+    Alerts(Vec<BudgetAlert>),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct BudgetAlert {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub attributes: BudgetAlertAttributes,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct BudgetAlertAttributes {
+    pub alert_type: String,
+    pub threshold_type: String,
+    pub threshold_value: f64,
+    pub actual_value: f64,
+    pub currency: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created: String,
+}
+
+impl BudgetAlertAttributes {
+    /// Share of the budget threshold which has been spent, if there is a
+    /// threshold at all.
+    fn utilization(&self) -> Option<f64> {
+        (self.threshold_value != 0.0).then(|| self.actual_value / self.threshold_value)
+    }
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "cost_budget_alerts",
+        path!("api" / "v2" / "cost" / "budget" / "alerts" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_budget_alerts,
+    )
+}
+
+/// Turns each budget alert into a log, along with a
+/// `datadog.cost.budget.utilization` gauge tagged with the budget tags.
+fn decode_budget_alerts(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let request: BudgetAlertsRequest = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+    let alerts = match request.data {
+        BudgetAlertsData::Alert(alert) => vec![*alert],
+        BudgetAlertsData::Alerts(alerts) => alerts,
+    };
+
+    let now = Utc::now();
+    let mut events = Vec::with_capacity(alerts.len() * 2);
+    for alert in alerts {
+        let attributes = alert.attributes;
+        let utilization = attributes.utilization();
+        let tags: BTreeMap<String, String> = parse_tags(attributes.tags.iter().map(String::as_str))
+            .map(|(key, value)| (key.into(), value.unwrap_or_default().into()))
+            .collect();
+
+        let mut log = LogEvent::default();
+        log.insert_flat("id", alert.id);
+        log.insert_flat("type", alert.kind);
+        log.insert_flat("alert_type", attributes.alert_type);
+        log.insert_flat("threshold_type", attributes.threshold_type);
+        log.insert_flat("threshold_value", attributes.threshold_value);
+        log.insert_flat("actual_value", attributes.actual_value);
+        log.insert_flat("currency", attributes.currency);
+        log.insert_flat("created", attributes.created);
+        if source.parse_ddtags {
+            for (key, value) in parse_tags(attributes.tags.iter().map(String::as_str)) {
+                match value {
+                    Some(value) => log.try_insert_flat(key, value),
+                    None => log.try_insert_flat(key, true),
+                }
+            }
+        } else {
+            log.insert_flat("tags", attributes.tags);
+        }
+        events.push(source.finish_log(log, now, &api_key));
+
+        if let Some(utilization) = utilization {
+            let mut metric = Metric::new(
+                "datadog.cost.budget.utilization",
+                MetricKind::Absolute,
+                MetricValue::Gauge { value: utilization },
+            )
+            .with_timestamp(Some(now))
+            .with_tags(Some(tags));
+            if let Some(k) = &api_key {
+                metric
+                    .metadata_mut()
+                    .set_datadog_api_key(Some(Arc::clone(k)));
+            }
+            events.push(metric.into());
+        }
+    }
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
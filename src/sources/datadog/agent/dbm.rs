@@ -0,0 +1,124 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::{TimeZone, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    config::log_schema,
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event,
+    },
+    internal_events::{DatadogAgentDbmMetricsReceived, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct DbmMetricsPayload {
+    pub host: String,
+    /// Unix timestamp in milliseconds.
+    pub timestamp: i64,
+    pub min_collection_interval: f64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub mysql_activity: Vec<DbmQueryMetrics>,
+    #[serde(default)]
+    pub postgres_activity: Vec<DbmQueryMetrics>,
+    #[serde(default)]
+    pub oracle_activity: Vec<DbmQueryMetrics>,
+}
+
+/// Metrics of a normalized query, like `calls`, `rows_sent` or `lock_time`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct DbmQueryMetrics {
+    pub query_signature: String,
+    #[serde(flatten)]
+    pub values: BTreeMap<String, serde_json::Value>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "dbm_metrics",
+        path!("api" / "v2" / "dbm" / "metrics" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_dbm_metrics,
+    )
+}
+
+/// Turns each numeric value of each query into a counter, namespaced with
+/// the query signature. Non numeric values are ignored.
+fn decode_dbm_metrics(
+    _source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let payload: DbmMetricsPayload = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let timestamp = Utc.timestamp_millis(payload.timestamp);
+    let mut tags: BTreeMap<String, String> = parse_tags(payload.tags.iter().map(String::as_str))
+        .map(|(key, value)| (key.into(), value.unwrap_or_default().into()))
+        .collect();
+    tags.insert(log_schema().host_key().to_owned(), payload.host);
+
+    let activities = [
+        ("mysql", payload.mysql_activity),
+        ("postgres", payload.postgres_activity),
+        ("oracle", payload.oracle_activity),
+    ];
+    let mut events = Vec::new();
+    for (dbms, queries) in activities {
+        let mut tags = tags.clone();
+        tags.insert("dbms".to_owned(), dbms.to_owned());
+        for query in queries {
+            for (name, value) in query.values {
+                let value = match value.as_f64() {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let mut metric = Metric::new(
+                    name,
+                    MetricKind::Incremental,
+                    MetricValue::Counter { value },
+                )
+                .with_namespace(Some(query.query_signature.clone()))
+                .with_timestamp(Some(timestamp))
+                .with_tags(Some(tags.clone()));
+                if let Some(k) = &api_key {
+                    metric
+                        .metadata_mut()
+                        .set_datadog_api_key(Some(Arc::clone(k)));
+                }
+                events.push(metric.into());
+            }
+        }
+    }
+
+    emit!(&DatadogAgentDbmMetricsReceived {
+        count: events.len()
+    });
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,160 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent},
+    internal_events::{DatadogAgentDiagnoseReceived, DatadogAgentParseError, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// The output of `datadog-agent diagnose --json`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct DiagnoseOutput {
+    pub diagnoses: Vec<Diagnosis>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct Diagnosis {
+    pub name: String,
+    pub result: String,
+    pub diagnosis: String,
+    #[serde(default)]
+    pub remediation: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DiagnosisResult {
+    Pass,
+    Warning,
+    Fail,
+}
+
+impl DiagnosisResult {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pass => "pass",
+            Self::Warning => "warning",
+            Self::Fail => "fail",
+        }
+    }
+
+    const fn severity_level(self) -> i64 {
+        match self {
+            Self::Pass => 0,
+            Self::Warning => 1,
+            Self::Fail => 2,
+        }
+    }
+}
+
+impl FromStr for DiagnosisResult {
+    type Err = UnknownDiagnosisResult;
+
+    fn from_str(result: &str) -> Result<Self, Self::Err> {
+        match result {
+            "pass" => Ok(Self::Pass),
+            "warning" => Ok(Self::Warning),
+            "fail" => Ok(Self::Fail),
+            _ => Err(UnknownDiagnosisResult(result.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UnknownDiagnosisResult(String);
+
+impl fmt::Display for UnknownDiagnosisResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown diagnosis result {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownDiagnosisResult {}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "diagnose",
+        path!("api" / "v1" / "diagnose" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_diagnose,
+    )
+}
+
+/// Turns each diagnosis into a log, its result being ranked by
+/// `severity_level`.
+fn decode_diagnose(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let output: DiagnoseOutput = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let mut summary = DatadogAgentDiagnoseReceived {
+        pass: 0,
+        warn: 0,
+        fail: 0,
+    };
+    let events = output
+        .diagnoses
+        .into_iter()
+        .map(|diagnosis| {
+            let result = diagnosis
+                .result
+                .parse::<DiagnosisResult>()
+                .map_err(|error| {
+                    emit!(&DatadogAgentParseError {
+                        endpoint: "diagnose",
+                        error: &error,
+                    });
+                    ErrorMessage::new(
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid diagnosis {:?}: {}", diagnosis.name, error),
+                    )
+                })?;
+            match result {
+                DiagnosisResult::Pass => summary.pass += 1,
+                DiagnosisResult::Warning => summary.warn += 1,
+                DiagnosisResult::Fail => summary.fail += 1,
+            }
+
+            let mut log = LogEvent::default();
+            log.insert_flat("name", diagnosis.name);
+            log.insert_flat("result", result.as_str());
+            log.insert_flat("severity_level", result.severity_level());
+            log.insert_flat("diagnosis", diagnosis.diagnosis);
+            if let Some(remediation) = diagnosis.remediation {
+                log.insert_flat("remediation", remediation);
+            }
+            Ok(source.finish_log(log, now, &api_key))
+        })
+        .collect::<Result<Vec<_>, ErrorMessage>>()?;
+
+    emit!(&summary);
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
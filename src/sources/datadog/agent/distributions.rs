@@ -0,0 +1,141 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::{TimeZone, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    config::log_schema,
+    event::{
+        metric::{Metric, MetricKind, MetricValue, Sample, StatisticKind},
+        Event,
+    },
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct DistributionsPayload {
+    pub series: Vec<DistributionSeries>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct DistributionSeries {
+    pub metric: String,
+    #[serde(rename = "type")]
+    pub kind: DistributionType,
+    /// `[timestamp, value]` pairs, the value being a single sample or a list
+    /// of them.
+    pub points: Vec<(i64, DistributionPoint)>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub resources: Vec<DistributionResource>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum DistributionType {
+    Distribution,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub(crate) enum DistributionPoint {
+    Sample(f64),
+    Samples(Vec<f64>),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct DistributionResource {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "distributions",
+        path!("api" / "v2" / "distributions" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_distributions,
+    )
+}
+
+/// Turns each point of each series into a distribution. The `host` and
+/// `datacenter` resources of a series become tags, the others are ignored.
+fn decode_distributions(
+    _source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let payload: DistributionsPayload = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let mut events = Vec::new();
+    for series in payload.series {
+        let mut tags: BTreeMap<String, String> = parse_tags(series.tags.iter().map(String::as_str))
+            .map(|(key, value)| (key.into(), value.unwrap_or_default().into()))
+            .collect();
+        for resource in series.resources {
+            match resource.kind.as_str() {
+                "host" => {
+                    tags.insert(log_schema().host_key().to_owned(), resource.name);
+                }
+                "datacenter" => {
+                    tags.insert("datacenter".to_owned(), resource.name);
+                }
+                _ => (),
+            }
+        }
+
+        for (timestamp, point) in series.points {
+            let samples = match point {
+                DistributionPoint::Sample(value) => vec![Sample { value, rate: 1 }],
+                DistributionPoint::Samples(values) => values
+                    .into_iter()
+                    .map(|value| Sample { value, rate: 1 })
+                    .collect(),
+            };
+            let mut metric = Metric::new(
+                series.metric.clone(),
+                MetricKind::Incremental,
+                MetricValue::Distribution {
+                    samples,
+                    statistic: StatisticKind::Histogram,
+                },
+            )
+            .with_timestamp(Some(Utc.timestamp(timestamp, 0)))
+            .with_tags(Some(tags.clone()));
+            if let Some(k) = &api_key {
+                metric
+                    .metadata_mut()
+                    .set_datadog_api_key(Some(Arc::clone(k)));
+            }
+            events.push(metric.into());
+        }
+    }
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,104 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use prost::Message;
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    config::log_schema,
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event,
+    },
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+mod dd_proto {
+    include!(concat!(env!("OUT_DIR"), "/datadog.dns.rs"));
+}
+
+use dd_proto::DnsStats;
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "dns",
+        path!("api" / "v0.2" / "dns" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_dns_stats,
+    )
+}
+
+/// Turns the counts of each DNS query stat entry into `datadog.dns.*`
+/// counters, tagged with the query type and name server.
+fn decode_dns_stats(
+    _source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let payload = DnsStats::decode(body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error decoding Datadog DNS stats: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let mut events = Vec::new();
+    for stats in payload.stats {
+        let mut tags: BTreeMap<String, String> = parse_tags(stats.tags.iter().map(String::as_str))
+            .map(|(key, value)| (key.into(), value.unwrap_or_default().into()))
+            .collect();
+        if !payload.host_name.is_empty() {
+            tags.insert(
+                log_schema().host_key().to_owned(),
+                payload.host_name.clone(),
+            );
+        }
+        tags.insert("query_type".to_owned(), stats.query_type);
+        tags.insert("name_server".to_owned(), stats.name_server);
+
+        let counts = [
+            ("query_count", stats.query_count),
+            ("success_count", stats.success_count),
+            ("failure_count", stats.failure_count),
+            ("timeout_count", stats.timeout_count),
+        ];
+        for (name, value) in counts {
+            let mut metric = Metric::new(
+                format!("datadog.dns.{}", name),
+                MetricKind::Incremental,
+                MetricValue::Counter {
+                    value: value as f64,
+                },
+            )
+            .with_timestamp(Some(now))
+            .with_tags(Some(tags.clone()));
+            if let Some(k) = &api_key {
+                metric
+                    .metadata_mut()
+                    .set_datadog_api_key(Some(Arc::clone(k)));
+            }
+            events.push(metric.into());
+        }
+    }
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
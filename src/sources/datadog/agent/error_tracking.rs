@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::{DatadogAgentErrorTrackingReceived, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ErrorTrackingEvent {
+    pub error: ErrorDetails,
+    pub service: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub environment: Option<String>,
+    #[serde(default)]
+    pub user: Option<ErrorUser>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub timestamp: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ErrorDetails {
+    pub message: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Either the raw stack trace or an array of stack frame objects.
+    #[serde(default)]
+    pub stack: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ErrorUser {
+    pub id: serde_json::Value,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "errors",
+        path!("api" / "v2" / "errors" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_error_tracking_events,
+    )
+}
+
+fn decode_error_tracking_events(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let errors: Vec<ErrorTrackingEvent> = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let events: Vec<Event> = errors
+        .into_iter()
+        .map(|error| {
+            let mut log = LogEvent::default();
+            log.insert("error.message", error.error.message);
+            log.insert("error.type", error.error.kind);
+            if let Some(stack) = error.error.stack {
+                log.insert("error.stack", Value::from(stack));
+            }
+            log.insert_flat("service", error.service);
+            if let Some(version) = error.version {
+                log.insert_flat("version", version);
+            }
+            if let Some(environment) = error.environment {
+                log.insert_flat("environment", environment);
+            }
+            if let Some(user) = error.user {
+                log.insert("user.id", Value::from(user.id));
+            }
+            log.insert_flat("timestamp", error.timestamp);
+            if source.parse_ddtags {
+                for (key, value) in parse_tags(error.tags.iter().map(String::as_str)) {
+                    match value {
+                        Some(value) => log.try_insert_flat(key, value),
+                        None => log.try_insert_flat(key, true),
+                    }
+                }
+            } else {
+                log.insert_flat("tags", error.tags);
+            }
+            source.finish_log(log, now, &api_key)
+        })
+        .collect();
+
+    emit!(&DatadogAgentErrorTrackingReceived {
+        count: events.len()
+    });
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
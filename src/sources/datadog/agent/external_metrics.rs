@@ -0,0 +1,70 @@
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use warp::{
+    filters::BoxedFilter,
+    path,
+    reply::{Reply, Response},
+    Filter,
+};
+
+use super::parse_tags;
+use crate::{
+    event::metric::{Metric, MetricValue},
+    metrics::Controller,
+};
+
+#[derive(Deserialize)]
+struct ExternalMetricQuery {
+    metric: String,
+    /// A `key:value` tag the metric must have, or just a key.
+    #[serde(default)]
+    tag: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ExternalMetricValue {
+    value: f64,
+    timestamp: i64,
+}
+
+/// Answers `GET /api/v1/external_metrics` with the current value of an
+/// internal gauge, so that the cluster agent can use Vector as an external
+/// metrics provider.
+pub(crate) fn build_warp_filter() -> BoxedFilter<(Response,)> {
+    warp::get()
+        .and(path!("api" / "v1" / "external_metrics"))
+        .and(warp::query::<ExternalMetricQuery>())
+        .map(|query: ExternalMetricQuery| match find_gauge(&query) {
+            Some(value) => warp::reply::json(&value).into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        })
+        .boxed()
+}
+
+/// Looks the gauge up in the internal metrics, which are missing when they
+/// aren't recorded.
+fn find_gauge(query: &ExternalMetricQuery) -> Option<ExternalMetricValue> {
+    let controller = Controller::get().ok()?;
+    controller
+        .capture_metrics()
+        .filter(|metric| metric.name() == query.metric && matches_tag(metric, &query.tag))
+        .find_map(|metric| match metric.value() {
+            MetricValue::Gauge { value } => Some(ExternalMetricValue {
+                value: *value,
+                timestamp: metric.timestamp().unwrap_or_else(Utc::now).timestamp(),
+            }),
+            _ => None,
+        })
+}
+
+fn matches_tag(metric: &Metric, tag: &Option<String>) -> bool {
+    let tag = match tag {
+        Some(tag) => tag,
+        None => return true,
+    };
+    parse_tags(std::iter::once(tag.as_str())).all(|(key, value)| match value {
+        Some(value) => metric.tag_value(key).as_deref() == Some(value),
+        None => metric.tags().map_or(false, |tags| tags.contains_key(key)),
+    })
+}
@@ -0,0 +1,183 @@
+use std::{collections::HashMap, net::IpAddr, path::PathBuf, sync::Arc};
+
+use bytes::BufMut;
+use chrono::Utc;
+use futures::{TryFutureExt, TryStreamExt};
+use http::StatusCode;
+use vector_core::ByteSizeOf;
+use warp::{
+    filters::BoxedFilter,
+    multipart::{FormData, Part},
+    path,
+    path::FullPath,
+    reply::Response,
+    Filter,
+};
+
+use super::{access, trace_context::TraceContext, ApiKeyQueryParams, DatadogAgentSource};
+use crate::{
+    event::{Event, LogEvent},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// Flares bundle the agent logs and configuration, which easily exceeds the
+/// default limit of the form filter.
+const MAX_FLARE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// The fields of a flare upload.
+struct Flare {
+    hostname: String,
+    email: String,
+    case_id: String,
+    zip: Vec<u8>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+    output_dir: Option<PathBuf>,
+) -> BoxedFilter<(Response,)> {
+    warp::post()
+        .and(path!("api" / "v1" / "flare" / ..))
+        .and(warp::path::full())
+        .and(warp::header::optional::<String>("dd-api-key"))
+        .and(warp::query::<ApiKeyQueryParams>())
+        .and(TraceContext::filter())
+        .and(access::client_ip())
+        .and(warp::multipart::form().max_length(MAX_FLARE_BYTES))
+        .and_then(
+            move |path: FullPath,
+                  api_token: Option<String>,
+                  query_params: ApiKeyQueryParams,
+                  trace_context: Option<TraceContext>,
+                  client_ip: Option<IpAddr>,
+                  form: FormData| {
+                let source = source.clone();
+                let out = out.clone();
+                let output_dir = output_dir.clone();
+                async move {
+                    let api_key =
+                        source.extract_api_key(path.as_str(), api_token, query_params.dd_api_key);
+                    let events = read_flare(form)
+                        .and_then(|flare| save_flare(output_dir, flare))
+                        .await
+                        .map(|flare| decode_flare(&source, flare, api_key.clone()));
+                    let events =
+                        source.finish_events("flare", events, &api_key, trace_context.as_ref());
+                    DatadogAgentSource::handle_request(
+                        events,
+                        client_ip,
+                        acknowledgements,
+                        out,
+                        multiple_outputs,
+                    )
+                    .await
+                }
+            },
+        )
+        .boxed()
+}
+
+async fn read_flare(form: FormData) -> Result<Flare, ErrorMessage> {
+    let parts: Vec<Part> = form.try_collect().await.map_err(invalid_form)?;
+
+    let mut fields = HashMap::new();
+    for part in parts {
+        let name = part.name().to_owned();
+        let data = part
+            .stream()
+            .try_fold(Vec::new(), |mut data, chunk| async move {
+                data.put(chunk);
+                Ok(data)
+            })
+            .await
+            .map_err(invalid_form)?;
+        fields.insert(name, data);
+    }
+
+    let mut field = |name: &str| {
+        fields.remove(name).ok_or_else(|| {
+            ErrorMessage::new(
+                StatusCode::BAD_REQUEST,
+                format!("Missing flare field {:?}", name),
+            )
+        })
+    };
+    let zip = field("flare_file")?;
+    let mut text = |name: &str| {
+        field(name).and_then(|value| {
+            String::from_utf8(value).map_err(|_| {
+                ErrorMessage::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Flare field {:?} is not valid UTF-8", name),
+                )
+            })
+        })
+    };
+
+    Ok(Flare {
+        hostname: text("hostname")?,
+        email: text("email")?,
+        case_id: text("case_id")?,
+        zip,
+    })
+}
+
+fn invalid_form(error: warp::Error) -> ErrorMessage {
+    ErrorMessage::new(
+        StatusCode::BAD_REQUEST,
+        format!("Error reading multipart form: {}", error),
+    )
+}
+
+/// Writes the flare archive to `<case_id>_<hostname>.zip` in `output_dir`.
+async fn save_flare(output_dir: Option<PathBuf>, flare: Flare) -> Result<Flare, ErrorMessage> {
+    if let Some(output_dir) = output_dir {
+        let path = output_dir.join(flare_file_name(&flare.case_id, &flare.hostname));
+        tokio::fs::write(&path, &flare.zip).await.map_err(|error| {
+            error!(message = "Failed to save flare.", path = ?path, %error);
+            ErrorMessage::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to save flare".to_owned(),
+            )
+        })?;
+    }
+    Ok(flare)
+}
+
+/// Keeps the name within the output directory, whatever the client sent.
+fn flare_file_name(case_id: &str, hostname: &str) -> String {
+    let sanitize = |name: &str| {
+        name.chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect::<String>()
+    };
+    format!("{}_{}.zip", sanitize(case_id), sanitize(hostname))
+}
+
+fn decode_flare(
+    source: &DatadogAgentSource,
+    flare: Flare,
+    api_key: Option<Arc<str>>,
+) -> Vec<Event> {
+    let mut log = LogEvent::default();
+    log.insert_flat("hostname", flare.hostname);
+    log.insert_flat("email", flare.email);
+    log.insert_flat("case_id", flare.case_id);
+    log.insert_flat("flare_size_bytes", flare.zip.len() as i64);
+    let events = vec![source.finish_log(log, Utc::now(), &api_key)];
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    events
+}
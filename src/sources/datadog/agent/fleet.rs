@@ -0,0 +1,109 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::{DatadogAgentFleetReportReceived, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// The version and configuration an agent reports to Fleet Automation.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct FleetReport {
+    pub hostname: String,
+    pub agent_version: String,
+    #[serde(default)]
+    pub python_version: Option<String>,
+    #[serde(default)]
+    pub os: Option<String>,
+    #[serde(default)]
+    pub flavor: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub config_hash: Option<String>,
+    #[serde(default)]
+    pub feature_flags: BTreeMap<String, bool>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "fleet",
+        path!("api" / "v2" / "fleet" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_fleet_report,
+    )
+}
+
+/// Turns a report into a log, its feature flags being kept as a nested
+/// object.
+fn decode_fleet_report(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let report: FleetReport = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    emit!(&DatadogAgentFleetReportReceived {
+        hostname: report.hostname.clone(),
+    });
+
+    let mut log = LogEvent::default();
+    log.insert_flat("hostname", report.hostname);
+    log.insert_flat("agent_version", report.agent_version);
+    let optional = [
+        ("python_version", report.python_version),
+        ("os", report.os),
+        ("flavor", report.flavor),
+        ("config_hash", report.config_hash),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            log.insert_flat(key, value);
+        }
+    }
+    if source.parse_ddtags {
+        for (key, value) in parse_tags(report.tags.iter().map(String::as_str)) {
+            match value {
+                Some(value) => log.try_insert_flat(key, value),
+                None => log.try_insert_flat(key, true),
+            }
+        }
+    } else {
+        log.insert_flat("tags", report.tags);
+    }
+    let feature_flags = report
+        .feature_flags
+        .into_iter()
+        .map(|(name, enabled)| (name, Value::from(enabled)))
+        .collect::<BTreeMap<_, _>>();
+    log.insert_flat("feature_flags", Value::from(feature_flags));
+    let events = vec![source.finish_log(log, Utc::now(), &api_key)];
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
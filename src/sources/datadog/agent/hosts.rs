@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct HostMetadata {
+    pub hostname: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub meta: Option<serde_json::Value>,
+    #[serde(default)]
+    pub host_tags: Option<serde_json::Value>,
+    #[serde(default)]
+    pub system_stats: Option<serde_json::Value>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "hosts",
+        path!("api" / "v1" / "hosts" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_host_metadata,
+    )
+}
+
+fn decode_host_metadata(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let host: HostMetadata = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let mut log = LogEvent::default();
+    log.insert_flat("hostname", host.hostname);
+    if source.parse_ddtags {
+        for (key, value) in parse_tags(host.tags.iter().map(String::as_str)) {
+            match value {
+                Some(value) => log.try_insert_flat(key, value),
+                None => log.try_insert_flat(key, true),
+            }
+        }
+    } else {
+        log.insert_flat("tags", host.tags);
+    }
+    let nested = [
+        ("meta", host.meta),
+        ("host_tags", host.host_tags),
+        ("system_stats", host.system_stats),
+    ];
+    for (key, value) in nested {
+        if let Some(value) = value {
+            log.insert_flat(key, Value::from(value));
+        }
+    }
+    let events = vec![source.finish_log(log, Utc::now(), &api_key)];
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
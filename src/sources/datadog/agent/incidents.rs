@@ -0,0 +1,194 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent},
+    internal_events::{DatadogAgentParseError, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct IncidentsRequest {
+    pub data: IncidentsData,
+}
+
+/// Incidents are sent one at a time, or several at once.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub(crate) enum IncidentsData {
+    Incident(Box<Incident>),
+    Incidents(Vec<Incident>),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct Incident {
+    #[serde(rename = "type")]
+    pub kind: IncidentType,
+    pub attributes: IncidentAttributes,
+    #[serde(default)]
+    pub relationships: Option<IncidentRelationships>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum IncidentType {
+    Incidents,
+}
+
+impl IncidentType {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Incidents => "incidents",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct IncidentAttributes {
+    pub title: String,
+    pub severity: String,
+    pub status: String,
+    pub created: String,
+    #[serde(default)]
+    pub resolved: Option<String>,
+    #[serde(default)]
+    pub customer_impact_scope: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct IncidentRelationships {
+    #[serde(default)]
+    pub commander_user: Option<IncidentRelationship>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct IncidentRelationship {
+    pub data: Option<IncidentRelationshipData>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct IncidentRelationshipData {
+    pub id: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct IncidentSeverity(i64);
+
+impl FromStr for IncidentSeverity {
+    type Err = UnknownIncidentSeverity;
+
+    fn from_str(severity: &str) -> Result<Self, Self::Err> {
+        match severity {
+            "SEV-1" => Ok(Self(1)),
+            "SEV-2" => Ok(Self(2)),
+            "SEV-3" => Ok(Self(3)),
+            "SEV-4" => Ok(Self(4)),
+            "SEV-5" => Ok(Self(5)),
+            _ => Err(UnknownIncidentSeverity(severity.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UnknownIncidentSeverity(String);
+
+impl fmt::Display for UnknownIncidentSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown incident severity {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownIncidentSeverity {}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "incidents",
+        path!("api" / "v2" / "incidents" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_incidents,
+    )
+}
+
+/// Turns each incident into a log, its `SEV-n` severity being ranked by
+/// `severity_level`.
+fn decode_incidents(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let request: IncidentsRequest = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+    let incidents = match request.data {
+        IncidentsData::Incident(incident) => vec![*incident],
+        IncidentsData::Incidents(incidents) => incidents,
+    };
+
+    let now = Utc::now();
+    let events = incidents
+        .into_iter()
+        .map(|incident| {
+            let attributes = incident.attributes;
+            let severity = attributes
+                .severity
+                .parse::<IncidentSeverity>()
+                .map_err(|error| {
+                    emit!(&DatadogAgentParseError {
+                        endpoint: "incidents",
+                        error: &error,
+                    });
+                    ErrorMessage::new(
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid incident {:?}: {}", attributes.title, error),
+                    )
+                })?;
+
+            let mut log = LogEvent::default();
+            log.insert_flat("type", incident.kind.as_str());
+            log.insert_flat("title", attributes.title);
+            log.insert_flat("severity", attributes.severity);
+            log.insert_flat("severity_level", severity.0);
+            log.insert_flat("status", attributes.status);
+            log.insert_flat("created", attributes.created);
+            if let Some(resolved) = attributes.resolved {
+                log.insert_flat("resolved", resolved);
+            }
+            if let Some(scope) = attributes.customer_impact_scope {
+                log.insert_flat("customer_impact_scope", scope);
+            }
+            if let Some(commander) = incident
+                .relationships
+                .and_then(|relationships| relationships.commander_user)
+                .and_then(|commander| commander.data)
+            {
+                log.insert_flat("commander_user_id", commander.id);
+            }
+            Ok(source.finish_log(log, now, &api_key))
+        })
+        .collect::<Result<Vec<_>, ErrorMessage>>()?;
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
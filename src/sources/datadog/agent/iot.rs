@@ -0,0 +1,106 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::{TimeZone, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    config::log_schema,
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event,
+    },
+    internal_events::{DatadogAgentIoTMetricsReceived, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// Compact telemetry sent by the IoT agent, keys are shortened to save space
+/// on constrained devices.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct IoTPayload {
+    #[serde(rename = "h")]
+    pub host: String,
+    /// Unix timestamp in seconds.
+    #[serde(rename = "t")]
+    pub timestamp: i64,
+    #[serde(rename = "m", default)]
+    pub metrics: Vec<IoTMetric>,
+    #[serde(rename = "s", default)]
+    pub service: Option<String>,
+}
+
+/// A `[name, value, tags]` entry.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct IoTMetric(String, f64, #[serde(default)] Vec<String>);
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "iot",
+        path!("api" / "v1" / "iot" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_iot_metrics,
+    )
+}
+
+/// Turns each metric entry into an absolute gauge.
+fn decode_iot_metrics(
+    _source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let payload: IoTPayload = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let timestamp = Utc.timestamp(payload.timestamp, 0);
+    let mut common_tags = BTreeMap::new();
+    common_tags.insert(log_schema().host_key().to_owned(), payload.host);
+    if let Some(service) = payload.service {
+        common_tags.insert("service".to_owned(), service);
+    }
+
+    let events = payload
+        .metrics
+        .into_iter()
+        .map(|IoTMetric(name, value, tags)| {
+            let mut tags: BTreeMap<String, String> = parse_tags(tags.iter().map(String::as_str))
+                .map(|(key, value)| (key.into(), value.unwrap_or_default().into()))
+                .collect();
+            tags.extend(common_tags.clone());
+            let mut metric = Metric::new(name, MetricKind::Absolute, MetricValue::Gauge { value })
+                .with_timestamp(Some(timestamp))
+                .with_tags(Some(tags));
+            if let Some(k) = &api_key {
+                metric
+                    .metadata_mut()
+                    .set_datadog_api_key(Some(Arc::clone(k)));
+            }
+            metric.into()
+        })
+        .collect::<Vec<Event>>();
+
+    emit!(&DatadogAgentIoTMetricsReceived {
+        count: events.len()
+    });
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
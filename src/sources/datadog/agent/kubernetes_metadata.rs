@@ -0,0 +1,191 @@
+use std::{collections::BTreeMap, fmt, str::FromStr, sync::Arc};
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::{
+        DatadogAgentKubernetesMetadataReceived, DatadogAgentParseError, EventsReceived,
+    },
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct KubernetesResource {
+    pub resource_type: String,
+    #[serde(default)]
+    pub namespace: Option<String>,
+    pub name: String,
+    pub uid: String,
+    pub creation_timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub status_phase: Option<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// The phases reported by Kubernetes resources, like pods, namespaces or
+/// persistent volumes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum KubernetesPhase {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Unknown,
+    Active,
+    Terminating,
+    Available,
+    Bound,
+    Released,
+    Lost,
+}
+
+impl KubernetesPhase {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "Pending",
+            Self::Running => "Running",
+            Self::Succeeded => "Succeeded",
+            Self::Failed => "Failed",
+            Self::Unknown => "Unknown",
+            Self::Active => "Active",
+            Self::Terminating => "Terminating",
+            Self::Available => "Available",
+            Self::Bound => "Bound",
+            Self::Released => "Released",
+            Self::Lost => "Lost",
+        }
+    }
+}
+
+impl FromStr for KubernetesPhase {
+    type Err = UnknownKubernetesPhase;
+
+    fn from_str(phase: &str) -> Result<Self, Self::Err> {
+        match phase {
+            "Pending" => Ok(Self::Pending),
+            "Running" => Ok(Self::Running),
+            "Succeeded" => Ok(Self::Succeeded),
+            "Failed" => Ok(Self::Failed),
+            "Unknown" => Ok(Self::Unknown),
+            "Active" => Ok(Self::Active),
+            "Terminating" => Ok(Self::Terminating),
+            "Available" => Ok(Self::Available),
+            "Bound" => Ok(Self::Bound),
+            "Released" => Ok(Self::Released),
+            "Lost" => Ok(Self::Lost),
+            _ => Err(UnknownKubernetesPhase(phase.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UnknownKubernetesPhase(String);
+
+impl fmt::Display for UnknownKubernetesPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown Kubernetes phase {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownKubernetesPhase {}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "kubernetes_metadata",
+        path!("api" / "v1" / "kubernetes_metadata" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_kubernetes_metadata,
+    )
+}
+
+fn decode_kubernetes_metadata(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let resources: Vec<KubernetesResource> = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let mut received = BTreeMap::<String, usize>::new();
+    let events = resources
+        .into_iter()
+        .map(|resource| {
+            let phase = resource
+                .status_phase
+                .as_deref()
+                .map(str::parse::<KubernetesPhase>)
+                .transpose()
+                .map_err(|error| {
+                    emit!(&DatadogAgentParseError {
+                        endpoint: "kubernetes_metadata",
+                        error: &error,
+                    });
+                    ErrorMessage::new(
+                        StatusCode::BAD_REQUEST,
+                        format!(
+                            "Invalid {} {:?}: {}",
+                            resource.resource_type, resource.name, error
+                        ),
+                    )
+                })?;
+            *received.entry(resource.resource_type.clone()).or_default() += 1;
+
+            let mut log = LogEvent::default();
+            log.insert_flat("resource_type", resource.resource_type);
+            if let Some(namespace) = resource.namespace {
+                log.insert_flat("namespace", namespace);
+            }
+            log.insert_flat("name", resource.name);
+            log.insert_flat("uid", resource.uid);
+            log.insert_flat("creation_timestamp", resource.creation_timestamp);
+            if let Some(phase) = phase {
+                log.insert_flat("status_phase", phase.as_str());
+            }
+            log.insert_flat("labels", into_object(resource.labels));
+            log.insert_flat("annotations", into_object(resource.annotations));
+            Ok(source.finish_log(log, now, &api_key))
+        })
+        .collect::<Result<Vec<_>, ErrorMessage>>()?;
+
+    for (resource_type, count) in received {
+        emit!(&DatadogAgentKubernetesMetadataReceived {
+            count,
+            resource_type,
+        });
+    }
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
+
+fn into_object(map: BTreeMap<String, String>) -> BTreeMap<String, Value> {
+    map.into_iter()
+        .map(|(key, value)| (key, Value::from(value)))
+        .collect()
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    config::log_schema,
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event, LogEvent, Value,
+    },
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// The inventory of a host, as sent by the agent.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct AgentMetadata {
+    pub hostname: String,
+    #[serde(default)]
+    pub timezones: Vec<String>,
+    pub cpu_cores: usize,
+    pub memory_total_mb: u64,
+    #[serde(default)]
+    pub filesystems: Vec<Filesystem>,
+    #[serde(default)]
+    pub network_interfaces: Vec<NetworkInterface>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct Filesystem {
+    pub mount: String,
+    pub device: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub size_mb: u64,
+    pub used_mb: u64,
+}
+
+impl Filesystem {
+    /// Share of the filesystem which is used, if it has any size at all.
+    fn utilization(&self) -> Option<f64> {
+        (self.size_mb > 0).then(|| self.used_mb as f64 / self.size_mb as f64)
+    }
+}
+
+impl From<Filesystem> for Value {
+    fn from(filesystem: Filesystem) -> Self {
+        [
+            ("mount", Value::from(filesystem.mount)),
+            ("device", Value::from(filesystem.device)),
+            ("type", Value::from(filesystem.kind)),
+            ("size_mb", Value::from(filesystem.size_mb as i64)),
+            ("used_mb", Value::from(filesystem.used_mb as i64)),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value))
+        .collect()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct NetworkInterface {
+    pub name: String,
+    #[serde(default)]
+    pub ipv4: Option<String>,
+    #[serde(default)]
+    pub ipv6: Option<String>,
+    #[serde(default)]
+    pub mac: Option<String>,
+}
+
+impl From<NetworkInterface> for Value {
+    fn from(interface: NetworkInterface) -> Self {
+        [
+            ("name", Some(interface.name)),
+            ("ipv4", interface.ipv4),
+            ("ipv6", interface.ipv6),
+            ("mac", interface.mac),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (key.to_owned(), Value::from(value))))
+        .collect()
+    }
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "metadata",
+        path!("api" / "v1" / "metadata" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_metadata,
+    )
+}
+
+/// Turns the metadata into a log, along with a
+/// `host.filesystem.utilization` gauge for each filesystem.
+fn decode_metadata(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let metadata: AgentMetadata = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let mut events = Vec::with_capacity(1 + metadata.filesystems.len());
+    for filesystem in &metadata.filesystems {
+        if let Some(utilization) = filesystem.utilization() {
+            let tags = [
+                (log_schema().host_key(), metadata.hostname.as_str()),
+                ("mount", filesystem.mount.as_str()),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect();
+            let mut metric = Metric::new(
+                "host.filesystem.utilization",
+                MetricKind::Absolute,
+                MetricValue::Gauge { value: utilization },
+            )
+            .with_timestamp(Some(now))
+            .with_tags(Some(tags));
+            if let Some(k) = &api_key {
+                metric
+                    .metadata_mut()
+                    .set_datadog_api_key(Some(Arc::clone(k)));
+            }
+            events.push(metric.into());
+        }
+    }
+
+    let mut log = LogEvent::default();
+    log.insert_flat("hostname", metadata.hostname);
+    log.insert_flat("timezones", metadata.timezones);
+    log.insert_flat("cpu_cores", metadata.cpu_cores as i64);
+    log.insert_flat("memory_total_mb", metadata.memory_total_mb as i64);
+    log.insert_flat("filesystems", metadata.filesystems);
+    log.insert_flat("network_interfaces", metadata.network_interfaces);
+    events.insert(0, source.finish_log(log, now, &api_key));
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -1,27 +1,93 @@
+mod access;
+mod agent_info;
+mod apm_telemetry;
+mod audit;
+#[cfg(any(test, feature = "datadog-agent-chaos"))]
+mod chaos;
+mod check_run;
+mod ci_pipeline;
+mod cluster_checks;
+mod config_update;
+mod container_runtime_events;
+mod containers;
+mod cost_budget;
+mod dbm;
+mod diagnose;
+mod distributions;
+mod dns;
+mod error_tracking;
+mod external_metrics;
+mod flare;
+mod fleet;
+mod hosts;
+mod incidents;
 #[cfg(all(test, feature = "datadog-agent-integration-tests"))]
 mod integration_tests;
+mod iot;
+mod kubernetes_metadata;
+mod metadata;
+mod otlp_metrics;
+mod otlp_traces;
+mod processes;
+mod profiling;
+mod rum;
+mod runtime_security;
+mod sbom;
+mod schema;
+mod security_signals;
+mod slo_correction;
+mod snmp_traps;
+mod status;
+mod synthetics;
+mod telemetry;
 #[cfg(test)]
 mod tests;
+mod trace_context;
+mod watchdog;
+mod webhook;
 
-use std::{collections::BTreeMap, io::Read, net::SocketAddr, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    io::{Read, Write},
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    time::SystemTime,
+};
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
-use chrono::{TimeZone, Utc};
-use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use chrono::{DateTime, TimeZone, Utc};
+use flate2::{
+    read::{MultiGzDecoder, ZlibDecoder},
+    write::GzEncoder,
+};
 use futures::{future, FutureExt};
-use http::StatusCode;
+use http::{header, HeaderValue, StatusCode};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Server,
+};
+use ipnet::IpNet;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use snafu::Snafu;
+use tokio::net::TcpStream;
 use tokio_util::codec::Decoder;
+use tower::Service;
 use vector_core::{
     event::{BatchNotifier, BatchStatus},
     ByteSizeOf,
 };
+use vrl::{diagnostic::Formatter, Program, Runtime};
 use warp::{
     filters::BoxedFilter, path, path::FullPath, reject::Rejection, reply::Response, Filter, Reply,
 };
 
+use self::{
+    access::{ClientIp, IpAccess, PeerAddr},
+    trace_context::TraceContext,
+};
 use super::sketch_parser::decode_ddsketch;
 use crate::{
     codecs::{
@@ -35,20 +101,24 @@ use crate::{
     },
     event::{
         metric::{Metric, MetricKind, MetricValue},
-        Event,
+        Event, LogEvent, VrlTarget,
+    },
+    internal_events::{
+        DatadogAgentEnrichmentError, DatadogAgentRequestReceived, EventsReceived,
+        HttpBytesReceived, HttpDecompressError,
     },
-    internal_events::{EventsReceived, HttpBytesReceived, HttpDecompressError},
     serde::{bool_or_struct, default_decoding, default_framing_message_based},
     sources::{
         self,
         util::{ErrorMessage, StreamDecodingError},
     },
-    tls::{MaybeTlsSettings, TlsConfig},
+    tls::{MaybeTlsIncomingStream, MaybeTlsSettings, TlsConfig},
     SourceSender,
 };
 
 const LOGS: &str = "logs";
 const METRICS: &str = "metrics";
+const LOG_PIPELINE_CHECKS: &str = "log_pipeline_checks";
 
 #[derive(Clone, Copy, Debug, Snafu)]
 pub(crate) enum ApiError {
@@ -73,6 +143,37 @@ pub struct DatadogAgentConfig {
     acknowledgements: AcknowledgementsConfig,
     #[serde(default = "crate::serde::default_false")]
     multiple_outputs: bool,
+    #[serde(default = "crate::serde::default_false")]
+    parse_ddtags: bool,
+    #[serde(default = "crate::serde::default_false")]
+    validate_schema: bool,
+    #[serde(default)]
+    fanout_api_keys: Vec<String>,
+    #[serde(default)]
+    allowed_ips: Option<Vec<IpNet>>,
+    #[serde(default)]
+    denied_ips: Option<Vec<IpNet>>,
+    #[serde(default = "crate::serde::default_false")]
+    behind_proxy: bool,
+    #[serde(default)]
+    trusted_proxy_count: u32,
+    #[serde(default)]
+    flare_output_dir: Option<PathBuf>,
+    #[serde(default)]
+    log_pipeline_check_pattern: Option<String>,
+    #[serde(default = "crate::serde::default_false")]
+    status_endpoint: bool,
+    #[serde(default)]
+    webhook_secret: Option<String>,
+    #[serde(default)]
+    enrich_script: Option<String>,
+    #[serde(default = "crate::serde::default_false")]
+    acknowledge_updates: bool,
+    #[serde(default = "crate::serde::default_false")]
+    external_metrics_provider: bool,
+    #[cfg(any(test, feature = "datadog-agent-chaos"))]
+    #[serde(default)]
+    simulate_delay: Option<chaos::SimulateDelay>,
 }
 
 inventory::submit! {
@@ -95,6 +196,22 @@ impl GenerateConfig for DatadogAgentConfig {
             decoding: default_decoding(),
             acknowledgements: Default::default(),
             multiple_outputs: false,
+            parse_ddtags: false,
+            validate_schema: false,
+            fanout_api_keys: Vec::new(),
+            allowed_ips: None,
+            denied_ips: None,
+            behind_proxy: false,
+            trusted_proxy_count: 0,
+            flare_output_dir: None,
+            log_pipeline_check_pattern: None,
+            status_endpoint: false,
+            webhook_secret: None,
+            enrich_script: None,
+            acknowledge_updates: false,
+            external_metrics_provider: false,
+            #[cfg(any(test, feature = "datadog-agent-chaos"))]
+            simulate_delay: None,
         })
         .unwrap()
     }
@@ -104,9 +221,33 @@ impl GenerateConfig for DatadogAgentConfig {
 #[typetag::serde(name = "datadog_agent")]
 impl SourceConfig for DatadogAgentConfig {
     async fn build(&self, cx: SourceContext) -> crate::Result<sources::Source> {
+        #[cfg(any(test, feature = "datadog-agent-chaos"))]
+        if let Some(simulate_delay) = &self.simulate_delay {
+            simulate_delay.validate()?;
+        }
         let decoder = DecodingConfig::new(self.framing.clone(), self.decoding.clone()).build()?;
         let tls = MaybeTlsSettings::from_config(&self.tls, true)?;
-        let source = DatadogAgentSource::new(self.store_api_key, decoder, tls.http_protocol_name());
+        let mut source = DatadogAgentSource::new(
+            self.store_api_key,
+            self.parse_ddtags,
+            self.validate_schema,
+            &self.fanout_api_keys,
+            decoder,
+            tls.http_protocol_name(),
+        );
+        if let Some(pattern) = &self.log_pipeline_check_pattern {
+            source.log_pipeline_check_pattern = Some(Regex::new(pattern)?);
+        }
+        source.webhook_secret = self.webhook_secret.as_deref().map(Arc::from);
+        if let Some(script) = &self.enrich_script {
+            let mut functions = vrl_stdlib::all();
+            functions.append(&mut enrichment::vrl_functions());
+            functions.append(&mut vector_vrl_functions::vrl_functions());
+
+            let program = vrl::compile(script, &functions, None)
+                .map_err(|diagnostics| Formatter::new(script, diagnostics).to_string())?;
+            source.enrich_program = Some(Arc::new(program));
+        }
         let listener = tls.bind(&self.address).await?;
         let acknowledgements = cx.globals.acknowledgements.merge(&self.acknowledgements);
         let log_service = source.clone().event_service(
@@ -124,18 +265,82 @@ impl SourceConfig for DatadogAgentConfig {
             cx.out.clone(),
             self.multiple_outputs,
         );
-        let series_v2_service = source.series_v2_service();
+        let flare_service = flare::build_warp_filter(
+            acknowledgements.enabled(),
+            self.multiple_outputs,
+            cx.out.clone(),
+            source.clone(),
+            self.flare_output_dir.clone(),
+        );
+        let config_update_service = config_update::build_warp_filter(
+            acknowledgements.enabled(),
+            self.multiple_outputs,
+            cx.out.clone(),
+            source.clone(),
+            self.acknowledge_updates,
+        );
+        let series_v2_service = source.clone().series_v2_service();
+        let mut services = log_service
+            .or(series_v1_service)
+            .unify()
+            .or(series_v2_service)
+            .unify()
+            .or(sketches_service)
+            .unify()
+            .or(flare_service)
+            .unify()
+            .or(config_update_service)
+            .unify()
+            .boxed();
+        for build_route in INTAKE_ROUTES {
+            let route = build_route(
+                acknowledgements.enabled(),
+                self.multiple_outputs,
+                cx.out.clone(),
+                source.clone(),
+            );
+            services = services.or(route).unify().boxed();
+        }
+        let services = if self.status_endpoint {
+            services
+                .or(status::build_warp_filter(
+                    cx.key.id().to_owned(),
+                    SystemTime::now(),
+                ))
+                .unify()
+                .boxed()
+        } else {
+            services
+        };
+        let services = if self.external_metrics_provider {
+            services
+                .or(external_metrics::build_warp_filter())
+                .unify()
+                .boxed()
+        } else {
+            services
+        };
+        #[cfg(any(test, feature = "datadog-agent-chaos"))]
+        let services = match self.simulate_delay.clone() {
+            Some(simulate_delay) => simulate_delay.wrap(services),
+            None => services,
+        };
+        let access = Arc::new(IpAccess {
+            allowed_ips: self.allowed_ips.clone(),
+            denied_ips: self.denied_ips.clone(),
+            behind_proxy: self.behind_proxy,
+            trusted_proxy_count: self.trusted_proxy_count,
+        });
+        let services = if access.is_enabled() {
+            access.wrap(services)
+        } else {
+            services
+        };
 
         let shutdown = cx.shutdown;
         Ok(Box::pin(async move {
             let span = crate::trace::current_span();
-            let routes = log_service
-                .or(series_v1_service)
-                .unify()
-                .or(series_v2_service)
-                .unify()
-                .or(sketches_service)
-                .unify()
+            let routes = services
                 .with(warp::trace(move |_info| span.clone()))
                 .recover(|r: Rejection| async move {
                     if let Some(e_msg) = r.find::<ErrorMessage>() {
@@ -146,26 +351,55 @@ impl SourceConfig for DatadogAgentConfig {
                         Err(r)
                     }
                 });
-            warp::serve(routes)
-                .serve_incoming_with_graceful_shutdown(
-                    listener.accept_stream(),
-                    shutdown.map(|_| ()),
-                )
-                .await;
+            let service = warp::service(with_response_encoding(routes));
+            // Served through hyper rather than `warp::serve` so that the
+            // address of each client is made available to the filters.
+            let make_service =
+                make_service_fn(move |stream: &MaybeTlsIncomingStream<TcpStream>| {
+                    let peer_addr = PeerAddr(stream.peer_addr());
+                    let access = Arc::clone(&access);
+                    let mut service = service.clone();
+                    future::ok::<_, Infallible>(service_fn(
+                        move |mut request: http::Request<Body>| {
+                            let forwarded_for = request
+                                .headers()
+                                .get("x-forwarded-for")
+                                .and_then(|value| value.to_str().ok());
+                            let client_ip = access.client_ip(peer_addr.0, forwarded_for);
+                            request.extensions_mut().insert(peer_addr);
+                            if let Some(client_ip) = client_ip {
+                                request.extensions_mut().insert(ClientIp(client_ip));
+                            }
+                            service.call(request)
+                        },
+                    ))
+                });
+            if let Err(error) =
+                Server::builder(hyper::server::accept::from_stream(listener.accept_stream()))
+                    .serve(make_service)
+                    .with_graceful_shutdown(shutdown.map(|_| ()))
+                    .await
+            {
+                error!(message = "Server error.", %error);
+            }
 
             Ok(())
         }))
     }
 
     fn outputs(&self) -> Vec<Output> {
-        if self.multiple_outputs {
+        let mut outputs = if self.multiple_outputs {
             vec![
                 Output::from((METRICS, DataType::Metric)),
                 Output::from((LOGS, DataType::Log)),
             ]
         } else {
             vec![Output::default(DataType::Any)]
+        };
+        if self.log_pipeline_check_pattern.is_some() {
+            outputs.push(Output::from((LOG_PIPELINE_CHECKS, DataType::Log)));
         }
+        outputs
     }
 
     fn source_type(&self) -> &'static str {
@@ -177,14 +411,73 @@ impl SourceConfig for DatadogAgentConfig {
     }
 }
 
+/// Builds the filter of an intake endpoint, given whether acknowledgements
+/// are enabled and whether the events go to separate outputs.
+type IntakeRoute = fn(bool, bool, SourceSender, DatadogAgentSource) -> BoxedFilter<(Response,)>;
+
+/// The intake endpoints which only need the source to be built, tried in
+/// order after the logs, series, sketches, flare and configuration ones.
+const INTAKE_ROUTES: &[IntakeRoute] = &[
+    security_signals::build_warp_filter,
+    runtime_security::build_warp_filter,
+    synthetics::build_warp_filter,
+    dbm::build_warp_filter,
+    profiling::build_warp_filter,
+    error_tracking::build_warp_filter,
+    ci_pipeline::build_warp_filter,
+    slo_correction::build_warp_filter,
+    processes::build_warp_filter,
+    hosts::build_warp_filter,
+    watchdog::build_warp_filter,
+    snmp_traps::build_warp_filter,
+    iot::build_warp_filter,
+    dns::build_warp_filter,
+    sbom::build_warp_filter,
+    audit::build_warp_filter,
+    otlp_traces::build_warp_filter,
+    check_run::build_warp_filter,
+    distributions::build_warp_filter,
+    containers::build_warp_filter,
+    kubernetes_metadata::build_warp_filter,
+    otlp_metrics::build_warp_filter,
+    webhook::build_warp_filter,
+    diagnose::build_warp_filter,
+    rum::build_warp_filter,
+    incidents::build_warp_filter,
+    fleet::build_warp_filter,
+    apm_telemetry::build_warp_filter,
+    metadata::build_warp_filter,
+    cost_budget::build_warp_filter,
+    agent_info::build_warp_filter,
+    cluster_checks::build_warp_filter,
+    telemetry::build_warp_filter,
+    container_runtime_events::build_warp_filter,
+];
+
 #[derive(Clone)]
 struct DatadogAgentSource {
     store_api_key: bool,
+    parse_ddtags: bool,
+    validate_schema: bool,
+    fanout_api_keys: Arc<[Arc<str>]>,
     api_key_matcher: Regex,
     log_schema_timestamp_key: &'static str,
     log_schema_source_type_key: &'static str,
     decoder: codecs::Decoder,
     protocol: &'static str,
+    log_pipeline_check_pattern: Option<Regex>,
+    /// Key of the HMAC signature required from webhooks.
+    webhook_secret: Option<Arc<str>>,
+    /// VRL program run over every decoded event.
+    enrich_program: Option<Arc<Program>>,
+}
+
+/// Sends the events for which `matches` holds to the `output` named output,
+/// instead of the default ones.
+#[derive(Clone, Copy)]
+struct EventRoute {
+    output: &'static str,
+    matches: fn(&DatadogAgentSource, &Event) -> bool,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -193,15 +486,31 @@ struct DatadogSeriesRequest {
 }
 
 impl DatadogAgentSource {
-    fn new(store_api_key: bool, decoder: codecs::Decoder, protocol: &'static str) -> Self {
+    fn new(
+        store_api_key: bool,
+        parse_ddtags: bool,
+        validate_schema: bool,
+        fanout_api_keys: &[String],
+        decoder: codecs::Decoder,
+        protocol: &'static str,
+    ) -> Self {
         Self {
             store_api_key,
+            parse_ddtags,
+            validate_schema,
+            fanout_api_keys: fanout_api_keys
+                .iter()
+                .map(|key| Arc::from(key.as_str()))
+                .collect(),
             api_key_matcher: Regex::new(r"^/v1/input/(?P<api_key>[[:alnum:]]{32})/??")
                 .expect("static regex always compiles"),
             log_schema_source_type_key: log_schema().source_type_key(),
             log_schema_timestamp_key: log_schema().timestamp_key(),
             decoder,
             protocol,
+            log_pipeline_check_pattern: None,
+            webhook_secret: None,
+            enrich_program: None,
         }
     }
 
@@ -224,21 +533,281 @@ impl DatadogAgentSource {
             .or_else(|| header.map(Arc::from))
     }
 
+    /// Parses a decompressed JSON payload, first validating it against the
+    /// `schema` of its endpoint if enabled.
+    fn parse_json<T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        schema: &jsonschema::JSONSchema,
+        body: &[u8],
+    ) -> Result<T, ErrorMessage> {
+        let parse_error = |error: serde_json::Error| {
+            ErrorMessage::new(
+                StatusCode::BAD_REQUEST,
+                format!("Error parsing JSON: {:?}", error),
+            )
+        };
+        if !self.validate_schema {
+            return serde_json::from_slice(body).map_err(parse_error);
+        }
+
+        let payload: serde_json::Value = serde_json::from_slice(body).map_err(parse_error)?;
+        schema::validate(endpoint, schema, &payload)?;
+        serde_json::from_value(payload).map_err(parse_error)
+    }
+
+    /// Runs the `enrich_script` program, if any, over the decoded events,
+    /// passing an event through unchanged when the program fails on it.
+    fn enrich(&self, events: Vec<Event>) -> Vec<Event> {
+        let program = match &self.enrich_program {
+            Some(program) => program,
+            None => return events,
+        };
+
+        let mut runtime = Runtime::default();
+        let timezone = shared::TimeZone::default();
+        let mut enriched = Vec::with_capacity(events.len());
+        for event in events {
+            let original = event.clone();
+            let mut target: VrlTarget = event.into();
+            let result = runtime.resolve(&mut target, program, &timezone);
+            runtime.clear();
+
+            match result {
+                Ok(_) => enriched.extend(target.into_events()),
+                Err(error) => {
+                    emit!(&DatadogAgentEnrichmentError {
+                        error: error.to_string(),
+                    });
+                    enriched.push(original);
+                }
+            }
+        }
+        enriched
+    }
+
+    /// Copies the events received without an API key once for each of the
+    /// `fanout_api_keys`.
+    fn fan_out(&self, events: Vec<Event>, api_key: &Option<Arc<str>>) -> Vec<Event> {
+        if api_key.is_some() || !self.store_api_key || self.fanout_api_keys.is_empty() {
+            return events;
+        }
+
+        let mut fanned_out = Vec::with_capacity(events.len() * self.fanout_api_keys.len());
+        for event in events {
+            for key in self.fanout_api_keys.iter() {
+                let mut event = event.clone();
+                event
+                    .metadata_mut()
+                    .set_datadog_api_key(Some(Arc::clone(key)));
+                fanned_out.push(event);
+            }
+        }
+        fanned_out
+    }
+
+    /// Runs the steps shared by all the intake endpoints over the events
+    /// decoded from a request: enrichment, API key fan out and trace context.
+    fn finish_events(
+        &self,
+        endpoint: &'static str,
+        events: Result<Vec<Event>, ErrorMessage>,
+        api_key: &Option<Arc<str>>,
+        trace_context: Option<&TraceContext>,
+    ) -> Result<Vec<Event>, ErrorMessage> {
+        let mut events = self.fan_out(self.enrich(events?), api_key);
+        if let Some(trace_context) = trace_context {
+            trace_context.apply(endpoint, &mut events);
+        }
+        Ok(events)
+    }
+
+    /// Finishes a log event decoded from one of the intake endpoints by adding
+    /// the source type, the ingestion timestamp and the API key.
+    fn finish_log(
+        &self,
+        mut log: LogEvent,
+        now: DateTime<Utc>,
+        api_key: &Option<Arc<str>>,
+    ) -> Event {
+        log.try_insert_flat(
+            self.log_schema_source_type_key,
+            Bytes::from("datadog_agent"),
+        );
+        log.try_insert_flat(self.log_schema_timestamp_key, now);
+        if let Some(k) = api_key {
+            log.metadata_mut().set_datadog_api_key(Some(Arc::clone(k)));
+        }
+        log.into()
+    }
+
+    /// Builds a `POST` filter for the intake `path`, the decompressed payloads
+    /// are turned into events by `decode_body`.
+    fn intake_filter<F>(
+        self,
+        endpoint: &'static str,
+        path: BoxedFilter<()>,
+        acknowledgements: bool,
+        out: SourceSender,
+        multiple_outputs: bool,
+        decode_body: F,
+    ) -> BoxedFilter<(Response,)>
+    where
+        F: Fn(&Self, Bytes, Option<Arc<str>>) -> Result<Vec<Event>, ErrorMessage>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.routed_intake_filter(
+            endpoint,
+            path,
+            acknowledgements,
+            out,
+            multiple_outputs,
+            None,
+            decode_body,
+        )
+    }
+
+    /// Like `intake_filter`, sending the events picked by `route` to their
+    /// own output.
+    #[allow(clippy::too_many_arguments)]
+    fn routed_intake_filter<F>(
+        self,
+        endpoint: &'static str,
+        path: BoxedFilter<()>,
+        acknowledgements: bool,
+        out: SourceSender,
+        multiple_outputs: bool,
+        route: Option<EventRoute>,
+        decode_body: F,
+    ) -> BoxedFilter<(Response,)>
+    where
+        F: Fn(&Self, Bytes, Option<Arc<str>>) -> Result<Vec<Event>, ErrorMessage>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+    {
+        warp::post()
+            .and(path)
+            .and(warp::path::full())
+            .and(warp::header::optional::<String>("content-encoding"))
+            .and(warp::header::optional::<String>("dd-api-key"))
+            .and(warp::query::<ApiKeyQueryParams>())
+            .and(TraceContext::filter())
+            .and(access::client_ip())
+            .and(received_body(endpoint))
+            .and_then(
+                move |path: FullPath,
+                      encoding_header: Option<String>,
+                      api_token: Option<String>,
+                      query_params: ApiKeyQueryParams,
+                      trace_context: Option<TraceContext>,
+                      client_ip: Option<IpAddr>,
+                      body: Bytes| {
+                    emit!(&HttpBytesReceived {
+                        byte_size: body.len(),
+                        http_path: path.as_str(),
+                        protocol: self.protocol,
+                    });
+                    let api_key =
+                        self.extract_api_key(path.as_str(), api_token, query_params.dd_api_key);
+                    let events = decode(&encoding_header, body)
+                        .and_then(|body| decode_body(&self, body, api_key.clone()));
+                    let mut events =
+                        self.finish_events(endpoint, events, &api_key, trace_context.as_ref());
+                    let routed = match (route, &mut events) {
+                        (Some(route), Ok(events)) => {
+                            let (routed, rest) = std::mem::take(events)
+                                .into_iter()
+                                .partition(|event| (route.matches)(&self, event));
+                            *events = rest;
+                            Some((route.output, routed))
+                        }
+                        _ => None,
+                    };
+                    Self::handle_routed_request(
+                        events,
+                        routed,
+                        client_ip,
+                        acknowledgements,
+                        out.clone(),
+                        multiple_outputs,
+                    )
+                },
+            )
+            .boxed()
+    }
+
     async fn handle_request(
         events: Result<Vec<Event>, ErrorMessage>,
+        client_ip: Option<IpAddr>,
+        acknowledgements: bool,
+        out: SourceSender,
+        multiple_outputs: bool,
+    ) -> Result<Response, Rejection> {
+        Self::handle_routed_request(
+            events,
+            None,
+            client_ip,
+            acknowledgements,
+            out,
+            multiple_outputs,
+        )
+        .await
+    }
+
+    /// Like `handle_request`, first sending the `routed` events to their own
+    /// named output.
+    async fn handle_routed_request(
+        events: Result<Vec<Event>, ErrorMessage>,
+        routed: Option<(&'static str, Vec<Event>)>,
+        client_ip: Option<IpAddr>,
         acknowledgements: bool,
         mut out: SourceSender,
-        output: Option<&str>,
+        multiple_outputs: bool,
     ) -> Result<Response, Rejection> {
         match events {
             Ok(mut events) => {
-                let receiver = BatchNotifier::maybe_apply_to_events(acknowledgements, &mut events);
+                let (output, mut routed) = routed.unwrap_or_default();
+                let (batch, receiver) = BatchNotifier::maybe_new_with_receiver(acknowledgements);
+                for event in events.iter_mut().chain(routed.iter_mut()) {
+                    if let Some(client_ip) = client_ip {
+                        event.metadata_mut().set_client_ip(Some(client_ip));
+                    }
+                    if let Some(batch) = &batch {
+                        event.add_batch_notifier(Arc::clone(batch));
+                    }
+                }
+                drop(batch);
 
-                let mut events = futures::stream::iter(events);
-                if let Some(name) = output {
-                    out.send_all_named(name, &mut events).await
+                let sent = if routed.is_empty() {
+                    Ok(())
                 } else {
-                    out.send_all(&mut events).await
+                    out.send_all_named(output, &mut futures::stream::iter(routed))
+                        .await
+                };
+                match sent {
+                    Err(error) => Err(error),
+                    Ok(()) if multiple_outputs => {
+                        // Logs and metrics go to their own output.
+                        let (logs, metrics): (Vec<_>, Vec<_>) = events
+                            .into_iter()
+                            .partition(|event| matches!(event, Event::Log(_)));
+                        match out
+                            .send_all_named(LOGS, &mut futures::stream::iter(logs))
+                            .await
+                        {
+                            Ok(()) => {
+                                out.send_all_named(METRICS, &mut futures::stream::iter(metrics))
+                                    .await
+                            }
+                            Err(error) => Err(error),
+                        }
+                    }
+                    Ok(()) => out.send_all(&mut futures::stream::iter(events)).await,
                 }
                 .map_err(move |error: crate::source_sender::ClosedError| {
                     // can only fail if receiving end disconnected, so we are shutting down,
@@ -278,30 +847,36 @@ impl DatadogAgentSource {
             .and(warp::header::optional::<String>("content-encoding"))
             .and(warp::header::optional::<String>("dd-api-key"))
             .and(warp::query::<ApiKeyQueryParams>())
-            .and(warp::body::bytes())
+            .and(TraceContext::filter())
+            .and(access::client_ip())
+            .and(received_body("logs"))
             .and_then(
                 move |_,
                       path: FullPath,
                       encoding_header: Option<String>,
                       api_token: Option<String>,
                       query_params: ApiKeyQueryParams,
+                      trace_context: Option<TraceContext>,
+                      client_ip: Option<IpAddr>,
                       body: Bytes| {
                     emit!(&HttpBytesReceived {
                         byte_size: body.len(),
                         http_path: path.as_str(),
                         protocol: self.protocol,
                     });
-                    let events = decode(&encoding_header, body).and_then(|body| {
-                        self.decode_log_body(
-                            body,
-                            self.extract_api_key(path.as_str(), api_token, query_params.dd_api_key),
-                        )
-                    });
-                    if multiple_outputs {
-                        Self::handle_request(events, acknowledgements, out.clone(), Some(LOGS))
-                    } else {
-                        Self::handle_request(events, acknowledgements, out.clone(), None)
-                    }
+                    let api_key =
+                        self.extract_api_key(path.as_str(), api_token, query_params.dd_api_key);
+                    let events = decode(&encoding_header, body)
+                        .and_then(|body| self.decode_log_body(body, api_key.clone()));
+                    let events =
+                        self.finish_events("logs", events, &api_key, trace_context.as_ref());
+                    Self::handle_request(
+                        events,
+                        client_ip,
+                        acknowledgements,
+                        out.clone(),
+                        multiple_outputs,
+                    )
                 },
             )
             .boxed()
@@ -319,29 +894,35 @@ impl DatadogAgentSource {
             .and(warp::header::optional::<String>("content-encoding"))
             .and(warp::header::optional::<String>("dd-api-key"))
             .and(warp::query::<ApiKeyQueryParams>())
-            .and(warp::body::bytes())
+            .and(TraceContext::filter())
+            .and(access::client_ip())
+            .and(received_body("series"))
             .and_then(
                 move |path: FullPath,
                       encoding_header: Option<String>,
                       api_token: Option<String>,
                       query_params: ApiKeyQueryParams,
+                      trace_context: Option<TraceContext>,
+                      client_ip: Option<IpAddr>,
                       body: Bytes| {
                     emit!(&HttpBytesReceived {
                         byte_size: body.len(),
                         http_path: path.as_str(),
                         protocol: self.protocol,
                     });
-                    let events = decode(&encoding_header, body).and_then(|body| {
-                        self.decode_datadog_series(
-                            body,
-                            self.extract_api_key(path.as_str(), api_token, query_params.dd_api_key),
-                        )
-                    });
-                    if multiple_outputs {
-                        Self::handle_request(events, acknowledgements, out.clone(), Some(METRICS))
-                    } else {
-                        Self::handle_request(events, acknowledgements, out.clone(), None)
-                    }
+                    let api_key =
+                        self.extract_api_key(path.as_str(), api_token, query_params.dd_api_key);
+                    let events = decode(&encoding_header, body)
+                        .and_then(|body| self.decode_datadog_series(body, api_key.clone()));
+                    let events =
+                        self.finish_events("series", events, &api_key, trace_context.as_ref());
+                    Self::handle_request(
+                        events,
+                        client_ip,
+                        acknowledgements,
+                        out.clone(),
+                        multiple_outputs,
+                    )
                 },
             )
             .boxed()
@@ -376,29 +957,35 @@ impl DatadogAgentSource {
             .and(warp::header::optional::<String>("content-encoding"))
             .and(warp::header::optional::<String>("dd-api-key"))
             .and(warp::query::<ApiKeyQueryParams>())
-            .and(warp::body::bytes())
+            .and(TraceContext::filter())
+            .and(access::client_ip())
+            .and(received_body("sketches"))
             .and_then(
                 move |path: FullPath,
                       encoding_header: Option<String>,
                       api_token: Option<String>,
                       query_params: ApiKeyQueryParams,
+                      trace_context: Option<TraceContext>,
+                      client_ip: Option<IpAddr>,
                       body: Bytes| {
                     emit!(&HttpBytesReceived {
                         byte_size: body.len(),
                         http_path: path.as_str(),
                         protocol: self.protocol,
                     });
-                    let events = decode(&encoding_header, body).and_then(|body| {
-                        self.decode_datadog_sketches(
-                            body,
-                            self.extract_api_key(path.as_str(), api_token, query_params.dd_api_key),
-                        )
-                    });
-                    if multiple_outputs {
-                        Self::handle_request(events, acknowledgements, out.clone(), Some(METRICS))
-                    } else {
-                        Self::handle_request(events, acknowledgements, out.clone(), None)
-                    }
+                    let api_key =
+                        self.extract_api_key(path.as_str(), api_token, query_params.dd_api_key);
+                    let events = decode(&encoding_header, body)
+                        .and_then(|body| self.decode_datadog_sketches(body, api_key.clone()));
+                    let events =
+                        self.finish_events("sketches", events, &api_key, trace_context.as_ref());
+                    Self::handle_request(
+                        events,
+                        client_ip,
+                        acknowledgements,
+                        out.clone(),
+                        multiple_outputs,
+                    )
                 },
             )
             .boxed()
@@ -447,12 +1034,7 @@ impl DatadogAgentSource {
             return Ok(Vec::new());
         }
 
-        let metrics: DatadogSeriesRequest = serde_json::from_slice(&body).map_err(|error| {
-            ErrorMessage::new(
-                StatusCode::BAD_REQUEST,
-                format!("Error parsing JSON: {:?}", error),
-            )
-        })?;
+        let metrics: DatadogSeriesRequest = self.parse_json("series", &schema::SERIES, &body)?;
 
         let decoded_metrics: Vec<Event> = metrics
             .series
@@ -482,17 +1064,15 @@ impl DatadogAgentSource {
             return Ok(Vec::new());
         }
 
-        let messages: Vec<LogMsg> = serde_json::from_slice(&body).map_err(|error| {
-            ErrorMessage::new(
-                StatusCode::BAD_REQUEST,
-                format!("Error parsing JSON: {:?}", error),
-            )
-        })?;
+        let messages: Vec<LogMsg> = self.parse_json("logs", &schema::LOGS, &body)?;
 
         let now = Utc::now();
         let mut decoded = Vec::new();
 
         for message in messages {
+            let ddtags = self
+                .parse_ddtags
+                .then(|| String::from_utf8_lossy(&message.ddtags).into_owned());
             let mut decoder = self.decoder.clone();
             let mut buffer = BytesMut::new();
             buffer.put(message.message);
@@ -506,7 +1086,17 @@ impl DatadogAgentSource {
                                 log.try_insert_flat("hostname", message.hostname.clone());
                                 log.try_insert_flat("service", message.service.clone());
                                 log.try_insert_flat("ddsource", message.ddsource.clone());
-                                log.try_insert_flat("ddtags", message.ddtags.clone());
+                                match &ddtags {
+                                    Some(ddtags) => {
+                                        for (key, value) in parse_tags(ddtags.split(',')) {
+                                            match value {
+                                                Some(value) => log.try_insert_flat(key, value),
+                                                None => log.try_insert_flat(key, true),
+                                            }
+                                        }
+                                    }
+                                    None => log.try_insert_flat("ddtags", message.ddtags.clone()),
+                                }
                                 log.try_insert_flat(
                                     self.log_schema_source_type_key,
                                     Bytes::from("datadog_agent"),
@@ -540,6 +1130,24 @@ impl DatadogAgentSource {
     }
 }
 
+/// Reads the request body, reporting its size before it gets decoded.
+fn received_body(endpoint: &'static str) -> BoxedFilter<(Bytes,)> {
+    warp::header::optional::<usize>("content-length")
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::body::bytes())
+        .map(
+            move |content_length: Option<usize>, content_type: Option<String>, body: Bytes| {
+                emit!(&DatadogAgentRequestReceived {
+                    endpoint,
+                    body_size_bytes: content_length.unwrap_or_else(|| body.len()),
+                    content_type: content_type.unwrap_or_default(),
+                });
+                body
+            },
+        )
+        .boxed()
+}
+
 fn decode(header: &Option<String>, mut body: Bytes) -> Result<Bytes, ErrorMessage> {
     if let Some(encodings) = header {
         for encoding in encodings.rsplit(',').map(str::trim) {
@@ -571,15 +1179,77 @@ fn decode(header: &Option<String>, mut body: Bytes) -> Result<Bytes, ErrorMessag
     Ok(body)
 }
 
-fn into_vector_metric(dd_metric: DatadogSeriesMetric, api_key: Option<Arc<str>>) -> Vec<Event> {
-    let mut tags: BTreeMap<String, String> = dd_metric
-        .tags
-        .unwrap_or_default()
-        .iter()
-        .map(|tag| {
-            let kv = tag.split_once(":").unwrap_or((tag, ""));
-            (kv.0.trim().into(), kv.1.trim().into())
+/// Wraps `filter` so that its replies are compressed according to the
+/// `Accept-Encoding` request header.
+fn with_response_encoding<F, T>(
+    filter: F,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (T,), Error = Rejection> + Clone + Send + Sync + 'static,
+    T: Reply + Send,
+{
+    warp::header::optional::<String>("accept-encoding")
+        .and(filter)
+        .and_then(|accept_encoding: Option<String>, reply: T| async move {
+            Ok::<_, Rejection>(encode_response(&accept_encoding, reply).await)
+        })
+}
+
+/// Gzip compresses the response body if the client listed `gzip` in its
+/// `Accept-Encoding` header.
+async fn encode_response(accept_encoding: &Option<String>, reply: impl Reply) -> Response {
+    let response = reply.into_response();
+    if !accepts_gzip(accept_encoding) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match warp::hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(error) => {
+            error!(message = "Failed to read response body.", %error);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if body.is_empty() {
+        return Response::from_parts(parts, body.into());
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let compressed = match encoder.write_all(&body).and_then(|_| encoder.finish()) {
+        Ok(compressed) => compressed,
+        Err(error) => {
+            error!(message = "Failed to compress response body.", %error);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    parts
+        .headers
+        .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, compressed.into())
+}
+
+fn accepts_gzip(accept_encoding: &Option<String>) -> bool {
+    accept_encoding.as_deref().map_or(false, |encodings| {
+        encodings.split(',').any(|encoding| {
+            let mut params = encoding.split(';').map(str::trim);
+            let name = params.next().unwrap_or_default();
+            let disabled = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .map_or(false, |q| q == 0.0)
+            });
+            (name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip")) && !disabled
         })
+    })
+}
+
+fn into_vector_metric(dd_metric: DatadogSeriesMetric, api_key: Option<Arc<str>>) -> Vec<Event> {
+    let dd_tags = dd_metric.tags.unwrap_or_default();
+    let mut tags: BTreeMap<String, String> = parse_tags(dd_tags.iter().map(String::as_str))
+        .map(|(key, value)| (key.into(), value.unwrap_or_default().into()))
         .collect();
 
     dd_metric
@@ -650,6 +1320,20 @@ fn into_vector_metric(dd_metric: DatadogSeriesMetric, api_key: Option<Arc<str>>)
     .collect()
 }
 
+/// Splits Datadog `key:value` tags on their first `:`, tags without a value are
+/// returned with `None` as their value. Empty tags are skipped.
+fn parse_tags<'a>(
+    tags: impl IntoIterator<Item = &'a str>,
+) -> impl Iterator<Item = (&'a str, Option<&'a str>)> {
+    tags.into_iter()
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| match tag.split_once(':') {
+            Some((key, value)) => (key.trim(), Some(value.trim())),
+            None => (tag, None),
+        })
+}
+
 fn handle_decode_error(encoding: &str, error: impl std::error::Error) -> ErrorMessage {
     emit!(&HttpDecompressError {
         encoding,
@@ -0,0 +1,239 @@
+use std::{collections::BTreeMap, iter, sync::Arc};
+
+use bytes::Bytes;
+use chrono::{TimeZone, Utc};
+use http::StatusCode;
+use prost::Message;
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{
+        metric::{Bucket, Metric, MetricKind, MetricValue, Quantile},
+        Event,
+    },
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+mod otlp_proto {
+    include!(concat!(env!("OUT_DIR"), "/otlp.metrics.rs"));
+}
+
+use otlp_proto::{
+    any_value, metric::Data, number_data_point, AggregationTemporality, AnyValue,
+    ExportMetricsServiceRequest, KeyValue,
+};
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "otlp_metrics",
+        path!("api" / "v0.4" / "metrics" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_otlp_metrics,
+    )
+}
+
+/// A data point turned into a metric value, along with its own attributes.
+struct Point {
+    attributes: Vec<KeyValue>,
+    time_unix_nano: u64,
+    kind: MetricKind,
+    value: MetricValue,
+}
+
+/// Turns each data point of an OTLP `ExportMetricsServiceRequest` into a
+/// metric, tagged with the attributes of its resource and of the point.
+fn decode_otlp_metrics(
+    _source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let request = ExportMetricsServiceRequest::decode(body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error decoding OTLP metrics: {:?}", error),
+        )
+    })?;
+
+    let mut events = Vec::new();
+    for resource_metrics in request.resource_metrics {
+        let resource_tags = resource_metrics
+            .resource
+            .map(|resource| attributes_to_tags(resource.attributes))
+            .unwrap_or_default();
+        for metric in resource_metrics
+            .scope_metrics
+            .into_iter()
+            .flat_map(|scope_metrics| scope_metrics.metrics)
+        {
+            let points = metric.data.map(data_points).unwrap_or_default();
+            for point in points {
+                let mut tags = resource_tags.clone();
+                tags.extend(attributes_to_tags(point.attributes));
+                let mut event = Metric::new(metric.name.clone(), point.kind, point.value)
+                    .with_timestamp(
+                        (point.time_unix_nano != 0)
+                            .then(|| Utc.timestamp_nanos(point.time_unix_nano as i64)),
+                    )
+                    .with_tags((!tags.is_empty()).then(|| tags));
+                if let Some(k) = &api_key {
+                    event
+                        .metadata_mut()
+                        .set_datadog_api_key(Some(Arc::clone(k)));
+                }
+                events.push(event.into());
+            }
+        }
+    }
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
+
+/// Monotonic sums are running totals and become absolute counters, the
+/// others report changes and become incremental ones. Points without a value
+/// are dropped.
+fn data_points(data: Data) -> Vec<Point> {
+    match data {
+        Data::Gauge(gauge) => gauge
+            .data_points
+            .into_iter()
+            .filter_map(|point| {
+                Some(Point {
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::Gauge {
+                        value: number_value(point.value?),
+                    },
+                    attributes: point.attributes,
+                    time_unix_nano: point.time_unix_nano,
+                })
+            })
+            .collect(),
+        Data::Sum(sum) => {
+            let kind = if sum.is_monotonic {
+                MetricKind::Absolute
+            } else {
+                MetricKind::Incremental
+            };
+            sum.data_points
+                .into_iter()
+                .filter_map(|point| {
+                    Some(Point {
+                        kind,
+                        value: MetricValue::Counter {
+                            value: number_value(point.value?),
+                        },
+                        attributes: point.attributes,
+                        time_unix_nano: point.time_unix_nano,
+                    })
+                })
+                .collect()
+        }
+        Data::Histogram(histogram) => {
+            let kind = temporality_kind(histogram.aggregation_temporality);
+            histogram
+                .data_points
+                .into_iter()
+                .map(|point| Point {
+                    kind,
+                    value: MetricValue::AggregatedHistogram {
+                        buckets: histogram_buckets(&point.bucket_counts, &point.explicit_bounds),
+                        count: point.count as u32,
+                        sum: point.sum,
+                    },
+                    attributes: point.attributes,
+                    time_unix_nano: point.time_unix_nano,
+                })
+                .collect()
+        }
+        Data::Summary(summary) => summary
+            .data_points
+            .into_iter()
+            .map(|point| Point {
+                kind: MetricKind::Absolute,
+                value: MetricValue::AggregatedSummary {
+                    quantiles: point
+                        .quantile_values
+                        .iter()
+                        .map(|quantile| Quantile {
+                            quantile: quantile.quantile,
+                            value: quantile.value,
+                        })
+                        .collect(),
+                    count: point.count as u32,
+                    sum: point.sum,
+                },
+                attributes: point.attributes,
+                time_unix_nano: point.time_unix_nano,
+            })
+            .collect(),
+    }
+}
+
+fn temporality_kind(temporality: i32) -> MetricKind {
+    match AggregationTemporality::from_i32(temporality) {
+        Some(AggregationTemporality::Delta) => MetricKind::Incremental,
+        _ => MetricKind::Absolute,
+    }
+}
+
+fn number_value(value: number_data_point::Value) -> f64 {
+    match value {
+        number_data_point::Value::AsDouble(value) => value,
+        number_data_point::Value::AsInt(value) => value as f64,
+    }
+}
+
+/// OTLP counts the observations of each bucket on its own while the buckets
+/// of an aggregated histogram are cumulative. The last OTLP bucket has no
+/// upper bound.
+fn histogram_buckets(counts: &[u64], bounds: &[f64]) -> Vec<Bucket> {
+    bounds
+        .iter()
+        .copied()
+        .chain(iter::once(f64::INFINITY))
+        .zip(counts)
+        .scan(0, |total, (upper_limit, count)| {
+            *total += count;
+            Some(Bucket {
+                upper_limit,
+                count: *total as u32,
+            })
+        })
+        .collect()
+}
+
+fn attributes_to_tags(attributes: Vec<KeyValue>) -> BTreeMap<String, String> {
+    attributes
+        .into_iter()
+        .filter_map(|attribute| Some((attribute.key, any_value_to_tag(attribute.value?)?)))
+        .collect()
+}
+
+/// Tags only hold scalars, so array, key value list and bytes attributes are
+/// dropped.
+fn any_value_to_tag(value: AnyValue) -> Option<String> {
+    match value.value? {
+        any_value::Value::StringValue(value) => Some(value),
+        any_value::Value::BoolValue(value) => Some(value.to_string()),
+        any_value::Value::IntValue(value) => Some(value.to_string()),
+        any_value::Value::DoubleValue(value) => Some(value.to_string()),
+        any_value::Value::ArrayValue(_)
+        | any_value::Value::KvlistValue(_)
+        | any_value::Value::BytesValue(_) => None,
+    }
+}
@@ -0,0 +1,128 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use prost::Message;
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+mod otlp_proto {
+    include!(concat!(env!("OUT_DIR"), "/otlp.trace.rs"));
+}
+
+use otlp_proto::{any_value, span::SpanKind, AnyValue, ExportTraceServiceRequest, KeyValue};
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "otlp_traces",
+        path!("api" / "v0.4" / "traces" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_otlp_traces,
+    )
+}
+
+/// Turns each span of an OTLP `ExportTraceServiceRequest` into a log, the
+/// identifiers being hex encoded.
+fn decode_otlp_traces(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let request = ExportTraceServiceRequest::decode(body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error decoding OTLP traces: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let mut events = Vec::new();
+    for resource_spans in request.resource_spans {
+        let resource = resource_spans
+            .resource
+            .map(|resource| attributes_to_map(resource.attributes));
+        for span in resource_spans
+            .scope_spans
+            .into_iter()
+            .flat_map(|scope_spans| scope_spans.spans)
+        {
+            let mut log = LogEvent::default();
+            log.insert_flat("trace_id", hex::encode(&span.trace_id));
+            log.insert_flat("span_id", hex::encode(&span.span_id));
+            if !span.parent_span_id.is_empty() {
+                log.insert_flat("parent_span_id", hex::encode(&span.parent_span_id));
+            }
+            log.insert_flat("name", span.name);
+            if let Some(kind) = SpanKind::from_i32(span.kind) {
+                log.insert_flat("kind", span_kind_name(kind));
+            }
+            log.insert_flat("start_time_unix_nano", span.start_time_unix_nano as i64);
+            log.insert_flat("end_time_unix_nano", span.end_time_unix_nano as i64);
+            log.insert_flat("attributes", attributes_to_map(span.attributes));
+            if let Some(resource) = resource.clone() {
+                log.insert_flat("resource", resource);
+            }
+            events.push(source.finish_log(log, now, &api_key));
+        }
+    }
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
+
+const fn span_kind_name(kind: SpanKind) -> &'static str {
+    match kind {
+        SpanKind::Unspecified => "unspecified",
+        SpanKind::Internal => "internal",
+        SpanKind::Server => "server",
+        SpanKind::Client => "client",
+        SpanKind::Producer => "producer",
+        SpanKind::Consumer => "consumer",
+    }
+}
+
+fn attributes_to_map(attributes: Vec<KeyValue>) -> BTreeMap<String, Value> {
+    attributes
+        .into_iter()
+        .map(|attribute| (attribute.key, any_value_to_value(attribute.value)))
+        .collect()
+}
+
+fn any_value_to_value(value: Option<AnyValue>) -> Value {
+    match value.and_then(|value| value.value) {
+        Some(any_value::Value::StringValue(value)) => value.into(),
+        Some(any_value::Value::BoolValue(value)) => value.into(),
+        Some(any_value::Value::IntValue(value)) => value.into(),
+        Some(any_value::Value::DoubleValue(value)) => value.into(),
+        Some(any_value::Value::ArrayValue(array)) => Value::Array(
+            array
+                .values
+                .into_iter()
+                .map(|value| any_value_to_value(Some(value)))
+                .collect(),
+        ),
+        Some(any_value::Value::KvlistValue(list)) => attributes_to_map(list.values).into(),
+        Some(any_value::Value::BytesValue(value)) => Value::Bytes(value.into()),
+        None => Value::Null,
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use prost::Message;
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent},
+    internal_events::{DatadogAgentProcessesReceived, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+mod dd_proto {
+    include!(concat!(env!("OUT_DIR"), "/datadog.process.rs"));
+}
+
+use dd_proto::{CollectorProcess, ProcessState};
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "collector",
+        path!("api" / "v1" / "collector" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_processes,
+    )
+}
+
+/// Turns each process of a `CollectorProcess` payload into a log.
+fn decode_processes(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let payload = CollectorProcess::decode(body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error decoding Datadog processes: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let events: Vec<Event> = payload
+        .processes
+        .into_iter()
+        .map(|process| {
+            let mut log = LogEvent::default();
+            log.insert_flat("hostname", payload.host_name.clone());
+            log.insert_flat("pid", process.pid);
+            let command = process.command.unwrap_or_default();
+            log.insert_flat("command", command.args.join(" "));
+            log.insert_flat("cmdline_list", command.args);
+            if let Some(user) = process.user {
+                log.insert_flat("user", user.name);
+            }
+            if let Some(memory) = process.memory {
+                log.insert("memory.rss", memory.rss as i64);
+            }
+            if let Some(cpu) = process.cpu {
+                log.insert("cpu.user_pct", cpu.user_pct as f64);
+            }
+            log.insert_flat("create_time", process.create_time);
+            if let Some(state) = ProcessState::from_i32(process.state) {
+                log.insert_flat("state", format!("{:?}", state));
+            }
+            source.finish_log(log, now, &api_key)
+        })
+        .collect();
+
+    emit!(&DatadogAgentProcessesReceived {
+        count: events.len()
+    });
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,174 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ProfileMetadata {
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    #[serde(default)]
+    pub tags_profiler: String,
+    pub family: String,
+    pub version: String,
+}
+
+/// A part of a `multipart/form-data` body.
+#[derive(Debug, PartialEq)]
+struct FormPart {
+    name: String,
+    data: Bytes,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "profile",
+        path!("api" / "v2" / "profile" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_profile,
+    )
+}
+
+/// Turns a profile upload into a single log holding the `event` metadata
+/// part, the attachments are base64 encoded into `profile_data`.
+fn decode_profile(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let parts = parse_form_data(&body).ok_or_else(|| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            "Error parsing multipart form data".to_owned(),
+        )
+    })?;
+
+    let mut metadata = None;
+    let mut profile_size_bytes = 0;
+    let mut profile_data = BTreeMap::new();
+    for part in parts {
+        if part.name == "event" {
+            let event: ProfileMetadata = serde_json::from_slice(&part.data).map_err(|error| {
+                ErrorMessage::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Error parsing JSON: {:?}", error),
+                )
+            })?;
+            metadata = Some(event);
+        } else {
+            profile_size_bytes += part.data.len();
+            profile_data.insert(part.name, Value::from(base64::encode(&part.data)));
+        }
+    }
+    let metadata = metadata.ok_or_else(|| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            "Missing profile \"event\" part".to_owned(),
+        )
+    })?;
+
+    let mut log = LogEvent::default();
+    log.insert_flat("start", metadata.start);
+    log.insert_flat("end", metadata.end);
+    log.insert_flat("family", metadata.family);
+    log.insert_flat("version", metadata.version);
+    log.insert_flat("attachments", metadata.attachments);
+    if source.parse_ddtags {
+        for (key, value) in parse_tags(metadata.tags_profiler.split(',')) {
+            match value {
+                Some(value) => log.try_insert_flat(key, value),
+                None => log.try_insert_flat(key, true),
+            }
+        }
+    } else {
+        log.insert_flat("tags_profiler", metadata.tags_profiler);
+    }
+    log.insert_flat("profile_size_bytes", profile_size_bytes as i64);
+    log.insert_flat("profile_data", profile_data);
+    let events = vec![source.finish_log(log, Utc::now(), &api_key)];
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
+
+/// Splits a `multipart/form-data` body into its parts.
+///
+/// The boundary is read from the first delimiter line of the body rather than
+/// from the `Content-Type` header, profilers don't send any preamble.
+fn parse_form_data(body: &[u8]) -> Option<Vec<FormPart>> {
+    let first_line = find(body, b"\r\n")?;
+    let delimiter = &body[..first_line];
+    if !delimiter.starts_with(b"--") || delimiter.len() == 2 {
+        return None;
+    }
+    let separator = [&b"\r\n"[..], delimiter].concat();
+
+    let mut parts = Vec::new();
+    let mut rest = &body[first_line + 2..];
+    loop {
+        let end = find(rest, &separator)?;
+        let (headers, data) = split_at_needle(&rest[..end], b"\r\n\r\n")?;
+        let name = form_part_name(headers)?;
+        parts.push(FormPart {
+            name,
+            data: Bytes::copy_from_slice(data),
+        });
+
+        rest = &rest[end + separator.len()..];
+        if rest.starts_with(b"--") {
+            return Some(parts);
+        }
+        rest = rest.strip_prefix(b"\r\n")?;
+    }
+}
+
+/// Extracts the `name` parameter of the `Content-Disposition` header of a part.
+fn form_part_name(headers: &[u8]) -> Option<String> {
+    let headers = std::str::from_utf8(headers).ok()?;
+    let disposition = headers.split("\r\n").find_map(|header| {
+        let (key, value) = header.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case("content-disposition")
+            .then(|| value)
+    })?;
+    disposition.split(';').find_map(|param| {
+        let (key, value) = param.trim().split_once('=')?;
+        (key == "name").then(|| value.trim_matches('"').to_owned())
+    })
+}
+
+fn split_at_needle<'a>(haystack: &'a [u8], needle: &[u8]) -> Option<(&'a [u8], &'a [u8])> {
+    let at = find(haystack, needle)?;
+    Some((&haystack[..at], &haystack[at + needle.len()..]))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,169 @@
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::{DatadogAgentParseError, DatadogAgentRumEventsReceived, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// The fields kept from every RUM event, as paths into the event.
+const COMMON_FIELDS: &[&str] = &[
+    "_dd.format_version",
+    "date",
+    "service",
+    "session.id",
+    "view.id",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RumEventType {
+    Error,
+    Action,
+    View,
+    Resource,
+    LongTask,
+}
+
+impl RumEventType {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Action => "action",
+            Self::View => "view",
+            Self::Resource => "resource",
+            Self::LongTask => "long_task",
+        }
+    }
+
+    /// The fields kept from events of this type besides the common ones.
+    const fn fields(self) -> &'static [&'static str] {
+        match self {
+            Self::Error => &["error.type", "error.message", "error.source", "error.stack"],
+            Self::Action => &["action.type", "action.target.name", "action.loading_time"],
+            Self::View => &[
+                "view.url",
+                "view.name",
+                "view.loading_time",
+                "view.time_spent",
+            ],
+            Self::Resource => &[
+                "resource.type",
+                "resource.url",
+                "resource.method",
+                "resource.status_code",
+                "resource.duration",
+            ],
+            Self::LongTask => &["long_task.duration"],
+        }
+    }
+}
+
+impl FromStr for RumEventType {
+    type Err = UnknownRumEventType;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "error" => Ok(Self::Error),
+            "action" => Ok(Self::Action),
+            "view" => Ok(Self::View),
+            "resource" => Ok(Self::Resource),
+            "long_task" => Ok(Self::LongTask),
+            _ => Err(UnknownRumEventType(kind.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UnknownRumEventType(String);
+
+impl fmt::Display for UnknownRumEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown RUM event type {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownRumEventType {}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "rum",
+        path!("api" / "v2" / "rum" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_rum,
+    )
+}
+
+/// Turns each RUM event into a log holding the common fields and those of
+/// its type, at the same paths as in the event.
+fn decode_rum(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let rum_events: Vec<serde_json::Map<String, serde_json::Value>> = serde_json::from_slice(&body)
+        .map_err(|error| {
+            ErrorMessage::new(
+                StatusCode::BAD_REQUEST,
+                format!("Error parsing JSON: {:?}", error),
+            )
+        })?;
+
+    let now = Utc::now();
+    let mut event_types = HashMap::new();
+    let events = rum_events
+        .into_iter()
+        .map(|rum_event| {
+            let rum_event = serde_json::Value::Object(rum_event);
+            let kind = rum_event["type"]
+                .as_str()
+                .unwrap_or_default()
+                .parse::<RumEventType>()
+                .map_err(|error| {
+                    emit!(&DatadogAgentParseError {
+                        endpoint: "rum",
+                        error: &error,
+                    });
+                    ErrorMessage::new(
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid RUM event: {}", error),
+                    )
+                })?;
+            *event_types.entry(kind.as_str().to_owned()).or_insert(0) += 1;
+
+            let mut log = LogEvent::default();
+            log.insert_flat("type", kind.as_str());
+            for path in COMMON_FIELDS.iter().chain(kind.fields()) {
+                if let Some(value) = rum_event.pointer(&format!("/{}", path.replace('.', "/"))) {
+                    log.insert(path, Value::from(value.clone()));
+                }
+            }
+            Ok(source.finish_log(log, now, &api_key))
+        })
+        .collect::<Result<Vec<_>, ErrorMessage>>()?;
+
+    emit!(&DatadogAgentRumEventsReceived {
+        count: events.len(),
+        event_types,
+    });
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    event::{Event, LogEvent},
+    internal_events::{DatadogAgentRuntimeSecurityEventReceived, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct RuntimeSecurityEvent {
+    pub rule_id: String,
+    pub event_type: String,
+    pub hostname: String,
+    pub timestamp: i64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub process: RuntimeSecurityProcess,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct RuntimeSecurityProcess {
+    pub name: String,
+    pub pid: i64,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "runtime_policy",
+        path!("api" / "v1" / "runtime_policy" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_runtime_security_events,
+    )
+}
+
+fn decode_runtime_security_events(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let violations: Vec<RuntimeSecurityEvent> = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let events: Vec<Event> = violations
+        .into_iter()
+        .map(|violation| {
+            let mut log = LogEvent::default();
+            log.insert_flat("rule_id", violation.rule_id);
+            log.insert_flat("event_type", violation.event_type);
+            log.insert_flat("hostname", violation.hostname);
+            log.insert_flat("timestamp", violation.timestamp);
+            log.insert("process.name", violation.process.name);
+            log.insert("process.pid", violation.process.pid);
+            if source.parse_ddtags {
+                for (key, value) in parse_tags(violation.tags.iter().map(String::as_str)) {
+                    match value {
+                        Some(value) => log.try_insert_flat(key, value),
+                        None => log.try_insert_flat(key, true),
+                    }
+                }
+            } else {
+                log.insert_flat("tags", violation.tags);
+            }
+            source.finish_log(log, now, &api_key)
+        })
+        .collect();
+
+    emit!(&DatadogAgentRuntimeSecurityEventReceived {
+        count: events.len()
+    });
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
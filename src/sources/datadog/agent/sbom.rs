@@ -0,0 +1,127 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event, LogEvent,
+    },
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SbomPayload {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub format: SbomFormat,
+    pub image_id: String,
+    #[serde(default)]
+    pub image_tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub components: Vec<SbomComponent>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SbomFormat {
+    CycloneDx,
+    Spdx,
+}
+
+impl SbomFormat {
+    const fn as_str(self) -> &'static str {
+        match self {
+            SbomFormat::CycloneDx => "cyclonedx",
+            SbomFormat::Spdx => "spdx",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SbomComponent {
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(rename = "type", default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub licenses: Vec<String>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "sbom",
+        path!("api" / "v2" / "sbom" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_sbom,
+    )
+}
+
+/// Summarizes the submission in a log, the components themselves are only
+/// counted as listing them would make for overly large events.
+fn decode_sbom(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let sbom: SbomPayload = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let component_count = sbom.components.len();
+    let mut tags = BTreeMap::new();
+    tags.insert("image_id".to_owned(), sbom.image_id.clone());
+    tags.insert("sbom_format".to_owned(), sbom.format.as_str().to_owned());
+    let mut metric = Metric::new(
+        "datadog.sbom.component_count",
+        MetricKind::Absolute,
+        MetricValue::Gauge {
+            value: component_count as f64,
+        },
+    )
+    .with_timestamp(Some(sbom.created_at))
+    .with_tags(Some(tags));
+    if let Some(k) = &api_key {
+        metric
+            .metadata_mut()
+            .set_datadog_api_key(Some(Arc::clone(k)));
+    }
+
+    let mut log = LogEvent::default();
+    log.insert_flat("id", sbom.id);
+    log.insert_flat("image_id", sbom.image_id);
+    log.insert_flat("image_tags", sbom.image_tags);
+    log.insert_flat("sbom_format", sbom.format.as_str());
+    log.insert_flat("component_count", component_count as i64);
+    let events = vec![
+        source.finish_log(log, sbom.created_at, &api_key),
+        metric.into(),
+    ];
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,53 @@
+//! JSON schemas of the payloads sent by the Datadog agent, used to reject
+//! malformed payloads before decoding them.
+
+use http::StatusCode;
+use jsonschema::JSONSchema;
+use once_cell::sync::Lazy;
+
+use crate::{internal_events::DatadogAgentSchemaValidationError, sources::util::ErrorMessage};
+
+pub(crate) static LOGS: Lazy<JSONSchema> = Lazy::new(|| compile(include_str!("schemas/logs.json")));
+
+pub(crate) static SERIES: Lazy<JSONSchema> =
+    Lazy::new(|| compile(include_str!("schemas/series.json")));
+
+pub(crate) static SECURITY_SIGNALS: Lazy<JSONSchema> =
+    Lazy::new(|| compile(include_str!("schemas/security_signals.json")));
+
+fn compile(schema: &str) -> JSONSchema {
+    let schema: serde_json::Value =
+        serde_json::from_str(schema).expect("bundled schema is valid JSON");
+    JSONSchema::compile(&schema).expect("bundled schema always compiles")
+}
+
+/// Validates the JSON `payload` received on `endpoint` against `schema`.
+pub(crate) fn validate(
+    endpoint: &'static str,
+    schema: &JSONSchema,
+    payload: &serde_json::Value,
+) -> Result<(), ErrorMessage> {
+    if let Err(errors) = schema.validate(payload) {
+        let errors = errors
+            .map(|error| {
+                let path = error.instance_path.to_string();
+                if path.is_empty() {
+                    error.to_string()
+                } else {
+                    format!("{}: {}", path, error)
+                }
+            })
+            .collect();
+        let event = DatadogAgentSchemaValidationError { endpoint, errors };
+        emit!(&event);
+        return Err(ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Payload failed schema validation: {}",
+                event.errors.join("; ")
+            ),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{schema, DatadogAgentSource};
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::{DatadogAgentSecuritySignalReceived, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SecuritySignal {
+    pub title: String,
+    pub severity: String,
+    pub timestamp: i64,
+    pub threat_type: String,
+    #[serde(default)]
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "security_signals",
+        path!("api" / "v1" / "security_analytics" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_security_signals,
+    )
+}
+
+fn decode_security_signals(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let signals: Vec<SecuritySignal> =
+        source.parse_json("security_signals", &schema::SECURITY_SIGNALS, &body)?;
+
+    let now = Utc::now();
+    let events: Vec<Event> = signals
+        .into_iter()
+        .map(|signal| {
+            let mut log = LogEvent::default();
+            if let Some(level) = severity_level(&signal.severity) {
+                log.insert_flat("severity_level", level);
+            }
+            log.insert_flat("title", signal.title);
+            log.insert_flat("severity", signal.severity);
+            log.insert_flat("timestamp", signal.timestamp);
+            log.insert_flat("threat_type", signal.threat_type);
+            log.insert_flat(
+                "attributes",
+                Value::from(serde_json::Value::Object(signal.attributes)),
+            );
+            log.insert_flat("tags", signal.tags);
+            source.finish_log(log, now, &api_key)
+        })
+        .collect();
+
+    emit!(&DatadogAgentSecuritySignalReceived {
+        count: events.len()
+    });
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
+
+/// Maps the signal severity to the matching syslog severity level.
+fn severity_level(severity: &str) -> Option<i64> {
+    match severity.to_ascii_lowercase().as_str() {
+        "critical" => Some(2),
+        "high" => Some(3),
+        "medium" => Some(4),
+        "low" => Some(5),
+        "info" => Some(6),
+        _ => None,
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SloCorrectionRequest {
+    pub data: SloCorrectionData,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SloCorrectionData {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub attributes: SloCorrectionAttributes,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SloCorrectionAttributes {
+    pub slo_id: String,
+    pub start: i64,
+    #[serde(default)]
+    pub end: Option<i64>,
+    pub category: String,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "slo_correction",
+        path!("api" / "v1" / "slo" / "correction" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_slo_correction,
+    )
+}
+
+fn decode_slo_correction(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let request: SloCorrectionRequest = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let attributes = request.data.attributes;
+    let mut log = LogEvent::default();
+    log.insert_flat("type", request.data.kind);
+    log.insert_flat("slo_id", attributes.slo_id);
+    log.insert_flat("start", attributes.start);
+    if let Some(end) = attributes.end {
+        log.insert_flat("end", end);
+    }
+    log.insert_flat("category", attributes.category);
+    if let Some(timezone) = attributes.timezone {
+        log.insert_flat("timezone", timezone);
+    }
+    if let Some(description) = attributes.description {
+        log.insert_flat("description", description);
+    }
+    let events = vec![source.finish_log(log, Utc::now(), &api_key)];
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,118 @@
+use std::{collections::BTreeMap, net::IpAddr, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::{DatadogAgentParseError, EventsReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SnmpTrapsPayload {
+    pub traps: Vec<SnmpTrap>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SnmpTrap {
+    pub oid: String,
+    #[serde(default)]
+    pub enterprise: Option<String>,
+    pub sender_ip: String,
+    #[serde(default)]
+    pub trap_type: Option<String>,
+    #[serde(default)]
+    pub variables: Vec<SnmpVariable>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SnmpVariable {
+    pub oid: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub value: serde_json::Value,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "snmp_traps",
+        path!("api" / "v1" / "snmp" / "traps" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_snmp_traps,
+    )
+}
+
+fn decode_snmp_traps(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let payload: SnmpTrapsPayload = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let events = payload
+        .traps
+        .into_iter()
+        .map(|trap| {
+            let sender_ip = trap.sender_ip.parse::<IpAddr>().map_err(|error| {
+                emit!(&DatadogAgentParseError {
+                    endpoint: "snmp_traps",
+                    error: &error,
+                });
+                ErrorMessage::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid sender IP {:?}: {}", trap.sender_ip, error),
+                )
+            })?;
+
+            let mut log = LogEvent::default();
+            log.insert_flat("oid", trap.oid);
+            if let Some(enterprise) = trap.enterprise {
+                log.insert_flat("enterprise", enterprise);
+            }
+            log.insert_flat("sender_ip", sender_ip.to_string());
+            if let Some(trap_type) = trap.trap_type {
+                log.insert_flat("trap_type", trap_type);
+            }
+            let variables = trap
+                .variables
+                .into_iter()
+                .map(|variable| {
+                    let mut object = BTreeMap::new();
+                    object.insert("oid".to_owned(), Value::from(variable.oid));
+                    object.insert("type".to_owned(), Value::from(variable.kind));
+                    object.insert("value".to_owned(), Value::from(variable.value));
+                    Value::from(object)
+                })
+                .collect::<Vec<_>>();
+            log.insert_flat("variables", variables);
+            Ok(source.finish_log(log, now, &api_key))
+        })
+        .collect::<Result<Vec<_>, ErrorMessage>>()?;
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,70 @@
+use std::{collections::BTreeMap, time::SystemTime};
+
+use serde::Serialize;
+use warp::{
+    filters::BoxedFilter,
+    path,
+    reply::{Reply, Response},
+    Filter,
+};
+
+use crate::{event::metric::MetricValue, metrics::Controller};
+
+#[derive(Serialize)]
+struct StatusReport {
+    status: &'static str,
+    version: String,
+    uptime_s: u64,
+    sources: BTreeMap<&'static str, SourceStatus>,
+}
+
+#[derive(Serialize)]
+struct SourceStatus {
+    events_received: u64,
+}
+
+/// Answers `GET /agent/status` as the Datadog Agent does, reporting on the
+/// source instead.
+pub(crate) fn build_warp_filter(
+    component_id: String,
+    started_at: SystemTime,
+) -> BoxedFilter<(Response,)> {
+    warp::get()
+        .and(path!("agent" / "status"))
+        .map(move || {
+            let report = StatusReport {
+                status: "running",
+                version: crate::vector_version().to_string(),
+                uptime_s: SystemTime::now()
+                    .duration_since(started_at)
+                    .unwrap_or_default()
+                    .as_secs(),
+                sources: BTreeMap::from([(
+                    "datadog_agent",
+                    SourceStatus {
+                        events_received: events_received(&component_id),
+                    },
+                )]),
+            };
+            warp::reply::json(&report).into_response()
+        })
+        .boxed()
+}
+
+/// Sums the `component_received_events_total` counters of the source, which
+/// are missing when internal metrics aren't recorded.
+fn events_received(component_id: &str) -> u64 {
+    Controller::get().map_or(0, |controller| {
+        controller
+            .capture_metrics()
+            .filter(|metric| {
+                metric.name() == "component_received_events_total"
+                    && metric.tag_value("component_id").as_deref() == Some(component_id)
+            })
+            .map(|metric| match metric.value() {
+                MetricValue::Counter { value } => *value as u64,
+                _ => 0,
+            })
+            .sum()
+    })
+}
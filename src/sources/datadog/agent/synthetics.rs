@@ -0,0 +1,109 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event, LogEvent,
+    },
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SyntheticsTestResult {
+    pub result_id: String,
+    pub test_public_id: String,
+    pub result: SyntheticsResultDetails,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SyntheticsResultDetails {
+    pub status: String,
+    pub passed: bool,
+    pub duration_ms: f64,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "synthetics",
+        path!("api" / "v1" / "synthetics" / "tests" / "results" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_synthetics_results,
+    )
+}
+
+/// Turns each test result into a duration gauge, failed tests are also
+/// reported as a log holding the error.
+fn decode_synthetics_results(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let results: Vec<SyntheticsTestResult> = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let now = Utc::now();
+    let mut events = Vec::with_capacity(results.len());
+    for test in results {
+        let tags = BTreeMap::from([
+            ("test_id".to_owned(), test.test_public_id.clone()),
+            ("status".to_owned(), test.result.status.clone()),
+            ("passed".to_owned(), test.result.passed.to_string()),
+        ]);
+        let mut metric = Metric::new(
+            "synthetics_test_duration_ms",
+            MetricKind::Absolute,
+            MetricValue::Gauge {
+                value: test.result.duration_ms,
+            },
+        )
+        .with_timestamp(Some(now))
+        .with_tags(Some(tags));
+        if let Some(k) = &api_key {
+            metric
+                .metadata_mut()
+                .set_datadog_api_key(Some(Arc::clone(k)));
+        }
+        events.push(metric.into());
+
+        if !test.result.passed {
+            let mut log = LogEvent::default();
+            log.insert_flat("result_id", test.result_id);
+            log.insert_flat("test_id", test.test_public_id);
+            log.insert_flat("status", test.result.status);
+            if let Some(error) = test.result.error {
+                log.insert_flat("error", error);
+            }
+            events.push(source.finish_log(log, now, &api_key));
+        }
+    }
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,147 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::DatadogAgentSource;
+use crate::{
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event, LogEvent, Value,
+    },
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// An entry of the telemetry sent by the Datadog Lambda Extension.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct TelemetryEntry {
+    #[serde(rename = "type")]
+    pub kind: TelemetryType,
+    pub time: DateTime<Utc>,
+    #[serde(default)]
+    pub record: serde_json::Value,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TelemetryType {
+    #[serde(rename = "report")]
+    Report,
+    #[serde(rename = "platform.start")]
+    PlatformStart,
+    #[serde(rename = "platform.end")]
+    PlatformEnd,
+    #[serde(rename = "function")]
+    Function,
+}
+
+impl TelemetryType {
+    const fn as_str(self) -> &'static str {
+        match self {
+            TelemetryType::Report => "report",
+            TelemetryType::PlatformStart => "platform.start",
+            TelemetryType::PlatformEnd => "platform.end",
+            TelemetryType::Function => "function",
+        }
+    }
+}
+
+/// The metrics of a report turned into gauges, by field of the record.
+const REPORT_METRICS: [(&str, &str); 3] = [
+    ("durationMs", "aws.lambda.duration_ms"),
+    ("billedDurationMs", "aws.lambda.billed_duration_ms"),
+    ("maxMemoryUsedMB", "aws.lambda.max_memory_used_mb"),
+];
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "lambda_telemetry",
+        path!("api" / "v1" / "telemetry" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_telemetry,
+    )
+}
+
+/// Turns each telemetry entry into a log with the fields of its record, along
+/// with gauges for the durations and memory usage of the reports.
+fn decode_telemetry(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let entries: Vec<TelemetryEntry> = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let mut events = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let metrics = match entry.kind {
+            TelemetryType::Report => report_metrics(&entry, &api_key),
+            _ => Vec::new(),
+        };
+
+        let mut log = LogEvent::default();
+        match entry.record {
+            serde_json::Value::Object(record) => {
+                for (key, value) in record {
+                    log.insert_flat(key, Value::from(value));
+                }
+            }
+            serde_json::Value::Null => {}
+            record => log.insert_flat("record", Value::from(record)),
+        }
+        log.insert_flat("type", entry.kind.as_str());
+        events.push(source.finish_log(log, entry.time, &api_key));
+        events.extend(metrics);
+    }
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
+
+fn report_metrics(entry: &TelemetryEntry, api_key: &Option<Arc<str>>) -> Vec<Event> {
+    let metrics = match entry.record.get("metrics") {
+        Some(metrics) => metrics,
+        None => return Vec::new(),
+    };
+    let tags = entry
+        .record
+        .get("requestId")
+        .and_then(serde_json::Value::as_str)
+        .map(|request_id| BTreeMap::from([("request_id".to_owned(), request_id.to_owned())]));
+
+    REPORT_METRICS
+        .iter()
+        .filter_map(|(field, name)| {
+            let value = metrics.get(field)?.as_f64()?;
+            let mut metric = Metric::new(*name, MetricKind::Absolute, MetricValue::Gauge { value })
+                .with_timestamp(Some(entry.time))
+                .with_tags(tags.clone());
+            if let Some(k) = api_key {
+                metric
+                    .metadata_mut()
+                    .set_datadog_api_key(Some(Arc::clone(k)));
+            }
+            Some(metric.into())
+        })
+        .collect()
+}
@@ -4,20 +4,24 @@ use crate::{
     common::datadog::{DatadogMetricType, DatadogPoint, DatadogSeriesMetric},
     config::{log_schema, SourceConfig, SourceContext},
     event::{
-        metric::{MetricKind, MetricSketch, MetricValue},
-        Event, EventStatus,
+        metric::{MetricKind, MetricSketch, MetricValue, StatisticKind},
+        Event, EventStatus, Value,
     },
+    metrics::Controller,
     serde::{default_decoding, default_framing_message_based},
-    test_util::{next_addr, spawn_collect_n, trace_init, wait_for_tcp},
+    test_util::{
+        components::init_test, next_addr, spawn_collect_n, temp_dir, trace_init, wait_for_tcp,
+    },
     SourceSender,
 };
 use bytes::Bytes;
 use chrono::{TimeZone, Utc};
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use http::HeaderMap;
 use pretty_assertions::assert_eq;
 use prost::Message;
 use quickcheck::{Arbitrary, Gen, QuickCheck, TestResult};
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
 use std::str;
 
@@ -25,6 +29,22 @@ mod dd_proto {
     include!(concat!(env!("OUT_DIR"), "/datadog.agentpayload.rs"));
 }
 
+mod dd_process_proto {
+    include!(concat!(env!("OUT_DIR"), "/datadog.process.rs"));
+}
+
+mod dd_dns_proto {
+    include!(concat!(env!("OUT_DIR"), "/datadog.dns.rs"));
+}
+
+mod otlp_proto {
+    include!(concat!(env!("OUT_DIR"), "/otlp.trace.rs"));
+}
+
+mod otlp_metrics_proto {
+    include!(concat!(env!("OUT_DIR"), "/otlp.metrics.rs"));
+}
+
 impl Arbitrary for LogMsg {
     fn arbitrary(g: &mut Gen) -> Self {
         LogMsg {
@@ -53,7 +73,7 @@ fn test_decode_log_body() {
             Box::new(BytesDecoder::new()),
             Box::new(BytesDeserializer::new()),
         );
-        let source = DatadogAgentSource::new(true, decoder, "http");
+        let source = DatadogAgentSource::new(true, false, false, &[], decoder, "http");
         let events = source.decode_log_body(body, api_key).unwrap();
         assert_eq!(events.len(), msgs.len());
         for (msg, event) in msgs.into_iter().zip(events.into_iter()) {
@@ -73,11 +93,73 @@ fn test_decode_log_body() {
     QuickCheck::new().quickcheck(inner as fn(Vec<LogMsg>) -> TestResult);
 }
 
+#[test]
+fn decode_log_body_parse_ddtags() {
+    let decoder = codecs::Decoder::new(
+        Box::new(BytesDecoder::new()),
+        Box::new(BytesDeserializer::new()),
+    );
+    let source = DatadogAgentSource::new(true, true, false, &[], decoder, "http");
+    let msgs = vec![LogMsg {
+        message: Bytes::from("foo"),
+        timestamp: 123,
+        hostname: Bytes::from("festeburg"),
+        status: Bytes::from("notice"),
+        service: Bytes::from("vector"),
+        ddsource: Bytes::from("curl"),
+        ddtags: Bytes::from("env:prod,version:1.0,region:us-east-1,url:http://foo,canary"),
+    }];
+    let body = Bytes::from(serde_json::to_string(&msgs).unwrap());
+
+    let events = source.decode_log_body(body, None).unwrap();
+    assert_eq!(events.len(), 1);
+
+    let log = events[0].as_log();
+    assert_eq!(log["message"], "foo".into());
+    assert_eq!(log["env"], "prod".into());
+    assert_eq!(log["version"], "1.0".into());
+    assert_eq!(log["region"], "us-east-1".into());
+    assert_eq!(log["url"], "http://foo".into());
+    assert_eq!(log["canary"], true.into());
+    assert!(log.get_flat("ddtags").is_none());
+}
+
 #[test]
 fn generate_config() {
     crate::test_util::test_generate_config::<DatadogAgentConfig>();
 }
 
+fn test_config(
+    acknowledgements: bool,
+    store_api_key: bool,
+    multiple_outputs: bool,
+) -> DatadogAgentConfig {
+    DatadogAgentConfig {
+        address: next_addr(),
+        tls: None,
+        store_api_key,
+        framing: default_framing_message_based(),
+        decoding: default_decoding(),
+        acknowledgements: acknowledgements.into(),
+        multiple_outputs,
+        parse_ddtags: false,
+        validate_schema: false,
+        fanout_api_keys: Vec::new(),
+        allowed_ips: None,
+        denied_ips: None,
+        behind_proxy: false,
+        trusted_proxy_count: 0,
+        flare_output_dir: None,
+        log_pipeline_check_pattern: None,
+        status_endpoint: false,
+        webhook_secret: None,
+        enrich_script: None,
+        acknowledge_updates: false,
+        external_metrics_provider: false,
+        simulate_delay: None,
+    }
+}
+
 async fn source(
     status: EventStatus,
     acknowledgements: bool,
@@ -88,31 +170,34 @@ async fn source(
     Option<impl Stream<Item = Event>>,
     Option<impl Stream<Item = Event>>,
     SocketAddr,
+) {
+    source_with_config(
+        status,
+        test_config(acknowledgements, store_api_key, multiple_outputs),
+    )
+    .await
+}
+
+async fn source_with_config(
+    status: EventStatus,
+    config: DatadogAgentConfig,
+) -> (
+    impl Stream<Item = Event>,
+    Option<impl Stream<Item = Event>>,
+    Option<impl Stream<Item = Event>>,
+    SocketAddr,
 ) {
     let (mut sender, recv) = SourceSender::new_test_finalize(status);
     let mut logs_output = None;
     let mut metrics_output = None;
-    if multiple_outputs {
+    if config.multiple_outputs {
         logs_output = Some(sender.add_outputs(status, "logs".to_string()));
         metrics_output = Some(sender.add_outputs(status, "metrics".to_string()));
     }
-    let address = next_addr();
+    let address = config.address;
     let context = SourceContext::new_test(sender);
     tokio::spawn(async move {
-        DatadogAgentConfig {
-            address,
-            tls: None,
-            store_api_key,
-            framing: default_framing_message_based(),
-            decoding: default_decoding(),
-            acknowledgements: acknowledgements.into(),
-            multiple_outputs,
-        }
-        .build(context)
-        .await
-        .unwrap()
-        .await
-        .unwrap();
+        config.build(context).await.unwrap().await.unwrap();
     });
     wait_for_tcp(address).await;
     (recv, logs_output, metrics_output, address)
@@ -130,6 +215,37 @@ async fn send_with_path(address: SocketAddr, body: &str, headers: HeaderMap, pat
         .as_u16()
 }
 
+/// The headers of a request sent with an API key.
+fn api_key_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "dd-api-key",
+        "12345678abcdefgh12345678abcdefgh".parse().unwrap(),
+    );
+    headers
+}
+
+/// Sends `body` to `path`, checking that it is accepted, and collects the
+/// `n` events it was decoded into from `rx`.
+async fn send_and_collect(
+    addr: SocketAddr,
+    body: &str,
+    headers: HeaderMap,
+    path: &'static str,
+    rx: impl Stream<Item = Event> + Unpin,
+    n: usize,
+) -> Vec<Event> {
+    let body = body.to_owned();
+    spawn_collect_n(
+        async move {
+            assert_eq!(200, send_with_path(addr, &body, headers, path).await);
+        },
+        rx,
+        n,
+    )
+    .await
+}
+
 #[tokio::test]
 async fn full_payload_v1() {
     trace_init();
@@ -893,3 +1009,2799 @@ async fn split_outputs() {
         );
     }
 }
+
+#[tokio::test]
+async fn decode_security_signals() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, true, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!([
+        {
+            "title": "Brute force attack",
+            "severity": "high",
+            "timestamp": 1542182950,
+            "threat_type": "credential_access",
+            "attributes": {"user": "root", "attempts": 12},
+            "tags": ["env:prod", "team:sec"]
+        },
+        {
+            "title": "New admin user",
+            "severity": "info",
+            "timestamp": 1542182951,
+            "threat_type": "persistence"
+        }
+    ]);
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v1/security_analytics",
+        rx,
+        2,
+    )
+    .await;
+
+    {
+        let log = events[0].as_log();
+        assert_eq!(log["title"], "Brute force attack".into());
+        assert_eq!(log["severity"], "high".into());
+        assert_eq!(log["severity_level"], 3.into());
+        assert_eq!(log["timestamp"], 1542182950.into());
+        assert_eq!(log["threat_type"], "credential_access".into());
+        assert_eq!(log["attributes.user"], "root".into());
+        assert_eq!(log["attributes.attempts"], 12.into());
+        assert_eq!(log["tags"], vec!["env:prod", "team:sec"].into());
+        assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+        assert_eq!(
+            &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+            "12345678abcdefgh12345678abcdefgh"
+        );
+
+        let log = events[1].as_log();
+        assert_eq!(log["title"], "New admin user".into());
+        assert_eq!(log["severity_level"], 6.into());
+        assert_eq!(
+            &events[1].metadata().datadog_api_key().as_ref().unwrap()[..],
+            "12345678abcdefgh12345678abcdefgh"
+        );
+    }
+}
+
+#[tokio::test]
+async fn decode_runtime_security_events() {
+    trace_init();
+    let (rx, _, _, addr) = source_with_config(
+        EventStatus::Delivered,
+        DatadogAgentConfig {
+            parse_ddtags: true,
+            ..test_config(true, true, false)
+        },
+    )
+    .await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!([{
+        "rule_id": "shell_in_container",
+        "event_type": "exec",
+        "hostname": "festeburg",
+        "timestamp": 1542182950,
+        "tags": ["env:prod", "critical"],
+        "process": {"name": "bash", "pid": 4242}
+    }]);
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v1/runtime_policy",
+        rx,
+        1,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["rule_id"], "shell_in_container".into());
+    assert_eq!(log["event_type"], "exec".into());
+    assert_eq!(log["hostname"], "festeburg".into());
+    assert_eq!(log["timestamp"], 1542182950.into());
+    assert_eq!(log["process.name"], "bash".into());
+    assert_eq!(log["process.pid"], 4242.into());
+    assert_eq!(log["env"], "prod".into());
+    assert_eq!(log["critical"], true.into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_synthetics_results() {
+    trace_init();
+    let (_, rx_logs, rx_metrics, addr) = source(EventStatus::Delivered, false, true, true).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!([
+        {
+            "result_id": "1",
+            "test_public_id": "abc-def-ghi",
+            "result": {"status": "success", "passed": true, "duration_ms": 120.5}
+        },
+        {
+            "result_id": "2",
+            "test_public_id": "jkl-mno-pqr",
+            "result": {
+                "status": "failure",
+                "passed": false,
+                "duration_ms": 3000.0,
+                "error": "Assertion on status code failed"
+            }
+        }
+    ]);
+
+    assert_eq!(
+        200,
+        send_with_path(
+            addr,
+            &body.to_string(),
+            headers,
+            "/api/v1/synthetics/tests/results"
+        )
+        .await
+    );
+
+    let metrics: Vec<Event> = rx_metrics.unwrap().take(2).collect().await;
+    {
+        let metric = metrics[0].as_metric();
+        assert_eq!(metric.name(), "synthetics_test_duration_ms");
+        assert_eq!(metric.value(), &MetricValue::Gauge { value: 120.5 });
+        assert_eq!(metric.tags().unwrap()["test_id"], "abc-def-ghi");
+        assert_eq!(metric.tags().unwrap()["status"], "success");
+        assert_eq!(metric.tags().unwrap()["passed"], "true");
+        assert_eq!(
+            &metrics[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+            "12345678abcdefgh12345678abcdefgh"
+        );
+
+        let metric = metrics[1].as_metric();
+        assert_eq!(metric.value(), &MetricValue::Gauge { value: 3000.0 });
+        assert_eq!(metric.tags().unwrap()["test_id"], "jkl-mno-pqr");
+        assert_eq!(metric.tags().unwrap()["passed"], "false");
+    }
+
+    let logs: Vec<Event> = rx_logs.unwrap().take(1).collect().await;
+    let log = logs[0].as_log();
+    assert_eq!(log["result_id"], "2".into());
+    assert_eq!(log["test_id"], "jkl-mno-pqr".into());
+    assert_eq!(log["status"], "failure".into());
+    assert_eq!(log["error"], "Assertion on status code failed".into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+}
+
+#[tokio::test]
+async fn decode_dbm_metrics() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "host": "db-1",
+        "timestamp": 1542182950123_i64,
+        "min_collection_interval": 10.0,
+        "tags": ["env:prod", "replica"],
+        "mysql_activity": [{
+            "query_signature": "94caeb4c54f97849",
+            "digest_text": "SELECT * FROM users WHERE id = ?",
+            "calls": 4,
+            "rows_sent": 20,
+            "lock_time": 1500.5
+        }],
+        "postgres_activity": [{
+            "query_signature": "e2b4a1b8d04ef17c",
+            "calls": 2,
+            "rows": 7
+        }]
+    });
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v2/dbm/metrics",
+        rx,
+        5,
+    )
+    .await;
+
+    let series: Vec<_> = events
+        .iter()
+        .map(|event| {
+            let metric = event.as_metric();
+            (
+                metric.namespace().unwrap(),
+                metric.name(),
+                metric.value().clone(),
+                metric.tags().unwrap()["dbms"].as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        series,
+        vec![
+            (
+                "94caeb4c54f97849",
+                "calls",
+                MetricValue::Counter { value: 4.0 },
+                "mysql"
+            ),
+            (
+                "94caeb4c54f97849",
+                "lock_time",
+                MetricValue::Counter { value: 1500.5 },
+                "mysql"
+            ),
+            (
+                "94caeb4c54f97849",
+                "rows_sent",
+                MetricValue::Counter { value: 20.0 },
+                "mysql"
+            ),
+            (
+                "e2b4a1b8d04ef17c",
+                "calls",
+                MetricValue::Counter { value: 2.0 },
+                "postgres"
+            ),
+            (
+                "e2b4a1b8d04ef17c",
+                "rows",
+                MetricValue::Counter { value: 7.0 },
+                "postgres"
+            ),
+        ]
+    );
+
+    let metric = events[0].as_metric();
+    assert_eq!(metric.kind(), MetricKind::Incremental);
+    assert_eq!(
+        metric.timestamp(),
+        Some(Utc.timestamp_millis(1542182950123))
+    );
+    let tags = metric.tags().unwrap();
+    assert_eq!(tags[log_schema().host_key()], "db-1");
+    assert_eq!(tags["env"], "prod");
+    assert_eq!(tags["replica"], "");
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_profile() {
+    trace_init();
+    let (rx, _, _, addr) = source_with_config(
+        EventStatus::Delivered,
+        DatadogAgentConfig {
+            parse_ddtags: true,
+            ..test_config(true, true, false)
+        },
+    )
+    .await;
+
+    let mut headers = api_key_headers();
+    headers.insert(
+        "content-type",
+        "multipart/form-data; boundary=7ab1c9".parse().unwrap(),
+    );
+
+    let metadata = serde_json::json!({
+        "start": "2022-02-14T10:00:00Z",
+        "end": "2022-02-14T10:01:00Z",
+        "attachments": ["cpu.pprof", "heap.pprof"],
+        "tags_profiler": "service:web,env:prod",
+        "family": "go",
+        "version": "4"
+    });
+    let body = format!(
+        "--7ab1c9\r\n\
+         Content-Disposition: form-data; name=\"event\"; filename=\"event.json\"\r\n\
+         Content-Type: application/json\r\n\r\n\
+         {}\r\n\
+         --7ab1c9\r\n\
+         Content-Disposition: form-data; name=\"cpu.pprof\"; filename=\"cpu.pprof\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n\
+         cpu-profile\r\n\
+         --7ab1c9\r\n\
+         Content-Disposition: form-data; name=\"heap.pprof\"; filename=\"heap.pprof\"\r\n\r\n\
+         heap\r\n\
+         --7ab1c9--\r\n",
+        metadata
+    );
+
+    let events = send_and_collect(addr, &body, headers, "/api/v2/profile", rx, 1).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["start"], "2022-02-14T10:00:00Z".into());
+    assert_eq!(log["end"], "2022-02-14T10:01:00Z".into());
+    assert_eq!(log["family"], "go".into());
+    assert_eq!(log["version"], "4".into());
+    assert_eq!(log["attachments"], vec!["cpu.pprof", "heap.pprof"].into());
+    assert_eq!(log["service"], "web".into());
+    assert_eq!(log["env"], "prod".into());
+    assert_eq!(log["profile_size_bytes"], 15.into());
+    assert_eq!(
+        log.get_flat("profile_data"),
+        Some(
+            &BTreeMap::from([
+                (
+                    "cpu.pprof".to_owned(),
+                    Value::from(base64::encode("cpu-profile"))
+                ),
+                ("heap.pprof".to_owned(), Value::from(base64::encode("heap"))),
+            ])
+            .into()
+        )
+    );
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_error_tracking_events() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!([{
+        "error": {
+            "message": "index out of range",
+            "type": "IndexError",
+            "stack": [
+                {"function": "handle", "file": "app.py", "line": 42},
+                {"function": "main", "file": "app.py", "line": 7}
+            ]
+        },
+        "service": "checkout",
+        "version": "1.2.3",
+        "environment": "prod",
+        "user": {"id": "u-123"},
+        "tags": ["team:payments"],
+        "timestamp": 1542182950
+    }]);
+
+    let events = send_and_collect(addr, &body.to_string(), headers, "/api/v2/errors", rx, 1).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["error.message"], "index out of range".into());
+    assert_eq!(log["error.type"], "IndexError".into());
+    assert_eq!(log["error.stack[0].function"], "handle".into());
+    assert_eq!(log["error.stack[0].line"], 42.into());
+    assert_eq!(log["error.stack[1].function"], "main".into());
+    assert_eq!(log["service"], "checkout".into());
+    assert_eq!(log["version"], "1.2.3".into());
+    assert_eq!(log["environment"], "prod".into());
+    assert_eq!(log["user.id"], "u-123".into());
+    assert_eq!(log["tags"], vec!["team:payments"].into());
+    assert_eq!(log["timestamp"], 1542182950.into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_ci_pipeline() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "ci_provider_name": "gitlab",
+        "pipeline_name": "deploy",
+        "pipeline_id": "4242",
+        "start": "2022-02-14T10:00:00Z",
+        "end": "2022-02-14T10:05:00Z",
+        "status": "success",
+        "git": {
+            "repository_url": "https://gitlab.com/acme/app.git",
+            "commit": {"sha": "1f2e3d4c"},
+            "author": {"email": "dev@acme.com"}
+        },
+        "stages": [
+            {"name": "build", "status": "success", "duration": 120},
+            {"name": "test", "status": "success", "duration": 180}
+        ]
+    });
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v2/ci/pipeline",
+        rx,
+        3,
+    )
+    .await;
+    assert_eq!(events.len(), 3);
+
+    let log = events[0].as_log();
+    assert_eq!(log["level"], "pipeline".into());
+    assert_eq!(log["ci_provider_name"], "gitlab".into());
+    assert_eq!(log["pipeline_name"], "deploy".into());
+    assert_eq!(log["pipeline_id"], "4242".into());
+    assert_eq!(log["start"], "2022-02-14T10:00:00Z".into());
+    assert_eq!(log["end"], "2022-02-14T10:05:00Z".into());
+    assert_eq!(log["status"], "success".into());
+    assert_eq!(
+        log["git.repository_url"],
+        "https://gitlab.com/acme/app.git".into()
+    );
+    assert_eq!(log["git.commit.sha"], "1f2e3d4c".into());
+    assert_eq!(log["git.author.email"], "dev@acme.com".into());
+    assert_eq!(log["stages[0].name"], "build".into());
+    assert_eq!(log["stages[1].duration"], 180.into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+
+    for (event, name) in events[1..].iter().zip(["build", "test"]) {
+        let log = event.as_log();
+        assert_eq!(log["level"], "stage".into());
+        assert_eq!(log["name"], name.into());
+        assert_eq!(log["status"], "success".into());
+        assert_eq!(log["pipeline_id"], "4242".into());
+        assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    }
+}
+
+#[tokio::test]
+async fn decode_slo_correction() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "data": {
+            "type": "correction",
+            "attributes": {
+                "slo_id": "sLbDYAqYi3fg",
+                "start": 1600000000,
+                "end": 1600003600,
+                "category": "Scheduled Maintenance",
+                "timezone": "UTC",
+                "description": "Database upgrade"
+            }
+        }
+    });
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v1/slo/correction",
+        rx,
+        1,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["type"], "correction".into());
+    assert_eq!(log["slo_id"], "sLbDYAqYi3fg".into());
+    assert_eq!(log["start"], 1600000000.into());
+    assert_eq!(log["end"], 1600003600.into());
+    assert_eq!(log["category"], "Scheduled Maintenance".into());
+    assert_eq!(log["timezone"], "UTC".into());
+    assert_eq!(log["description"], "Database upgrade".into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_processes() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let payload = dd_process_proto::CollectorProcess {
+        host_name: "a_host".to_string(),
+        processes: vec![dd_process_proto::Process {
+            key: 1,
+            pid: 4242,
+            command: Some(dd_process_proto::Command {
+                args: vec![
+                    "nginx".to_string(),
+                    "-g".to_string(),
+                    "daemon off;".to_string(),
+                ],
+                cwd: "/".to_string(),
+                ppid: 1,
+                exe: "/usr/sbin/nginx".to_string(),
+            }),
+            user: Some(dd_process_proto::ProcessUser {
+                name: "www-data".to_string(),
+                uid: 33,
+                gid: 33,
+            }),
+            memory: Some(dd_process_proto::MemoryStat {
+                rss: 10485760,
+                vms: 52428800,
+            }),
+            cpu: Some(dd_process_proto::CpuStat {
+                last_cpu: "cpu".to_string(),
+                total_pct: 3.5,
+                user_pct: 2.5,
+                system_pct: 1.0,
+            }),
+            create_time: 1542182950000,
+            state: dd_process_proto::ProcessState::S as i32,
+        }],
+        group_id: 1,
+        group_size: 1,
+    };
+    let mut buf = Vec::new();
+    payload.encode(&mut buf).unwrap();
+
+    let events = spawn_collect_n(
+        async move {
+            assert_eq!(
+                200,
+                send_with_path(
+                    addr,
+                    unsafe { str::from_utf8_unchecked(&buf) },
+                    headers,
+                    "/api/v1/collector"
+                )
+                .await
+            );
+        },
+        rx,
+        1,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["hostname"], "a_host".into());
+    assert_eq!(log["pid"], 4242.into());
+    assert_eq!(log["command"], "nginx -g daemon off;".into());
+    assert_eq!(
+        log["cmdline_list"],
+        vec!["nginx", "-g", "daemon off;"].into()
+    );
+    assert_eq!(log["user"], "www-data".into());
+    assert_eq!(log["memory.rss"], 10485760.into());
+    assert_eq!(log["cpu.user_pct"], 2.5.into());
+    assert_eq!(log["create_time"], 1542182950000_i64.into());
+    assert_eq!(log["state"], "S".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_host_metadata() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "hostname": "a_host",
+        "tags": ["env:prod", "role:db"],
+        "meta": {"socket-hostname": "a_host", "timezones": ["UTC"]},
+        "host_tags": {"system": ["env:prod"]},
+        "system_stats": {"cpu_cores": 8, "total_memory": 16777216, "platform": "linux"}
+    });
+
+    let events = send_and_collect(addr, &body.to_string(), headers, "/api/v1/hosts", rx, 1).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["hostname"], "a_host".into());
+    assert_eq!(log["tags"], vec!["env:prod", "role:db"].into());
+    assert_eq!(log["meta.timezones[0]"], "UTC".into());
+    assert_eq!(log["host_tags.system[0]"], "env:prod".into());
+    assert_eq!(log["system_stats.cpu_cores"], 8.into());
+    assert_eq!(log["system_stats.total_memory"], 16777216.into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_host_metadata_parses_tags() {
+    trace_init();
+    let (rx, _, _, addr) = source_with_config(
+        EventStatus::Delivered,
+        DatadogAgentConfig {
+            parse_ddtags: true,
+            ..test_config(true, true, false)
+        },
+    )
+    .await;
+
+    let body = serde_json::json!({
+        "hostname": "a_host",
+        "tags": ["env:prod", "critical"]
+    });
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        HeaderMap::new(),
+        "/api/v1/hosts",
+        rx,
+        1,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["env"], "prod".into());
+    assert_eq!(log["critical"], true.into());
+}
+
+#[tokio::test]
+async fn decode_watchdog_report() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "type": "anomaly",
+        "detected_at": 1640995200000i64,
+        "category": "apm",
+        "sub_type": "latency",
+        "tags": ["env:prod"],
+        "anomaly": {
+            "name": "High latency on checkout",
+            "start": 1640995100000i64,
+            "end": 1640995300000i64,
+            "message": "p99 latency increased by 300%"
+        },
+        "scope": [
+            {"name": "service", "value": "checkout"},
+            {"name": "env", "value": "prod"}
+        ]
+    });
+
+    let events =
+        send_and_collect(addr, &body.to_string(), headers, "/api/v1/watchdog", rx, 1).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["type"], "anomaly".into());
+    assert_eq!(log["detected_at"], 1640995200000i64.into());
+    assert_eq!(log["category"], "apm".into());
+    assert_eq!(log["sub_type"], "latency".into());
+    assert_eq!(log["tags"], vec!["env:prod"].into());
+    assert_eq!(log["anomaly_name"], "High latency on checkout".into());
+    assert_eq!(log["anomaly_start"], 1640995100000i64.into());
+    assert_eq!(log["anomaly_end"], 1640995300000i64.into());
+    assert_eq!(
+        log["anomaly_message"],
+        "p99 latency increased by 300%".into()
+    );
+    assert_eq!(log["scope.service"], "checkout".into());
+    assert_eq!(log["scope.env"], "prod".into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_snmp_traps() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "traps": [
+            {
+                "oid": "1.3.6.1.6.3.1.1.5.3",
+                "enterprise": "1.3.6.1.4.1.8072.3.2.10",
+                "sender_ip": "10.0.0.1",
+                "trap_type": "linkDown",
+                "variables": [
+                    {"oid": "1.3.6.1.2.1.2.2.1.1", "type": "integer", "value": 2},
+                    {"oid": "1.3.6.1.2.1.2.2.1.2", "type": "string", "value": "eth1"}
+                ]
+            },
+            {
+                "oid": "1.3.6.1.6.3.1.1.5.4",
+                "sender_ip": "fe80::1",
+                "trap_type": "linkUp",
+                "variables": []
+            }
+        ]
+    });
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v1/snmp/traps",
+        rx,
+        2,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["oid"], "1.3.6.1.6.3.1.1.5.3".into());
+    assert_eq!(log["enterprise"], "1.3.6.1.4.1.8072.3.2.10".into());
+    assert_eq!(log["sender_ip"], "10.0.0.1".into());
+    assert_eq!(log["trap_type"], "linkDown".into());
+    assert_eq!(log["variables[0].oid"], "1.3.6.1.2.1.2.2.1.1".into());
+    assert_eq!(log["variables[0].type"], "integer".into());
+    assert_eq!(log["variables[0].value"], 2.into());
+    assert_eq!(log["variables[1].value"], "eth1".into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+
+    let log = events[1].as_log();
+    assert_eq!(log["oid"], "1.3.6.1.6.3.1.1.5.4".into());
+    assert_eq!(log["sender_ip"], "fe80::1".into());
+    assert_eq!(log["trap_type"], "linkUp".into());
+}
+
+#[tokio::test]
+async fn rejects_snmp_traps_with_invalid_sender_ip() {
+    trace_init();
+    let (_rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let body = serde_json::json!({
+        "traps": [{"oid": "1.3.6.1.6.3.1.1.5.3", "sender_ip": "not-an-ip"}]
+    });
+
+    assert_eq!(
+        400,
+        send_with_path(
+            addr,
+            &body.to_string(),
+            HeaderMap::new(),
+            "/api/v1/snmp/traps"
+        )
+        .await
+    );
+}
+
+#[tokio::test]
+async fn decode_iot_metrics() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "h": "sensor-12",
+        "t": 1542182950,
+        "m": [
+            ["temperature", 21.5, ["room:kitchen", "floor:1"]],
+            ["humidity", 48, []]
+        ],
+        "s": "thermostat"
+    });
+
+    let events = send_and_collect(addr, &body.to_string(), headers, "/api/v1/iot", rx, 2).await;
+
+    let metric = events[0].as_metric();
+    assert_eq!(metric.name(), "temperature");
+    assert_eq!(metric.kind(), MetricKind::Absolute);
+    assert_eq!(metric.value(), &MetricValue::Gauge { value: 21.5 });
+    assert_eq!(metric.timestamp(), Some(Utc.timestamp(1542182950, 0)));
+    let tags = metric.tags().unwrap();
+    assert_eq!(tags[log_schema().host_key()], "sensor-12");
+    assert_eq!(tags["service"], "thermostat");
+    assert_eq!(tags["room"], "kitchen");
+    assert_eq!(tags["floor"], "1");
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+
+    let metric = events[1].as_metric();
+    assert_eq!(metric.name(), "humidity");
+    assert_eq!(metric.value(), &MetricValue::Gauge { value: 48.0 });
+    assert_eq!(metric.timestamp(), Some(Utc.timestamp(1542182950, 0)));
+    assert!(!metric.tags().unwrap().contains_key("room"));
+}
+
+#[tokio::test]
+async fn decode_dns_stats() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let payload = dd_dns_proto::DnsStats {
+        host_name: "a_host".to_string(),
+        stats: vec![dd_dns_proto::DnsQueryStats {
+            query_type: "A".to_string(),
+            name_server: "10.0.0.2".to_string(),
+            query_count: 12,
+            success_count: 9,
+            failure_count: 2,
+            timeout_count: 1,
+            tags: vec!["env:prod".to_string()],
+        }],
+    };
+    let mut buf = Vec::new();
+    payload.encode(&mut buf).unwrap();
+
+    let events = spawn_collect_n(
+        async move {
+            assert_eq!(
+                200,
+                send_with_path(
+                    addr,
+                    unsafe { str::from_utf8_unchecked(&buf) },
+                    headers,
+                    "/api/v0.2/dns"
+                )
+                .await
+            );
+        },
+        rx,
+        4,
+    )
+    .await;
+
+    let series: Vec<_> = events
+        .iter()
+        .map(|event| {
+            let metric = event.as_metric();
+            (metric.name(), metric.value().clone())
+        })
+        .collect();
+    assert_eq!(
+        series,
+        vec![
+            (
+                "datadog.dns.query_count",
+                MetricValue::Counter { value: 12.0 }
+            ),
+            (
+                "datadog.dns.success_count",
+                MetricValue::Counter { value: 9.0 }
+            ),
+            (
+                "datadog.dns.failure_count",
+                MetricValue::Counter { value: 2.0 }
+            ),
+            (
+                "datadog.dns.timeout_count",
+                MetricValue::Counter { value: 1.0 }
+            ),
+        ]
+    );
+
+    let metric = events[0].as_metric();
+    assert_eq!(metric.kind(), MetricKind::Incremental);
+    let tags = metric.tags().unwrap();
+    assert_eq!(tags["query_type"], "A");
+    assert_eq!(tags["name_server"], "10.0.0.2");
+    assert_eq!(tags[log_schema().host_key()], "a_host");
+    assert_eq!(tags["env"], "prod");
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_sbom() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "id": "4a3c5b1e-sbom",
+        "type": "cyclonedx",
+        "image_id": "sha256:0123456789abcdef",
+        "image_tags": ["nginx:1.21", "nginx:latest"],
+        "created_at": "2022-01-01T00:00:00Z",
+        "components": [
+            {"name": "openssl", "version": "1.1.1k", "type": "library", "licenses": ["OpenSSL"]},
+            {"name": "zlib", "version": "1.2.11", "type": "library", "licenses": ["Zlib"]},
+            {"name": "musl", "type": "library"}
+        ]
+    });
+
+    let events = send_and_collect(addr, &body.to_string(), headers, "/api/v2/sbom", rx, 2).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["image_id"], "sha256:0123456789abcdef".into());
+    assert_eq!(log["image_tags"], vec!["nginx:1.21", "nginx:latest"].into());
+    assert_eq!(log["sbom_format"], "cyclonedx".into());
+    assert_eq!(log["component_count"], 3.into());
+    assert!(!log.contains("components"));
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+
+    let metric = events[1].as_metric();
+    assert_eq!(metric.name(), "datadog.sbom.component_count");
+    assert_eq!(metric.kind(), MetricKind::Absolute);
+    assert_eq!(metric.value(), &MetricValue::Gauge { value: 3.0 });
+    assert_eq!(
+        metric.timestamp(),
+        Some(Utc.ymd(2022, 1, 1).and_hms(0, 0, 0))
+    );
+    let tags = metric.tags().unwrap();
+    assert_eq!(tags["image_id"], "sha256:0123456789abcdef");
+    assert_eq!(tags["sbom_format"], "cyclonedx");
+    assert_eq!(
+        &events[1].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_audit_events() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "data": [{
+            "id": "AAAAAYG-audit",
+            "type": "audit",
+            "attributes": {
+                "timestamp": 1640995200000i64,
+                "evt": {"name": "Request", "category": "Dashboard"},
+                "usr": {"id": "1234", "email": "jane@example.com"},
+                "http": {"url": "/api/v1/dashboard/abc", "method": "PATCH"}
+            }
+        }]
+    });
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v2/audit/events",
+        rx,
+        1,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["id"], "AAAAAYG-audit".into());
+    assert_eq!(log["type"], "audit".into());
+    assert_eq!(log["timestamp"], 1640995200000i64.into());
+    assert_eq!(log["evt_name"], "Request".into());
+    assert_eq!(log["evt_category"], "Dashboard".into());
+    assert_eq!(log["usr_id"], "1234".into());
+    assert_eq!(log["usr_email"], "jane@example.com".into());
+    assert_eq!(log["http_url"], "/api/v1/dashboard/abc".into());
+    assert_eq!(log["http_method"], "PATCH".into());
+    assert!(!log.contains("evt"));
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_flare() {
+    trace_init();
+    let output_dir = temp_dir();
+    std::fs::create_dir(&output_dir).unwrap();
+    let (rx, _, _, addr) = source_with_config(
+        EventStatus::Delivered,
+        DatadogAgentConfig {
+            flare_output_dir: Some(output_dir.clone()),
+            ..test_config(false, true, false)
+        },
+    )
+    .await;
+
+    let mut headers = api_key_headers();
+    headers.insert(
+        "content-type",
+        "multipart/form-data; boundary=flare-boundary"
+            .parse()
+            .unwrap(),
+    );
+
+    let body = [
+        "--flare-boundary\r\n",
+        "Content-Disposition: form-data; name=\"hostname\"\r\n\r\n",
+        "a-host\r\n",
+        "--flare-boundary\r\n",
+        "Content-Disposition: form-data; name=\"email\"\r\n\r\n",
+        "ops@example.com\r\n",
+        "--flare-boundary\r\n",
+        "Content-Disposition: form-data; name=\"case_id\"\r\n\r\n",
+        "12345\r\n",
+        "--flare-boundary\r\n",
+        "Content-Disposition: form-data; name=\"flare_file\"; filename=\"datadog-agent.zip\"\r\n",
+        "Content-Type: application/octet-stream\r\n\r\n",
+        "PK-not-really-a-zip\r\n",
+        "--flare-boundary--\r\n",
+    ]
+    .concat();
+
+    let events = send_and_collect(addr, &body, headers, "/api/v1/flare", rx, 1).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["hostname"], "a-host".into());
+    assert_eq!(log["email"], "ops@example.com".into());
+    assert_eq!(log["case_id"], "12345".into());
+    assert_eq!(log["flare_size_bytes"], 19.into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+    assert_eq!(
+        std::fs::read(output_dir.join("12345_a-host.zip")).unwrap(),
+        b"PK-not-really-a-zip"
+    );
+}
+
+#[tokio::test]
+async fn decode_otlp_traces() {
+    use otlp_proto::{any_value, AnyValue, KeyValue, KeyValueList};
+
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let attribute = |key: &str, value: any_value::Value| KeyValue {
+        key: key.to_string(),
+        value: Some(AnyValue { value: Some(value) }),
+    };
+    let request = otlp_proto::ExportTraceServiceRequest {
+        resource_spans: vec![otlp_proto::ResourceSpans {
+            resource: Some(otlp_proto::Resource {
+                attributes: vec![attribute(
+                    "service.name",
+                    any_value::Value::StringValue("checkout".to_string()),
+                )],
+            }),
+            scope_spans: vec![otlp_proto::ScopeSpans {
+                spans: vec![otlp_proto::Span {
+                    trace_id: vec![0x0a; 16],
+                    span_id: vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08],
+                    parent_span_id: vec![0xff; 8],
+                    name: "GET /cart".to_string(),
+                    kind: otlp_proto::span::SpanKind::Server as i32,
+                    start_time_unix_nano: 1_640_995_200_000_000_000,
+                    end_time_unix_nano: 1_640_995_200_250_000_000,
+                    attributes: vec![
+                        attribute(
+                            "http.method",
+                            any_value::Value::StringValue("GET".to_string()),
+                        ),
+                        attribute("http.status_code", any_value::Value::IntValue(200)),
+                        attribute(
+                            "peer",
+                            any_value::Value::KvlistValue(KeyValueList {
+                                values: vec![attribute(
+                                    "service",
+                                    any_value::Value::StringValue("cart".to_string()),
+                                )],
+                            }),
+                        ),
+                    ],
+                }],
+            }],
+        }],
+    };
+    let mut buf = Vec::new();
+    request.encode(&mut buf).unwrap();
+
+    let events = spawn_collect_n(
+        async move {
+            assert_eq!(
+                200,
+                send_with_path(
+                    addr,
+                    unsafe { str::from_utf8_unchecked(&buf) },
+                    headers,
+                    "/api/v0.4/traces"
+                )
+                .await
+            );
+        },
+        rx,
+        1,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["trace_id"], "0a".repeat(16).into());
+    assert_eq!(log["span_id"], "0102030405060708".into());
+    assert_eq!(log["parent_span_id"], "ffffffffffffffff".into());
+    assert_eq!(log["name"], "GET /cart".into());
+    assert_eq!(log["kind"], "server".into());
+    assert_eq!(
+        log["start_time_unix_nano"],
+        1_640_995_200_000_000_000i64.into()
+    );
+    assert_eq!(
+        log["end_time_unix_nano"],
+        1_640_995_200_250_000_000i64.into()
+    );
+    let attributes = match &log["attributes"] {
+        Value::Map(attributes) => attributes,
+        value => panic!("attributes should be a map, got {:?}", value),
+    };
+    assert_eq!(attributes["http.method"], "GET".into());
+    assert_eq!(attributes["http.status_code"], 200.into());
+    assert_eq!(
+        attributes["peer"],
+        Value::from(BTreeMap::from([(
+            "service".to_string(),
+            Value::from("cart")
+        )]))
+    );
+    let resource = match &log["resource"] {
+        Value::Map(resource) => resource,
+        value => panic!("resource should be a map, got {:?}", value),
+    };
+    assert_eq!(resource["service.name"], "checkout".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_distributions() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "series": [
+            {
+                "metric": "request.latency",
+                "type": "distribution",
+                "points": [[1_640_995_200, 0.25], [1_640_995_210, [0.5, 1.5]]],
+                "tags": ["service:web", "canary"],
+                "resources": [
+                    {"name": "a-host", "type": "host"},
+                    {"name": "us1", "type": "datacenter"},
+                    {"name": "web-1", "type": "pod"}
+                ]
+            }
+        ]
+    })
+    .to_string();
+
+    let events = send_and_collect(addr, &body, headers, "/api/v2/distributions", rx, 2).await;
+
+    let metric = events[0].as_metric();
+    assert_eq!(metric.name(), "request.latency");
+    assert_eq!(metric.kind(), MetricKind::Incremental);
+    assert_eq!(
+        metric.value(),
+        &MetricValue::Distribution {
+            samples: vector_core::samples![0.25 => 1],
+            statistic: StatisticKind::Histogram,
+        }
+    );
+    assert_eq!(
+        metric.timestamp(),
+        Some(Utc.ymd(2022, 1, 1).and_hms(0, 0, 0))
+    );
+    let tags = metric.tags().unwrap();
+    assert_eq!(tags["service"], "web");
+    assert_eq!(tags["canary"], "");
+    assert_eq!(tags[log_schema().host_key()], "a-host");
+    assert_eq!(tags["datacenter"], "us1");
+    assert!(!tags.contains_key("pod"));
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+
+    let metric = events[1].as_metric();
+    assert_eq!(
+        metric.value(),
+        &MetricValue::Distribution {
+            samples: vector_core::samples![0.5 => 1, 1.5 => 1],
+            statistic: StatisticKind::Histogram,
+        }
+    );
+    assert_eq!(
+        metric.timestamp(),
+        Some(Utc.ymd(2022, 1, 1).and_hms(0, 0, 10))
+    );
+}
+
+#[tokio::test]
+async fn decode_containers() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!([
+        {
+            "id": "3f4e1a",
+            "name": "web",
+            "image": "nginx:1.21",
+            "state": "running",
+            "status": "Up 2 hours",
+            "created": 1_640_995_200,
+            "cpu_limit": 0.5,
+            "memory_limit": 268_435_456,
+            "labels": {"app": "web", "team": "edge"},
+            "networks": [{"name": "bridge", "ip_address": "172.17.0.2"}]
+        },
+        {
+            "id": "9b2c7d",
+            "name": "worker",
+            "image": "worker:latest",
+            "state": "paused",
+            "status": "Paused",
+            "created": 1_640_995_300
+        },
+        {
+            "id": "c0ffee",
+            "name": "migrate",
+            "image": "migrate:1",
+            "state": "exited",
+            "status": "Exited (0) 5 minutes ago",
+            "created": 1_640_995_400,
+            "networks": [
+                {"name": "bridge", "ip_address": "172.17.0.3"},
+                {"name": "db", "ip_address": "10.0.0.3"}
+            ]
+        }
+    ])
+    .to_string();
+
+    let invalid_headers = headers.clone();
+    let events = send_and_collect(addr, &body, headers, "/api/v1/container/list", rx, 3).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["id"], "3f4e1a".into());
+    assert_eq!(log["name"], "web".into());
+    assert_eq!(log["image"], "nginx:1.21".into());
+    assert_eq!(log["state"], "running".into());
+    assert_eq!(log["status"], "Up 2 hours".into());
+    assert_eq!(log["created"], Utc.ymd(2022, 1, 1).and_hms(0, 0, 0).into());
+    assert_eq!(log["cpu_limit"], 0.5.into());
+    assert_eq!(log["memory_limit"], 268_435_456.into());
+    assert_eq!(log["labels.app"], "web".into());
+    assert_eq!(log["labels.team"], "edge".into());
+    assert_eq!(log["networks[0].name"], "bridge".into());
+    assert_eq!(log["networks[0].ip_address"], "172.17.0.2".into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+
+    let log = events[1].as_log();
+    assert_eq!(log["state"], "paused".into());
+    assert!(!log.contains("cpu_limit"));
+    assert_eq!(log["labels"], Value::from(BTreeMap::new()));
+    assert_eq!(log["networks"], Value::Array(Vec::new()));
+
+    let log = events[2].as_log();
+    assert_eq!(log["state"], "exited".into());
+    assert_eq!(log["networks[1].name"], "db".into());
+
+    let body = serde_json::json!([{
+        "id": "abc",
+        "name": "web",
+        "image": "nginx",
+        "state": "rebooting",
+        "status": "",
+        "created": 0
+    }])
+    .to_string();
+    assert_eq!(
+        400,
+        send_with_path(addr, &body, invalid_headers, "/api/v1/container/list").await
+    );
+}
+
+#[tokio::test]
+async fn decode_kubernetes_metadata() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!([
+        {
+            "resource_type": "pod",
+            "namespace": "default",
+            "name": "web-7d4b9c-x2x8p",
+            "uid": "6b1a5f0e-0000-4000-8000-000000000001",
+            "creation_timestamp": "2022-01-01T00:00:00Z",
+            "status_phase": "Running",
+            "labels": {"app": "web", "pod-template-hash": "7d4b9c"},
+            "annotations": {"kubernetes.io/psp": "restricted"}
+        },
+        {
+            "resource_type": "deployment",
+            "namespace": "default",
+            "name": "web",
+            "uid": "6b1a5f0e-0000-4000-8000-000000000002",
+            "creation_timestamp": "2021-12-31T12:00:00Z",
+            "labels": {"app": "web"}
+        }
+    ])
+    .to_string();
+
+    let events = send_and_collect(addr, &body, headers, "/api/v1/kubernetes_metadata", rx, 2).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["resource_type"], "pod".into());
+    assert_eq!(log["namespace"], "default".into());
+    assert_eq!(log["name"], "web-7d4b9c-x2x8p".into());
+    assert_eq!(log["uid"], "6b1a5f0e-0000-4000-8000-000000000001".into());
+    assert_eq!(
+        log["creation_timestamp"],
+        Utc.ymd(2022, 1, 1).and_hms(0, 0, 0).into()
+    );
+    assert_eq!(log["status_phase"], "Running".into());
+    let labels = log["labels"].as_map().unwrap();
+    assert_eq!(labels["app"], "web".into());
+    assert_eq!(labels["pod-template-hash"], "7d4b9c".into());
+    let annotations = log["annotations"].as_map().unwrap();
+    assert_eq!(annotations["kubernetes.io/psp"], "restricted".into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+
+    let log = events[1].as_log();
+    assert_eq!(log["resource_type"], "deployment".into());
+    assert_eq!(log["name"], "web".into());
+    assert_eq!(
+        log["creation_timestamp"],
+        Utc.ymd(2021, 12, 31).and_hms(12, 0, 0).into()
+    );
+    assert!(!log.contains("status_phase"));
+    assert_eq!(log["labels"].as_map().unwrap()["app"], "web".into());
+    assert_eq!(log["annotations"], Value::from(BTreeMap::new()));
+    assert_eq!(
+        &events[1].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn decode_otlp_metrics() {
+    use otlp_metrics_proto::{
+        any_value, metric::Data, number_data_point, AnyValue, Gauge, KeyValue, NumberDataPoint, Sum,
+    };
+
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let point = |value: number_data_point::Value| NumberDataPoint {
+        attributes: vec![],
+        start_time_unix_nano: 0,
+        time_unix_nano: 1_640_995_200_000_000_000,
+        value: Some(value),
+    };
+    let request = otlp_metrics_proto::ExportMetricsServiceRequest {
+        resource_metrics: vec![otlp_metrics_proto::ResourceMetrics {
+            resource: Some(otlp_metrics_proto::Resource {
+                attributes: vec![KeyValue {
+                    key: "service.name".to_string(),
+                    value: Some(AnyValue {
+                        value: Some(any_value::Value::StringValue("checkout".to_string())),
+                    }),
+                }],
+            }),
+            scope_metrics: vec![otlp_metrics_proto::ScopeMetrics {
+                metrics: vec![
+                    otlp_metrics_proto::Metric {
+                        name: "queue.depth".to_string(),
+                        description: String::new(),
+                        unit: String::new(),
+                        data: Some(Data::Gauge(Gauge {
+                            data_points: vec![point(number_data_point::Value::AsDouble(3.5))],
+                        })),
+                    },
+                    otlp_metrics_proto::Metric {
+                        name: "requests".to_string(),
+                        description: String::new(),
+                        unit: String::new(),
+                        data: Some(Data::Sum(Sum {
+                            data_points: vec![point(number_data_point::Value::AsInt(42))],
+                            aggregation_temporality:
+                                otlp_metrics_proto::AggregationTemporality::Cumulative as i32,
+                            is_monotonic: true,
+                        })),
+                    },
+                ],
+            }],
+        }],
+    };
+    let mut buf = Vec::new();
+    request.encode(&mut buf).unwrap();
+
+    let events = spawn_collect_n(
+        async move {
+            assert_eq!(
+                200,
+                send_with_path(
+                    addr,
+                    unsafe { str::from_utf8_unchecked(&buf) },
+                    headers,
+                    "/api/v0.4/metrics"
+                )
+                .await
+            );
+        },
+        rx,
+        2,
+    )
+    .await;
+
+    let gauge = events[0].as_metric();
+    assert_eq!(gauge.name(), "queue.depth");
+    assert_eq!(gauge.kind(), MetricKind::Absolute);
+    assert_eq!(*gauge.value(), MetricValue::Gauge { value: 3.5 });
+    assert_eq!(
+        gauge.timestamp(),
+        Some(Utc.ymd(2022, 1, 1).and_hms(0, 0, 0))
+    );
+    assert_eq!(
+        gauge.tag_value("service.name"),
+        Some("checkout".to_string())
+    );
+    assert_eq!(
+        &events[0].metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+
+    let counter = events[1].as_metric();
+    assert_eq!(counter.name(), "requests");
+    assert_eq!(counter.kind(), MetricKind::Absolute);
+    assert_eq!(*counter.value(), MetricValue::Counter { value: 42.0 });
+    assert_eq!(
+        counter.tag_value("service.name"),
+        Some("checkout".to_string())
+    );
+}
+
+#[tokio::test]
+async fn reports_status() {
+    trace_init();
+    let config = DatadogAgentConfig {
+        status_endpoint: true,
+        ..test_config(false, true, false)
+    };
+    let (_, _, _, addr) = source_with_config(EventStatus::Delivered, config).await;
+
+    let response = reqwest::Client::new()
+        .get(&format!("http://{}/agent/status", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["status"], "running");
+    assert_eq!(report["version"], crate::vector_version().to_string());
+    assert!(report["uptime_s"].is_u64());
+    assert!(report["sources"]["datadog_agent"]["events_received"].is_u64());
+}
+
+#[tokio::test]
+async fn provides_external_metrics() {
+    init_test();
+    let config = DatadogAgentConfig {
+        external_metrics_provider: true,
+        ..test_config(false, true, false)
+    };
+    let (_, _, _, addr) = source_with_config(EventStatus::Delivered, config).await;
+
+    metrics::gauge!("queue_depth", 42.0, "queue" => "orders");
+    metrics::gauge!("queue_depth", 7.0, "queue" => "payments");
+
+    let get = |query: &'static str| {
+        reqwest::Client::new()
+            .get(&format!(
+                "http://{}/api/v1/external_metrics?{}",
+                addr, query
+            ))
+            .send()
+    };
+
+    let response = get("metric=queue_depth&tag=queue:payments").await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let value: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(value["value"], 7.0);
+    assert!(value["timestamp"].is_i64());
+
+    let response = get("metric=queue_depth&tag=queue:shipping").await.unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = get("metric=missing").await.unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn verifies_webhook_signatures() {
+    use hmac::{Hmac, Mac, NewMac};
+
+    trace_init();
+    let config = DatadogAgentConfig {
+        webhook_secret: Some("s3cr3t".to_string()),
+        ..test_config(false, true, false)
+    };
+    let (rx, _, _, addr) = source_with_config(EventStatus::Delivered, config).await;
+
+    let body = r#"{"title":"Disk full","priority":"normal"}"#;
+    let signed = |signature: &str| {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-datadog-signature", signature.parse().unwrap());
+        headers.insert("x-b3-traceid", "463ac35c9f6413ad".parse().unwrap());
+        headers
+    };
+    let mut mac = Hmac::<sha1::Sha1>::new_from_slice(b"s3cr3t").unwrap();
+    mac.update(body.as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+
+    assert_eq!(
+        403,
+        send_with_path(addr, body, HeaderMap::new(), "/webhook").await
+    );
+    assert_eq!(
+        403,
+        send_with_path(addr, body, signed(&"00".repeat(20)), "/webhook").await
+    );
+
+    let events = spawn_collect_n(
+        async move {
+            assert_eq!(
+                200,
+                send_with_path(addr, body, signed(&signature), "/webhook").await
+            );
+        },
+        rx,
+        1,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["title"], "Disk full".into());
+    assert_eq!(log["priority"], "normal".into());
+    assert_eq!(
+        events[0].metadata().trace_id().as_deref(),
+        Some("463ac35c9f6413ad")
+    );
+}
+
+#[tokio::test]
+async fn decode_diagnose() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "diagnoses": [
+            {
+                "name": "connectivity-datadog-core-endpoints",
+                "result": "pass",
+                "diagnosis": "Successfully connected to the intake",
+            },
+            {
+                "name": "check-datadog-yaml",
+                "result": "fail",
+                "diagnosis": "Invalid api_key",
+                "remediation": "Set a valid api_key in datadog.yaml",
+            },
+        ],
+    });
+
+    let events =
+        send_and_collect(addr, &body.to_string(), headers, "/api/v1/diagnose", rx, 2).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["name"], "connectivity-datadog-core-endpoints".into());
+    assert_eq!(log["result"], "pass".into());
+    assert_eq!(log["severity_level"], 0.into());
+    assert!(log.get("remediation").is_none());
+
+    let log = events[1].as_log();
+    assert_eq!(log["name"], "check-datadog-yaml".into());
+    assert_eq!(log["result"], "fail".into());
+    assert_eq!(log["severity_level"], 2.into());
+    assert_eq!(
+        log["remediation"],
+        "Set a valid api_key in datadog.yaml".into()
+    );
+}
+
+#[tokio::test]
+async fn decode_rum() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let common = |kind: &str| {
+        serde_json::json!({
+            "_dd": { "format_version": 2 },
+            "type": kind,
+            "date": 1_640_995_200_000i64,
+            "service": "shop",
+            "session": { "id": "session-1" },
+            "view": { "id": "view-1", "url": "https://shop.example/cart" },
+        })
+    };
+    let with = |mut event: serde_json::Value, key: &str, value: serde_json::Value| {
+        event[key] = value;
+        event
+    };
+    let body = serde_json::json!([
+        with(
+            common("error"),
+            "error",
+            serde_json::json!({ "type": "TypeError", "message": "x is undefined", "source": "source" }),
+        ),
+        with(
+            common("action"),
+            "action",
+            serde_json::json!({ "type": "click", "target": { "name": "Checkout" } }),
+        ),
+        common("view"),
+        with(
+            common("resource"),
+            "resource",
+            serde_json::json!({ "type": "xhr", "url": "https://shop.example/api", "status_code": 200 }),
+        ),
+        with(
+            common("long_task"),
+            "long_task",
+            serde_json::json!({ "duration": 120_000_000 }),
+        ),
+    ]);
+
+    let events = send_and_collect(addr, &body.to_string(), headers, "/api/v2/rum", rx, 5).await;
+
+    for event in &events {
+        let log = event.as_log();
+        assert_eq!(log["_dd.format_version"], 2.into());
+        assert_eq!(log["date"], 1_640_995_200_000i64.into());
+        assert_eq!(log["service"], "shop".into());
+        assert_eq!(log["session.id"], "session-1".into());
+        assert_eq!(log["view.id"], "view-1".into());
+        assert_eq!(
+            &event.metadata().datadog_api_key().as_ref().unwrap()[..],
+            "12345678abcdefgh12345678abcdefgh"
+        );
+    }
+
+    let error = events[0].as_log();
+    assert_eq!(error["type"], "error".into());
+    assert_eq!(error["error.type"], "TypeError".into());
+    assert_eq!(error["error.message"], "x is undefined".into());
+    assert!(error.get("view.url").is_none());
+
+    let action = events[1].as_log();
+    assert_eq!(action["type"], "action".into());
+    assert_eq!(action["action.type"], "click".into());
+    assert_eq!(action["action.target.name"], "Checkout".into());
+
+    let view = events[2].as_log();
+    assert_eq!(view["type"], "view".into());
+    assert_eq!(view["view.url"], "https://shop.example/cart".into());
+
+    let resource = events[3].as_log();
+    assert_eq!(resource["type"], "resource".into());
+    assert_eq!(resource["resource.type"], "xhr".into());
+    assert_eq!(resource["resource.status_code"], 200.into());
+
+    let long_task = events[4].as_log();
+    assert_eq!(long_task["type"], "long_task".into());
+    assert_eq!(long_task["long_task.duration"], 120_000_000.into());
+}
+
+#[tokio::test]
+async fn decode_incidents() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "data": {
+            "type": "incidents",
+            "attributes": {
+                "title": "Checkout latency above SLO",
+                "severity": "SEV-2",
+                "status": "resolved",
+                "created": "2022-01-12T10:00:00Z",
+                "resolved": "2022-01-12T11:30:00Z",
+                "customer_impact_scope": "Checkout is slow for EU customers",
+            },
+            "relationships": {
+                "commander_user": {
+                    "data": {"type": "users", "id": "00000000-0000-0000-0000-000000000001"},
+                },
+            },
+        },
+    });
+
+    let events =
+        send_and_collect(addr, &body.to_string(), headers, "/api/v2/incidents", rx, 1).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["type"], "incidents".into());
+    assert_eq!(log["title"], "Checkout latency above SLO".into());
+    assert_eq!(log["severity"], "SEV-2".into());
+    assert_eq!(log["severity_level"], 2.into());
+    assert_eq!(log["status"], "resolved".into());
+    assert_eq!(log["created"], "2022-01-12T10:00:00Z".into());
+    assert_eq!(log["resolved"], "2022-01-12T11:30:00Z".into());
+    assert_eq!(
+        log["customer_impact_scope"],
+        "Checkout is slow for EU customers".into()
+    );
+    assert_eq!(
+        log["commander_user_id"],
+        "00000000-0000-0000-0000-000000000001".into()
+    );
+}
+
+#[tokio::test]
+async fn decode_fleet_report() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "hostname": "web-1",
+        "agent_version": "7.33.0",
+        "python_version": "3.8.11",
+        "os": "linux",
+        "flavor": "agent",
+        "tags": ["env:prod", "team:web"],
+        "config_hash": "5f3c1a",
+        "feature_flags": {
+            "remote_config": true,
+            "process_discovery": false,
+        },
+    });
+
+    let events = send_and_collect(addr, &body.to_string(), headers, "/api/v2/fleet", rx, 1).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["hostname"], "web-1".into());
+    assert_eq!(log["agent_version"], "7.33.0".into());
+    assert_eq!(log["os"], "linux".into());
+    assert_eq!(log["config_hash"], "5f3c1a".into());
+    assert_eq!(log["tags"], vec!["env:prod", "team:web"].into());
+    assert_eq!(log["feature_flags.remote_config"], true.into());
+    assert_eq!(log["feature_flags.process_discovery"], false.into());
+}
+
+#[tokio::test]
+async fn decode_apm_telemetry() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "api_version": "v1",
+        "seq_id": 7,
+        "runtime_id": "0a1b2c3d",
+        "service": "checkout",
+        "env": "prod",
+        "tracer_version": "0.58.0",
+        "language_name": "python",
+        "language_version": "3.9.7",
+        "payload": [
+            {
+                "request_type": "app-started",
+                "payload": {"configuration": [{"name": "DD_TRACE_ENABLED", "value": true}]},
+            },
+            {
+                "request_type": "app-heartbeat",
+            },
+        ],
+    });
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v2/apm/telemetry",
+        rx,
+        2,
+    )
+    .await;
+
+    for event in &events {
+        let log = event.as_log();
+        assert_eq!(log["service"], "checkout".into());
+        assert_eq!(log["env"], "prod".into());
+        assert_eq!(log["runtime_id"], "0a1b2c3d".into());
+        assert_eq!(log["tracer_version"], "0.58.0".into());
+    }
+
+    let log = events[0].as_log();
+    assert_eq!(log["request_type"], "app-started".into());
+    assert_eq!(
+        log["payload.configuration[0].name"],
+        "DD_TRACE_ENABLED".into()
+    );
+
+    let log = events[1].as_log();
+    assert_eq!(log["request_type"], "app-heartbeat".into());
+    assert!(log.get("payload").is_none());
+}
+
+#[tokio::test]
+async fn decode_agent_metadata() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "hostname": "db-1",
+        "timezones": ["UTC"],
+        "cpu_cores": 8,
+        "memory_total_mb": 32768,
+        "filesystems": [
+            {"mount": "/", "device": "/dev/sda1", "type": "ext4", "size_mb": 100, "used_mb": 25},
+            {"mount": "/data", "device": "/dev/sdb1", "type": "xfs", "size_mb": 400, "used_mb": 300},
+        ],
+        "network_interfaces": [
+            {"name": "eth0", "ipv4": "10.0.0.5", "mac": "02:42:ac:11:00:02"},
+        ],
+    });
+
+    let events =
+        send_and_collect(addr, &body.to_string(), headers, "/api/v1/metadata", rx, 3).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["hostname"], "db-1".into());
+    assert_eq!(log["timezones"], vec!["UTC"].into());
+    assert_eq!(log["cpu_cores"], 8.into());
+    assert_eq!(log["memory_total_mb"], 32768.into());
+    assert_eq!(log["filesystems[1].mount"], "/data".into());
+    assert_eq!(log["filesystems[1].type"], "xfs".into());
+    assert_eq!(log["filesystems[1].used_mb"], 300.into());
+    assert_eq!(log["network_interfaces[0].name"], "eth0".into());
+    assert_eq!(log["network_interfaces[0].ipv4"], "10.0.0.5".into());
+    assert!(log.get("network_interfaces[0].ipv6").is_none());
+
+    let utilization = events[1..]
+        .iter()
+        .map(|event| {
+            let metric = event.as_metric();
+            assert_eq!(metric.name(), "host.filesystem.utilization");
+            assert_eq!(metric.tag_value("host").as_deref(), Some("db-1"));
+            match metric.value() {
+                MetricValue::Gauge { value } => (metric.tag_value("mount").unwrap(), *value),
+                value => panic!("unexpected metric value {:?}", value),
+            }
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        utilization,
+        vec![("/".to_owned(), 0.25), ("/data".to_owned(), 0.75)]
+    );
+}
+
+#[tokio::test]
+async fn decode_cost_budget_alerts() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "data": {
+            "id": "budget-alert-1",
+            "type": "budget_alert",
+            "attributes": {
+                "alert_type": "forecasted",
+                "threshold_type": "percentage",
+                "threshold_value": 1000.0,
+                "actual_value": 850.0,
+                "currency": "USD",
+                "tags": ["team:storage", "env:prod"],
+                "created": "2022-03-01T12:00:00Z",
+            },
+        },
+    });
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v2/cost/budget/alerts",
+        rx,
+        2,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["id"], "budget-alert-1".into());
+    assert_eq!(log["type"], "budget_alert".into());
+    assert_eq!(log["alert_type"], "forecasted".into());
+    assert_eq!(log["threshold_type"], "percentage".into());
+    assert_eq!(log["threshold_value"], 1000.0.into());
+    assert_eq!(log["actual_value"], 850.0.into());
+    assert_eq!(log["currency"], "USD".into());
+    assert_eq!(log["created"], "2022-03-01T12:00:00Z".into());
+    assert_eq!(log["tags"], vec!["team:storage", "env:prod"].into());
+
+    let metric = events[1].as_metric();
+    assert_eq!(metric.name(), "datadog.cost.budget.utilization");
+    assert_eq!(metric.tag_value("team").as_deref(), Some("storage"));
+    assert_eq!(metric.tag_value("env").as_deref(), Some("prod"));
+    assert_eq!(metric.value(), &MetricValue::Gauge { value: 0.85 });
+}
+
+#[tokio::test]
+async fn decode_agent_info() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!({
+        "agent_version": "7.35.0",
+        "python_version": "3.8.11",
+        "checks": {
+            "cpu": {"status": "OK", "total_runs": 120},
+            "disk": {"status": "WARNING", "total_runs": 120},
+            "postgres": {"status": "ERROR", "last_error": "connection refused"},
+        },
+        "logs_agent": {"is_running": true},
+        "apm_agent": {"is_running": false},
+        "forwarder_status": {"transactions_success": 42},
+        "hostname": "agent-1",
+    });
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v1/agent_info",
+        rx,
+        4,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["agent_version"], "7.35.0".into());
+    assert_eq!(log["python_version"], "3.8.11".into());
+    assert_eq!(log["hostname"], "agent-1".into());
+    assert_eq!(log["checks.cpu.status"], "OK".into());
+    assert_eq!(log["checks.cpu.total_runs"], 120.into());
+    assert_eq!(
+        log["checks.postgres.last_error"],
+        "connection refused".into()
+    );
+    assert_eq!(log["logs_agent.is_running"], true.into());
+    assert_eq!(log["apm_agent.is_running"], false.into());
+    assert_eq!(log["forwarder_status.transactions_success"], 42.into());
+
+    let statuses = events[1..]
+        .iter()
+        .map(|event| {
+            let metric = event.as_metric();
+            assert_eq!(metric.name(), "datadog.agent.check.status");
+            match metric.value() {
+                MetricValue::Gauge { value } => (metric.tag_value("check_name").unwrap(), *value),
+                value => panic!("unexpected metric value {:?}", value),
+            }
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statuses,
+        vec![
+            ("cpu".to_owned(), 0.0),
+            ("disk".to_owned(), 1.0),
+            ("postgres".to_owned(), 2.0),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn decode_config_update() {
+    trace_init();
+    let config = DatadogAgentConfig {
+        acknowledge_updates: true,
+        ..test_config(false, true, false)
+    };
+    let (rx, _, _, addr) = source_with_config(EventStatus::Delivered, config).await;
+
+    let body = serde_json::json!({
+        "check_name": "postgres",
+        "config_updates": {
+            "min_collection_interval": 30,
+            "collect_function_metrics": true,
+        },
+        "api_key": "12345678abcdefgh12345678abcdefgh",
+    });
+
+    let events = spawn_collect_n(
+        async move {
+            let response = reqwest::Client::new()
+                .post(&format!("http://{}/api/v1/config/update", addr))
+                .body(body.to_string())
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status().as_u16(), 200);
+
+            let reply: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(reply, serde_json::json!({"status": "ok"}));
+        },
+        rx,
+        1,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["check_name"], "postgres".into());
+    assert_eq!(log["config_updates.min_collection_interval"], 30.into());
+    assert_eq!(log["config_updates.collect_function_metrics"], true.into());
+    assert_eq!(log["api_key"], "12345678abcdefgh12345678abcdefgh".into());
+}
+
+#[tokio::test]
+async fn decode_cluster_checks() {
+    trace_init();
+    let (rx, _, _, addr) = source_with_config(
+        EventStatus::Delivered,
+        DatadogAgentConfig {
+            parse_ddtags: true,
+            ..test_config(false, true, false)
+        },
+    )
+    .await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!([{
+        "check_name": "postgres",
+        "node_name": "node-1",
+        "result": {
+            "status": "OK",
+            "metrics": [{
+                "metric": "postgresql.connections",
+                "type": "gauge",
+                "interval": null,
+                "points": [[1_542_182_950, 12.0]],
+                "tags": ["db:orders"],
+                "host": "db-1",
+            }],
+            "service_checks": [{
+                "check": "postgres.can_connect",
+                "host_name": "db-1",
+                "timestamp": 1_542_182_950,
+                "status": 0,
+                "tags": ["db:orders"],
+            }],
+            "events": [{
+                "title": "Failover",
+                "text": "Replica promoted",
+                "timestamp": 1_542_182_950,
+                "alert_type": "warning",
+                "tags": ["db:orders"],
+            }],
+        },
+    }]);
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v1/cluster_checks",
+        rx,
+        3,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["check_name"], "postgres".into());
+    assert_eq!(log["node_name"], "node-1".into());
+    assert_eq!(log["status"], "OK".into());
+    assert_eq!(log["metric_count"], 1.into());
+    assert_eq!(log["event_count"], 1.into());
+    assert_eq!(
+        log["service_checks[0].check"],
+        "postgres.can_connect".into()
+    );
+
+    let metric = events[1].as_metric();
+    assert_eq!(metric.name(), "postgresql.connections");
+    assert_eq!(metric.value(), &MetricValue::Gauge { value: 12.0 });
+    assert_eq!(metric.tag_value("db").as_deref(), Some("orders"));
+    assert_eq!(metric.tag_value("host").as_deref(), Some("db-1"));
+    assert_eq!(
+        &metric.metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+
+    let log = events[2].as_log();
+    assert_eq!(log["title"], "Failover".into());
+    assert_eq!(log["text"], "Replica promoted".into());
+    assert_eq!(log["alert_type"], "warning".into());
+    assert_eq!(log["check_name"], "postgres".into());
+    assert_eq!(log["node_name"], "node-1".into());
+    assert_eq!(log["db"], "orders".into());
+    assert_eq!(
+        log["timestamp"],
+        Utc.ymd(2018, 11, 14).and_hms(8, 9, 10).into()
+    );
+}
+
+#[tokio::test]
+async fn decode_lambda_telemetry() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!([
+        {
+            "type": "platform.start",
+            "time": "2022-03-01T10:00:00Z",
+            "record": {"requestId": "req-1", "version": "$LATEST"},
+        },
+        {
+            "type": "function",
+            "time": "2022-03-01T10:00:01Z",
+            "record": "Processing order 42",
+        },
+        {
+            "type": "report",
+            "time": "2022-03-01T10:00:02Z",
+            "record": {
+                "requestId": "req-1",
+                "metrics": {
+                    "durationMs": 120.5,
+                    "billedDurationMs": 121.0,
+                    "memorySizeMB": 128,
+                    "maxMemoryUsedMB": 64,
+                    "initDurationMs": 210.3,
+                },
+            },
+        },
+    ]);
+
+    let events =
+        send_and_collect(addr, &body.to_string(), headers, "/api/v1/telemetry", rx, 6).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["type"], "platform.start".into());
+    assert_eq!(log["requestId"], "req-1".into());
+    assert_eq!(log["version"], "$LATEST".into());
+    assert_eq!(
+        log["timestamp"],
+        Utc.ymd(2022, 3, 1).and_hms(10, 0, 0).into()
+    );
+
+    let log = events[1].as_log();
+    assert_eq!(log["type"], "function".into());
+    assert_eq!(log["record"], "Processing order 42".into());
+
+    let log = events[2].as_log();
+    assert_eq!(log["type"], "report".into());
+    assert_eq!(log["requestId"], "req-1".into());
+    assert_eq!(log["metrics.durationMs"], 120.5.into());
+    assert_eq!(log["metrics.memorySizeMB"], 128.into());
+    assert_eq!(log["metrics.initDurationMs"], 210.3.into());
+
+    let gauges = events[3..]
+        .iter()
+        .map(|event| {
+            let metric = event.as_metric();
+            assert_eq!(metric.tag_value("request_id").as_deref(), Some("req-1"));
+            assert_eq!(
+                metric.timestamp(),
+                Some(Utc.ymd(2022, 3, 1).and_hms(10, 0, 2))
+            );
+            match metric.value() {
+                MetricValue::Gauge { value } => (metric.name(), *value),
+                value => panic!("unexpected metric value {:?}", value),
+            }
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        gauges,
+        vec![
+            ("aws.lambda.duration_ms", 120.5),
+            ("aws.lambda.billed_duration_ms", 121.0),
+            ("aws.lambda.max_memory_used_mb", 64.0),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn decode_container_runtime_events() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!([
+        {
+            "id": "abc123",
+            "type": "container",
+            "action": "start",
+            "status": "start",
+            "actor": {
+                "ID": "abc123",
+                "Attributes": {"name": "web", "image": "nginx:1.21"},
+            },
+            "time": 1_646_128_800,
+            "timeNano": 1_646_128_800_000_000_123i64,
+        },
+        {
+            "id": "def456",
+            "type": "container",
+            "action": "die",
+            "status": "die",
+            "actor": {
+                "ID": "def456",
+                "Attributes": {"name": "worker", "image": "redis:6", "exitCode": "137"},
+            },
+            "time": 1_646_128_860,
+        },
+    ]);
+
+    let events = send_and_collect(
+        addr,
+        &body.to_string(),
+        headers,
+        "/api/v1/container_runtime_events",
+        rx,
+        3,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["id"], "abc123".into());
+    assert_eq!(log["type"], "container".into());
+    assert_eq!(log["action"], "start".into());
+    assert_eq!(log["status"], "start".into());
+    assert_eq!(log["actor.id"], "abc123".into());
+    assert_eq!(log["actor.attributes.name"], "web".into());
+    assert_eq!(log["actor.attributes.image"], "nginx:1.21".into());
+    assert_eq!(
+        log["timestamp"],
+        Utc.timestamp_nanos(1_646_128_800_000_000_123).into()
+    );
+
+    let log = events[1].as_log();
+    assert_eq!(log["action"], "die".into());
+    assert_eq!(log["actor.attributes.exitCode"], "137".into());
+    assert_eq!(log["timestamp"], Utc.timestamp(1_646_128_860, 0).into());
+
+    let metric = events[2].as_metric();
+    assert_eq!(metric.name(), "container.exit_code");
+    assert_eq!(metric.value(), &MetricValue::Gauge { value: 137.0 });
+    assert_eq!(metric.tag_value("container_id").as_deref(), Some("def456"));
+    assert_eq!(metric.tag_value("image").as_deref(), Some("redis:6"));
+    assert_eq!(metric.timestamp(), Some(Utc.timestamp(1_646_128_860, 0)));
+    assert_eq!(
+        &metric.metadata().datadog_api_key().as_ref().unwrap()[..],
+        "12345678abcdefgh12345678abcdefgh"
+    );
+}
+
+#[tokio::test]
+async fn enriches_events_with_script() {
+    trace_init();
+    let config = DatadogAgentConfig {
+        enrich_script: Some(r#".enriched_by = "vector""#.to_string()),
+        ..test_config(false, true, false)
+    };
+    let (rx, _, _, addr) = source_with_config(EventStatus::Delivered, config).await;
+
+    let events = spawn_collect_n(
+        async move {
+            assert_eq!(
+                200,
+                send_with_path(
+                    addr,
+                    &serde_json::to_string(&[LogMsg {
+                        message: Bytes::from("foo"),
+                        timestamp: 123,
+                        hostname: Bytes::from("festeburg"),
+                        status: Bytes::from("notice"),
+                        service: Bytes::from("vector"),
+                        ddsource: Bytes::from("curl"),
+                        ddtags: Bytes::from("one,two,three"),
+                    }])
+                    .unwrap(),
+                    HeaderMap::new(),
+                    "/v1/input/"
+                )
+                .await
+            );
+        },
+        rx,
+        1,
+    )
+    .await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["message"], "foo".into());
+    assert_eq!(log["enriched_by"], "vector".into());
+}
+
+#[tokio::test]
+async fn routes_log_pipeline_checks() {
+    trace_init();
+    let (mut sender, mut rx) = SourceSender::new_test_finalize(EventStatus::Delivered);
+    let checks = sender.add_outputs(EventStatus::Delivered, "log_pipeline_checks".to_string());
+    let config = DatadogAgentConfig {
+        log_pipeline_check_pattern: Some(r"^datadog\.logs\.".to_string()),
+        ..test_config(false, true, false)
+    };
+    let addr = config.address;
+    let context = SourceContext::new_test(sender);
+    tokio::spawn(async move {
+        config.build(context).await.unwrap().await.unwrap();
+    });
+    wait_for_tcp(addr).await;
+
+    let headers = api_key_headers();
+
+    let body = serde_json::json!([
+        {
+            "check": "datadog.logs.agent",
+            "host_name": "a-host",
+            "timestamp": 1_600_000_000,
+            "status": 0,
+            "tags": ["pipeline:main"]
+        },
+        {
+            "check": "ntp.in_sync",
+            "host_name": "a-host",
+            "timestamp": 1_600_000_000,
+            "status": 1,
+            "message": "Offset too large"
+        },
+        {
+            "check": "datadog.logs.agent",
+            "host_name": "another-host",
+            "timestamp": 1_600_000_010,
+            "status": 2,
+            "message": "Sender is blocked"
+        }
+    ])
+    .to_string();
+
+    let events = send_and_collect(addr, &body, headers, "/api/v1/check_run", checks, 2).await;
+
+    let log = events[0].as_log();
+    assert_eq!(log["check"], "datadog.logs.agent".into());
+    assert_eq!(log["host"], "a-host".into());
+    assert_eq!(log["status"], "ok".into());
+    assert_eq!(log["tags"], vec!["pipeline:main"].into());
+    assert_eq!(
+        log[log_schema().timestamp_key()],
+        Utc.timestamp(1_600_000_000, 0).into()
+    );
+    let log = events[1].as_log();
+    assert_eq!(log["host"], "another-host".into());
+    assert_eq!(log["status"], "critical".into());
+    assert_eq!(log["message"], "Sender is blocked".into());
+
+    let event = rx.next().await.unwrap();
+    let log = event.as_log();
+    assert_eq!(log["check"], "ntp.in_sync".into());
+    assert_eq!(log["status"], "warning".into());
+    assert_eq!(log["message"], "Offset too large".into());
+    assert_eq!(log[log_schema().source_type_key()], "datadog_agent".into());
+}
+
+#[tokio::test]
+async fn denies_requests_outside_allowed_ips() {
+    trace_init();
+    let (rx, _, _, addr) = source_with_config(
+        EventStatus::Delivered,
+        DatadogAgentConfig {
+            allowed_ips: Some(vec!["127.0.0.1/32".parse().unwrap()]),
+            behind_proxy: true,
+            ..test_config(false, true, false)
+        },
+    )
+    .await;
+
+    let body = serde_json::json!({"hostname": "a_host"}).to_string();
+    let events = spawn_collect_n(
+        async move {
+            assert_eq!(
+                200,
+                send_with_path(addr, &body, HeaderMap::new(), "/api/v1/hosts").await
+            );
+
+            let mut headers = HeaderMap::new();
+            headers.insert("x-forwarded-for", "10.0.0.1".parse().unwrap());
+            assert_eq!(
+                403,
+                send_with_path(addr, &body, headers, "/api/v1/hosts").await
+            );
+
+            // Only the entry appended by the proxy can be trusted.
+            let mut headers = HeaderMap::new();
+            headers.insert("x-forwarded-for", "127.0.0.1, 10.0.0.1".parse().unwrap());
+            assert_eq!(
+                403,
+                send_with_path(addr, &body, headers, "/api/v1/hosts").await
+            );
+        },
+        rx,
+        1,
+    )
+    .await;
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].as_log()["hostname"], "a_host".into());
+}
+
+#[tokio::test]
+async fn extracts_client_ip_behind_trusted_proxies() {
+    trace_init();
+    let forwarded_for = "203.0.113.1, 198.51.100.2, 192.0.2.3";
+    for (trusted_proxy_count, client_ip) in [(1, "198.51.100.2"), (2, "203.0.113.1")] {
+        let (rx, _, _, addr) = source_with_config(
+            EventStatus::Delivered,
+            DatadogAgentConfig {
+                trusted_proxy_count,
+                ..test_config(false, true, false)
+            },
+        )
+        .await;
+
+        let body = serde_json::json!({"hostname": "a_host"}).to_string();
+        let events = spawn_collect_n(
+            async move {
+                let mut headers = HeaderMap::new();
+                headers.insert("x-forwarded-for", forwarded_for.parse().unwrap());
+                assert_eq!(
+                    200,
+                    send_with_path(addr, &body, headers, "/api/v1/hosts").await
+                );
+            },
+            rx,
+            1,
+        )
+        .await;
+
+        assert_eq!(
+            *events[0].metadata().client_ip(),
+            Some(client_ip.parse().unwrap())
+        );
+    }
+
+    // Not enough entries for the proxies to be trusted.
+    let (_rx, _, _, addr) = source_with_config(
+        EventStatus::Delivered,
+        DatadogAgentConfig {
+            trusted_proxy_count: 3,
+            ..test_config(false, true, false)
+        },
+    )
+    .await;
+    let body = serde_json::json!({"hostname": "a_host"}).to_string();
+    let mut headers = HeaderMap::new();
+    headers.insert("x-forwarded-for", forwarded_for.parse().unwrap());
+    assert_eq!(
+        403,
+        send_with_path(addr, &body, headers, "/api/v1/hosts").await
+    );
+}
+
+#[tokio::test]
+async fn fanout_api_keys() {
+    trace_init();
+    let (rx, _, _, addr) = source_with_config(
+        EventStatus::Delivered,
+        DatadogAgentConfig {
+            fanout_api_keys: vec![
+                "12345678abcdefgh12345678abcdefgh".to_owned(),
+                "abcdefgh12345678abcdefgh12345678".to_owned(),
+            ],
+            ..test_config(true, true, false)
+        },
+    )
+    .await;
+
+    let events = spawn_collect_n(
+        async move {
+            assert_eq!(
+                200,
+                send_with_path(
+                    addr,
+                    &serde_json::to_string(&[LogMsg {
+                        message: Bytes::from("foo"),
+                        timestamp: 123,
+                        hostname: Bytes::from("festeburg"),
+                        status: Bytes::from("notice"),
+                        service: Bytes::from("vector"),
+                        ddsource: Bytes::from("curl"),
+                        ddtags: Bytes::from("one,two,three"),
+                    }])
+                    .unwrap(),
+                    HeaderMap::new(),
+                    "/v1/input/"
+                )
+                .await
+            );
+        },
+        rx,
+        2,
+    )
+    .await;
+
+    for (event, api_key) in events.iter().zip([
+        "12345678abcdefgh12345678abcdefgh",
+        "abcdefgh12345678abcdefgh12345678",
+    ]) {
+        assert_eq!(event.as_log()["message"], "foo".into());
+        assert_eq!(
+            &event.metadata().datadog_api_key().as_ref().unwrap()[..],
+            api_key
+        );
+    }
+}
+
+#[tokio::test]
+async fn gzip_compressed_response() {
+    use std::io::Read;
+
+    trace_init();
+    let (_rx, _, _, addr) = source(EventStatus::Delivered, true, true, false).await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("http://{}/v1/input/", addr))
+        .header("accept-encoding", "deflate, gzip;q=0.8")
+        .body("{not json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(400, response.status().as_u16());
+    assert_eq!(
+        "gzip",
+        response.headers()["content-encoding"].to_str().unwrap()
+    );
+
+    let body = response.bytes().await.unwrap();
+    let mut decoded = String::new();
+    flate2::read::GzDecoder::new(&body[..])
+        .read_to_string(&mut decoded)
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_str(&decoded).unwrap();
+    assert_eq!(json["code"], 400);
+    assert!(json["message"]
+        .as_str()
+        .unwrap()
+        .contains("Error parsing JSON"));
+}
+
+#[tokio::test]
+async fn uncompressed_response_without_accept_encoding() {
+    trace_init();
+    let (_rx, _, _, addr) = source(EventStatus::Delivered, true, true, false).await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("http://{}/v1/input/", addr))
+        .body("{not json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(400, response.status().as_u16());
+    assert!(response.headers().get("content-encoding").is_none());
+
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["code"], 400);
+}
+
+#[tokio::test]
+async fn schema_validation_rejects_malformed_payload() {
+    trace_init();
+    let (_rx, _, _, addr) = source_with_config(
+        EventStatus::Delivered,
+        DatadogAgentConfig {
+            validate_schema: true,
+            ..test_config(true, true, false)
+        },
+    )
+    .await;
+
+    let body = serde_json::json!([
+        {
+            "title": "Brute force attack",
+            "severity": "high",
+            "timestamp": 1542182950
+        }
+    ]);
+    let response = reqwest::Client::new()
+        .post(&format!("http://{}/api/v1/security_analytics", addr))
+        .body(body.to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(400, response.status().as_u16());
+
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["code"], 400);
+    let message = json["message"].as_str().unwrap();
+    assert!(message.starts_with("Payload failed schema validation"));
+    assert!(message.contains("\"threat_type\" is a required property"));
+}
+
+#[tokio::test]
+async fn propagates_b3_trace_context() {
+    trace_init();
+    let (rx, _, _, addr) = source(EventStatus::Delivered, true, true, false).await;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-b3-traceid", "463ac35c9f6413ad".parse().unwrap());
+    headers.insert("x-b3-spanid", "a2fb4a1d1a96d312".parse().unwrap());
+
+    let events = spawn_collect_n(
+        async move {
+            assert_eq!(
+                200,
+                send_with_path(
+                    addr,
+                    &serde_json::to_string(&[LogMsg {
+                        message: Bytes::from("foo"),
+                        timestamp: 123,
+                        hostname: Bytes::from("festeburg"),
+                        status: Bytes::from("notice"),
+                        service: Bytes::from("vector"),
+                        ddsource: Bytes::from("curl"),
+                        ddtags: Bytes::from("one,two,three"),
+                    }])
+                    .unwrap(),
+                    headers,
+                    "/v1/input/"
+                )
+                .await
+            );
+        },
+        rx,
+        1,
+    )
+    .await;
+
+    let metadata = events[0].metadata();
+    assert_eq!(metadata.trace_id().as_deref(), Some("463ac35c9f6413ad"));
+    assert_eq!(metadata.span_id().as_deref(), Some("a2fb4a1d1a96d312"));
+    assert_eq!(metadata.parent_span_id(), &None);
+}
+
+#[tokio::test]
+async fn simulate_delay_errors() {
+    trace_init();
+    let (rx, _, _, addr) = source_with_config(
+        EventStatus::Delivered,
+        DatadogAgentConfig {
+            simulate_delay: Some(
+                toml::from_str("min_ms = 10\nmax_ms = 20\nerror_rate = 1.0").unwrap(),
+            ),
+            ..test_config(false, true, false)
+        },
+    )
+    .await;
+
+    for _ in 0..3 {
+        assert_eq!(
+            503,
+            send_with_path(
+                addr,
+                &serde_json::to_string(&[LogMsg {
+                    message: Bytes::from("foo"),
+                    timestamp: 123,
+                    hostname: Bytes::from("festeburg"),
+                    status: Bytes::from("notice"),
+                    service: Bytes::from("vector"),
+                    ddsource: Bytes::from("curl"),
+                    ddtags: Bytes::from("one,two,three"),
+                }])
+                .unwrap(),
+                HeaderMap::new(),
+                "/v1/input/"
+            )
+            .await
+        );
+    }
+
+    // Failed requests never send their events downstream.
+    assert!(futures::FutureExt::now_or_never(Box::pin(rx).next()).is_none());
+}
+
+#[tokio::test]
+async fn simulate_delay_rejects_invalid_error_rate() {
+    let config = DatadogAgentConfig {
+        simulate_delay: Some(toml::from_str("min_ms = 10\nmax_ms = 20\nerror_rate = 1.5").unwrap()),
+        ..test_config(false, true, false)
+    };
+    let (sender, _) = SourceSender::new_test();
+    assert!(config.build(SourceContext::new_test(sender)).await.is_err());
+}
+
+fn request_body_histogram(endpoint: &str) -> (u32, f64) {
+    Controller::get()
+        .unwrap()
+        .capture_metrics()
+        .find_map(|metric| {
+            let matches = metric.name() == "http_request_body_bytes"
+                && metric.tags().map_or(false, |tags| {
+                    tags.get("endpoint").map(String::as_str) == Some(endpoint)
+                });
+            match metric.value() {
+                MetricValue::AggregatedHistogram { count, sum, .. } if matches => {
+                    Some((*count, *sum))
+                }
+                _ => None,
+            }
+        })
+        .unwrap_or((0, 0.0))
+}
+
+#[tokio::test]
+async fn request_body_size_histogram() {
+    init_test();
+    let (_rx, _, _, addr) = source(EventStatus::Delivered, false, true, false).await;
+
+    let (count_before, sum_before) = request_body_histogram("sketches");
+    for size in [10, 100, 1000] {
+        // The payload isn't a valid sketch, its size is recorded nonetheless.
+        send_with_path(
+            addr,
+            &"a".repeat(size),
+            HeaderMap::new(),
+            "/api/beta/sketches",
+        )
+        .await;
+    }
+    let (count_after, sum_after) = request_body_histogram("sketches");
+
+    assert_eq!(count_after - count_before, 3);
+    assert_eq!(sum_after - sum_before, 1110.0);
+}
@@ -0,0 +1,51 @@
+use warp::{filters::BoxedFilter, Filter};
+
+use crate::{event::Event, internal_events::DatadogAgentTraceContext};
+
+/// The B3 trace context sent along an agent request.
+#[derive(Clone, Debug)]
+pub(crate) struct TraceContext {
+    trace_id: Option<String>,
+    span_id: Option<String>,
+    parent_span_id: Option<String>,
+}
+
+impl TraceContext {
+    /// Extracts the trace context from the `X-B3-*` headers, if any.
+    pub(crate) fn filter() -> BoxedFilter<(Option<Self>,)> {
+        warp::header::optional::<String>("x-b3-traceid")
+            .and(warp::header::optional::<String>("x-b3-spanid"))
+            .and(warp::header::optional::<String>("x-b3-parentspanid"))
+            .map(
+                |trace_id: Option<String>,
+                 span_id: Option<String>,
+                 parent_span_id: Option<String>| {
+                    (trace_id.is_some() || span_id.is_some() || parent_span_id.is_some()).then(
+                        || Self {
+                            trace_id,
+                            span_id,
+                            parent_span_id,
+                        },
+                    )
+                },
+            )
+            .boxed()
+    }
+
+    /// Stores the trace context in the metadata of the `events` received on
+    /// `endpoint`.
+    pub(crate) fn apply(&self, endpoint: &'static str, events: &mut [Event]) {
+        if let Some(trace_id) = &self.trace_id {
+            emit!(&DatadogAgentTraceContext {
+                trace_id: trace_id.clone(),
+                endpoint,
+            });
+        }
+        for event in events {
+            let metadata = event.metadata_mut();
+            metadata.set_trace_id(self.trace_id.clone());
+            metadata.set_span_id(self.span_id.clone());
+            metadata.set_parent_span_id(self.parent_span_id.clone());
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, reply::Response, Filter};
+
+use super::{parse_tags, DatadogAgentSource};
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::EventsReceived,
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct WatchdogReport {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub detected_at: i64,
+    pub category: String,
+    #[serde(default)]
+    pub sub_type: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub anomaly: WatchdogAnomaly,
+    #[serde(default)]
+    pub scope: Vec<WatchdogScope>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct WatchdogAnomaly {
+    pub name: String,
+    pub start: i64,
+    #[serde(default)]
+    pub end: Option<i64>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct WatchdogScope {
+    pub name: String,
+    pub value: String,
+}
+
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    source.intake_filter(
+        "watchdog",
+        path!("api" / "v1" / "watchdog" / ..).boxed(),
+        acknowledgements,
+        out,
+        multiple_outputs,
+        decode_watchdog_report,
+    )
+}
+
+fn decode_watchdog_report(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let report: WatchdogReport = serde_json::from_slice(&body).map_err(|error| {
+        ErrorMessage::new(
+            StatusCode::BAD_REQUEST,
+            format!("Error parsing JSON: {:?}", error),
+        )
+    })?;
+
+    let mut log = LogEvent::default();
+    log.insert_flat("type", report.kind);
+    log.insert_flat("detected_at", report.detected_at);
+    log.insert_flat("category", report.category);
+    if let Some(sub_type) = report.sub_type {
+        log.insert_flat("sub_type", sub_type);
+    }
+    if source.parse_ddtags {
+        for (key, value) in parse_tags(report.tags.iter().map(String::as_str)) {
+            match value {
+                Some(value) => log.try_insert_flat(key, value),
+                None => log.try_insert_flat(key, true),
+            }
+        }
+    } else {
+        log.insert_flat("tags", report.tags);
+    }
+
+    let anomaly = report.anomaly;
+    log.insert_flat("anomaly_name", anomaly.name);
+    log.insert_flat("anomaly_start", anomaly.start);
+    if let Some(end) = anomaly.end {
+        log.insert_flat("anomaly_end", end);
+    }
+    if let Some(message) = anomaly.message {
+        log.insert_flat("anomaly_message", message);
+    }
+
+    let scope = report
+        .scope
+        .into_iter()
+        .map(|scope| (scope.name, Value::from(scope.value)))
+        .collect::<BTreeMap<_, _>>();
+    log.insert_flat("scope", scope);
+    let events = vec![source.finish_log(log, Utc::now(), &api_key)];
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
@@ -0,0 +1,142 @@
+use std::{net::IpAddr, sync::Arc};
+
+use bytes::Bytes;
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use http::StatusCode;
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+use vector_core::ByteSizeOf;
+use warp::{filters::BoxedFilter, path, path::FullPath, reply::Response, Filter};
+
+use super::{
+    access, received_body, trace_context::TraceContext, ApiKeyQueryParams, DatadogAgentSource,
+};
+use crate::{
+    event::{Event, LogEvent, Value},
+    internal_events::{DatadogAgentSignatureVerificationFailed, EventsReceived, HttpBytesReceived},
+    sources::util::ErrorMessage,
+    SourceSender,
+};
+
+/// Webhooks are signed over the body as sent, so they don't go through
+/// `intake_filter`, which only hands over the decoded body. Once verified, the
+/// events go through the same steps as the other intake endpoints.
+pub(crate) fn build_warp_filter(
+    acknowledgements: bool,
+    multiple_outputs: bool,
+    out: SourceSender,
+    source: DatadogAgentSource,
+) -> BoxedFilter<(Response,)> {
+    warp::post()
+        .and(path!("webhook" / ..))
+        .and(warp::path::full())
+        .and(warp::header::optional::<String>("dd-api-key"))
+        .and(warp::query::<ApiKeyQueryParams>())
+        .and(warp::header::optional::<String>("x-datadog-signature"))
+        .and(TraceContext::filter())
+        .and(access::client_ip())
+        .and(received_body("webhook"))
+        .and_then(
+            move |path: FullPath,
+                  api_token: Option<String>,
+                  query_params: ApiKeyQueryParams,
+                  signature: Option<String>,
+                  trace_context: Option<TraceContext>,
+                  client_ip: Option<IpAddr>,
+                  body: Bytes| {
+                let source = source.clone();
+                let out = out.clone();
+                async move {
+                    emit!(&HttpBytesReceived {
+                        byte_size: body.len(),
+                        http_path: path.as_str(),
+                        protocol: source.protocol,
+                    });
+                    let api_key =
+                        source.extract_api_key(path.as_str(), api_token, query_params.dd_api_key);
+                    let events = check_signature(&source, signature.as_deref(), &body)
+                        .and_then(|_| decode_webhook(&source, body, api_key.clone()));
+                    let events =
+                        source.finish_events("webhook", events, &api_key, trace_context.as_ref());
+                    DatadogAgentSource::handle_request(
+                        events,
+                        client_ip,
+                        acknowledgements,
+                        out,
+                        multiple_outputs,
+                    )
+                    .await
+                }
+            },
+        )
+        .boxed()
+}
+
+/// Rejects the webhooks which aren't signed with the configured secret, if
+/// any.
+fn check_signature(
+    source: &DatadogAgentSource,
+    signature: Option<&str>,
+    body: &[u8],
+) -> Result<(), ErrorMessage> {
+    let secret = match &source.webhook_secret {
+        Some(secret) => secret,
+        None => return Ok(()),
+    };
+    verify_signature(secret, signature, body).map_err(|reason| {
+        emit!(&DatadogAgentSignatureVerificationFailed { reason });
+        ErrorMessage::new(
+            StatusCode::FORBIDDEN,
+            format!("Webhook signature is {}", reason),
+        )
+    })
+}
+
+/// Checks that `signature` is the hex encoded HMAC-SHA1 of `body` keyed with
+/// `secret`, in constant time.
+fn verify_signature(
+    secret: &str,
+    signature: Option<&str>,
+    body: &[u8],
+) -> Result<(), &'static str> {
+    let signature = signature.ok_or("missing")?;
+    let signature = hex::decode(signature.trim()).map_err(|_| "invalid")?;
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let expected = mac.finalize().into_bytes();
+    if bool::from(expected.as_slice().ct_eq(&signature)) {
+        Ok(())
+    } else {
+        Err("invalid")
+    }
+}
+
+/// Turns the JSON object sent by the webhook into a log, as is.
+fn decode_webhook(
+    source: &DatadogAgentSource,
+    body: Bytes,
+    api_key: Option<Arc<str>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let payload: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&body)
+        .map_err(|error| {
+            ErrorMessage::new(
+                StatusCode::BAD_REQUEST,
+                format!("Error parsing JSON: {:?}", error),
+            )
+        })?;
+
+    let mut log = LogEvent::default();
+    for (key, value) in payload {
+        log.insert_flat(key, Value::from(value));
+    }
+    let events = vec![source.finish_log(log, Utc::now(), &api_key)];
+
+    emit!(&EventsReceived {
+        byte_size: events.size_of(),
+        count: events.len(),
+    });
+
+    Ok(events)
+}
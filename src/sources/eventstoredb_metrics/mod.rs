@@ -1,10 +1,10 @@
-use std::time::Duration;
+use std::{cmp, future::Future, time::Duration};
 
 use futures::{stream, FutureExt, StreamExt};
 use http::Uri;
 use hyper::{Body, Request};
 use serde::{Deserialize, Serialize};
-use tokio_stream::wrappers::IntervalStream;
+use tokio::time::sleep;
 
 use self::types::Stats;
 use crate::{
@@ -15,6 +15,7 @@ use crate::{
         EventStoreDbMetricsHttpError, EventStoreDbMetricsReceived, EventStoreDbStatsParsingError,
     },
     tls::TlsSettings,
+    SourceSender,
 };
 
 pub mod types;
@@ -25,6 +26,8 @@ struct EventStoreDbConfig {
     endpoint: String,
     #[serde(default = "default_scrape_interval_secs")]
     scrape_interval_secs: u64,
+    #[serde(default = "default_max_scrape_interval_secs")]
+    max_scrape_interval_secs: u64,
     default_namespace: Option<String>,
 }
 
@@ -32,6 +35,10 @@ pub const fn default_scrape_interval_secs() -> u64 {
     15
 }
 
+pub const fn default_max_scrape_interval_secs() -> u64 {
+    300
+}
+
 pub fn default_endpoint() -> String {
     "https://localhost:2113/stats".to_string()
 }
@@ -49,6 +56,7 @@ impl SourceConfig for EventStoreDbConfig {
         eventstoredb(
             self.endpoint.as_str(),
             self.scrape_interval_secs,
+            self.max_scrape_interval_secs,
             self.default_namespace.clone(),
             cx,
         )
@@ -63,73 +71,189 @@ impl SourceConfig for EventStoreDbConfig {
     }
 }
 
+/// Polling schedule of the source, backing off exponentially while the
+/// endpoint can't be reached.
+#[derive(Debug)]
+struct BackoffState {
+    current_interval: Duration,
+    consecutive_failures: u32,
+    scrape_interval: Duration,
+    max_scrape_interval: Duration,
+}
+
+impl BackoffState {
+    fn new(scrape_interval: Duration, max_scrape_interval: Duration) -> Self {
+        Self {
+            current_interval: scrape_interval,
+            consecutive_failures: 0,
+            scrape_interval,
+            max_scrape_interval: cmp::max(scrape_interval, max_scrape_interval),
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.current_interval = self.scrape_interval;
+    }
+
+    fn record_failure(&mut self) {
+        self.consecutive_failures += 1;
+        self.current_interval = cmp::min(self.current_interval * 2, self.max_scrape_interval);
+    }
+}
+
+enum ScrapeOutcome {
+    Success,
+    HttpError,
+    ParseError,
+    Closed,
+}
+
+/// Scrapes right away, then waits for the current interval of `backoff`
+/// between each scrape until `shutdown` resolves.
+async fn poll_schedule<F, Fut>(mut backoff: BackoffState, shutdown: impl Future, mut scrape: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ScrapeOutcome>,
+{
+    tokio::pin!(shutdown);
+    loop {
+        match scrape().await {
+            ScrapeOutcome::Success => backoff.record_success(),
+            ScrapeOutcome::HttpError => {
+                backoff.record_failure();
+                debug!(
+                    message = "Backing off scrapes.",
+                    consecutive_failures = backoff.consecutive_failures,
+                    interval = ?backoff.current_interval,
+                );
+            }
+            ScrapeOutcome::ParseError => {}
+            ScrapeOutcome::Closed => break,
+        }
+
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = sleep(backoff.current_interval) => {}
+        }
+    }
+}
+
 fn eventstoredb(
     endpoint: &str,
     interval: u64,
+    max_interval: u64,
     namespace: Option<String>,
-    mut cx: SourceContext,
+    cx: SourceContext,
 ) -> crate::Result<super::Source> {
-    let mut ticks = IntervalStream::new(tokio::time::interval(Duration::from_secs(interval)))
-        .take_until(cx.shutdown);
+    let backoff = BackoffState::new(
+        Duration::from_secs(interval),
+        Duration::from_secs(max_interval),
+    );
     let tls_settings = TlsSettings::from_options(&None)?;
     let client = HttpClient::new(tls_settings, &cx.proxy)?;
     let url: Uri = endpoint.parse()?;
+    let out = cx.out;
 
     Ok(Box::pin(
-        async move {
-            while ticks.next().await.is_some() {
-                let req = Request::get(&url)
-                    .header("content-type", "application/json")
-                    .body(Body::empty())
-                    .expect("Building request should be infallible.");
-
-                match client.send(req).await {
-                    Err(error) => {
-                        emit!(&EventStoreDbMetricsHttpError {
-                            error: error.into(),
-                        });
-                        continue;
-                    }
-
-                    Ok(resp) => {
-                        let bytes = match hyper::body::to_bytes(resp.into_body()).await {
-                            Ok(b) => b,
-                            Err(error) => {
-                                emit!(&EventStoreDbMetricsHttpError {
-                                    error: error.into(),
-                                });
-                                continue;
-                            }
-                        };
-
-                        match serde_json::from_slice::<Stats>(bytes.as_ref()) {
-                            Err(error) => {
-                                emit!(&EventStoreDbStatsParsingError { error });
-                                continue;
-                            }
-
-                            Ok(stats) => {
-                                let metrics = stats.metrics(namespace.clone());
-
-                                emit!(&EventStoreDbMetricsReceived {
-                                    events: metrics.len(),
-                                    byte_size: bytes.len(),
-                                });
-
-                                let mut metrics = stream::iter(metrics).map(Event::Metric);
-                                if let Err(error) = cx.out.send_all(&mut metrics).await {
-                                    error!(message = "Error sending metric.", %error);
-                                    break;
-                                }
-                            }
-                        }
-                    }
+        poll_schedule(backoff, cx.shutdown, move || {
+            scrape(client.clone(), url.clone(), namespace.clone(), out.clone())
+        })
+        .map(Ok),
+    ))
+}
+
+async fn scrape(
+    client: HttpClient,
+    url: Uri,
+    namespace: Option<String>,
+    mut out: SourceSender,
+) -> ScrapeOutcome {
+    let req = Request::get(&url)
+        .header("content-type", "application/json")
+        .body(Body::empty())
+        .expect("Building request should be infallible.");
+
+    let resp = match client.send(req).await {
+        Ok(resp) => resp,
+        Err(error) => {
+            emit!(&EventStoreDbMetricsHttpError {
+                error: error.into(),
+            });
+            return ScrapeOutcome::HttpError;
+        }
+    };
+
+    let bytes = match hyper::body::to_bytes(resp.into_body()).await {
+        Ok(b) => b,
+        Err(error) => {
+            emit!(&EventStoreDbMetricsHttpError {
+                error: error.into(),
+            });
+            return ScrapeOutcome::HttpError;
+        }
+    };
+
+    match serde_json::from_slice::<Stats>(bytes.as_ref()) {
+        Err(error) => {
+            emit!(&EventStoreDbStatsParsingError { error });
+            ScrapeOutcome::ParseError
+        }
+
+        Ok(stats) => {
+            let metrics = stats.metrics(namespace);
+
+            emit!(&EventStoreDbMetricsReceived {
+                events: metrics.len(),
+                byte_size: bytes.len(),
+            });
+
+            let mut metrics = stream::iter(metrics).map(Event::Metric);
+            match out.send_all(&mut metrics).await {
+                Ok(()) => ScrapeOutcome::Success,
+                Err(error) => {
+                    error!(message = "Error sending metric.", %error);
+                    ScrapeOutcome::Closed
                 }
             }
         }
-        .map(Ok)
-        .boxed(),
-    ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::future;
+    use tokio::time::Instant;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn backs_off_while_scrapes_fail() {
+        tokio::time::pause();
+
+        let start = Instant::now();
+        let scrapes = Arc::new(Mutex::new(Vec::new()));
+        let backoff = BackoffState::new(Duration::from_secs(15), Duration::from_secs(60));
+        let shutdown = sleep(Duration::from_secs(200));
+
+        poll_schedule(backoff, shutdown, || {
+            let mut scrapes = scrapes.lock().unwrap();
+            scrapes.push(start.elapsed().as_secs());
+            future::ready(if scrapes.len() <= 3 {
+                ScrapeOutcome::HttpError
+            } else {
+                ScrapeOutcome::Success
+            })
+        })
+        .await;
+
+        assert_eq!(
+            *scrapes.lock().unwrap(),
+            vec![0, 30, 90, 150, 165, 180, 195]
+        );
+    }
 }
 
 #[cfg(all(test, feature = "eventstoredb_metrics-integration-tests"))]
@@ -147,6 +271,7 @@ mod integration_tests {
         let config = EventStoreDbConfig {
             endpoint: EVENTSTOREDB_SCRAP_ADDRESS.to_owned(),
             scrape_interval_secs: 1,
+            max_scrape_interval_secs: 1,
             default_namespace: None,
         };
 
@@ -141,6 +141,12 @@ pub async fn build_pieces(
     let (enrichment_tables, enrichment_errors) = load_enrichment_tables(config, diff).await;
     errors.extend(enrichment_errors);
 
+    // Only pay for tagging every event with its source when a sink uses it.
+    let track_provenance = config
+        .sinks
+        .values()
+        .any(|sink| sink.inner.tracks_provenance());
+
     // Build sources
     for (key, source) in config
         .sources
@@ -153,12 +159,19 @@ pub async fn build_pieces(
         let mut builder = SourceSender::builder().with_buffer(SOURCE_SENDER_BUFFER_SIZE);
         let mut pumps = Vec::new();
         let mut controls = HashMap::new();
+        let source_id: Arc<str> = Arc::from(key.id());
         for output in source_outputs {
             let mut rx = builder.add_output(output.clone());
 
             let (mut fanout, control) = Fanout::new();
+            let source_id = track_provenance.then(|| Arc::clone(&source_id));
             let pump = async move {
-                while let Some(event) = rx.next().await {
+                while let Some(mut event) = rx.next().await {
+                    if let Some(source_id) = &source_id {
+                        event
+                            .metadata_mut()
+                            .set_source_id(Some(Arc::clone(source_id)));
+                    }
                     fanout.feed(event).await?;
                 }
                 fanout.flush().await?;
@@ -59,6 +59,21 @@ components: sources: datadog_agent: {
 	configuration: {
 		acknowledgements: configuration._acknowledgements
 		address:          sources.http.configuration.address
+		fanout_api_keys: {
+			common: false
+			description: """
+				Datadog API keys to duplicate the events received without an API key to. One copy of each event is
+				emitted for each of the keys, holding that key in its metadata. This is only effective when
+				`store_api_key` is `true`.
+				"""
+			required: false
+			type: array: {
+				default: []
+				items: type: string: {
+					examples: ["${DATADOG_API_KEY_1}", "${DATADOG_API_KEY_2}"]
+				}
+			}
+		}
 		multiple_outputs: {
 			common: false
 			description: """
@@ -69,12 +84,32 @@ components: sources: datadog_agent: {
 			required: false
 			type: bool: default: false
 		}
+		parse_ddtags: {
+			common: false
+			description: """
+				If this setting is set to `true` the comma separated `ddtags` of received logs are split into separate
+				fields. Each `key:value` tag is inserted as a `key` field holding `value`, tags without a value are
+				inserted as boolean fields set to `true`. The raw `ddtags` field is then omitted.
+				"""
+			required: false
+			type: bool: default: false
+		}
 		store_api_key: {
 			common:      false
 			description: "When incoming events contain a Datadog API key, if this setting is set to `true` the key will kept in the event metadata and will be used if the event is sent to a Datadog sink."
 			required:    false
 			type: bool: default: true
 		}
+		validate_schema: {
+			common: false
+			description: """
+				If this setting is set to `true` the JSON payloads of the logs, series and security signals endpoints are
+				validated against the schema of the Datadog agent payloads before being decoded. Payloads failing the
+				validation are rejected with a `400 Bad Request` response listing the validation errors.
+				"""
+			required: false
+			type: bool: default: false
+		}
 	}
 
 	outputs: [
@@ -133,8 +168,8 @@ components: sources: datadog_agent: {
 					}
 				}
 				ddtags: {
-					description: "The coma separated tags list extracted from the event."
-					required:    true
+					description: "The coma separated tags list extracted from the event. Omitted if [parse_ddtags](#parse_ddtags) is enabled."
+					required:    false
 					type: string: {
 						examples: ["env:prod,region:ap-east-1"]
 					}
@@ -53,6 +53,15 @@ components: sources: eventstoredb_metrics: {
 				default: "https://localhost:2113/stats"
 			}
 		}
+		max_scrape_interval_secs: {
+			common:      false
+			description: "The longest interval between scrapes, in seconds. The interval doubles after each failed scrape, up to this value, and is reset by the next successful scrape."
+			required:    false
+			type: uint: {
+				default: 300
+				unit:    "seconds"
+			}
+		}
 		scrape_interval_secs: {
 			common:      true
 			description: "The interval between scrapes, in seconds."